@@ -0,0 +1,177 @@
+//! Project-scoped allowlist of extra roots that file tools may access.
+
+use crate::error::ContextError;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AllowedRoot {
+    pub id: String,
+    pub path: String,
+    pub added_at: DateTime<Utc>,
+}
+
+pub struct AllowedRootStore {
+    db_path: String,
+}
+
+impl AllowedRootStore {
+    /// Open the store, creating its table if needed
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database can't be opened
+    pub fn new(db_path: &str) -> Result<Self, ContextError> {
+        let store = Self {
+            db_path: db_path.to_string(),
+        };
+        store.init_schema()?;
+        Ok(store)
+    }
+
+    fn get_db(&self) -> Result<rusqlite::Connection, ContextError> {
+        Ok(rusqlite::Connection::open(&self.db_path)?)
+    }
+
+    fn init_schema(&self) -> Result<(), ContextError> {
+        let db = self.get_db()?;
+        db.execute(
+            "CREATE TABLE IF NOT EXISTS allowed_roots (
+                id TEXT PRIMARY KEY,
+                path TEXT UNIQUE NOT NULL,
+                added_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+        Ok(())
+    }
+
+    /// Allowed roots, sorted by path
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database query fails
+    pub fn list(&self) -> Result<Vec<AllowedRoot>, ContextError> {
+        let db = self.get_db()?;
+        let mut stmt = db.prepare(
+            "SELECT id, path, added_at FROM allowed_roots ORDER BY path ASC",
+        )?;
+
+        let rows = stmt.query_map([], |row| {
+            Ok(AllowedRoot {
+                id: row.get(0)?,
+                path: row.get(1)?,
+                added_at: row
+                    .get::<_, String>(2)?
+                    .parse()
+                    .unwrap_or_else(|_| Utc::now()),
+            })
+        })?;
+
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| ContextError::Database(e.to_string()))
+    }
+
+    /// Allow file tools to access an existing directory outside the project root.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path isn't an existing directory or is already
+    /// allowed
+    pub fn add(&self, path: &str) -> Result<AllowedRoot, ContextError> {
+        let canonical = std::fs::canonicalize(path)
+            .map_err(|_| ContextError::InvalidPath(path.to_string()))?;
+        if !canonical.is_dir() {
+            return Err(ContextError::InvalidPath(path.to_string()));
+        }
+        let path_str = canonical.to_string_lossy().to_string();
+
+        let db = self.get_db()?;
+        let exists: bool = db
+            .query_row(
+                "SELECT 1 FROM allowed_roots WHERE path = ?1",
+                [&path_str],
+                |_| Ok(true),
+            )
+            .unwrap_or(false);
+        if exists {
+            return Err(ContextError::AlreadyExists(path_str));
+        }
+
+        let id = uuid::Uuid::new_v4().to_string();
+        let now = Utc::now();
+        db.execute(
+            "INSERT INTO allowed_roots (id, path, added_at) VALUES (?1, ?2, ?3)",
+            [&id, &path_str, &now.to_rfc3339()],
+        )?;
+
+        Ok(AllowedRoot {
+            id,
+            path: path_str,
+            added_at: now,
+        })
+    }
+
+    /// Stop allowing a root
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no root has this id
+    pub fn remove(&self, id: &str) -> Result<(), ContextError> {
+        let db = self.get_db()?;
+        let rows = db.execute("DELETE FROM allowed_roots WHERE id = ?1", [id])?;
+        if rows == 0 {
+            return Err(ContextError::NotInContext(id.to_string()));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn create_store(temp: &TempDir) -> AllowedRootStore {
+        let db_path = temp.path().join("project.db");
+        AllowedRootStore::new(db_path.to_str().unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn add_then_list_returns_canonical_path() {
+        let temp = TempDir::new().unwrap();
+        let shared = TempDir::new().unwrap();
+        let store = create_store(&temp);
+
+        let root = store.add(shared.path().to_str().unwrap()).unwrap();
+        let roots = store.list().unwrap();
+
+        assert_eq!(roots.len(), 1);
+        assert_eq!(roots[0].id, root.id);
+        assert_eq!(
+            roots[0].path,
+            std::fs::canonicalize(shared.path()).unwrap().to_string_lossy()
+        );
+    }
+
+    #[tokio::test]
+    async fn add_rejects_duplicates_and_missing_dirs() {
+        let temp = TempDir::new().unwrap();
+        let shared = TempDir::new().unwrap();
+        let store = create_store(&temp);
+
+        store.add(shared.path().to_str().unwrap()).unwrap();
+        let duplicate = store.add(shared.path().to_str().unwrap());
+        assert!(matches!(duplicate, Err(ContextError::AlreadyExists(_))));
+
+        let missing = store.add("/tmp/aiharness/does/not/exist");
+        assert!(matches!(missing, Err(ContextError::InvalidPath(_))));
+    }
+
+    #[tokio::test]
+    async fn remove_missing_root_errors() {
+        let temp = TempDir::new().unwrap();
+        let store = create_store(&temp);
+        assert!(store.remove("nope").is_err());
+    }
+}
@@ -2,16 +2,34 @@
 //! 
 //! Single source of truth for:
 //! - Project registry + per-project stores
+//! - Client tokens and tool policies for the HTTP/MCP server
+//! - Approval queue for tool calls that need a human decision
+//! - Global prompt library
+//! - MCP Streamable HTTP sessions and their resource subscriptions
+//! - Persistent terminal sessions (background processes live on project stores)
 //! - Tool registry
-//! - Event history (tool calls)
+//! - Event history (tool calls, persisted per project)
+//! - Security events (blocked sandbox escapes)
 //! - HTTP server control
 
 use crate::{
-    error::ContextError,
+    approvals::{ApprovalQueue, ApprovalRequest, ApprovalRuleStore},
+    client_tokens::ClientTokenStore,
+    discovery,
+    mcp_sessions::{InFlightRequests, McpSessionManager},
+    mcp_gateway::McpGateway,
+    processes::{ProcessEvent, DEFAULT_STOP_TIMEOUT},
+    resource_subscriptions::ResourceSubscriptions,
+    terminals::{TerminalEvent, TerminalManager},
+    error::{ContextError, ToolError},
+    prompts::PromptStore,
     projects::{ProjectRegistry, ProjectStore, ProjectStoreCache},
-    tools::{create_standard_registry, ToolRegistry},
-    ToolCallEvent,
+    tool_history::{ToolCallPage, ToolCallQuery},
+    tool_policies::ToolPolicyStore,
+    tools::{create_standard_registry, Caller, PathSandbox, ProgressReporter, ToolContext, ToolRegistry},
+    SecurityEvent, ToolCallEvent,
 };
+use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::sync::{RwLock, broadcast};
 
@@ -24,16 +42,42 @@ pub struct AppState {
     pub project_registry: ProjectRegistry,
     /// Cached project stores
     pub project_stores: ProjectStoreCache,
+    /// Bearer tokens accepted by the HTTP server (global)
+    pub client_tokens: ClientTokenStore,
+    /// Per-client, per-project tool permission policies (global)
+    pub tool_policies: ToolPolicyStore,
+    /// Rules selecting calls that need human approval (global)
+    pub approval_rules: ApprovalRuleStore,
+    /// Calls waiting for approval (shared so waiters don't hold the state lock)
+    pub approvals: Arc<ApprovalQueue>,
+    /// Prompts shared by every project (global)
+    pub prompts: PromptStore,
+    /// Live MCP Streamable HTTP sessions
+    pub mcp_sessions: Arc<McpSessionManager>,
+    /// MCP requests being served, for `notifications/cancelled`
+    pub mcp_requests: Arc<InFlightRequests>,
+    /// Resources MCP sessions subscribed to (polled by the resource watcher)
+    pub resource_subscriptions: Arc<ResourceSubscriptions>,
+    /// Connections to projects' upstream MCP servers
+    pub mcp_gateway: Arc<McpGateway>,
+    /// Persistent terminals opened by tools
+    pub terminals: Arc<TerminalManager>,
     /// Tool registry
     pub tool_registry: ToolRegistry,
     /// Event history (tool calls)
     event_history: RwLock<Vec<ToolCallEvent>>,
     /// Event broadcaster for real-time updates
     event_sender: broadcast::Sender<ToolCallEvent>,
+    /// Security event history
+    security_events: RwLock<Vec<SecurityEvent>>,
+    /// Security event broadcaster for real-time updates
+    security_sender: broadcast::Sender<SecurityEvent>,
     /// HTTP server handle
     http_server: RwLock<Option<ServerHandle>>,
     /// HTTP server port
     http_port: RwLock<u16>,
+    /// Where the server's discovery file is written (none in tests)
+    data_dir: Option<PathBuf>,
 }
 
 impl AppState {
@@ -42,20 +86,38 @@ impl AppState {
         let project_registry = ProjectRegistry::new(registry_path).await?;
         let project_stores = ProjectStoreCache::new();
         ensure_default_project(&project_registry, &project_stores, app_data_dir).await?;
+        let client_tokens = ClientTokenStore::new(registry_path)?;
+        let tool_policies = ToolPolicyStore::new(registry_path)?;
+        let approval_rules = ApprovalRuleStore::new(registry_path)?;
+        let prompts = PromptStore::new(registry_path)?;
         
         let port = 8787;
         let tool_registry = create_standard_registry(port);
         let event_history = RwLock::new(Vec::new());
         let (event_sender, _) = broadcast::channel(100);
+        let (security_sender, _) = broadcast::channel(100);
         
         Ok(Self {
             project_registry,
             project_stores,
+            client_tokens,
+            tool_policies,
+            approval_rules,
+            approvals: Arc::new(ApprovalQueue::default()),
+            prompts,
+            mcp_sessions: Arc::new(McpSessionManager::default()),
+            mcp_requests: Arc::new(InFlightRequests::default()),
+            resource_subscriptions: Arc::new(ResourceSubscriptions::default()),
+            mcp_gateway: Arc::new(McpGateway::default()),
+            terminals: Arc::new(TerminalManager::default()),
             tool_registry,
             event_history,
             event_sender,
+            security_events: RwLock::new(Vec::new()),
+            security_sender,
             http_server: RwLock::new(None),
             http_port: RwLock::new(port),
+            data_dir: Some(app_data_dir.to_path_buf()),
         })
     }
     
@@ -63,24 +125,55 @@ impl AppState {
     #[cfg(test)]
     pub async fn new_for_test(project_registry: ProjectRegistry) -> Self {
         let project_stores = ProjectStoreCache::new();
+        let client_tokens =
+            ClientTokenStore::new(project_registry.db_path()).expect("client token store");
+        let tool_policies =
+            ToolPolicyStore::new(project_registry.db_path()).expect("tool policy store");
+        let approval_rules =
+            ApprovalRuleStore::new(project_registry.db_path()).expect("approval rule store");
+        let prompts = PromptStore::new(project_registry.db_path()).expect("prompt store");
         let port = 8787;
         let tool_registry = create_standard_registry(port);
         let event_history = RwLock::new(Vec::new());
         let (event_sender, _) = broadcast::channel(100);
+        let (security_sender, _) = broadcast::channel(100);
         
         Self {
             project_registry,
             project_stores,
+            client_tokens,
+            tool_policies,
+            approval_rules,
+            approvals: Arc::new(ApprovalQueue::default()),
+            prompts,
+            mcp_sessions: Arc::new(McpSessionManager::default()),
+            mcp_requests: Arc::new(InFlightRequests::default()),
+            resource_subscriptions: Arc::new(ResourceSubscriptions::default()),
+            mcp_gateway: Arc::new(McpGateway::default()),
+            terminals: Arc::new(TerminalManager::default()),
             tool_registry,
             event_history,
             event_sender,
+            security_events: RwLock::new(Vec::new()),
+            security_sender,
             http_server: RwLock::new(None),
             http_port: RwLock::new(port),
+            data_dir: None,
         }
     }
     
     /// Record a tool call event
     pub async fn record_event(&self, event: ToolCallEvent) {
+        // Persist to the project's history
+        match self.get_project_store(&event.project_id).await {
+            Ok(store) => {
+                if let Err(e) = store.tool_call_store.read().await.record(&event) {
+                    tracing::warn!("Failed to persist tool call {}: {}", event.id, e);
+                }
+            }
+            Err(e) => tracing::warn!("No store for project {}: {}", event.project_id, e),
+        }
+
         // Add to history
         let mut history = self.event_history.write().await;
         history.insert(0, event.clone());
@@ -91,16 +184,137 @@ impl AppState {
         let _ = self.event_sender.send(event);
     }
     
-    /// Get event history
+    /// Get recent in-memory event history (all projects, since startup)
     pub async fn get_history(&self) -> Vec<ToolCallEvent> {
         self.event_history.read().await.clone()
     }
+
+    /// Query a project's persisted tool-call history
+    ///
+    /// # Errors
+    ///
+    /// Returns `ContextError` if the project is unknown or the query fails
+    pub async fn query_events(
+        &self,
+        project_id: &str,
+        query: &ToolCallQuery,
+    ) -> Result<ToolCallPage, ContextError> {
+        let store = self.get_project_store(project_id).await?;
+        let page = store.tool_call_store.read().await.query(query)?;
+        Ok(page)
+    }
+
+    /// Names of the tools with calls in a project's history
+    ///
+    /// # Errors
+    ///
+    /// Returns `ContextError` if the project is unknown or its history can't be read
+    pub async fn event_tool_names(&self, project_id: &str) -> Result<Vec<String>, ContextError> {
+        let store = self.get_project_store(project_id).await?;
+        let names = store.tool_call_store.read().await.tool_names()?;
+        Ok(names)
+    }
     
     /// Subscribe to events
     pub fn subscribe(&self) -> broadcast::Receiver<ToolCallEvent> {
         self.event_sender.subscribe()
     }
 
+    /// Record a security event
+    pub async fn record_security_event(&self, event: SecurityEvent) {
+        tracing::warn!(
+            "Security event: kind={} tool={} project_id={} detail={}",
+            event.kind, event.tool_name, event.project_id, event.detail
+        );
+        let mut history = self.security_events.write().await;
+        history.insert(0, event.clone());
+        history.truncate(100); // Keep last 100
+        drop(history);
+
+        let _ = self.security_sender.send(event);
+    }
+
+    /// Record a security event if a tool error was a blocked sandbox escape or policy denial
+    pub async fn record_tool_error(&self, tool_name: &str, project_id: &str, error: &ToolError) {
+        let kind = match error {
+            ToolError::PathOutsideProject { .. } => "path_escape",
+            ToolError::PolicyDenied(_) => "policy_denied",
+            _ => return,
+        };
+        self.record_security_event(SecurityEvent {
+            id: uuid::Uuid::new_v4().to_string(),
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            kind: kind.to_string(),
+            tool_name: tool_name.to_string(),
+            project_id: project_id.to_string(),
+            detail: error.to_string(),
+        })
+        .await;
+    }
+
+    /// Get security event history
+    pub async fn get_security_events(&self) -> Vec<SecurityEvent> {
+        self.security_events.read().await.clone()
+    }
+
+    /// Subscribe to security events
+    pub fn subscribe_security(&self) -> broadcast::Receiver<SecurityEvent> {
+        self.security_sender.subscribe()
+    }
+
+    /// Subscribe to tool calls waiting for approval
+    pub fn subscribe_approvals(&self) -> broadcast::Receiver<ApprovalRequest> {
+        self.approvals.subscribe()
+    }
+
+    /// Subscribe to terminal input and output
+    pub fn subscribe_terminals(&self) -> broadcast::Receiver<TerminalEvent> {
+        self.terminals.subscribe()
+    }
+
+    /// Subscribe to background processes starting and exiting (all projects)
+    pub fn subscribe_processes(&self) -> broadcast::Receiver<ProcessEvent> {
+        self.project_stores.subscribe_processes()
+    }
+
+    /// Build the file tool sandbox for a project (root plus allowed roots)
+    ///
+    /// # Errors
+    ///
+    /// Returns `ContextError` if the project is unknown or its root cannot be resolved
+    pub async fn project_sandbox(&self, project_id: &str) -> Result<PathSandbox, ContextError> {
+        let store = self.get_project_store(project_id).await?;
+        let extra_roots = store
+            .allowed_root_store
+            .read()
+            .await
+            .list()?
+            .into_iter()
+            .map(|root| root.path)
+            .collect::<Vec<_>>();
+        PathSandbox::new(&store.info.root_path, &extra_roots)
+            .map_err(|e| ContextError::InvalidPath(e.to_string()))
+    }
+
+    /// Build the execution context for a tool call on a project
+    ///
+    /// # Errors
+    ///
+    /// Returns `ContextError` if the project is unknown or its root cannot be resolved
+    pub async fn tool_context(&self, project_id: &str, caller: Caller) -> Result<ToolContext, ContextError> {
+        let store = self.get_project_store(project_id).await?;
+        let sandbox = self.project_sandbox(project_id).await?;
+        Ok(ToolContext {
+            project_id: project_id.to_string(),
+            store,
+            sandbox,
+            caller,
+            terminals: self.terminals.clone(),
+            progress: ProgressReporter::default(),
+            cancel: tokio_util::sync::CancellationToken::new(),
+        })
+    }
+
     pub async fn get_project_store(&self, project_id: &str) -> Result<Arc<ProjectStore>, ContextError> {
         crate::debug_log(&format!("get_project_store: START project_id={}", project_id));
         
@@ -151,18 +365,41 @@ impl AppState {
         *self.http_port.write().await = port;
     }
     
-    /// Set HTTP server handle
+    /// Set HTTP server handle and advertise the port in the discovery file
     pub async fn set_server_handle(&self, handle: ServerHandle) {
         *self.http_server.write().await = Some(handle);
+        if let Some(data_dir) = &self.data_dir {
+            let port = self.get_port().await;
+            if let Err(e) = discovery::write(data_dir, port) {
+                tracing::warn!("Failed to write discovery file: {e}");
+            }
+        }
     }
     
     /// Stop the HTTP server
     pub async fn stop_server(&self) {
         if let Some(handle) = self.http_server.write().await.take() {
             handle.abort();
+            if let Some(data_dir) = &self.data_dir {
+                discovery::remove(data_dir);
+            }
             tracing::info!("HTTP server stopped");
         }
     }
+
+    /// Stop everything the app started before it exits: the HTTP server,
+    /// terminals and every project's background processes.
+    pub async fn shutdown(&self) {
+        self.stop_server().await;
+        self.terminals.close_all();
+        let stores = self.project_stores.all().await;
+        futures::future::join_all(
+            stores
+                .iter()
+                .map(|store| store.processes.stop_all(DEFAULT_STOP_TIMEOUT)),
+        )
+        .await;
+    }
 }
 
 async fn ensure_default_project(
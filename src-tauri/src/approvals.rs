@@ -0,0 +1,518 @@
+//! Human approval gates for tool calls.
+//!
+//! Approval rules (in the global registry DB) pick out calls that need a
+//! human decision. Matching calls are parked in the [`ApprovalQueue`], shown
+//! to the UI, and resumed when someone approves or denies them, or when the
+//! request times out. Without a UI (the headless server) nobody can decide,
+//! so matching calls are refused at once.
+
+use crate::error::ContextError;
+use crate::tool_policies::{glob_matches, PolicyRequest};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+use tokio::sync::{broadcast, oneshot};
+
+/// How long a call waits for a decision before it is denied
+pub const DEFAULT_APPROVAL_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Which calls require approval
+///
+/// `tool`, `path` and `command` are glob patterns; unset `path`/`command`
+/// match any call to the tool.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApprovalRule {
+    pub id: String,
+    /// Project id, or `None` for every project
+    pub project_id: Option<String>,
+    pub tool: String,
+    pub path: Option<String>,
+    pub command: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Input for creating an approval rule
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct NewApprovalRule {
+    #[serde(default)]
+    pub project_id: Option<String>,
+    pub tool: String,
+    #[serde(default)]
+    pub path: Option<String>,
+    #[serde(default)]
+    pub command: Option<String>,
+}
+
+impl ApprovalRule {
+    /// Whether a call needs approval under this rule
+    #[must_use]
+    pub fn matches(&self, request: &PolicyRequest) -> bool {
+        glob_matches(&self.tool, &request.tool_name)
+            && self
+                .path
+                .as_deref()
+                .map_or(true, |p| request.paths.iter().any(|path| glob_matches(p, path)))
+            && self.command.as_deref().map_or(true, |c| {
+                request
+                    .command
+                    .as_deref()
+                    .is_some_and(|command| glob_matches(c, command.trim()))
+            })
+    }
+}
+
+pub struct ApprovalRuleStore {
+    db_path: String,
+}
+
+impl ApprovalRuleStore {
+    /// Open the store, creating its table if needed
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database can't be opened
+    pub fn new(db_path: &str) -> Result<Self, ContextError> {
+        let store = Self {
+            db_path: db_path.to_string(),
+        };
+        store.init_schema()?;
+        Ok(store)
+    }
+
+    fn get_db(&self) -> Result<rusqlite::Connection, ContextError> {
+        Ok(rusqlite::Connection::open(&self.db_path)?)
+    }
+
+    fn init_schema(&self) -> Result<(), ContextError> {
+        let db = self.get_db()?;
+        db.execute(
+            "CREATE TABLE IF NOT EXISTS approval_rules (
+                id TEXT PRIMARY KEY,
+                project_id TEXT,
+                tool TEXT NOT NULL,
+                path TEXT,
+                command TEXT,
+                created_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+        Ok(())
+    }
+
+    /// All rules, oldest first
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database query fails
+    pub fn list(&self) -> Result<Vec<ApprovalRule>, ContextError> {
+        self.query("", None)
+    }
+
+    /// Rules that apply to calls in `project_id`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database query fails
+    pub fn for_project(&self, project_id: &str) -> Result<Vec<ApprovalRule>, ContextError> {
+        self.query("WHERE project_id IS NULL OR project_id = ?1", Some(project_id))
+    }
+
+    fn query(&self, filter: &str, project_id: Option<&str>) -> Result<Vec<ApprovalRule>, ContextError> {
+        let db = self.get_db()?;
+        let mut stmt = db.prepare(&format!(
+            "SELECT id, project_id, tool, path, command, created_at FROM approval_rules {filter}
+             ORDER BY created_at ASC"
+        ))?;
+
+        let map_row = |row: &rusqlite::Row<'_>| {
+            Ok(ApprovalRule {
+                id: row.get(0)?,
+                project_id: row.get(1)?,
+                tool: row.get(2)?,
+                path: row.get(3)?,
+                command: row.get(4)?,
+                created_at: row
+                    .get::<_, String>(5)?
+                    .parse()
+                    .unwrap_or_else(|_| Utc::now()),
+            })
+        };
+        let rows = match project_id {
+            Some(project_id) => stmt.query_map([project_id], map_row)?,
+            None => stmt.query_map([], map_row)?,
+        };
+
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| ContextError::Database(e.to_string()))
+    }
+
+    /// Add a rule after validating its glob patterns.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a pattern is invalid or the rule can't be saved
+    pub fn add(&self, rule: NewApprovalRule) -> Result<ApprovalRule, ContextError> {
+        let patterns = std::iter::once(&rule.tool)
+            .chain(rule.path.as_ref())
+            .chain(rule.command.as_ref());
+        for pattern in patterns {
+            globset::Glob::new(pattern)
+                .map_err(|e| ContextError::Config(format!("Invalid pattern '{pattern}': {e}")))?;
+        }
+
+        let id = uuid::Uuid::new_v4().to_string();
+        let now = Utc::now();
+        let db = self.get_db()?;
+        db.execute(
+            "INSERT INTO approval_rules (id, project_id, tool, path, command, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            rusqlite::params![id, rule.project_id, rule.tool, rule.path, rule.command, now.to_rfc3339()],
+        )?;
+
+        Ok(ApprovalRule {
+            id,
+            project_id: rule.project_id,
+            tool: rule.tool,
+            path: rule.path,
+            command: rule.command,
+            created_at: now,
+        })
+    }
+
+    /// Delete a rule
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no rule has this id
+    pub fn remove(&self, id: &str) -> Result<(), ContextError> {
+        let db = self.get_db()?;
+        let rows = db.execute("DELETE FROM approval_rules WHERE id = ?1", [id])?;
+        if rows == 0 {
+            return Err(ContextError::NotInContext(id.to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// A suspended tool call, as shown to the UI
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApprovalRequest {
+    pub id: String,
+    pub timestamp: String,
+    pub tool_name: String,
+    pub project_id: String,
+    pub arguments: serde_json::Value,
+    /// Client token id of the caller, if authenticated
+    pub client_id: Option<String>,
+    /// Rule that required the approval
+    pub rule_id: String,
+    /// Shell command the call would run, if any
+    pub command: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApprovalDecision {
+    Approved,
+    Denied,
+    TimedOut,
+    /// Nobody could be asked (no UI is attached)
+    NoApprover,
+}
+
+/// Outcome of an approval request, stored with the tool call event
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApprovalRecord {
+    pub decision: ApprovalDecision,
+    pub decided_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl ApprovalRecord {
+    fn new(decision: ApprovalDecision, reason: Option<String>) -> Self {
+        Self {
+            decision,
+            decided_at: Utc::now().to_rfc3339(),
+            reason,
+        }
+    }
+
+    #[must_use]
+    pub fn is_approved(&self) -> bool {
+        self.decision == ApprovalDecision::Approved
+    }
+
+    /// Error message for a call that was not approved
+    #[must_use]
+    pub fn denial_message(&self) -> String {
+        match (self.decision, &self.reason) {
+            (ApprovalDecision::TimedOut, _) => "Approval request timed out".to_string(),
+            (ApprovalDecision::NoApprover, _) => {
+                "Tool call needs an approval, but the server runs without the GUI to give it"
+                    .to_string()
+            }
+            (_, Some(reason)) => format!("Denied by user: {reason}"),
+            _ => "Denied by user".to_string(),
+        }
+    }
+}
+
+struct PendingApproval {
+    request: ApprovalRequest,
+    responder: oneshot::Sender<ApprovalRecord>,
+}
+
+/// Calls waiting for a human decision
+pub struct ApprovalQueue {
+    pending: Mutex<HashMap<String, PendingApproval>>,
+    sender: broadcast::Sender<ApprovalRequest>,
+    timeout: Duration,
+    /// Whether a UI is there to decide
+    attended: bool,
+}
+
+impl ApprovalQueue {
+    #[must_use]
+    pub fn new(timeout: Duration) -> Self {
+        let (sender, _) = broadcast::channel(100);
+        Self {
+            pending: Mutex::new(HashMap::new()),
+            sender,
+            timeout,
+            attended: true,
+        }
+    }
+
+    /// A queue with no UI to decide: every request is refused immediately
+    #[must_use]
+    pub fn unattended() -> Self {
+        Self {
+            attended: false,
+            ..Self::default()
+        }
+    }
+
+    /// Park a call until it is approved, denied or times out.
+    ///
+    /// The request is broadcast to subscribers (the UI) first. If the waiting
+    /// caller goes away, the request is dropped from the queue. An unattended
+    /// queue answers `NoApprover` without waiting.
+    pub async fn request(&self, request: ApprovalRequest) -> ApprovalRecord {
+        if !self.attended {
+            tracing::warn!(
+                tool = %request.tool_name,
+                project = %request.project_id,
+                "Refused tool call {}: it needs an approval, which requires the GUI",
+                request.id
+            );
+            return ApprovalRecord::new(ApprovalDecision::NoApprover, None);
+        }
+        let id = request.id.clone();
+        let (responder, receiver) = oneshot::channel();
+        self.lock().insert(
+            id.clone(),
+            PendingApproval {
+                request: request.clone(),
+                responder,
+            },
+        );
+        let _guard = PendingGuard { queue: self, id: &id };
+        let _ = self.sender.send(request);
+
+        match tokio::time::timeout(self.timeout, receiver).await {
+            Ok(Ok(record)) => record,
+            Ok(Err(_)) => ApprovalRecord::new(
+                ApprovalDecision::Denied,
+                Some("Approval request was cancelled".to_string()),
+            ),
+            Err(_) => ApprovalRecord::new(ApprovalDecision::TimedOut, None),
+        }
+    }
+
+    /// Approve or deny a pending call.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no call with this id is waiting
+    pub fn resolve(&self, id: &str, approved: bool, reason: Option<String>) -> Result<(), String> {
+        let pending = self
+            .lock()
+            .remove(id)
+            .ok_or_else(|| format!("No pending approval: {id}"))?;
+        let decision = if approved {
+            ApprovalDecision::Approved
+        } else {
+            ApprovalDecision::Denied
+        };
+        pending
+            .responder
+            .send(ApprovalRecord::new(decision, reason))
+            .map_err(|_| format!("Tool call is no longer waiting: {id}"))
+    }
+
+    /// Calls currently waiting, oldest first
+    #[must_use]
+    pub fn pending(&self) -> Vec<ApprovalRequest> {
+        let mut requests: Vec<_> = self.lock().values().map(|p| p.request.clone()).collect();
+        requests.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+        requests
+    }
+
+    /// Subscribe to new approval requests
+    pub fn subscribe(&self) -> broadcast::Receiver<ApprovalRequest> {
+        self.sender.subscribe()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, PendingApproval>> {
+        self.pending.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Default for ApprovalQueue {
+    fn default() -> Self {
+        Self::new(DEFAULT_APPROVAL_TIMEOUT)
+    }
+}
+
+/// Removes a request from the queue when its caller stops waiting
+struct PendingGuard<'a> {
+    queue: &'a ApprovalQueue,
+    id: &'a str,
+}
+
+impl Drop for PendingGuard<'_> {
+    fn drop(&mut self) {
+        self.queue.lock().remove(self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use tempfile::TempDir;
+
+    fn approval_request(id: &str) -> ApprovalRequest {
+        ApprovalRequest {
+            id: id.to_string(),
+            timestamp: Utc::now().to_rfc3339(),
+            tool_name: "write_file".to_string(),
+            project_id: "default".to_string(),
+            arguments: serde_json::json!({ "path": "a.txt" }),
+            client_id: None,
+            rule_id: "rule".to_string(),
+            command: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn request_waits_for_resolution() {
+        let queue = Arc::new(ApprovalQueue::default());
+        let mut rx = queue.subscribe();
+
+        let waiter = {
+            let queue = queue.clone();
+            tokio::spawn(async move { queue.request(approval_request("a1")).await })
+        };
+        let announced = rx.recv().await.unwrap();
+        assert_eq!(announced.id, "a1");
+        assert_eq!(queue.pending().len(), 1);
+
+        queue.resolve("a1", false, Some("not now".to_string())).unwrap();
+        let record = waiter.await.unwrap();
+        assert_eq!(record.decision, ApprovalDecision::Denied);
+        assert_eq!(record.denial_message(), "Denied by user: not now");
+        assert!(queue.pending().is_empty());
+        assert!(queue.resolve("a1", true, None).is_err());
+    }
+
+    #[tokio::test]
+    async fn request_times_out() {
+        let queue = ApprovalQueue::new(Duration::from_millis(20));
+        let record = queue.request(approval_request("a2")).await;
+        assert_eq!(record.decision, ApprovalDecision::TimedOut);
+        assert!(!record.is_approved());
+        assert!(queue.pending().is_empty());
+    }
+
+    #[tokio::test]
+    async fn unattended_queue_refuses_at_once() {
+        let queue = ApprovalQueue::unattended();
+        let record = tokio::time::timeout(Duration::from_secs(1), queue.request(approval_request("a3")))
+            .await
+            .unwrap();
+        assert_eq!(record.decision, ApprovalDecision::NoApprover);
+        assert!(record.denial_message().contains("without the GUI"));
+        assert!(queue.pending().is_empty());
+    }
+
+    #[test]
+    fn rule_matches_tool_path_and_command() {
+        let rule = ApprovalRule {
+            id: "r".to_string(),
+            project_id: None,
+            tool: "build_*".to_string(),
+            path: None,
+            command: Some("npm publish*".to_string()),
+            created_at: Utc::now(),
+        };
+        let mut request = PolicyRequest {
+            tool_name: "build_run_command".to_string(),
+            paths: Vec::new(),
+            command: Some("npm publish --tag next".to_string()),
+            ..PolicyRequest::default()
+        };
+        assert!(rule.matches(&request));
+        request.command = Some("npm test".to_string());
+        assert!(!rule.matches(&request));
+
+        let rule = ApprovalRule {
+            tool: "write_file".to_string(),
+            path: Some("src/**".to_string()),
+            command: None,
+            ..rule
+        };
+        let request = PolicyRequest {
+            tool_name: "write_file".to_string(),
+            paths: vec!["src/main.rs".to_string()],
+            command: None,
+            ..PolicyRequest::default()
+        };
+        assert!(rule.matches(&request));
+    }
+
+    #[tokio::test]
+    async fn rule_store_scopes_by_project() {
+        let temp = TempDir::new().unwrap();
+        let db_path = temp.path().join("registry.db");
+        let store = ApprovalRuleStore::new(db_path.to_str().unwrap()).unwrap();
+
+        store
+            .add(NewApprovalRule {
+                tool: "write_file".to_string(),
+                ..NewApprovalRule::default()
+            })
+            .unwrap();
+        let scoped = store
+            .add(NewApprovalRule {
+                project_id: Some("p1".to_string()),
+                tool: "build_run_command".to_string(),
+                ..NewApprovalRule::default()
+            })
+            .unwrap();
+
+        assert_eq!(store.for_project("p1").unwrap().len(), 2);
+        assert_eq!(store.for_project("p2").unwrap().len(), 1);
+        store.remove(&scoped.id).unwrap();
+        assert_eq!(store.list().unwrap().len(), 1);
+
+        let invalid = store.add(NewApprovalRule {
+            tool: "[".to_string(),
+            ..NewApprovalRule::default()
+        });
+        assert!(invalid.is_err());
+    }
+}
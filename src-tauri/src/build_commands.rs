@@ -210,7 +210,7 @@ fn clear_default(db: &rusqlite::Connection) -> Result<(), ContextError> {
     Ok(())
 }
 
-fn ensure_column(
+pub(crate) fn ensure_column(
     db: &rusqlite::Connection,
     table: &str,
     column: &str,
@@ -0,0 +1,258 @@
+//! Bearer tokens that authenticate AI clients against the HTTP/MCP server.
+//!
+//! Tokens live in the global registry DB. Only a SHA-256 hash is stored; the
+//! plaintext is returned once, when the token is created.
+
+use crate::error::ContextError;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Prefix that makes tokens easy to recognize in config files
+const TOKEN_PREFIX: &str = "aih_";
+
+/// A registered client (never includes the token itself)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClientToken {
+    pub id: String,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+/// A freshly created token, the only time the plaintext is available
+#[derive(Debug, Clone, Serialize)]
+pub struct NewClientToken {
+    #[serde(flatten)]
+    pub client: ClientToken,
+    pub token: String,
+}
+
+pub struct ClientTokenStore {
+    db_path: String,
+}
+
+impl ClientTokenStore {
+    /// Open the store, creating its table if needed
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database can't be opened
+    pub fn new(db_path: &str) -> Result<Self, ContextError> {
+        let store = Self {
+            db_path: db_path.to_string(),
+        };
+        store.init_schema()?;
+        Ok(store)
+    }
+
+    fn get_db(&self) -> Result<rusqlite::Connection, ContextError> {
+        Ok(rusqlite::Connection::open(&self.db_path)?)
+    }
+
+    fn init_schema(&self) -> Result<(), ContextError> {
+        let db = self.get_db()?;
+        db.execute(
+            "CREATE TABLE IF NOT EXISTS client_tokens (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                token_hash TEXT UNIQUE NOT NULL,
+                created_at TEXT NOT NULL,
+                last_used_at TEXT
+            )",
+            [],
+        )?;
+        Ok(())
+    }
+
+    /// Issue a new token for the client called `name`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the name is empty or the token can't be saved
+    pub fn create(&self, name: &str) -> Result<NewClientToken, ContextError> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(ContextError::Config("Client name cannot be empty".to_string()));
+        }
+
+        let token = format!(
+            "{}{}{}",
+            TOKEN_PREFIX,
+            uuid::Uuid::new_v4().simple(),
+            uuid::Uuid::new_v4().simple()
+        );
+        let id = uuid::Uuid::new_v4().to_string();
+        let now = Utc::now();
+
+        let db = self.get_db()?;
+        db.execute(
+            "INSERT INTO client_tokens (id, name, token_hash, created_at) VALUES (?1, ?2, ?3, ?4)",
+            [&id, name, &hash_token(&token), &now.to_rfc3339()],
+        )?;
+
+        Ok(NewClientToken {
+            client: ClientToken {
+                id,
+                name: name.to_string(),
+                created_at: now,
+                last_used_at: None,
+            },
+            token,
+        })
+    }
+
+    /// All clients, including revoked ones
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database query fails
+    pub fn list(&self) -> Result<Vec<ClientToken>, ContextError> {
+        let db = self.get_db()?;
+        let mut stmt = db.prepare(
+            "SELECT id, name, created_at, last_used_at FROM client_tokens ORDER BY created_at ASC",
+        )?;
+
+        let rows = stmt.query_map([], |row| {
+            Ok(ClientToken {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                created_at: row
+                    .get::<_, String>(2)?
+                    .parse()
+                    .unwrap_or_else(|_| Utc::now()),
+                last_used_at: row
+                    .get::<_, Option<String>>(3)?
+                    .and_then(|s| s.parse().ok()),
+            })
+        })?;
+
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| ContextError::Database(e.to_string()))
+    }
+
+    /// Revoke a client's token
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no client has this id
+    pub fn revoke(&self, id: &str) -> Result<(), ContextError> {
+        let db = self.get_db()?;
+        let rows = db.execute("DELETE FROM client_tokens WHERE id = ?1", [id])?;
+        if rows == 0 {
+            return Err(ContextError::NotInContext(id.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Look up the client owning `token`, recording the use.
+    ///
+    /// Returns `None` for unknown or revoked tokens.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database query fails
+    pub fn verify(&self, token: &str) -> Result<Option<ClientToken>, ContextError> {
+        let db = self.get_db()?;
+        let hash = hash_token(token);
+        let now = Utc::now();
+        let updated = db.execute(
+            "UPDATE client_tokens SET last_used_at = ?1 WHERE token_hash = ?2",
+            [&now.to_rfc3339(), &hash],
+        )?;
+        if updated == 0 {
+            return Ok(None);
+        }
+
+        let client = db.query_row(
+            "SELECT id, name, created_at FROM client_tokens WHERE token_hash = ?1",
+            [&hash],
+            |row| {
+                Ok(ClientToken {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    created_at: row
+                        .get::<_, String>(2)?
+                        .parse()
+                        .unwrap_or_else(|_| Utc::now()),
+                    last_used_at: Some(now),
+                })
+            },
+        )?;
+        Ok(Some(client))
+    }
+}
+
+/// Hex-encoded SHA-256 of a token
+fn hash_token(token: &str) -> String {
+    use std::fmt::Write;
+
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .fold(String::with_capacity(64), |mut hex, b| {
+            let _ = write!(hex, "{b:02x}");
+            hex
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn create_store(temp: &TempDir) -> ClientTokenStore {
+        let db_path = temp.path().join("registry.db");
+        ClientTokenStore::new(db_path.to_str().unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn create_then_verify_returns_client() {
+        let temp = TempDir::new().unwrap();
+        let store = create_store(&temp);
+
+        let created = store.create("claude").unwrap();
+        assert!(created.token.starts_with(TOKEN_PREFIX));
+
+        let client = store.verify(&created.token).unwrap().unwrap();
+        assert_eq!(client.id, created.client.id);
+        assert_eq!(client.name, "claude");
+
+        let listed = store.list().unwrap();
+        assert_eq!(listed.len(), 1);
+        assert!(listed[0].last_used_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn tokens_are_stored_hashed() {
+        let temp = TempDir::new().unwrap();
+        let store = create_store(&temp);
+        let created = store.create("kimi").unwrap();
+
+        let db = store.get_db().unwrap();
+        let stored: String = db
+            .query_row("SELECT token_hash FROM client_tokens", [], |row| row.get(0))
+            .unwrap();
+        assert_ne!(stored, created.token);
+        assert_eq!(stored, hash_token(&created.token));
+    }
+
+    #[tokio::test]
+    async fn revoked_and_unknown_tokens_do_not_verify() {
+        let temp = TempDir::new().unwrap();
+        let store = create_store(&temp);
+        let created = store.create("gemini").unwrap();
+
+        assert!(store.verify("aih_bogus").unwrap().is_none());
+
+        store.revoke(&created.client.id).unwrap();
+        assert!(store.verify(&created.token).unwrap().is_none());
+        assert!(store.revoke(&created.client.id).is_err());
+    }
+
+    #[tokio::test]
+    async fn create_rejects_empty_name() {
+        let temp = TempDir::new().unwrap();
+        let store = create_store(&temp);
+        assert!(store.create("  ").is_err());
+    }
+}
@@ -0,0 +1,96 @@
+//! Runtime discovery file
+//!
+//! While the HTTP server runs, `server.json` in the app data directory records
+//! its port and process id, so the stdio proxy can find the server without
+//! being told `AIH_PORT`. The file is removed when the server stops; a stale
+//! file (left by a crash) is harmless because the proxy health-checks the port.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// File name inside the app data directory
+const DISCOVERY_FILE: &str = "server.json";
+
+/// Bundle identifier; Tauri's app data directory is named after it
+const APP_IDENTIFIER: &str = "com.aiharness";
+
+/// A running HTTP server
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServerInfo {
+    pub port: u16,
+    pub pid: u32,
+    pub started_at: DateTime<Utc>,
+}
+
+/// The app data directory: `AIH_DATA_DIR`, else the platform data directory
+/// the app uses (e.g. `~/.local/share/com.aiharness`).
+#[must_use]
+pub fn default_data_dir() -> Option<PathBuf> {
+    match std::env::var("AIH_DATA_DIR") {
+        Ok(dir) if !dir.is_empty() => Some(PathBuf::from(dir)),
+        _ => dirs::data_dir().map(|dir| dir.join(APP_IDENTIFIER)),
+    }
+}
+
+#[must_use]
+pub fn discovery_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(DISCOVERY_FILE)
+}
+
+/// Record that this process serves on `port`.
+///
+/// # Errors
+///
+/// Returns an error if the file can't be written
+pub fn write(data_dir: &Path, port: u16) -> std::io::Result<()> {
+    let info = ServerInfo {
+        port,
+        pid: std::process::id(),
+        started_at: Utc::now(),
+    };
+    std::fs::create_dir_all(data_dir)?;
+    let json = serde_json::to_string_pretty(&info)?;
+    std::fs::write(discovery_path(data_dir), json)
+}
+
+/// The server recorded in `data_dir`, if any.
+#[must_use]
+pub fn read(data_dir: &Path) -> Option<ServerInfo> {
+    let json = std::fs::read_to_string(discovery_path(data_dir)).ok()?;
+    serde_json::from_str(&json).ok()
+}
+
+/// Remove the discovery file if this process wrote it.
+pub fn remove(data_dir: &Path) {
+    if read(data_dir).is_some_and(|info| info.pid == std::process::id()) {
+        let _ = std::fs::remove_file(discovery_path(data_dir));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn discovery_file_round_trips_and_is_removed_by_its_writer() {
+        let temp_dir = TempDir::new().unwrap();
+        let data_dir = temp_dir.path().join("data");
+        assert!(read(&data_dir).is_none());
+
+        write(&data_dir, 9123).unwrap();
+        let info = read(&data_dir).unwrap();
+        assert_eq!(info.port, 9123);
+        assert_eq!(info.pid, std::process::id());
+
+        remove(&data_dir);
+        assert!(read(&data_dir).is_none());
+
+        // Another process's file is left alone
+        let other = ServerInfo { pid: info.pid + 1, ..info };
+        std::fs::write(discovery_path(&data_dir), serde_json::to_string(&other).unwrap()).unwrap();
+        remove(&data_dir);
+        assert_eq!(read(&data_dir), Some(other));
+    }
+}
@@ -22,7 +22,23 @@ pub enum ToolError {
     /// Execution timeout
     Timeout { tool: String, duration_ms: u64 },
     /// Binary file (cannot read as text)
-    BinaryFile(String),
+    BinaryFile { path: String, mime: String },
+    /// Path resolves outside the project root and its allowed roots
+    PathOutsideProject { path: String, root: String },
+    /// An edit could not be applied (missing or ambiguous anchor, stale diff)
+    EditFailed(String),
+    /// A client tool policy denied the call
+    PolicyDenied(String),
+    /// A project-scoped tool was called without a project context
+    ProjectRequired(String),
+    /// A project store operation failed
+    Project(ContextError),
+    /// A shell command could not be run or exited unsuccessfully
+    CommandFailed(String),
+    /// The caller cancelled the call
+    Cancelled,
+    /// An upstream MCP server failed or reported an error
+    Upstream { server: String, message: String },
 }
 
 impl fmt::Display for ToolError {
@@ -40,7 +56,21 @@ impl fmt::Display for ToolError {
             Self::Timeout { tool, duration_ms } => {
                 write!(f, "Tool '{}' timed out after {}ms", tool, duration_ms)
             }
-            Self::BinaryFile(p) => write!(f, "Binary file cannot be read as text: {}", p),
+            Self::BinaryFile { path, mime } => {
+                write!(f, "Binary file cannot be read as text: {path} ({mime})")
+            }
+            Self::PathOutsideProject { path, root } => {
+                write!(f, "Path is outside the project root: {path} (root: {root})")
+            }
+            Self::EditFailed(e) => write!(f, "Edit failed: {e}"),
+            Self::PolicyDenied(reason) => write!(f, "Denied by policy: {reason}"),
+            Self::ProjectRequired(t) => write!(f, "Tool '{t}' requires a project context"),
+            Self::Project(e) => write!(f, "{e}"),
+            Self::CommandFailed(e) => write!(f, "{e}"),
+            Self::Cancelled => write!(f, "Cancelled"),
+            Self::Upstream { server, message } => {
+                write!(f, "Upstream server '{server}': {message}")
+            }
         }
     }
 }
@@ -57,6 +87,12 @@ impl From<std::io::Error> for ToolError {
     }
 }
 
+impl From<ContextError> for ToolError {
+    fn from(e: ContextError) -> Self {
+        Self::Project(e)
+    }
+}
+
 /// Errors that can occur in context management
 #[derive(Debug, Clone, PartialEq)]
 pub enum ContextError {
@@ -109,6 +145,18 @@ mod tests {
         assert_eq!(err.to_string(), "Permission denied: /root/file");
     }
 
+    #[test]
+    fn tool_error_display_policy_denied() {
+        let err = ToolError::PolicyDenied("'write_file' is not allowed".to_string());
+        assert_eq!(err.to_string(), "Denied by policy: 'write_file' is not allowed");
+    }
+
+    #[test]
+    fn tool_error_from_context_error_keeps_message() {
+        let err: ToolError = ContextError::NotInContext("abc".to_string()).into();
+        assert_eq!(err.to_string(), "File not in context: abc");
+    }
+
     #[test]
     fn tool_error_display_file_too_large() {
         let err = ToolError::FileTooLarge {
@@ -134,6 +182,33 @@ mod tests {
         assert!(matches!(tool_err, ToolError::PermissionDenied(_)));
     }
 
+    #[test]
+    fn tool_error_display_path_outside_project() {
+        let err = ToolError::PathOutsideProject {
+            path: "/etc/passwd".to_string(),
+            root: "/home/me/project".to_string(),
+        };
+        assert_eq!(
+            err.to_string(),
+            "Path is outside the project root: /etc/passwd (root: /home/me/project)"
+        );
+    }
+
+    #[test]
+    fn tool_error_display_edit_failed() {
+        let err = ToolError::EditFailed("edit #1: search text not found in a.rs".to_string());
+        assert_eq!(err.to_string(), "Edit failed: edit #1: search text not found in a.rs");
+    }
+
+    #[test]
+    fn tool_error_display_binary_file_includes_mime() {
+        let err = ToolError::BinaryFile {
+            path: "logo.png".to_string(),
+            mime: "image/png".to_string(),
+        };
+        assert_eq!(err.to_string(), "Binary file cannot be read as text: logo.png (image/png)");
+    }
+
     // ContextError tests
     #[test]
     fn context_error_display_database() {
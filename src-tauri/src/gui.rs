@@ -0,0 +1,1834 @@
+//! Tauri desktop app
+//!
+//! The commands the frontend invokes and the window's startup (app state,
+//! event forwarding, HTTP server auto-start). Compiled with the `gui` feature,
+//! which is on by default.
+
+use crate::{
+    approvals, debug_log, http_server, mcp_config, processes, prompts, terminals,
+    tool_dispatch, tool_history, tool_policies, tools, upstream_servers, AllowedRootInfo,
+    AppState, BuildCommandInfo, ClientTokenInfo, ContextFileInfo, ContextNoteInfo,
+    DirectoryEntryInfo, DirectoryListingInfo, ProjectInfo, RawLogEvent, SecurityEvent,
+    ServerStatus, TodoItemInfo, ToolCallEvent,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+use tauri::{Emitter, Manager};
+use tokio::sync::RwLock;
+
+#[tauri::command]
+fn debug_log_cmd(msg: String) {
+    debug_log(&msg);
+}
+
+/// Common arguments struct for project-scoped commands
+#[derive(Debug, Deserialize)]
+struct ProjectArgs {
+    #[serde(default)]
+    project_id: Option<String>,
+}
+
+/// Start the HTTP server
+#[tauri::command]
+async fn start_server(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+    app_handle: tauri::AppHandle,
+) -> Result<ServerStatus, String> {
+    // Check if already running
+    {
+        let state_read = state.read().await;
+        if state_read.is_server_running().await {
+            let port = state_read.get_port().await;
+            return Ok(ServerStatus { running: true, port });
+        }
+    }
+    
+    // Get port
+    let port = {
+        let state_read = state.read().await;
+        state_read.get_port().await
+    };
+    
+    // Clone the Arc for the server
+    let server_state = Arc::clone(&state);
+    
+    // Start HTTP server
+    let handle = http_server::start_http_server(server_state, port)
+        .await
+        .map_err(|e| format!("Failed to start server: {e}"))?;
+    
+    // Store the handle
+    {
+    let state_write = state.write().await;
+    state_write.set_server_handle(handle).await;
+    }
+    
+    // Emit startup event
+    let startup_event = RawLogEvent {
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        source: "server".to_string(),
+        message: format!("HTTP server started on port {port}"),
+    };
+    app_handle.emit("raw-log", &startup_event).ok();
+    
+    tracing::info!("HTTP server started on port {port}");
+    
+    Ok(ServerStatus { running: true, port })
+}
+
+/// Stop the HTTP server
+#[tauri::command]
+async fn stop_server(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+) -> Result<ServerStatus, String> {
+    let state = state.write().await;
+    state.stop_server().await;
+    Ok(ServerStatus { running: false, port: 0 })
+}
+
+/// Get server status
+#[tauri::command]
+async fn get_server_status(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+) -> Result<ServerStatus, String> {
+    let state = state.read().await;
+    let running = state.is_server_running().await;
+    let port = state.get_port().await;
+    Ok(ServerStatus { running, port })
+}
+
+/// Execute a tool directly
+#[tauri::command]
+async fn execute_tool(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+    app_handle: tauri::AppHandle,
+    tool_name: String,
+    arguments: serde_json::Value,
+    project_id: Option<String>,
+) -> Result<String, String> {
+    let call = tool_dispatch::ToolCall {
+        tool_name,
+        arguments,
+        project_id: project_id.unwrap_or_else(|| "default".to_string()),
+        caller: tools::Caller::App,
+        progress: tools::ProgressReporter::default(),
+        cancel: tokio_util::sync::CancellationToken::new(),
+    };
+    let outcome = tool_dispatch::dispatch(&state, call).await;
+    
+    // Also emit raw log event
+    let raw_event = RawLogEvent {
+        timestamp: outcome.event.timestamp.clone(),
+        source: "tool".to_string(),
+        message: serde_json::json!({
+            "event": "tool_call_end",
+            "id": outcome.event.id,
+            "tool_name": outcome.event.tool_name,
+            "success": outcome.event.success,
+            "duration_ms": outcome.event.duration_ms
+        }).to_string(),
+    };
+    app_handle.emit("raw-log", &raw_event).ok();
+    
+    outcome.result.map(|output| output.content)
+}
+
+/// Get event history (most recent page of the project's persisted history)
+#[tauri::command]
+async fn get_event_history(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+    project_id: Option<String>,
+) -> Result<Vec<ToolCallEvent>, String> {
+    let state = state.read().await;
+    let project_id = project_id.unwrap_or_else(|| "default".to_string());
+    state
+        .query_events(&project_id, &tool_history::ToolCallQuery::default())
+        .await
+        .map(|page| page.events)
+        .map_err(|e| e.to_string())
+}
+
+/// Query persisted event history with filters and pagination
+#[tauri::command]
+async fn query_event_history(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+    project_id: Option<String>,
+    query: Option<tool_history::ToolCallQuery>,
+) -> Result<tool_history::ToolCallPage, String> {
+    let state = state.read().await;
+    let project_id = project_id.unwrap_or_else(|| "default".to_string());
+    state
+        .query_events(&project_id, &query.unwrap_or_default())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Get security event history
+#[tauri::command]
+async fn get_security_events(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+    project_id: Option<String>,
+) -> Result<Vec<SecurityEvent>, String> {
+    let state = state.read().await;
+    let events = state.get_security_events().await;
+    let project_id = project_id.unwrap_or_else(|| "default".to_string());
+    Ok(events
+        .into_iter()
+        .filter(|event| event.project_id == project_id)
+        .collect())
+}
+
+#[tauri::command]
+async fn list_projects(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+) -> Result<Vec<ProjectInfo>, String> {
+    let state = state.read().await;
+    let projects = state
+        .project_registry
+        .list_projects()
+        .await
+        .map_err(|e| e.to_string())?;
+    
+    // Debug: Log all projects and their db_paths
+    for p in &projects {
+        tracing::info!(
+            "Project: id={}, name={}, root_path={}, db_path={}",
+            p.id, p.name, p.root_path, p.db_path
+        );
+    }
+    
+    Ok(projects
+        .into_iter()
+        .map(|p| ProjectInfo {
+            id: p.id,
+            name: p.name,
+            root_path: p.root_path,
+            db_path: p.db_path,
+            created_at: p.created_at.to_rfc3339(),
+            updated_at: p.updated_at.to_rfc3339(),
+        })
+        .collect())
+}
+
+#[tauri::command]
+async fn create_project(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+    name: String,
+    root_path: String,
+) -> Result<ProjectInfo, String> {
+    let state = state.read().await;
+    let project = state
+        .project_registry
+        .create_project(&name, &root_path)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(ProjectInfo {
+        id: project.id,
+        name: project.name,
+        root_path: project.root_path,
+        db_path: project.db_path,
+        created_at: project.created_at.to_rfc3339(),
+        updated_at: project.updated_at.to_rfc3339(),
+    })
+}
+
+#[tauri::command]
+async fn list_todos(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+    args: ProjectArgs,
+) -> Result<Vec<TodoItemInfo>, String> {
+    let project_id = args.project_id.unwrap_or_else(|| "default".to_string());
+    debug_log(&format!("=== list_todos START === project_id={project_id}"));
+    
+    let store = {
+        let state_read = state.read().await;
+        state_read
+            .get_project_store(&project_id)
+            .await
+            .map_err(|e| e.to_string())?
+    };
+    
+    debug_log(&format!(
+        "list_todos: GOT STORE project_id={} db_path={}",
+        project_id, store.info.db_path
+    ));
+    
+    let todos = store
+        .todo_store
+        .read()
+        .await
+        .list()
+        .await
+        .map_err(|e| e.to_string())?;
+    
+    // Log each todo
+    for todo in &todos {
+        debug_log(&format!(
+            "list_todos: project_id={} todo_id={} title={}",
+            project_id, todo.id, todo.title
+        ));
+    }
+    
+    debug_log(&format!(
+        "=== list_todos END === project_id={} count={}",
+        project_id, todos.len()
+    ));
+    
+    let items = todos.into_iter().map(todo_info_from).collect();
+    Ok(items)
+}
+
+#[derive(Debug, Deserialize)]
+struct AddTodoArgs {
+    title: String,
+    description: Option<String>,
+    position: Option<i64>,
+    #[serde(default)]
+    project_id: Option<String>,
+}
+
+#[tauri::command]
+async fn add_todo(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+    args: AddTodoArgs,
+) -> Result<TodoItemInfo, String> {
+    let project_id = args.project_id.unwrap_or_else(|| "default".to_string());
+    let title = args.title;
+    let description = args.description;
+    let position = args.position;
+    let store = {
+        let state_read = state.read().await;
+        state_read
+            .get_project_store(&project_id)
+            .await
+            .map_err(|e| e.to_string())?
+    };
+    
+    // Debug logging
+    tracing::info!(
+        "add_todo: project_id={}, db_path={}, title={}",
+        project_id, store.info.db_path, title
+    );
+    
+    let todo = store
+        .todo_store
+        .read()
+        .await
+        .add(&title, description, position)
+        .await
+        .map_err(|e| e.to_string())?;
+    let info = todo_info_from(todo);
+    Ok(info)
+}
+
+#[derive(Debug, Deserialize)]
+struct SetTodoCompletedArgs {
+    id: String,
+    completed: bool,
+    #[serde(default)]
+    project_id: Option<String>,
+}
+
+#[tauri::command]
+async fn set_todo_completed(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+    args: SetTodoCompletedArgs,
+) -> Result<(), String> {
+    let project_id = args.project_id.unwrap_or_else(|| "default".to_string());
+    let id = args.id;
+    let completed = args.completed;
+    let store = {
+        let state_read = state.read().await;
+        state_read
+            .get_project_store(&project_id)
+            .await
+            .map_err(|e| e.to_string())?
+    };
+    let result = store
+        .todo_store
+        .read()
+        .await
+        .set_completed(&id, completed)
+        .await
+        .map_err(|e| e.to_string());
+    result
+}
+
+#[derive(Debug, Deserialize)]
+struct RemoveTodoArgs {
+    id: String,
+    #[serde(default)]
+    project_id: Option<String>,
+}
+
+#[tauri::command]
+async fn remove_todo(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+    args: RemoveTodoArgs,
+) -> Result<(), String> {
+    let project_id = args.project_id.unwrap_or_else(|| "default".to_string());
+    let id = args.id;
+    let store = {
+        let state_read = state.read().await;
+        state_read
+            .get_project_store(&project_id)
+            .await
+            .map_err(|e| e.to_string())?
+    };
+    let result = store
+        .todo_store
+        .read()
+        .await
+        .remove(&id)
+        .await
+        .map_err(|e| e.to_string());
+    result
+}
+
+#[tauri::command]
+async fn move_todo(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+    id: String,
+    position: i64,
+    project_id: Option<String>,
+) -> Result<(), String> {
+    let project_id = project_id.unwrap_or_else(|| "default".to_string());
+    let store = {
+        let state_read = state.read().await;
+        state_read
+            .get_project_store(&project_id)
+            .await
+            .map_err(|e| e.to_string())?
+    };
+    let result = store
+        .todo_store
+        .read()
+        .await
+        .move_to(&id, position)
+        .await
+        .map_err(|e| e.to_string());
+    result
+}
+
+#[tauri::command]
+async fn get_next_todo(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+    project_id: Option<String>,
+) -> Result<Option<TodoItemInfo>, String> {
+    let project_id = project_id.unwrap_or_else(|| "default".to_string());
+    let store = {
+        let state_read = state.read().await;
+        state_read
+            .get_project_store(&project_id)
+            .await
+            .map_err(|e| e.to_string())?
+    };
+    let todo = store
+        .todo_store
+        .read()
+        .await
+        .get_next()
+        .await
+        .map_err(|e| e.to_string())?;
+    let info = todo.map(todo_info_from);
+    Ok(info)
+}
+
+fn todo_info_from(todo: crate::todos::TodoItem) -> TodoItemInfo {
+    TodoItemInfo {
+        id: todo.id,
+        title: todo.title,
+        description: todo.description,
+        completed: todo.completed,
+        position: todo.position,
+        created_at: todo.created_at.to_rfc3339(),
+        updated_at: todo.updated_at.to_rfc3339(),
+    }
+}
+
+/// Add context file
+#[tauri::command]
+async fn add_context_file(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+    path: String,
+    project_id: Option<String>,
+) -> Result<ContextFileInfo, String> {
+    let project_id = project_id.unwrap_or_else(|| "default".to_string());
+    let store = {
+        let state_read = state.read().await;
+        state_read
+            .get_project_store(&project_id)
+            .await
+            .map_err(|e| e.to_string())?
+    };
+    let store = store.context_store.read().await;
+    let file = store.add_file(&path).await.map_err(|e| e.to_string())?;
+    
+    let name = std::path::Path::new(&file.path)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("unknown")
+        .to_string();
+    
+    Ok(ContextFileInfo {
+        id: file.id,
+        path: file.path,
+        name,
+        added_at: file.added_at.to_rfc3339(),
+        last_read_at: file.last_read_at.map(|d| d.to_rfc3339()),
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct RemoveContextFileArgs {
+    path: String,
+    #[serde(default)]
+    project_id: Option<String>,
+}
+
+/// Remove context file
+#[tauri::command]
+async fn remove_context_file(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+    args: RemoveContextFileArgs,
+) -> Result<(), String> {
+    let project_id = args.project_id.unwrap_or_else(|| "default".to_string());
+    let store = {
+        let state_read = state.read().await;
+        state_read
+            .get_project_store(&project_id)
+            .await
+            .map_err(|e| e.to_string())?
+    };
+    let store = store.context_store.read().await;
+    store.remove_file(&args.path).await.map_err(|e| e.to_string())
+}
+
+/// List context files
+#[tauri::command]
+async fn list_context_files(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+    project_id: Option<String>,
+) -> Result<Vec<ContextFileInfo>, String> {
+    let project_id = project_id.unwrap_or_else(|| "default".to_string());
+    let store = {
+        let state_read = state.read().await;
+        state_read
+            .get_project_store(&project_id)
+            .await
+            .map_err(|e| e.to_string())?
+    };
+    let store = store.context_store.read().await;
+    let files = store.list_files().await.map_err(|e| e.to_string())?;
+    
+    let infos: Vec<ContextFileInfo> = files
+        .into_iter()
+        .map(|file| {
+            let name = std::path::Path::new(&file.path)
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or("unknown")
+                .to_string();
+            
+            ContextFileInfo {
+                id: file.id,
+                path: file.path,
+                name,
+                added_at: file.added_at.to_rfc3339(),
+                last_read_at: file.last_read_at.map(|d| d.to_rfc3339()),
+            }
+        })
+        .collect();
+    
+    Ok(infos)
+}
+
+/// List context notes
+#[tauri::command]
+async fn list_context_notes(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+    project_id: Option<String>,
+) -> Result<Vec<ContextNoteInfo>, String> {
+    let project_id = project_id.unwrap_or_else(|| "default".to_string());
+    let store = {
+        let state_read = state.read().await;
+        state_read
+            .get_project_store(&project_id)
+            .await
+            .map_err(|e| e.to_string())?
+    };
+    let store = store.context_note_store.read().await;
+    let notes = store.list().await.map_err(|e| e.to_string())?;
+    Ok(notes.into_iter().map(context_note_info_from).collect())
+}
+
+/// Add context note
+#[tauri::command]
+async fn add_context_note(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+    project_id: Option<String>,
+    content: String,
+    position: Option<i64>,
+) -> Result<ContextNoteInfo, String> {
+    let project_id = project_id.unwrap_or_else(|| "default".to_string());
+    let store = {
+        let state_read = state.read().await;
+        state_read
+            .get_project_store(&project_id)
+            .await
+            .map_err(|e| e.to_string())?
+    };
+    let store = store.context_note_store.read().await;
+    let note = store
+        .add(&content, position)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(context_note_info_from(note))
+}
+
+#[derive(Debug, Deserialize)]
+struct UpdateContextNoteArgs {
+    #[serde(default)]
+    project_id: Option<String>,
+    id: String,
+    content: String,
+}
+
+/// Update context note
+#[tauri::command]
+async fn update_context_note(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+    args: UpdateContextNoteArgs,
+) -> Result<(), String> {
+    let project_id = args.project_id.unwrap_or_else(|| "default".to_string());
+    let store = {
+        let state_read = state.read().await;
+        state_read
+            .get_project_store(&project_id)
+            .await
+            .map_err(|e| e.to_string())?
+    };
+    let store = store.context_note_store.read().await;
+    store.update(&args.id, &args.content).await.map_err(|e| e.to_string())
+}
+
+#[derive(Debug, Deserialize)]
+struct RemoveContextNoteArgs {
+    #[serde(default)]
+    project_id: Option<String>,
+    id: String,
+}
+
+/// Remove context note
+#[tauri::command]
+async fn remove_context_note(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+    args: RemoveContextNoteArgs,
+) -> Result<(), String> {
+    let project_id = args.project_id.unwrap_or_else(|| "default".to_string());
+    let store = {
+        let state_read = state.read().await;
+        state_read
+            .get_project_store(&project_id)
+            .await
+            .map_err(|e| e.to_string())?
+    };
+    let store = store.context_note_store.read().await;
+    store.remove(&args.id).await.map_err(|e| e.to_string())
+}
+
+/// Move context note
+#[derive(Debug, Deserialize)]
+struct MoveContextNoteArgs {
+    #[serde(default)]
+    project_id: Option<String>,
+    id: String,
+    position: i64,
+}
+
+#[tauri::command]
+async fn move_context_note(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+    args: MoveContextNoteArgs,
+) -> Result<(), String> {
+    let project_id = args.project_id.unwrap_or_else(|| "default".to_string());
+    let store = {
+        let state_read = state.read().await;
+        state_read
+            .get_project_store(&project_id)
+            .await
+            .map_err(|e| e.to_string())?
+    };
+    let store = store.context_note_store.read().await;
+    store.move_to(&args.id, args.position).await.map_err(|e| e.to_string())
+}
+
+fn context_note_info_from(note: crate::context_notes::ContextNote) -> ContextNoteInfo {
+    ContextNoteInfo {
+        id: note.id,
+        content: note.content,
+        position: note.position,
+        created_at: note.created_at.to_rfc3339(),
+        updated_at: note.updated_at.to_rfc3339(),
+    }
+}
+
+/// List a project's prompts, or the global prompt library
+#[tauri::command]
+async fn list_prompts(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+    project_id: Option<String>,
+    global: Option<bool>,
+) -> Result<Vec<prompts::Prompt>, String> {
+    let state = state.read().await;
+    if global.unwrap_or(false) {
+        return state.prompts.list().map_err(|e| e.to_string());
+    }
+    let project_id = project_id.unwrap_or_else(|| "default".to_string());
+    let store = state
+        .get_project_store(&project_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    let store = store.prompt_store.read().await;
+    store.list().map_err(|e| e.to_string())
+}
+
+#[derive(Debug, Deserialize)]
+struct AddPromptArgs {
+    #[serde(default)]
+    project_id: Option<String>,
+    /// Add to the global library instead of the project's
+    #[serde(default)]
+    global: bool,
+    prompt: prompts::NewPrompt,
+}
+
+/// Add a prompt to a project's library or the global one
+#[tauri::command]
+async fn add_prompt(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+    args: AddPromptArgs,
+) -> Result<prompts::Prompt, String> {
+    let state = state.read().await;
+    if args.global {
+        return state.prompts.add(args.prompt).map_err(|e| e.to_string());
+    }
+    let project_id = args.project_id.unwrap_or_else(|| "default".to_string());
+    let store = state
+        .get_project_store(&project_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    let store = store.prompt_store.read().await;
+    store.add(args.prompt).map_err(|e| e.to_string())
+}
+
+#[derive(Debug, Deserialize)]
+struct UpdatePromptArgs {
+    #[serde(default)]
+    project_id: Option<String>,
+    #[serde(default)]
+    global: bool,
+    id: String,
+    prompt: prompts::NewPrompt,
+}
+
+/// Replace a prompt's name, description, arguments and template
+#[tauri::command]
+async fn update_prompt(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+    args: UpdatePromptArgs,
+) -> Result<(), String> {
+    let state = state.read().await;
+    if args.global {
+        return state
+            .prompts
+            .update(&args.id, &args.prompt)
+            .map_err(|e| e.to_string());
+    }
+    let project_id = args.project_id.unwrap_or_else(|| "default".to_string());
+    let store = state
+        .get_project_store(&project_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    let store = store.prompt_store.read().await;
+    store
+        .update(&args.id, &args.prompt)
+        .map_err(|e| e.to_string())
+}
+
+#[derive(Debug, Deserialize)]
+struct RemovePromptArgs {
+    #[serde(default)]
+    project_id: Option<String>,
+    #[serde(default)]
+    global: bool,
+    id: String,
+}
+
+/// Remove a prompt
+#[tauri::command]
+async fn remove_prompt(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+    args: RemovePromptArgs,
+) -> Result<(), String> {
+    let state = state.read().await;
+    if args.global {
+        return state.prompts.remove(&args.id).map_err(|e| e.to_string());
+    }
+    let project_id = args.project_id.unwrap_or_else(|| "default".to_string());
+    let store = state
+        .get_project_store(&project_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    let store = store.prompt_store.read().await;
+    store.remove(&args.id).map_err(|e| e.to_string())
+}
+
+#[derive(Debug, Deserialize)]
+struct RenderPromptArgs {
+    #[serde(default)]
+    project_id: Option<String>,
+    name: String,
+    #[serde(default)]
+    arguments: std::collections::HashMap<String, String>,
+}
+
+/// Preview a prompt as a client would get it for a project
+#[tauri::command]
+async fn render_prompt(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+    args: RenderPromptArgs,
+) -> Result<String, String> {
+    let project_id = args.project_id.unwrap_or_else(|| "default".to_string());
+    let (prompt, ctx) = {
+        let state_read = state.read().await;
+        let ctx = state_read
+            .tool_context(&project_id, tools::Caller::App)
+            .await
+            .map_err(|e| e.to_string())?;
+        let prompt = prompts::find(&ctx.store, &state_read.prompts, &args.name)
+            .await
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("Unknown prompt: {}", args.name))?;
+        (prompt, ctx)
+    };
+    prompts::render(&state, &prompt, &args.arguments, &ctx)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// List the upstream MCP servers registered for a project
+#[tauri::command]
+async fn list_upstream_servers(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+    args: ProjectArgs,
+) -> Result<Vec<upstream_servers::UpstreamServer>, String> {
+    let project_id = args.project_id.unwrap_or_else(|| "default".to_string());
+    let state = state.read().await;
+    let store = state
+        .get_project_store(&project_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    let store = store.upstream_server_store.read().await;
+    store.list().map_err(|e| e.to_string())
+}
+
+#[derive(Debug, Deserialize)]
+struct AddUpstreamServerArgs {
+    #[serde(default)]
+    project_id: Option<String>,
+    server: upstream_servers::NewUpstreamServer,
+}
+
+/// Register an upstream MCP server for a project
+#[tauri::command]
+async fn add_upstream_server(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+    args: AddUpstreamServerArgs,
+) -> Result<upstream_servers::UpstreamServer, String> {
+    let project_id = args.project_id.unwrap_or_else(|| "default".to_string());
+    let state = state.read().await;
+    let store = state
+        .get_project_store(&project_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    let store = store.upstream_server_store.read().await;
+    store.add(args.server).map_err(|e| e.to_string())
+}
+
+#[derive(Debug, Deserialize)]
+struct SetUpstreamServerEnabledArgs {
+    #[serde(default)]
+    project_id: Option<String>,
+    id: String,
+    enabled: bool,
+}
+
+/// Enable or disable an upstream MCP server; disabling closes its connection
+#[tauri::command]
+async fn set_upstream_server_enabled(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+    args: SetUpstreamServerEnabledArgs,
+) -> Result<(), String> {
+    let project_id = args.project_id.unwrap_or_else(|| "default".to_string());
+    let state = state.read().await;
+    let store = state
+        .get_project_store(&project_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    store
+        .upstream_server_store
+        .read()
+        .await
+        .set_enabled(&args.id, args.enabled)
+        .map_err(|e| e.to_string())?;
+    if !args.enabled {
+        state.mcp_gateway.disconnect(&args.id).await;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+struct RemoveUpstreamServerArgs {
+    #[serde(default)]
+    project_id: Option<String>,
+    id: String,
+}
+
+/// Remove an upstream MCP server and close its connection
+#[tauri::command]
+async fn remove_upstream_server(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+    args: RemoveUpstreamServerArgs,
+) -> Result<(), String> {
+    let project_id = args.project_id.unwrap_or_else(|| "default".to_string());
+    let state = state.read().await;
+    let store = state
+        .get_project_store(&project_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    store
+        .upstream_server_store
+        .read()
+        .await
+        .remove(&args.id)
+        .map_err(|e| e.to_string())?;
+    state.mcp_gateway.disconnect(&args.id).await;
+    Ok(())
+}
+
+/// List the tools a project's upstream MCP servers currently offer
+#[tauri::command]
+async fn list_upstream_tools(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+    args: ProjectArgs,
+) -> Result<Vec<tools::ToolDefinition>, String> {
+    let project_id = args.project_id.unwrap_or_else(|| "default".to_string());
+    let (store, gateway) = {
+        let state_read = state.read().await;
+        let store = state_read
+            .get_project_store(&project_id)
+            .await
+            .map_err(|e| e.to_string())?;
+        (store, state_read.mcp_gateway.clone())
+    };
+    Ok(gateway
+        .tools(&store)
+        .await
+        .iter()
+        .map(tools::Tool::definition)
+        .collect())
+}
+
+/// List a project's open terminals
+#[tauri::command]
+async fn list_terminals(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+    args: ProjectArgs,
+) -> Result<Vec<terminals::TerminalInfo>, String> {
+    let project_id = args.project_id.unwrap_or_else(|| "default".to_string());
+    Ok(state.read().await.terminals.list(Some(&project_id)))
+}
+
+#[derive(Debug, Deserialize)]
+struct CloseTerminalArgs {
+    #[serde(default)]
+    project_id: Option<String>,
+    id: String,
+}
+
+/// Close a terminal an agent opened
+#[tauri::command]
+async fn close_terminal(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+    args: CloseTerminalArgs,
+) -> Result<(), String> {
+    let project_id = args.project_id.unwrap_or_else(|| "default".to_string());
+    state.read().await.terminals.close(&project_id, &args.id)
+}
+
+/// List a project's background processes
+#[tauri::command]
+async fn list_processes(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+    args: ProjectArgs,
+) -> Result<Vec<processes::ProcessInfo>, String> {
+    let project_id = args.project_id.unwrap_or_else(|| "default".to_string());
+    let store = state
+        .read()
+        .await
+        .get_project_store(&project_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(store.processes.list())
+}
+
+#[derive(Debug, Deserialize)]
+struct StopProcessArgs {
+    #[serde(default)]
+    project_id: Option<String>,
+    id: String,
+}
+
+/// Stop a background process
+#[tauri::command]
+async fn stop_process(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+    args: StopProcessArgs,
+) -> Result<processes::ProcessInfo, String> {
+    let project_id = args.project_id.unwrap_or_else(|| "default".to_string());
+    let store = state
+        .read()
+        .await
+        .get_project_store(&project_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    store
+        .processes
+        .stop(&args.id, processes::DEFAULT_STOP_TIMEOUT)
+        .await
+}
+
+/// List extra roots file tools may access for a project
+#[tauri::command]
+async fn list_allowed_roots(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+    project_id: Option<String>,
+) -> Result<Vec<AllowedRootInfo>, String> {
+    let project_id = project_id.unwrap_or_else(|| "default".to_string());
+    let store = {
+        let state_read = state.read().await;
+        state_read
+            .get_project_store(&project_id)
+            .await
+            .map_err(|e| e.to_string())?
+    };
+    let store = store.allowed_root_store.read().await;
+    let roots = store.list().map_err(|e| e.to_string())?;
+    Ok(roots.into_iter().map(allowed_root_info_from).collect())
+}
+
+/// Allow file tools to access an extra root for a project
+#[tauri::command]
+async fn add_allowed_root(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+    project_id: Option<String>,
+    path: String,
+) -> Result<AllowedRootInfo, String> {
+    let project_id = project_id.unwrap_or_else(|| "default".to_string());
+    let store = {
+        let state_read = state.read().await;
+        state_read
+            .get_project_store(&project_id)
+            .await
+            .map_err(|e| e.to_string())?
+    };
+    let store = store.allowed_root_store.read().await;
+    let root = store.add(&path).map_err(|e| e.to_string())?;
+    Ok(allowed_root_info_from(root))
+}
+
+#[derive(Debug, Deserialize)]
+struct RemoveAllowedRootArgs {
+    #[serde(default)]
+    project_id: Option<String>,
+    id: String,
+}
+
+/// Remove an extra root from a project's allowlist
+#[tauri::command]
+async fn remove_allowed_root(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+    args: RemoveAllowedRootArgs,
+) -> Result<(), String> {
+    let project_id = args.project_id.unwrap_or_else(|| "default".to_string());
+    let store = {
+        let state_read = state.read().await;
+        state_read
+            .get_project_store(&project_id)
+            .await
+            .map_err(|e| e.to_string())?
+    };
+    let store = store.allowed_root_store.read().await;
+    store.remove(&args.id).map_err(|e| e.to_string())
+}
+
+fn allowed_root_info_from(root: crate::allowed_roots::AllowedRoot) -> AllowedRootInfo {
+    AllowedRootInfo {
+        id: root.id,
+        path: root.path,
+        added_at: root.added_at.to_rfc3339(),
+    }
+}
+
+/// List build commands
+#[tauri::command]
+async fn list_build_commands(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+    project_id: Option<String>,
+) -> Result<Vec<BuildCommandInfo>, String> {
+    let project_id = project_id.unwrap_or_else(|| "default".to_string());
+    let store = {
+        let state_read = state.read().await;
+        state_read
+            .get_project_store(&project_id)
+            .await
+            .map_err(|e| e.to_string())?
+    };
+    let store = store.build_command_store.read().await;
+    let commands = store.list().await.map_err(|e| e.to_string())?;
+    Ok(commands
+        .into_iter()
+        .map(build_command_info_from)
+        .collect())
+}
+
+/// Add build command
+#[tauri::command]
+async fn add_build_command(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+    project_id: Option<String>,
+    name: String,
+    command: String,
+    working_dir: Option<String>,
+) -> Result<BuildCommandInfo, String> {
+    let project_id = project_id.unwrap_or_else(|| "default".to_string());
+    let store = {
+        let state_read = state.read().await;
+        state_read
+            .get_project_store(&project_id)
+            .await
+            .map_err(|e| e.to_string())?
+    };
+    let store = store.build_command_store.read().await;
+    let command = store
+        .add(&name, &command, working_dir)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(build_command_info_from(command))
+}
+
+#[derive(Debug, Deserialize)]
+struct RemoveBuildCommandArgs {
+    #[serde(default)]
+    project_id: Option<String>,
+    id: String,
+}
+
+/// Remove build command
+#[tauri::command]
+async fn remove_build_command(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+    args: RemoveBuildCommandArgs,
+) -> Result<(), String> {
+    let project_id = args.project_id.unwrap_or_else(|| "default".to_string());
+    let store = {
+        let state_read = state.read().await;
+        state_read
+            .get_project_store(&project_id)
+            .await
+            .map_err(|e| e.to_string())?
+    };
+    let store = store.build_command_store.read().await;
+    store.remove(&args.id).await.map_err(|e| e.to_string())
+}
+
+#[derive(Debug, Deserialize)]
+struct RunBuildCommandArgs {
+    #[serde(default)]
+    project_id: Option<String>,
+    id: String,
+}
+
+/// Run build command
+#[tauri::command]
+async fn run_build_command(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+    args: RunBuildCommandArgs,
+) -> Result<String, String> {
+    let project_id = args.project_id.unwrap_or_else(|| "default".to_string());
+    let id = args.id;
+    debug_log(&format!(
+        "=== run_build_command START === project_id={project_id} command_id={id}"
+    ));
+    let (command, root_path) = {
+        let state_read = state.read().await;
+        let project = state_read
+            .project_registry
+            .get_project(&project_id)
+            .await
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("Unknown project: {project_id}"))?;
+        let store = state_read
+            .get_project_store(&project_id)
+            .await
+            .map_err(|e| e.to_string())?;
+        let store = store.build_command_store.read().await;
+        let command = store
+            .get(&id)
+            .await
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| "Build command not found".to_string())?;
+        let working_dir = command
+            .working_dir
+            .clone()
+            .unwrap_or_else(|| project.root_path.clone());
+        debug_log(&format!("run_build_command: project_id={} command='{}' working_dir='{}'", 
+            project_id, command.command, working_dir));
+        (command.command, working_dir)
+    };
+
+    let result = tools::build::run_shell_command(&command, &root_path).await;
+    debug_log(&format!("=== run_build_command END === project_id={} result={:?}", project_id, result.is_ok()));
+    result
+}
+
+#[derive(Debug, Deserialize)]
+struct SetDefaultBuildCommandArgs {
+    #[serde(default)]
+    project_id: Option<String>,
+    id: String,
+}
+
+/// Set default build command
+#[tauri::command]
+async fn set_default_build_command(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+    args: SetDefaultBuildCommandArgs,
+) -> Result<(), String> {
+    let project_id = args.project_id.unwrap_or_else(|| "default".to_string());
+    let store = {
+        let state_read = state.read().await;
+        state_read
+            .get_project_store(&project_id)
+            .await
+            .map_err(|e| e.to_string())?
+    };
+    let store = store.build_command_store.read().await;
+    store.set_default(&args.id).await.map_err(|e| e.to_string())
+}
+
+#[derive(Debug, Deserialize)]
+struct GetDefaultBuildCommandArgs {
+    #[serde(default)]
+    project_id: Option<String>,
+}
+
+/// Get default build command
+#[tauri::command]
+async fn get_default_build_command(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+    args: GetDefaultBuildCommandArgs,
+) -> Result<Option<BuildCommandInfo>, String> {
+    let project_id = args.project_id.unwrap_or_else(|| "default".to_string());
+    let store = {
+        let state_read = state.read().await;
+        state_read
+            .get_project_store(&project_id)
+            .await
+            .map_err(|e| e.to_string())?
+    };
+    let store = store.build_command_store.read().await;
+    let command = store.get_default().await.map_err(|e| e.to_string())?;
+    Ok(command.map(build_command_info_from))
+}
+
+fn build_command_info_from(command: crate::build_commands::BuildCommand) -> BuildCommandInfo {
+    BuildCommandInfo {
+        id: command.id,
+        name: command.name,
+        command: command.command,
+        working_dir: command.working_dir,
+        is_default: command.is_default,
+        created_at: command.created_at.to_rfc3339(),
+    }
+}
+
+/// List a project's directory contents (relative to project root).
+#[tauri::command]
+async fn list_project_directory(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+    project_id: Option<String>,
+    sub_path: Option<String>,
+) -> Result<DirectoryListingInfo, String> {
+    let project_id = project_id.unwrap_or_else(|| "default".to_string());
+    let project = {
+        let state_read = state.read().await;
+        state_read
+            .project_registry
+            .get_project(&project_id)
+            .await
+            .map_err(|e| e.to_string())?
+    }
+    .ok_or_else(|| format!("Unknown project: {project_id}"))?;
+
+    let root = std::path::PathBuf::from(&project.root_path);
+    let requested = sub_path.unwrap_or_default();
+    let requested_path = root.join(requested);
+    let canonical =
+        std::fs::canonicalize(&requested_path).map_err(|e| format!("Invalid path: {e}"))?;
+
+    if !canonical.starts_with(&root) {
+        return Err("Path is outside project root".to_string());
+    }
+
+    list_directory_impl(&canonical, Some(&root)).await
+}
+
+/// List any absolute directory contents.
+#[tauri::command]
+async fn list_directory(path: String) -> Result<DirectoryListingInfo, String> {
+    let requested = std::path::PathBuf::from(path);
+    let canonical = std::fs::canonicalize(&requested).map_err(|e| format!("Invalid path: {e}"))?;
+    if !canonical.is_absolute() {
+        return Err("Path must be absolute".to_string());
+    }
+    list_directory_impl(&canonical, None).await
+}
+
+async fn list_directory_impl(
+    path: &std::path::Path,
+    root: Option<&std::path::Path>,
+) -> Result<DirectoryListingInfo, String> {
+    if !path.is_dir() {
+        return Err("Path is not a directory".to_string());
+    }
+
+    let mut entries = tokio::fs::read_dir(path)
+        .await
+        .map_err(|e| format!("Failed to read directory: {e}"))?;
+    let mut dirs: Vec<DirectoryEntryInfo> = Vec::new();
+    let mut files: Vec<DirectoryEntryInfo> = Vec::new();
+
+    while let Some(entry) = entries
+        .next_entry()
+        .await
+        .map_err(|e| format!("Failed to read directory: {e}"))?
+    {
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let entry_path = entry.path();
+        let metadata = entry
+            .metadata()
+            .await
+            .map_err(|e| format!("Failed to read metadata: {e}"))?;
+        let is_dir = metadata.is_dir();
+        let entry_info = DirectoryEntryInfo {
+            name: file_name,
+            path: entry_path.to_string_lossy().to_string(),
+            is_dir,
+        };
+
+        if is_dir {
+            dirs.push(entry_info);
+        } else {
+            files.push(entry_info);
+        }
+    }
+
+    dirs.sort_by(|a, b| a.name.cmp(&b.name));
+    files.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut entries = Vec::with_capacity(dirs.len() + files.len());
+    entries.extend(dirs);
+    entries.extend(files);
+
+    let parent_path = path
+        .parent()
+        .and_then(|parent| match root {
+            Some(root) if parent.starts_with(root) => Some(parent),
+            Some(_) => None,
+            None => Some(parent),
+        })
+        .map(|parent| parent.to_string_lossy().to_string());
+
+    Ok(DirectoryListingInfo {
+        path: path.to_string_lossy().to_string(),
+        parent_path,
+        entries,
+    })
+}
+
+/// Get the user's home directory
+#[tauri::command]
+async fn get_home_directory() -> Result<String, String> {
+    dirs::home_dir()
+        .map(|p| p.to_string_lossy().to_string())
+        .ok_or_else(|| "Could not determine home directory".to_string())
+}
+
+/// Get list of supported AI tools for MCP configuration
+#[tauri::command]
+async fn get_mcp_supported_tools() -> Result<Vec<mcp_config::AiToolInfo>, String> {
+    Ok(mcp_config::get_mcp_config_info())
+}
+
+/// List HTTP client tokens
+#[tauri::command]
+async fn list_client_tokens(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+) -> Result<Vec<ClientTokenInfo>, String> {
+    let state = state.read().await;
+    let tokens = state.client_tokens.list().map_err(|e| e.to_string())?;
+    Ok(tokens.into_iter().map(client_token_info_from).collect())
+}
+
+/// Create an HTTP client token; the returned token is not retrievable later
+#[tauri::command]
+async fn create_client_token(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+    name: String,
+) -> Result<ClientTokenInfo, String> {
+    let state = state.read().await;
+    let created = state
+        .client_tokens
+        .create(&name)
+        .map_err(|e| e.to_string())?;
+    Ok(ClientTokenInfo {
+        token: Some(created.token),
+        ..client_token_info_from(created.client)
+    })
+}
+
+/// Revoke an HTTP client token
+#[tauri::command]
+async fn revoke_client_token(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+    id: String,
+) -> Result<(), String> {
+    let state = state.read().await;
+    state.client_tokens.revoke(&id).map_err(|e| e.to_string())
+}
+
+/// List tool calls waiting for approval
+#[tauri::command]
+async fn list_pending_approvals(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+) -> Result<Vec<approvals::ApprovalRequest>, String> {
+    let state = state.read().await;
+    Ok(state.approvals.pending())
+}
+
+/// Approve a suspended tool call
+#[tauri::command]
+async fn approve_tool_call(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+    id: String,
+) -> Result<(), String> {
+    let state = state.read().await;
+    state.approvals.resolve(&id, true, None)
+}
+
+/// Deny a suspended tool call
+#[tauri::command]
+async fn deny_tool_call(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+    id: String,
+    reason: Option<String>,
+) -> Result<(), String> {
+    let state = state.read().await;
+    state.approvals.resolve(&id, false, reason)
+}
+
+/// List rules that require approval for tool calls
+#[tauri::command]
+async fn list_approval_rules(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+) -> Result<Vec<approvals::ApprovalRule>, String> {
+    let state = state.read().await;
+    state.approval_rules.list().map_err(|e| e.to_string())
+}
+
+/// Require approval for tool calls matching a rule
+#[tauri::command]
+async fn add_approval_rule(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+    rule: approvals::NewApprovalRule,
+) -> Result<approvals::ApprovalRule, String> {
+    let state = state.read().await;
+    state.approval_rules.add(rule).map_err(|e| e.to_string())
+}
+
+/// Remove an approval rule
+#[tauri::command]
+async fn remove_approval_rule(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+    id: String,
+) -> Result<(), String> {
+    let state = state.read().await;
+    state.approval_rules.remove(&id).map_err(|e| e.to_string())
+}
+
+/// List tool permission policies
+#[tauri::command]
+async fn list_tool_policies(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+) -> Result<Vec<tool_policies::ToolPolicy>, String> {
+    let state = state.read().await;
+    state.tool_policies.list().map_err(|e| e.to_string())
+}
+
+/// Add a tool permission policy for a client and/or project
+#[tauri::command]
+async fn add_tool_policy(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+    policy: tool_policies::NewToolPolicy,
+) -> Result<tool_policies::ToolPolicy, String> {
+    let state = state.read().await;
+    state.tool_policies.add(policy).map_err(|e| e.to_string())
+}
+
+/// Remove a tool permission policy
+#[tauri::command]
+async fn remove_tool_policy(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+    id: String,
+) -> Result<(), String> {
+    let state = state.read().await;
+    state.tool_policies.remove(&id).map_err(|e| e.to_string())
+}
+
+fn client_token_info_from(client: crate::client_tokens::ClientToken) -> ClientTokenInfo {
+    ClientTokenInfo {
+        id: client.id,
+        name: client.name,
+        created_at: client.created_at.to_rfc3339(),
+        last_used_at: client.last_used_at.map(|t| t.to_rfc3339()),
+        token: None,
+    }
+}
+
+/// Configure MCP for one tool with a newly issued client token
+///
+/// The token is revoked again if configuration does not succeed.
+async fn configure_mcp_with_new_token(
+    state: &AppState,
+    tool: mcp_config::AiTool,
+    project_id: &str,
+    port: u16,
+) -> Result<mcp_config::McpSetupResult, String> {
+    let name = format!("{} ({})", tool.display_name(), project_id);
+    let created = state
+        .client_tokens
+        .create(&name)
+        .map_err(|e| e.to_string())?;
+
+    let result = mcp_config::configure_mcp(tool, project_id, port, &created.token).await;
+    if !matches!(&result, Ok(setup) if setup.success) {
+        let _ = state.client_tokens.revoke(&created.client.id);
+    }
+    result.map_err(|e| e.to_string())
+}
+
+/// Configure MCP for a specific AI tool and project
+#[tauri::command(rename_all = "snake_case")]
+async fn configure_mcp_for_tool(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+    tool: String,
+    project_id: String,
+    port: u16,
+) -> Result<mcp_config::McpSetupResult, String> {
+    let ai_tool = match tool.as_str() {
+        "claude" => mcp_config::AiTool::Claude,
+        "kimi" => mcp_config::AiTool::Kimi,
+        "gemini" => mcp_config::AiTool::Gemini,
+        "codex" => mcp_config::AiTool::Codex,
+        _ => return Err(format!("Unknown AI tool: {tool}")),
+    };
+
+    let state = state.read().await;
+    configure_mcp_with_new_token(&state, ai_tool, &project_id, port).await
+}
+
+/// Configure MCP for all supported AI tools
+#[tauri::command(rename_all = "snake_case")]
+async fn configure_mcp_for_all_tools(
+    state: tauri::State<'_, Arc<RwLock<AppState>>>,
+    project_id: String,
+    port: u16,
+) -> Result<Vec<mcp_config::McpSetupResult>, String> {
+    let tools = vec![
+        mcp_config::AiTool::Claude,
+        mcp_config::AiTool::Kimi,
+        mcp_config::AiTool::Gemini,
+        mcp_config::AiTool::Codex,
+    ];
+
+    let state = state.read().await;
+    let mut results = Vec::new();
+
+    for tool in tools {
+        let result = configure_mcp_with_new_token(&state, tool, &project_id, port).await;
+        results.push(result.unwrap_or_else(|e| mcp_config::McpSetupResult {
+            success: false,
+            message: format!("Error: {e}"),
+            config_path: None,
+        }));
+    }
+
+    Ok(results)
+}
+
+/// Forward tool calls, security events, approval requests, and process and
+/// terminal events to the UI
+async fn forward_events(handle: &tauri::AppHandle, state: &Arc<RwLock<AppState>>) {
+    // Subscribe to events and forward to Tauri
+    let mut rx = {
+        let state_read = state.read().await;
+        state_read.subscribe()
+    };
+    let app_handle = handle.clone();
+    tauri::async_runtime::spawn(async move {
+        while let Ok(event) = rx.recv().await {
+            // Emit to Tauri UI
+            let _ = app_handle.emit("tool-call", &event);
+
+            // Also emit raw log
+            let raw_event = RawLogEvent {
+                timestamp: event.timestamp.clone(),
+                source: "tool".to_string(),
+                message: serde_json::json!({
+                    "event": "tool_call_end",
+                    "id": &event.id,
+                    "tool_name": &event.tool_name,
+                    "success": event.success,
+                    "duration_ms": event.duration_ms
+                })
+                .to_string(),
+            };
+            let _ = app_handle.emit("raw-log", &raw_event);
+        }
+    });
+
+    // Forward security events to the UI
+    let mut security_rx = {
+        let state_read = state.read().await;
+        state_read.subscribe_security()
+    };
+    let app_handle = handle.clone();
+    tauri::async_runtime::spawn(async move {
+        while let Ok(event) = security_rx.recv().await {
+            let _ = app_handle.emit("security-event", &event);
+        }
+    });
+
+    // Forward approval requests to the UI
+    let mut approval_rx = {
+        let state_read = state.read().await;
+        state_read.subscribe_approvals()
+    };
+    let app_handle = handle.clone();
+    tauri::async_runtime::spawn(async move {
+        while let Ok(request) = approval_rx.recv().await {
+            let _ = app_handle.emit("tool-approval-request", &request);
+        }
+    });
+
+    // Forward background process state changes to the UI
+    let mut process_rx = {
+        let state_read = state.read().await;
+        state_read.subscribe_processes()
+    };
+    let app_handle = handle.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            match process_rx.recv().await {
+                Ok(event) => {
+                    let _ = app_handle.emit("process-event", &event);
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => {}
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    });
+
+    // Mirror terminal input and output in the UI
+    let mut terminal_rx = {
+        let state_read = state.read().await;
+        state_read.subscribe_terminals()
+    };
+    let app_handle = handle.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            match terminal_rx.recv().await {
+                Ok(event) => {
+                    let _ = app_handle.emit("terminal-event", &event);
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => {}
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+/// Start the HTTP server unless it is already running, logging the outcome
+async fn auto_start_server(app_handle: tauri::AppHandle, state: Arc<RwLock<AppState>>) {
+    let port = {
+        let state_read = state.read().await;
+        state_read.get_port().await
+    };
+
+    let already_running = {
+        let state_read = state.read().await;
+        state_read.is_server_running().await
+    };
+
+    if already_running {
+        return;
+    }
+
+    match http_server::start_http_server(state.clone(), port).await {
+        Ok(server_handle) => {
+            {
+                let state_write = state.write().await;
+                state_write.set_server_handle(server_handle).await;
+            }
+
+            let startup_event = RawLogEvent {
+                timestamp: chrono::Utc::now().to_rfc3339(),
+                source: "server".to_string(),
+                message: format!("HTTP server auto-started on port {port}"),
+            };
+            let _ = app_handle.emit("raw-log", &startup_event);
+        }
+        Err(e) => {
+            tracing::error!("Failed to auto-start HTTP server: {e}");
+            let error_event = RawLogEvent {
+                timestamp: chrono::Utc::now().to_rfc3339(),
+                source: "server".to_string(),
+                message: format!("HTTP server auto-start failed: {e}"),
+            };
+            let _ = app_handle.emit("raw-log", &error_event);
+        }
+    }
+}
+
+/// Create the app state, forward its events to the UI and start the HTTP server
+async fn init_app(handle: tauri::AppHandle) {
+    let app_dir = handle.path().app_data_dir().unwrap();
+    std::fs::create_dir_all(&app_dir).ok();
+
+    let registry_path = app_dir.join("registry.db");
+
+    match AppState::new(registry_path.to_str().unwrap(), &app_dir).await {
+        Ok(state) => {
+            tracing::info!("App state initialized");
+            let state = Arc::new(RwLock::new(state));
+            forward_events(&handle, &state).await;
+            handle.manage(state.clone());
+            tauri::async_runtime::spawn(auto_start_server(handle.clone(), state));
+        }
+        Err(e) => {
+            tracing::error!("Failed to initialize: {e}");
+        }
+    }
+}
+
+/// Run the Tauri application
+pub fn run() {
+    tauri::Builder::default()
+        .plugin(tauri_plugin_shell::init())
+        .setup(|app| {
+            tauri::async_runtime::block_on(init_app(app.handle().clone()));
+            Ok(())
+        })
+        .plugin(tauri_plugin_dialog::init())
+        .invoke_handler(tauri::generate_handler![
+            start_server,
+            stop_server,
+            get_server_status,
+            execute_tool,
+            get_event_history,
+            query_event_history,
+            get_security_events,
+            list_projects,
+            create_project,
+            add_context_file,
+            remove_context_file,
+            list_context_files,
+            list_context_notes,
+            add_context_note,
+            update_context_note,
+            remove_context_note,
+            move_context_note,
+            list_prompts,
+            add_prompt,
+            update_prompt,
+            remove_prompt,
+            render_prompt,
+            list_upstream_servers,
+            add_upstream_server,
+            set_upstream_server_enabled,
+            remove_upstream_server,
+            list_upstream_tools,
+            list_terminals,
+            close_terminal,
+            list_processes,
+            stop_process,
+            list_project_directory,
+            list_directory,
+            get_home_directory,
+            list_allowed_roots,
+            add_allowed_root,
+            remove_allowed_root,
+            list_build_commands,
+            add_build_command,
+            remove_build_command,
+            run_build_command,
+            set_default_build_command,
+            get_default_build_command,
+            list_todos,
+            add_todo,
+            set_todo_completed,
+            remove_todo,
+            move_todo,
+            get_next_todo,
+            get_mcp_supported_tools,
+            list_client_tokens,
+            create_client_token,
+            revoke_client_token,
+            list_tool_policies,
+            list_pending_approvals,
+            approve_tool_call,
+            deny_tool_call,
+            list_approval_rules,
+            add_approval_rule,
+            remove_approval_rule,
+            add_tool_policy,
+            remove_tool_policy,
+            configure_mcp_for_tool,
+            configure_mcp_for_all_tools,
+            debug_log_cmd,
+        ])
+        .build(tauri::generate_context!())
+        .expect("error building app")
+        .run(|app_handle, event| {
+            // Don't leave dev servers and shells running after the app quits
+            if let tauri::RunEvent::Exit = event {
+                if let Some(state) = app_handle.try_state::<Arc<RwLock<AppState>>>() {
+                    tauri::async_runtime::block_on(async {
+                        state.read().await.shutdown().await;
+                    });
+                }
+            }
+        });
+}
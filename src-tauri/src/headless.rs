@@ -0,0 +1,384 @@
+//! Headless server mode
+//!
+//! `aiharness serve --headless` runs the HTTP/MCP server with the project
+//! registry, tools and upstream MCP servers, but without the Tauri window, so
+//! it works on a machine or container without a display. Logs go to stdout
+//! (where journald picks them up under systemd) and SIGTERM or Ctrl-C stops
+//! the server cleanly. With nobody to approve them, calls that need an
+//! approval fail at once. Projects, allowed roots, tool policies and upstream
+//! servers come from a setup file (see [`headless_config`]).
+
+use crate::{
+    approvals::ApprovalQueue, client_tokens::ClientTokenStore, discovery, headless_config,
+    http_server, tools::create_standard_registry, AppState,
+};
+use std::io::IsTerminal;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+const DEFAULT_PORT: u16 = 8787;
+
+pub const USAGE: &str = "Usage: aiharness serve --headless [--port <port>] [--bind <addr>] [--data-dir <dir>]
+                                [--config <file>]
+       aiharness serve --headless --create-token <name> [--data-dir <dir>]
+
+  --port <port>          HTTP port (default: AIH_PORT, else 8787)
+  --bind <addr>          Address to listen on (default: 127.0.0.1; tokens are sent
+                         unencrypted, so only expose it on a trusted network)
+  --data-dir <dir>       Where the registry, default project and discovery file live
+                         (default: AIH_DATA_DIR, else the app's data directory)
+  --config <file>        YAML file of projects, allowed roots, tool policies and
+                         upstream servers to add on start (default: AIH_CONFIG)
+  --create-token <name>  Create a client token, print it and exit";
+
+/// Settings for the headless server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServeOptions {
+    pub port: u16,
+    /// Address to listen on
+    pub bind: IpAddr,
+    pub data_dir: PathBuf,
+    /// Setup file applied on start
+    pub config: Option<PathBuf>,
+    /// Create a client token with this name instead of serving
+    pub create_token: Option<String>,
+}
+
+/// Whether the command line asks for the headless server (`--headless`).
+#[must_use]
+pub fn is_requested(args: &[String]) -> bool {
+    args.iter().any(|arg| arg == "--headless")
+}
+
+impl ServeOptions {
+    /// Parse `serve --headless [--port <port>] [--bind <addr>] [--data-dir <dir>]
+    /// [--config <file>] [--create-token <name>]` (flags also take
+    /// `--flag=value`), falling back to `AIH_PORT`, `AIH_DATA_DIR` and `AIH_CONFIG`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a message for an unknown flag, a missing or invalid value, or
+    /// when no data directory can be determined
+    pub fn parse(args: &[String]) -> Result<Self, String> {
+        let mut port = None;
+        let mut bind = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let mut data_dir = None;
+        let mut config = None;
+        let mut create_token = None;
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) => (flag, Some(value.to_string())),
+                None => (arg.as_str(), None),
+            };
+            match flag {
+                "serve" | "--headless" if inline.is_none() => {}
+                "--port" | "--bind" | "--data-dir" | "--config" | "--create-token" => {
+                    let value = inline
+                        .or_else(|| args.next().cloned())
+                        .ok_or_else(|| format!("{flag} needs a value"))?;
+                    match flag {
+                        "--port" => port = Some(parse_port(&value)?),
+                        "--bind" => {
+                            bind = value
+                                .parse()
+                                .map_err(|_| format!("Invalid bind address: {value}"))?;
+                        }
+                        "--data-dir" => data_dir = Some(PathBuf::from(value)),
+                        "--config" => config = Some(PathBuf::from(value)),
+                        _ => create_token = Some(value),
+                    }
+                }
+                _ => return Err(format!("Unknown argument: {arg}")),
+            }
+        }
+
+        let port = match port {
+            Some(port) => port,
+            None => match std::env::var("AIH_PORT") {
+                Ok(value) if !value.is_empty() => parse_port(&value)?,
+                _ => DEFAULT_PORT,
+            },
+        };
+        let data_dir = data_dir
+            .or_else(discovery::default_data_dir)
+            .ok_or_else(|| "Could not determine a data directory; pass --data-dir".to_string())?;
+        let config = config.or_else(|| {
+            std::env::var_os("AIH_CONFIG")
+                .filter(|value| !value.is_empty())
+                .map(PathBuf::from)
+        });
+
+        Ok(Self {
+            port,
+            bind,
+            data_dir,
+            config,
+            create_token,
+        })
+    }
+}
+
+fn parse_port(value: &str) -> Result<u16, String> {
+    value.parse().map_err(|_| format!("Invalid port: {value}"))
+}
+
+/// Log to stdout, without colors unless it's a terminal (journald stores
+/// escape codes verbatim).
+pub fn init_logging() {
+    tracing_subscriber::fmt()
+        .with_ansi(std::io::stdout().is_terminal())
+        .init();
+}
+
+/// Run the server until SIGTERM or Ctrl-C (or just create a client token).
+///
+/// # Errors
+///
+/// Returns an error if the data directory, setup file or HTTP server can't be
+/// set up
+pub async fn serve(options: ServeOptions) -> anyhow::Result<()> {
+    if let Some(name) = &options.create_token {
+        std::fs::create_dir_all(&options.data_dir)?;
+        let registry_path = options.data_dir.join("registry.db");
+        let store = ClientTokenStore::new(&registry_path.to_string_lossy())?;
+        // The token is only ever shown here, on stdout rather than in the logs
+        println!("{}", store.create(name)?.token);
+        return Ok(());
+    }
+
+    let state = start(&options).await?;
+    tracing::info!(
+        "AIHarness headless server listening on http://{} (data dir {})",
+        SocketAddr::new(options.bind, options.port),
+        options.data_dir.display()
+    );
+    if !options.bind.is_loopback() {
+        tracing::warn!("Serving beyond this machine over plain HTTP; client tokens are sent unencrypted");
+    }
+
+    shutdown_signal().await;
+    tracing::info!("Shutting down");
+    state.read().await.shutdown().await;
+    Ok(())
+}
+
+/// Open the app state in `options.data_dir` and start the HTTP server.
+async fn start(options: &ServeOptions) -> anyhow::Result<Arc<RwLock<AppState>>> {
+    std::fs::create_dir_all(&options.data_dir)?;
+    let registry_path = options.data_dir.join("registry.db");
+    let registry_path = registry_path
+        .to_str()
+        .ok_or_else(|| anyhow::anyhow!("Data dir is not valid UTF-8"))?;
+
+    let mut state = AppState::new(registry_path, &options.data_dir).await?;
+    if let Some(path) = &options.config {
+        let config = headless_config::load(path)?;
+        headless_config::apply(&state, &config).await?;
+    }
+    state.tool_registry = create_standard_registry(options.port);
+    state.approvals = Arc::new(ApprovalQueue::unattended());
+    state.set_port(options.port).await;
+    let state = Arc::new(RwLock::new(state));
+    log_events(&state).await;
+
+    let addr = SocketAddr::new(options.bind, options.port);
+    let handle = http_server::start_http_server_on(state.clone(), addr)
+        .await
+        .map_err(anyhow::Error::msg)?;
+    state.read().await.set_server_handle(handle).await;
+    Ok(state)
+}
+
+/// Log what the GUI would show: tool calls, security events and background
+/// processes. (Calls refused for want of an approval are logged by the queue.)
+async fn log_events(state: &Arc<RwLock<AppState>>) {
+    let state_read = state.read().await;
+
+    let mut tool_calls = state_read.subscribe();
+    tokio::spawn(async move {
+        while let Ok(event) = tool_calls.recv().await {
+            tracing::info!(
+                tool = %event.tool_name,
+                project = %event.project_id,
+                success = event.success,
+                duration_ms = event.duration_ms,
+                "tool call"
+            );
+        }
+    });
+
+    let mut security = state_read.subscribe_security();
+    tokio::spawn(async move {
+        while let Ok(event) = security.recv().await {
+            tracing::warn!(
+                kind = %event.kind,
+                tool = %event.tool_name,
+                project = %event.project_id,
+                "{}",
+                event.detail
+            );
+        }
+    });
+
+    let mut processes = state_read.subscribe_processes();
+    tokio::spawn(async move {
+        loop {
+            match processes.recv().await {
+                Ok(event) => tracing::info!(
+                    process = %event.process.name,
+                    project = %event.project_id,
+                    status = ?event.process.status,
+                    exit_code = ?event.process.exit_code,
+                    "background process {:?}",
+                    event.kind
+                ),
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => {}
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+/// Resolve on SIGTERM (how systemd and container runtimes stop services) or Ctrl-C.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!("Failed to listen for Ctrl-C: {e}");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                tracing::error!("Failed to listen for SIGTERM: {e}");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        () = ctrl_c => {}
+        () = terminate => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| (*arg).to_string()).collect()
+    }
+
+    #[test]
+    fn parse_reads_flags_in_both_forms() {
+        assert!(is_requested(&args(&["serve", "--headless"])));
+        assert!(is_requested(&args(&["--headless"])));
+        assert!(!is_requested(&args(&["serve"])));
+        assert!(!is_requested(&args(&["--mcp-stdio-proxy"])));
+
+        let options =
+            ServeOptions::parse(&args(&["serve", "--headless", "--port", "9100", "--data-dir=/srv/aih"]))
+                .unwrap();
+        assert_eq!(
+            options,
+            ServeOptions {
+                port: 9100,
+                bind: IpAddr::V4(Ipv4Addr::LOCALHOST),
+                data_dir: PathBuf::from("/srv/aih"),
+                config: None,
+                create_token: None,
+            }
+        );
+        let options = ServeOptions::parse(&args(&[
+            "serve",
+            "--headless",
+            "--data-dir",
+            "/srv/aih",
+            "--create-token=ci",
+            "--config",
+            "/etc/aiharness.yaml",
+        ]))
+        .unwrap();
+        assert_eq!(options.create_token.as_deref(), Some("ci"));
+        assert_eq!(options.config, Some(PathBuf::from("/etc/aiharness.yaml")));
+        let options = ServeOptions::parse(&args(&["serve", "--headless", "--bind", "0.0.0.0"])).unwrap();
+        assert_eq!(options.bind, IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+        assert!(ServeOptions::parse(&args(&["serve", "--bind=localhost"])).is_err());
+
+        assert!(ServeOptions::parse(&args(&["serve", "--port"])).is_err());
+        assert!(ServeOptions::parse(&args(&["serve", "--port", "http"])).is_err());
+        assert!(ServeOptions::parse(&args(&["serve", "--verbose"])).is_err());
+    }
+
+    #[tokio::test]
+    async fn start_serves_from_the_data_dir_and_stops_cleanly() {
+        let temp_dir = TempDir::new().unwrap();
+        let port = {
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            listener.local_addr().unwrap().port()
+        };
+        let options = ServeOptions {
+            port,
+            bind: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            data_dir: temp_dir.path().join("data"),
+            config: None,
+            create_token: None,
+        };
+
+        let state = start(&options).await.unwrap();
+        assert!(options.data_dir.join("registry.db").exists());
+        assert_eq!(discovery::read(&options.data_dir).unwrap().port, port);
+
+        let health = reqwest::get(format!("http://127.0.0.1:{port}/"))
+            .await
+            .unwrap();
+        assert!(health.status().is_success());
+
+        // Nobody can approve a call, so it fails rather than waiting
+        state
+            .read()
+            .await
+            .approval_rules
+            .add(crate::approvals::NewApprovalRule {
+                project_id: None,
+                tool: "todo_add".to_string(),
+                path: None,
+                command: None,
+            })
+            .unwrap();
+        let call = crate::tool_dispatch::ToolCall {
+            tool_name: "todo_add".to_string(),
+            arguments: serde_json::json!({ "title": "x" }),
+            project_id: "default".to_string(),
+            caller: crate::tools::Caller::Client {
+                id: "c1".to_string(),
+                name: "test".to_string(),
+            },
+            progress: crate::tools::ProgressReporter::default(),
+            cancel: tokio_util::sync::CancellationToken::new(),
+        };
+        let outcome = tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            crate::tool_dispatch::dispatch(&state, call),
+        )
+        .await
+        .unwrap();
+        assert!(outcome.result.unwrap_err().contains("without the GUI"));
+
+        state.read().await.stop_server().await;
+        assert!(discovery::read(&options.data_dir).is_none());
+    }
+}
@@ -0,0 +1,257 @@
+//! Setup file for the headless server
+//!
+//! Without the GUI there is nowhere to add projects, allowed roots, tool
+//! policies or upstream MCP servers, so `serve --headless --config <file>`
+//! reads them from a YAML (or JSON) file on start:
+//!
+//! ```yaml
+//! projects:
+//!   - id: web                # used in /mcp/web
+//!     name: Web app
+//!     root: /srv/web
+//!     allowed_roots: [/srv/shared]
+//!     upstream_servers:
+//!       - name: github
+//!         transport: stdio
+//!         command: github-mcp-server
+//! policies:
+//!   - client: ci             # a token created with --create-token ci
+//!     project_id: web
+//!     preset: read_only
+//! ```
+//!
+//! Entries are added when missing and left alone when already there, so the
+//! same file can be applied on every start. Removing an entry from the file
+//! does not remove it from the server.
+
+use crate::{
+    app_state::AppState,
+    error::ContextError,
+    tool_policies::{NewToolPolicy, ToolPolicyRules},
+    upstream_servers::NewUpstreamServer,
+};
+use anyhow::{anyhow, bail, Context};
+use serde::Deserialize;
+use std::path::Path;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HeadlessConfig {
+    #[serde(default)]
+    pub projects: Vec<ProjectConfig>,
+    #[serde(default)]
+    pub policies: Vec<PolicyConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectConfig {
+    /// Project id (`default` is the built-in project)
+    pub id: String,
+    /// Display name (default: the id)
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Project root, required to create the project
+    #[serde(default)]
+    pub root: Option<String>,
+    /// Extra directories the file tools may use
+    #[serde(default)]
+    pub allowed_roots: Vec<String>,
+    #[serde(default)]
+    pub upstream_servers: Vec<NewUpstreamServer>,
+}
+
+/// A tool policy whose client is named by its token name
+#[derive(Debug, Deserialize)]
+pub struct PolicyConfig {
+    /// Client token name, or unset for every client
+    #[serde(default)]
+    pub client: Option<String>,
+    /// Project id, or unset for every project
+    #[serde(default)]
+    pub project_id: Option<String>,
+    #[serde(flatten)]
+    pub rules: ToolPolicyRules,
+}
+
+/// Read a setup file.
+///
+/// # Errors
+///
+/// Returns an error if the file can't be read or isn't a valid setup file
+pub fn load(path: &Path) -> anyhow::Result<HeadlessConfig> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Could not read {}", path.display()))?;
+    serde_yaml::from_str(&content).with_context(|| format!("Invalid setup file {}", path.display()))
+}
+
+/// Add what the setup file lists and the server doesn't have yet.
+///
+/// # Errors
+///
+/// Returns an error naming the entry that couldn't be applied
+pub async fn apply(state: &AppState, config: &HeadlessConfig) -> anyhow::Result<()> {
+    for project in &config.projects {
+        apply_project(state, project)
+            .await
+            .with_context(|| format!("Project {}", project.id))?;
+    }
+
+    let clients = state.client_tokens.list()?;
+    let existing = state.tool_policies.list()?;
+    for policy in &config.policies {
+        let mut client_id = None;
+        if let Some(name) = &policy.client {
+            let mut named = clients.iter().filter(|client| &client.name == name);
+            client_id = match (named.next(), named.next()) {
+                (Some(client), None) => Some(client.id.clone()),
+                (Some(_), Some(_)) => bail!("Several client tokens are named {name}"),
+                (None, _) => {
+                    bail!("No client token named {name}; create it with --create-token {name}")
+                }
+            };
+        }
+        let policy = NewToolPolicy {
+            client_id,
+            project_id: policy.project_id.clone(),
+            rules: policy.rules.clone(),
+        };
+        let present = existing.iter().any(|p| {
+            p.client_id == policy.client_id
+                && p.project_id == policy.project_id
+                && p.rules == policy.rules
+        });
+        if !present {
+            let added = state.tool_policies.add(policy)?;
+            tracing::info!("Added tool policy {}", added.id);
+        }
+    }
+    Ok(())
+}
+
+async fn apply_project(state: &AppState, project: &ProjectConfig) -> anyhow::Result<()> {
+    let registry = &state.project_registry;
+    if let Some(existing) = registry.get_project(&project.id).await? {
+        if let Some(root) = &project.root {
+            let same = std::fs::canonicalize(root)
+                .is_ok_and(|root| root == Path::new(&existing.root_path));
+            if !same {
+                bail!("Already exists with root {}", existing.root_path);
+            }
+        }
+    } else {
+        let root = project
+            .root
+            .as_deref()
+            .ok_or_else(|| anyhow!("'root' is needed to create the project"))?;
+        let name = project.name.as_deref().unwrap_or(&project.id);
+        registry
+            .create_project_with_id(project.id.clone(), name, root)
+            .await?;
+        tracing::info!("Added project {} at {}", project.id, root);
+    }
+
+    let store = state.get_project_store(&project.id).await?;
+    let allowed_roots = store.allowed_root_store.read().await;
+    for path in &project.allowed_roots {
+        match allowed_roots.add(path) {
+            Ok(root) => tracing::info!("Allowed {} for project {}", root.path, project.id),
+            Err(ContextError::AlreadyExists(_)) => {}
+            Err(e) => return Err(e).with_context(|| format!("Allowed root {path}")),
+        }
+    }
+    let servers = store.upstream_server_store.read().await;
+    for server in &project.upstream_servers {
+        if servers.get_by_name(&server.name)?.is_none() {
+            servers
+                .add(server.clone())
+                .with_context(|| format!("Upstream server {}", server.name))?;
+            tracing::info!(
+                "Added upstream server {} to project {}",
+                server.name,
+                project.id
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tool_policies::PolicyPreset;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn apply_adds_missing_entries_once() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry_path = temp_dir.path().join("registry.db");
+        let state = AppState::new(registry_path.to_str().unwrap(), temp_dir.path())
+            .await
+            .unwrap();
+        state.client_tokens.create("ci").unwrap();
+        let shared = temp_dir.path().join("shared");
+        std::fs::create_dir(&shared).unwrap();
+        let config: HeadlessConfig = serde_yaml::from_str(&format!(
+            "
+projects:
+  - id: web
+    root: {root}
+    allowed_roots: [{shared}]
+    upstream_servers:
+      - name: echo
+        transport: stdio
+        command: echo
+policies:
+  - client: ci
+    project_id: web
+    preset: read_only
+",
+            root = temp_dir.path().join("web").display(),
+            shared = shared.display(),
+        ))
+        .unwrap();
+
+        // Applying twice (as on every restart) adds everything once
+        apply(&state, &config).await.unwrap();
+        apply(&state, &config).await.unwrap();
+        let project = state
+            .project_registry
+            .get_project("web")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(project.name, "web");
+        let store = state.get_project_store("web").await.unwrap();
+        assert_eq!(
+            store.allowed_root_store.read().await.list().unwrap().len(),
+            1
+        );
+        assert_eq!(
+            store
+                .upstream_server_store
+                .read()
+                .await
+                .list()
+                .unwrap()
+                .len(),
+            1
+        );
+        let policies = state.tool_policies.list().unwrap();
+        assert_eq!(policies.len(), 1);
+        assert_eq!(policies[0].rules.preset, Some(PolicyPreset::ReadOnly));
+
+        // A project can't be moved, and a policy needs an existing client
+        let moved: HeadlessConfig =
+            serde_yaml::from_str("projects: [{ id: web, root: / }]").unwrap();
+        assert!(apply(&state, &moved).await.is_err());
+        let unknown: HeadlessConfig =
+            serde_yaml::from_str("policies: [{ client: nobody, preset: no_shell }]").unwrap();
+        let error = apply(&state, &unknown).await.unwrap_err();
+        assert!(
+            error.to_string().contains("--create-token nobody"),
+            "{error}"
+        );
+        assert!(serde_yaml::from_str::<HeadlessConfig>("project: []").is_err());
+    }
+}
@@ -4,90 +4,235 @@
 
 use axum::{
     routing::{get, post},
-    extract::{Path, State},
-    response::IntoResponse,
+    extract::{Extension, Path, Query, Request, State},
+    http::{header, HeaderMap, HeaderName, HeaderValue, Method, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
     Json, Router,
 };
 use serde_json::json;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, SocketAddr};
 use std::sync::Arc;
-use tokio::sync::RwLock;
-use tower_http::cors::CorsLayer;
+use tokio::sync::{mpsc, RwLock};
+use tokio_util::sync::CancellationToken;
+use tower_http::cors::{AllowOrigin, CorsLayer};
 
 use crate::{
     app_state::AppState,
-    tools::ToolDefinition,
-    ToolCallEvent,
+    error::{ContextError, ToolError},
+    mcp_gateway,
+    mcp_resources,
+    mcp_sessions::{McpSession, McpSessionManager, PROTOCOL_VERSION_HEADER, SESSION_HEADER},
+    prompts,
+    resource_subscriptions::{self, Watched},
+    tool_dispatch::{self, ToolCall},
+    tool_history::ToolCallQuery,
+    tool_policies::{self, PolicyRequest, ToolPolicy},
+    tools::{Caller, Progress, ProgressReporter, Tool},
 };
 
 /// Shared state for HTTP handlers
 type HttpState = Arc<RwLock<AppState>>;
 
-/// Start HTTP server
+/// Start HTTP server on the loopback interface
 pub async fn start_http_server(
     app_state: Arc<RwLock<AppState>>,
     port: u16,
 ) -> Result<tokio::task::JoinHandle<()>, String> {
-    let app = create_router(app_state);
+    start_http_server_on(app_state, SocketAddr::from((Ipv4Addr::LOCALHOST, port))).await
+}
+
+/// Start HTTP server on an address
+///
+/// # Errors
+///
+/// Returns an error if the address can't be bound
+pub async fn start_http_server_on(
+    app_state: Arc<RwLock<AppState>>,
+    addr: SocketAddr,
+) -> Result<tokio::task::JoinHandle<()>, String> {
+    let app = create_router(app_state.clone());
     
-    let addr = format!("127.0.0.1:{}", port);
     let listener = tokio::net::TcpListener::bind(&addr)
         .await
         .map_err(|e| format!("Failed to bind: {}", e))?;
     
     tracing::info!("HTTP server starting on http://{}", addr);
     
+    // The resource watcher lives and dies with the server
     let handle = tokio::spawn(async move {
-        if let Err(e) = axum::serve(listener, app).await {
-            tracing::error!("HTTP server error: {}", e);
+        tokio::select! {
+            result = axum::serve(listener, app) => {
+                if let Err(e) = result {
+                    tracing::error!("HTTP server error: {e}");
+                }
+            }
+            () = resource_subscriptions::watch(app_state) => {}
         }
     });
     
     Ok(handle)
 }
 
+/// Origins allowed to make browser requests (the app's own webview)
+const ALLOWED_ORIGINS: [&str; 3] = [
+    "tauri://localhost",
+    "http://tauri.localhost",
+    "https://tauri.localhost",
+];
+
+/// The frontend dev server, allowed only in debug builds
+#[cfg(debug_assertions)]
+const DEV_SERVER_ORIGIN: Option<&str> = Some("http://localhost:1420");
+#[cfg(not(debug_assertions))]
+const DEV_SERVER_ORIGIN: Option<&str> = None;
+
 /// Create HTTP router
+///
+/// Everything except the health check requires a client bearer token.
 fn create_router(app_state: HttpState) -> Router {
-    Router::new()
-        .route("/", get(health_check))
+    let protected = Router::new()
         .route("/tools", get(list_tools))
         .route("/call", post(execute_tool))
+        // MCP Streamable HTTP endpoints: POST messages, GET a session's SSE stream,
+        // DELETE to end the session.
         // Legacy MCP endpoint (uses default project or project_id in params)
-        .route("/mcp", post(handle_mcp_request))
+        .route(
+            "/mcp",
+            post(handle_mcp_request).get(open_mcp_stream).delete(end_mcp_session),
+        )
         // Project-specific MCP endpoint (project_id in URL path)
-        .route("/mcp/:project_id", post(handle_mcp_request_for_project))
+        .route(
+            "/mcp/:project_id",
+            post(handle_mcp_request_for_project)
+                .get(open_mcp_stream)
+                .delete(end_mcp_session),
+        )
         .route("/events", get(get_events))
         .route("/events/stream", get(stream_events))
-        .layer(CorsLayer::permissive())
+        .route_layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            require_client_token,
+        ));
+
+    Router::new()
+        .route("/", get(health_check))
+        .merge(protected)
+        .layer(cors_layer())
         .with_state(app_state)
 }
 
+/// CORS restricted to the app's own origins
+fn cors_layer() -> CorsLayer {
+    CorsLayer::new()
+        .allow_origin(AllowOrigin::list(
+            ALLOWED_ORIGINS
+                .iter()
+                .chain(DEV_SERVER_ORIGIN.iter())
+                .map(|origin| HeaderValue::from_static(origin)),
+        ))
+        .allow_methods([Method::GET, Method::POST, Method::DELETE])
+        .allow_headers([
+            header::AUTHORIZATION,
+            header::CONTENT_TYPE,
+            HeaderName::from_static(SESSION_HEADER),
+        ])
+        .expose_headers([HeaderName::from_static(SESSION_HEADER)])
+}
+
+/// Reject requests without a valid `Authorization: Bearer <token>` header
+///
+/// The authenticated client is passed to handlers as a [`Caller`] extension.
+async fn require_client_token(
+    State(state): State<HttpState>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let token = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(parse_bearer_token)
+        .map(str::to_string);
+    let Some(token) = token else {
+        return unauthorized("Missing bearer token");
+    };
+
+    let verified = {
+        let state = state.read().await;
+        state.client_tokens.verify(&token)
+    };
+    match verified {
+        Ok(Some(client)) => {
+            request.extensions_mut().insert(Caller::Client {
+                id: client.id,
+                name: client.name,
+            });
+            next.run(request).await
+        }
+        Ok(None) => unauthorized("Invalid or revoked token"),
+        Err(e) => {
+            tracing::error!("Token verification failed: {e}");
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": e.to_string() })),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// Extract the token from an `Authorization` header value
+fn parse_bearer_token(value: &str) -> Option<&str> {
+    let (scheme, token) = value.trim().split_once(' ')?;
+    let token = token.trim();
+    (scheme.eq_ignore_ascii_case("bearer") && !token.is_empty()).then_some(token)
+}
+
+fn unauthorized(message: &str) -> Response {
+    (
+        StatusCode::UNAUTHORIZED,
+        [(header::WWW_AUTHENTICATE, "Bearer")],
+        Json(json!({ "error": message })),
+    )
+        .into_response()
+}
+
+fn error_response(status: StatusCode, message: &str) -> Response {
+    (status, Json(json!({ "error": message }))).into_response()
+}
+
+/// Body of the health check, which the stdio proxy looks for to tell this
+/// server from whatever else answers on the port
+pub const HEALTH_MESSAGE: &str = "AIHarness Server Running";
+
 /// Health check
 async fn health_check() -> &'static str {
-    "AIHarness Server Running"
+    HEALTH_MESSAGE
 }
 
 /// List available tools
 async fn list_tools(State(state): State<HttpState>) -> Json<serde_json::Value> {
     let state = state.read().await;
-    let mut tools = state.tool_registry.list();
-    tools.extend(todo_tool_definitions());
-    tools.extend(build_tool_definitions());
-    tools.extend(next_session_tool_definitions());
+    let tools = state.tool_registry.list();
     Json(json!({ "tools": map_tools(&tools, "input_schema") }))
 }
 
 /// Execute a tool
 async fn execute_tool(
     State(state): State<HttpState>,
+    Extension(caller): Extension<Caller>,
     Json(body): Json<serde_json::Value>,
 ) -> Json<serde_json::Value> {
     let (tool_name, arguments) = parse_tool_call_body(&body);
     let project_id = parse_project_id(&body);
-    match execute_tool_call(state, &tool_name, arguments, project_id).await {
+    match execute_tool_call(state, &tool_name, arguments, project_id, caller).await {
         Ok(result) => Json(json!({
             "success": true,
             "content": result.content,
+            "data": result.data,
             "duration_ms": result.duration_ms,
         })),
         Err(error) => Json(json!({
@@ -97,15 +242,101 @@ async fn execute_tool(
     }
 }
 
-/// Get event history
-async fn get_events(State(state): State<HttpState>) -> Json<Vec<ToolCallEvent>> {
+/// Query parameters for `/events`
+#[derive(Debug, Default, Deserialize)]
+struct EventsParams {
+    project: Option<String>,
+    tool: Option<String>,
+    since: Option<String>,
+    success: Option<bool>,
+    search: Option<String>,
+    limit: Option<usize>,
+    offset: Option<usize>,
+}
+
+impl EventsParams {
+    fn into_query(self) -> (String, ToolCallQuery) {
+        let project_id = self.project.unwrap_or_else(|| "default".to_string());
+        let query = ToolCallQuery {
+            tool: self.tool,
+            tools: None,
+            since: self.since,
+            success: self.success,
+            search: self.search,
+            limit: self.limit,
+            offset: self.offset,
+        };
+        (project_id, query)
+    }
+}
+
+/// Query persisted tool-call history
+///
+/// Only the requested project's calls are returned, and only calls to tools
+/// the client's policies for that project allow.
+async fn get_events(
+    State(state): State<HttpState>,
+    Extension(caller): Extension<Caller>,
+    Query(params): Query<EventsParams>,
+) -> Json<serde_json::Value> {
+    let (project_id, query) = params.into_query();
     let state = state.read().await;
-    let history = state.get_history().await;
-    Json(history)
+    match query_visible_events(&state, &caller, &project_id, query).await {
+        Ok(page) => Json(json!(page)),
+        Err(e) => Json(json!({ "error": e.to_string() })),
+    }
+}
+
+async fn query_visible_events(
+    state: &AppState,
+    caller: &Caller,
+    project_id: &str,
+    mut query: ToolCallQuery,
+) -> Result<crate::tool_history::ToolCallPage, ContextError> {
+    let policies = caller_policies(state, caller, project_id)?;
+    if !policies.is_empty() {
+        let names = state.event_tool_names(project_id).await?;
+        query.tools = Some(
+            names
+                .into_iter()
+                .filter(|name| tool_visible(&policies, name))
+                .collect(),
+        );
+    }
+    state.query_events(project_id, &query).await
+}
+
+/// Policies that apply to the caller in a project (none for the app itself)
+fn caller_policies(
+    state: &AppState,
+    caller: &Caller,
+    project_id: &str,
+) -> Result<Vec<ToolPolicy>, ContextError> {
+    match caller.client_id() {
+        Some(client_id) => state.tool_policies.for_call(Some(client_id), project_id),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Whether calls to a tool may be shown to a client with these policies
+fn tool_visible(policies: &[ToolPolicy], tool_name: &str) -> bool {
+    let request = PolicyRequest {
+        tool_name: tool_name.to_string(),
+        ..PolicyRequest::default()
+    };
+    tool_policies::evaluate(policies, &request).is_ok()
 }
 
-/// MCP protocol version
-const MCP_PROTOCOL_VERSION: &str = "2024-11-05";
+/// MCP protocol versions this server speaks, newest first
+const SUPPORTED_PROTOCOL_VERSIONS: [&str; 3] = ["2025-06-18", "2025-03-26", "2024-11-05"];
+
+/// Pick the protocol version for a session: the client's if supported, else our newest
+fn negotiate_protocol_version(requested: Option<&str>) -> &'static str {
+    SUPPORTED_PROTOCOL_VERSIONS
+        .iter()
+        .find(|version| Some(**version) == requested)
+        .unwrap_or(&SUPPORTED_PROTOCOL_VERSIONS[0])
+}
 
 /// JSON-RPC request
 #[derive(Debug, Clone, Deserialize)]
@@ -154,53 +385,362 @@ fn json_rpc_error_response(
 /// Handle MCP JSON-RPC requests over HTTP (legacy - uses default project or project_id in params)
 async fn handle_mcp_request(
     State(state): State<HttpState>,
+    Extension(caller): Extension<Caller>,
+    headers: HeaderMap,
     Json(request): Json<serde_json::Value>,
-) -> Json<JsonRpcResponse> {
-    handle_mcp_request_internal(state, request, None).await
+) -> Response {
+    handle_mcp_post(state, &headers, request, None, caller).await
 }
 
 /// Handle MCP JSON-RPC requests for a specific project (project_id in URL path)
 async fn handle_mcp_request_for_project(
     State(state): State<HttpState>,
     Path(project_id): Path<String>,
+    Extension(caller): Extension<Caller>,
+    headers: HeaderMap,
     Json(request): Json<serde_json::Value>,
-) -> Json<JsonRpcResponse> {
-    handle_mcp_request_internal(state, request, Some(project_id)).await
+) -> Response {
+    handle_mcp_post(state, &headers, request, Some(project_id), caller).await
 }
 
-/// Internal handler for MCP requests with optional project_id override
-async fn handle_mcp_request_internal(
+/// Session id sent by the client, if any
+fn session_id(headers: &HeaderMap) -> Option<&str> {
+    headers.get(SESSION_HEADER).and_then(|v| v.to_str().ok())
+}
+
+/// Whether the client accepts an SSE response
+fn accepts_event_stream(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|accept| accept.contains("text/event-stream"))
+}
+
+fn session_not_found() -> Response {
+    error_response(StatusCode::NOT_FOUND, "Unknown or expired MCP session")
+}
+
+/// Streamable HTTP POST: one JSON-RPC message, or a batch array, from the client.
+///
+/// `initialize` starts a session, returned in the `Mcp-Session-Id` header.
+/// Requests carrying a session id must name a live session of this client,
+/// started on the same endpoint (so a session for one project can't be used
+/// on another project's path); requests without one are served statelessly,
+/// as before sessions existed.
+/// An `Mcp-Protocol-Version` header must be a supported version (and match the
+/// session's). Messages that need no reply are acknowledged with 202. A
+/// `tools/call` from a client that accepts `text/event-stream` is answered on
+/// an SSE stream, which also carries the call's progress notifications.
+async fn handle_mcp_post(
     state: HttpState,
-    request: serde_json::Value,
+    headers: &HeaderMap,
+    body: serde_json::Value,
     project_id_override: Option<String>,
-) -> Json<JsonRpcResponse> {
-    let request = match parse_json_rpc_request(request) {
+    caller: Caller,
+) -> Response {
+    let sessions = state.read().await.mcp_sessions.clone();
+
+    let session = match session_id(headers) {
+        Some(id) => match sessions.get(id, caller.client_id(), project_id_override.as_deref()) {
+            Some(session) => Some(session),
+            None => return session_not_found(),
+        },
+        None => None,
+    };
+    if let Some(version) = headers
+        .get(PROTOCOL_VERSION_HEADER)
+        .and_then(|v| v.to_str().ok())
+    {
+        let supported = SUPPORTED_PROTOCOL_VERSIONS.contains(&version);
+        let matches_session = session.as_ref().map_or(true, |s| s.protocol_version == version);
+        if !supported || !matches_session {
+            return error_response(
+                StatusCode::BAD_REQUEST,
+                &format!("Unsupported MCP protocol version: {version}"),
+            );
+        }
+    }
+
+    let request = match body {
+        serde_json::Value::Array(batch) => {
+            return handle_mcp_batch(&state, batch, project_id_override.as_deref(), &caller, session.as_ref()).await;
+        }
+        request => request,
+    };
+    let method = request
+        .get("method")
+        .and_then(|v| v.as_str())
+        .unwrap_or_default()
+        .to_string();
+
+    if method == "tools/call" && request.get("id").is_some() && accepts_event_stream(headers) {
+        use axum::response::sse::{Event, KeepAlive, Sse};
+        use std::convert::Infallible;
+
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let stream = async_stream::stream! {
+            let call = handle_mcp_message(
+                &state,
+                request,
+                project_id_override.as_deref(),
+                &caller,
+                session.as_ref(),
+                Some(&sender),
+            );
+            tokio::pin!(call);
+            let response = loop {
+                let next = tokio::select! {
+                    response = &mut call => Err(response),
+                    Some(message) = receiver.recv() => Ok(message),
+                };
+                match next {
+                    Ok(message) => yield Ok::<_, Infallible>(Event::default().json_data(&message).unwrap()),
+                    Err(response) => break response,
+                }
+            };
+            while let Ok(message) = receiver.try_recv() {
+                yield Ok(Event::default().json_data(&message).unwrap());
+            }
+            if let Some(response) = response {
+                yield Ok(Event::default().json_data(&response).unwrap());
+            }
+        };
+        return Sse::new(stream).keep_alive(KeepAlive::default()).into_response();
+    }
+
+    let Some(response) =
+        handle_mcp_message(&state, request, project_id_override.as_deref(), &caller, session.as_ref(), None).await
+    else {
+        return StatusCode::ACCEPTED.into_response();
+    };
+
+    // A successful initialize outside a session starts one
+    let negotiated = response
+        .result
+        .as_ref()
+        .and_then(|r| r.get("protocolVersion"))
+        .and_then(|v| v.as_str())
+        .filter(|_| method == "initialize" && session.is_none())
+        .map(str::to_string);
+    let mut http_response = Json(response).into_response();
+    if let Some(version) = negotiated {
+        let created = sessions.create(caller.client_id(), project_id_override.as_deref(), &version);
+        if let Ok(value) = HeaderValue::from_str(&created.id) {
+            http_response.headers_mut().insert(SESSION_HEADER, value);
+        }
+    }
+    http_response
+}
+
+/// Handle a JSON-RPC batch: replies for the requests in it, in order.
+///
+/// `initialize` cannot be batched. A batch of only notifications gets 202.
+async fn handle_mcp_batch(
+    state: &HttpState,
+    batch: Vec<serde_json::Value>,
+    project_id_override: Option<&str>,
+    caller: &Caller,
+    session: Option<&McpSession>,
+) -> Response {
+    if batch.is_empty() {
+        return Json(json_rpc_error_response(-32600, "Empty batch", None)).into_response();
+    }
+
+    let mut responses = Vec::new();
+    for message in batch {
+        let response = if message.get("method").and_then(|v| v.as_str()) == Some("initialize") {
+            Some(json_rpc_error_response(
+                -32600,
+                "initialize cannot be part of a batch",
+                message.get("id").cloned(),
+            ))
+        } else {
+            handle_mcp_message(state, message, project_id_override, caller, session, None).await
+        };
+        responses.extend(response);
+    }
+
+    if responses.is_empty() {
+        StatusCode::ACCEPTED.into_response()
+    } else {
+        Json(responses).into_response()
+    }
+}
+
+/// Streamable HTTP GET: SSE stream of server-initiated messages for a session
+async fn open_mcp_stream(
+    State(state): State<HttpState>,
+    project_id: Option<Path<String>>,
+    Extension(caller): Extension<Caller>,
+    headers: HeaderMap,
+) -> Response {
+    use axum::response::sse::{Event, KeepAlive, Sse};
+    use std::convert::Infallible;
+    use tokio::sync::broadcast::error::RecvError;
+
+    if !accepts_event_stream(&headers) {
+        return error_response(StatusCode::NOT_ACCEPTABLE, "Accept must include text/event-stream");
+    }
+    let Some(id) = session_id(&headers) else {
+        return error_response(StatusCode::BAD_REQUEST, "Missing Mcp-Session-Id header");
+    };
+    let project_id = project_id.map(|Path(id)| id);
+    let receiver = state
+        .read()
+        .await
+        .mcp_sessions
+        .subscribe(id, caller.client_id(), project_id.as_deref());
+    let Some(mut receiver) = receiver else {
+        return session_not_found();
+    };
+
+    let stream = async_stream::stream! {
+        loop {
+            match receiver.recv().await {
+                Ok(message) => yield Ok::<_, Infallible>(Event::default().json_data(&message).unwrap()),
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::warn!("MCP session stream lagged, dropped {skipped} messages");
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    };
+    Sse::new(stream).keep_alive(KeepAlive::default()).into_response()
+}
+
+/// Streamable HTTP DELETE: end a session
+async fn end_mcp_session(
+    State(state): State<HttpState>,
+    project_id: Option<Path<String>>,
+    Extension(caller): Extension<Caller>,
+    headers: HeaderMap,
+) -> Response {
+    let Some(id) = session_id(&headers) else {
+        return error_response(StatusCode::BAD_REQUEST, "Missing Mcp-Session-Id header");
+    };
+    let project_id = project_id.map(|Path(id)| id);
+    let removed = state
+        .read()
+        .await
+        .mcp_sessions
+        .remove(id, caller.client_id(), project_id.as_deref());
+    if removed {
+        StatusCode::NO_CONTENT.into_response()
+    } else {
+        session_not_found()
+    }
+}
+
+/// Handle one JSON-RPC message with an optional `project_id` override.
+///
+/// `caller` is the authenticated client, used for policy checks; `session` is
+/// the Streamable HTTP session the message arrived on, if any; `stream` is the
+/// SSE stream the reply goes out on, if any. Returns `None` for messages that
+/// get no reply: notifications and responses.
+async fn handle_mcp_message(
+    state: &HttpState,
+    message: serde_json::Value,
+    project_id_override: Option<&str>,
+    caller: &Caller,
+    session: Option<&McpSession>,
+    stream: Option<&mpsc::UnboundedSender<serde_json::Value>>,
+) -> Option<JsonRpcResponse> {
+    let is_client_response = message.get("method").is_none()
+        && (message.get("result").is_some() || message.get("error").is_some());
+    if is_client_response {
+        return None;
+    }
+
+    let request = match parse_json_rpc_request(message) {
         Ok(req) => req,
-        Err(response) => return Json(response),
+        Err(response) => return Some(response),
     };
+    let Some(id) = request.id.clone() else {
+        handle_mcp_notification(state, caller, session, &request.method, request.params.as_ref()).await;
+        return None;
+    };
+    let id = Some(id);
 
     let response = match request.method.as_str() {
-        "initialize" => handle_mcp_initialize(request.id),
-        "tools/list" => handle_mcp_tools_list(&state, request.id).await,
-        "tools/call" => handle_mcp_tools_call(&state, request.id, request.params, project_id_override.as_deref()).await,
-        "resources/list" => handle_mcp_resources_list(&state, request.id, request.params, project_id_override.as_deref()).await,
-        "resources/read" => handle_mcp_resources_read(&state, request.id, request.params, project_id_override.as_deref()).await,
-        _ => json_rpc_error_response(-32601, format!("Method not found: {}", request.method), request.id),
+        "initialize" => handle_mcp_initialize(id, request.params.as_ref()),
+        "ping" => JsonRpcResponse {
+            jsonrpc: "2.0".to_string(),
+            result: Some(json!({})),
+            error: None,
+            id,
+        },
+        "tools/list" => handle_mcp_tools_list(state, id, request.params, project_id_override).await,
+        "tools/call" => {
+            let outbound = McpOutbound { session, stream };
+            handle_mcp_tools_call(state, id, request.params, project_id_override, caller, outbound).await
+        }
+        "resources/list" => handle_mcp_resources_list(state, id, request.params, project_id_override).await,
+        "resources/templates/list" => handle_mcp_resource_templates_list(id),
+        "resources/read" => handle_mcp_resources_read(state, id, request.params, project_id_override, caller).await,
+        "resources/subscribe" => {
+            handle_mcp_resources_subscribe(state, id, request.params, project_id_override, caller, session).await
+        }
+        "resources/unsubscribe" => handle_mcp_resources_unsubscribe(state, id, request.params, session).await,
+        "prompts/list" => handle_mcp_prompts_list(state, id, request.params, project_id_override).await,
+        "prompts/get" => handle_mcp_prompts_get(state, id, request.params, project_id_override, caller).await,
+        _ => json_rpc_error_response(-32601, format!("Method not found: {}", request.method), id),
     };
+    Some(response)
+}
+
+/// Handle a client notification (no reply is ever sent).
+///
+/// `notifications/cancelled` stops the in-flight request with that id from the
+/// same session (or, without a session, from the same client).
+async fn handle_mcp_notification(
+    state: &HttpState,
+    caller: &Caller,
+    session: Option<&McpSession>,
+    method: &str,
+    params: Option<&serde_json::Value>,
+) {
+    match method {
+        "notifications/initialized" => tracing::debug!("MCP client initialized"),
+        "notifications/cancelled" => {
+            let Some(request_id) = params.and_then(|p| p.get("requestId")) else {
+                return;
+            };
+            let cancelled = state
+                .read()
+                .await
+                .mcp_requests
+                .cancel(session.map(|s| s.id.as_str()), caller.client_id(), request_id);
+            tracing::debug!("MCP client cancelled request {request_id} (running: {cancelled})");
+        }
+        _ => tracing::debug!("Ignoring MCP notification: {method}"),
+    }
+}
 
-    Json(response)
+/// Query parameters for `/events/stream`
+#[derive(Debug, Default, Deserialize)]
+struct StreamParams {
+    project: Option<String>,
 }
 
 /// Stream events (SSE - Server Sent Events)
-async fn stream_events(State(state): State<HttpState>) -> axum::response::Response {
+///
+/// Filtered like `/events`: one project's calls, to tools the client may use there.
+async fn stream_events(
+    State(state): State<HttpState>,
+    Extension(caller): Extension<Caller>,
+    Query(params): Query<StreamParams>,
+) -> axum::response::Response {
     use axum::response::sse::{Event, Sse};
     use std::convert::Infallible;
     
-    let state = state.read().await;
-    let mut rx = state.subscribe();
+    let project_id = params.project.unwrap_or_else(|| "default".to_string());
+    let mut rx = state.read().await.subscribe();
     
     let stream = async_stream::stream! {
         while let Ok(event) = rx.recv().await {
+            if !event_visible(&state, &caller, &project_id, &event).await {
+                continue;
+            }
             yield Ok::<_, Infallible>(Event::default().json_data(&event).unwrap());
         }
     };
@@ -208,8 +748,30 @@ async fn stream_events(State(state): State<HttpState>) -> axum::response::Respon
     Sse::new(stream).into_response()
 }
 
+/// Whether a live event belongs on a client's `/events/stream`
+async fn event_visible(
+    state: &HttpState,
+    caller: &Caller,
+    project_id: &str,
+    event: &crate::ToolCallEvent,
+) -> bool {
+    if event.project_id != project_id {
+        return false;
+    }
+    let state = state.read().await;
+    // Policies are re-read per event so changes apply to open streams
+    match caller_policies(&state, caller, project_id) {
+        Ok(policies) => tool_visible(&policies, &event.tool_name),
+        Err(e) => {
+            tracing::warn!("Dropping event {} from stream: {}", event.id, e);
+            false
+        }
+    }
+}
+
 struct ToolCallResult {
     content: String,
+    data: Option<serde_json::Value>,
     duration_ms: u64,
 }
 
@@ -218,68 +780,26 @@ async fn execute_tool_call(
     tool_name: &str,
     arguments: serde_json::Value,
     project_id: String,
+    caller: Caller,
 ) -> Result<ToolCallResult, String> {
-    use std::time::Instant;
-    use uuid::Uuid;
-
-    let start = Instant::now();
-    let call_id = Uuid::new_v4().to_string();
-    let timestamp = chrono::Utc::now().to_rfc3339();
-
-    let result = if is_todo_tool(tool_name) {
-        execute_todo_tool_call(state.clone(), tool_name, arguments.clone(), &project_id).await
-    } else if is_build_tool(tool_name) {
-        execute_build_tool_call(state.clone(), tool_name, arguments.clone(), &project_id).await
-    } else if is_next_session_tool(tool_name) {
-        execute_next_session_tool_call(state.clone(), tool_name, arguments.clone(), &project_id).await
-    } else {
-        let state_read = state.read().await;
-        let tool = match state_read.tool_registry.get(tool_name) {
-            Some(t) => t,
-            None => return Err(format!("Tool not found: {}", tool_name)),
-        };
-        tool.execute(arguments.clone())
-            .await
-            .map(|r| r.content)
-            .map_err(|e| e.to_string())
-    };
-    let duration_ms = start.elapsed().as_millis() as u64;
-
-    let event = match &result {
-        Ok(output) => ToolCallEvent {
-            id: call_id.clone(),
-            timestamp: timestamp.clone(),
-            tool_name: tool_name.to_string(),
-            project_id: project_id.clone(),
-            arguments: arguments.clone(),
-            success: true,
-            content: output.clone(),
-            duration_ms,
-        },
-        Err(e) => ToolCallEvent {
-            id: call_id.clone(),
-            timestamp: timestamp.clone(),
-            tool_name: tool_name.to_string(),
-            project_id: project_id.clone(),
-            arguments: arguments.clone(),
-            success: false,
-            content: e.to_string(),
-            duration_ms,
-        },
+    let call = ToolCall {
+        tool_name: tool_name.to_string(),
+        arguments,
+        project_id,
+        caller,
+        progress: ProgressReporter::default(),
+        cancel: CancellationToken::new(),
     };
+    run_tool_call(state, call).await
+}
 
-    {
-        let state_read = state.read().await;
-        state_read.record_event(event).await;
-    }
-
-    match result {
-        Ok(output) => Ok(ToolCallResult {
-            content: output,
-            duration_ms,
-        }),
-        Err(e) => Err(e.to_string()),
-    }
+async fn run_tool_call(state: HttpState, call: ToolCall) -> Result<ToolCallResult, String> {
+    let outcome = tool_dispatch::dispatch(&state, call).await;
+    outcome.result.map(|output| ToolCallResult {
+        content: output.content,
+        data: output.data,
+        duration_ms: outcome.event.duration_ms,
+    })
 }
 
 /// Extract tool call inputs from HTTP request body.
@@ -297,19 +817,6 @@ fn parse_project_id(body: &serde_json::Value) -> String {
         .to_string()
 }
 
-fn require_arg_string(args: &serde_json::Value, key: &str) -> Result<String, String> {
-    args.get(key)
-        .and_then(|v| v.as_str())
-        .map(|s| s.to_string())
-        .ok_or_else(|| format!("Missing '{}'", key))
-}
-
-fn require_arg_i64(args: &serde_json::Value, key: &str) -> Result<i64, String> {
-    args.get(key)
-        .and_then(|v| v.as_i64())
-        .ok_or_else(|| format!("Missing '{}'", key))
-}
-
 /// Parse a JSON-RPC request and validate protocol version.
 fn parse_json_rpc_request(request: serde_json::Value) -> Result<JsonRpcRequest, JsonRpcResponse> {
     let parsed: Result<JsonRpcRequest, _> = serde_json::from_value(request);
@@ -348,68 +855,113 @@ fn require_str_param<'a>(
 }
 
 /// Map tool definitions into JSON payloads.
+///
+/// The output schema key follows the input one (`inputSchema` -> `outputSchema`).
 fn map_tools(tools: &[crate::tools::ToolDefinition], schema_key: &str) -> Vec<serde_json::Value> {
+    let output_key = schema_key.replace("input", "output");
     tools
         .iter()
         .map(|t| {
-            json!({
+            let mut tool = json!({
                 "name": t.name,
                 "description": t.description,
                 schema_key: t.input_schema,
-            })
+            });
+            if let Some(output_schema) = &t.output_schema {
+                tool[&output_key] = output_schema.clone();
+            }
+            if !t.annotations.is_empty() {
+                tool["annotations"] = json!(t.annotations);
+            }
+            tool
         })
         .collect::<Vec<_>>()
 }
 
 /// Build a standard MCP tool call response.
-fn mcp_content_response(id: Option<serde_json::Value>, content: String, is_error: bool) -> JsonRpcResponse {
+///
+/// `structured` is the tool's `data`, sent as `structuredContent` next to the text.
+fn mcp_content_response(
+    id: Option<serde_json::Value>,
+    content: &str,
+    structured: Option<serde_json::Value>,
+    is_error: bool,
+) -> JsonRpcResponse {
+    let mut result = json!({
+        "content": [
+            {
+                "type": "text",
+                "text": content
+            }
+        ],
+        "isError": is_error
+    });
+    if let Some(structured) = structured {
+        result["structuredContent"] = structured;
+    }
     JsonRpcResponse {
         jsonrpc: "2.0".to_string(),
-        result: Some(json!({
-            "content": [
-                {
-                    "type": "text",
-                    "text": content
-                }
-            ],
-            "isError": is_error
-        })),
+        result: Some(result),
         error: None,
         id,
     }
 }
 
-/// Handle MCP initialize.
-fn handle_mcp_initialize(id: Option<serde_json::Value>) -> JsonRpcResponse {
+/// Handle MCP initialize, negotiating the protocol version.
+fn handle_mcp_initialize(
+    id: Option<serde_json::Value>,
+    params: Option<&serde_json::Value>,
+) -> JsonRpcResponse {
     JsonRpcResponse {
         jsonrpc: "2.0".to_string(),
-        result: Some(json!({
-            "protocolVersion": MCP_PROTOCOL_VERSION,
-            "capabilities": {
-                "tools": {},
-                "resources": {}
-            },
-            "serverInfo": {
-                "name": "aiharness",
-                "version": env!("CARGO_PKG_VERSION")
-            }
-        })),
+        result: Some(initialize_result(params)),
         error: None,
         id,
     }
 }
 
+/// The `initialize` result for a client's params (also used by the stdio
+/// proxy to answer `initialize` while the server is down).
+pub(crate) fn initialize_result(params: Option<&serde_json::Value>) -> serde_json::Value {
+    let requested = params
+        .and_then(|p| p.get("protocolVersion"))
+        .and_then(|v| v.as_str());
+    json!({
+        "protocolVersion": negotiate_protocol_version(requested),
+        "capabilities": {
+            "tools": {},
+            "resources": { "subscribe": true, "listChanged": true },
+            "prompts": {}
+        },
+        "serverInfo": {
+            "name": "aiharness",
+            "version": env!("CARGO_PKG_VERSION")
+        }
+    })
+}
+
 /// Handle MCP tools/list.
+///
+/// Lists the built-in tools, then the tools of the project's upstream MCP servers.
 async fn handle_mcp_tools_list(
     state: &HttpState,
     id: Option<serde_json::Value>,
+    params: Option<serde_json::Value>,
+    project_id_override: Option<&str>,
 ) -> JsonRpcResponse {
-    let state = state.read().await;
-    let mut tools = state.tool_registry.list();
-    tools.extend(todo_tool_definitions());
-    tools.extend(build_tool_definitions());
-    tools.extend(next_session_tool_definitions());
-    let tools = map_tools(&tools, "inputSchema");
+    let project_id = mcp_project_id(project_id_override, params.as_ref());
+    let (mut definitions, gateway, store) = {
+        let state_read = state.read().await;
+        (
+            state_read.tool_registry.list(),
+            state_read.mcp_gateway.clone(),
+            state_read.get_project_store(&project_id).await.ok(),
+        )
+    };
+    if let Some(store) = store {
+        definitions.extend(gateway.tools(&store).await.iter().map(Tool::definition));
+    }
+    let tools = map_tools(&definitions, "inputSchema");
     JsonRpcResponse {
         jsonrpc: "2.0".to_string(),
         result: Some(json!({ "tools": tools })),
@@ -418,12 +970,77 @@ async fn handle_mcp_tools_list(
     }
 }
 
+/// Project a request applies to: the URL path override, else `projectId` /
+/// `project_id` in params, else "default".
+fn mcp_project_id(project_id_override: Option<&str>, params: Option<&serde_json::Value>) -> String {
+    project_id_override
+        .or_else(|| {
+            params
+                .and_then(|p| p.get("projectId").or_else(|| p.get("project_id")))
+                .and_then(|v| v.as_str())
+        })
+        .unwrap_or("default")
+        .to_string()
+}
+
+/// Where server messages about a request in progress are sent
+#[derive(Clone, Copy, Default)]
+struct McpOutbound<'a> {
+    session: Option<&'a McpSession>,
+    stream: Option<&'a mpsc::UnboundedSender<serde_json::Value>>,
+}
+
+impl McpOutbound<'_> {
+    /// Reporter sending `notifications/progress` for `token`: on the request's
+    /// SSE stream, else the session's stream. Without either, updates are dropped.
+    fn progress_reporter(
+        self,
+        sessions: Arc<McpSessionManager>,
+        token: serde_json::Value,
+    ) -> ProgressReporter {
+        let send: Box<dyn Fn(serde_json::Value) + Send + Sync> = match (self.stream, self.session) {
+            (Some(stream), _) => {
+                let stream = stream.clone();
+                Box::new(move |message| {
+                    let _ = stream.send(message);
+                })
+            }
+            (None, Some(session)) => {
+                let session_id = session.id.clone();
+                Box::new(move |message| {
+                    sessions.send(&session_id, message);
+                })
+            }
+            (None, None) => return ProgressReporter::default(),
+        };
+        ProgressReporter::new(move |update: Progress| {
+            let mut params = json!({ "progressToken": token, "progress": update.progress });
+            if let Some(total) = update.total {
+                params["total"] = json!(total);
+            }
+            if let Some(message) = update.message {
+                params["message"] = json!(message);
+            }
+            send(json!({
+                "jsonrpc": "2.0",
+                "method": "notifications/progress",
+                "params": params
+            }));
+        })
+    }
+}
+
 /// Handle MCP tools/call.
+///
+/// A `progressToken` in `_meta` asks for progress notifications. The call is
+/// stopped by a `notifications/cancelled` naming its request id.
 async fn handle_mcp_tools_call(
     state: &HttpState,
     id: Option<serde_json::Value>,
     params: Option<serde_json::Value>,
     project_id_override: Option<&str>,
+    caller: &Caller,
+    outbound: McpOutbound<'_>,
 ) -> JsonRpcResponse {
     let params = match require_params(params, id.clone()) {
         Ok(p) => p,
@@ -436,22 +1053,34 @@ async fn handle_mcp_tools_call(
     };
 
     let arguments = params.get("arguments").cloned().unwrap_or(json!({}));
-    
-    // Use project_id from URL path override, or from params, or default to "default"
-    let project_id = project_id_override
-        .map(|s| s.to_string())
-        .or_else(|| {
-            params
-                .get("projectId")
-                .or_else(|| params.get("project_id"))
-                .and_then(|v| v.as_str())
-                .map(|s| s.to_string())
-        })
-        .unwrap_or_else(|| "default".to_string());
+    let project_id = mcp_project_id(project_id_override, Some(&params));
+
+    let (sessions, requests) = {
+        let state_read = state.read().await;
+        (state_read.mcp_sessions.clone(), state_read.mcp_requests.clone())
+    };
+    let progress = params
+        .get("_meta")
+        .and_then(|meta| meta.get("progressToken"))
+        .map(|token| outbound.progress_reporter(sessions, token.clone()))
+        .unwrap_or_default();
+    let request = requests.start(
+        outbound.session.map(|s| s.id.as_str()),
+        caller.client_id(),
+        id.as_ref().unwrap_or(&serde_json::Value::Null),
+    );
+    let call = ToolCall {
+        tool_name: tool_name.to_string(),
+        arguments,
+        project_id,
+        caller: caller.clone(),
+        progress,
+        cancel: request.cancel.clone(),
+    };
 
-    match execute_tool_call(state.clone(), tool_name, arguments, project_id).await {
-        Ok(result) => mcp_content_response(id, result.content, false),
-        Err(error) => mcp_content_response(id, error, true),
+    match run_tool_call(state.clone(), call).await {
+        Ok(result) => mcp_content_response(id, &result.content, result.data, false),
+        Err(error) => mcp_content_response(id, &error, None, true),
     }
 }
 
@@ -462,17 +1091,7 @@ async fn handle_mcp_resources_list(
     params: Option<serde_json::Value>,
     project_id_override: Option<&str>,
 ) -> JsonRpcResponse {
-    // Use project_id from URL path override, or from params, or default to "default"
-    let project_id = project_id_override
-        .map(|s| s.to_string())
-        .or_else(|| {
-            params
-                .as_ref()
-                .and_then(|p| p.get("projectId").or_else(|| p.get("project_id")))
-                .and_then(|v| v.as_str())
-                .map(|s| s.to_string())
-        })
-        .unwrap_or_else(|| "default".to_string());
+    let project_id = mcp_project_id(project_id_override, params.as_ref());
     let store = {
         let state_read = state.read().await;
         match state_read.get_project_store(&project_id).await {
@@ -485,7 +1104,7 @@ async fn handle_mcp_resources_list(
         Err(e) => return json_rpc_error_response(-32603, e.to_string(), id),
     };
 
-    let resources = files
+    let mut resources = files
         .into_iter()
         .map(|f| {
             json!({
@@ -498,6 +1117,9 @@ async fn handle_mcp_resources_list(
             })
         })
         .collect::<Vec<_>>();
+    resources.extend(mcp_resources::list(&project_id));
+    let gateway = state.read().await.mcp_gateway.clone();
+    resources.extend(gateway.resources(&store).await);
 
     JsonRpcResponse {
         jsonrpc: "2.0".to_string(),
@@ -507,12 +1129,27 @@ async fn handle_mcp_resources_list(
     }
 }
 
+/// Handle MCP resources/templates/list.
+fn handle_mcp_resource_templates_list(id: Option<serde_json::Value>) -> JsonRpcResponse {
+    JsonRpcResponse {
+        jsonrpc: "2.0".to_string(),
+        result: Some(json!({ "resourceTemplates": mcp_resources::templates() })),
+        error: None,
+        id,
+    }
+}
+
 /// Handle MCP resources/read.
+///
+/// `aiharness://` URIs read project data; `file://` URIs read a project's
+/// context files, or any file inside the project sandbox; `upstream://` URIs
+/// read a resource of one of the project's upstream MCP servers.
 async fn handle_mcp_resources_read(
     state: &HttpState,
     id: Option<serde_json::Value>,
     params: Option<serde_json::Value>,
-    _project_id_override: Option<&str>,
+    project_id_override: Option<&str>,
+    caller: &Caller,
 ) -> JsonRpcResponse {
     let params = match require_params(params, id.clone()) {
         Ok(p) => p,
@@ -524,487 +1161,398 @@ async fn handle_mcp_resources_read(
         Err(e) => return e,
     };
 
-    let path = match uri.strip_prefix("file://") {
-        Some(p) => p,
-        None => return json_rpc_error_response(-32602, "Invalid uri", id),
-    };
-
-    let result = {
-        let state_read = state.read().await;
-        let read_file = match state_read
-            .tool_registry
-            .get("read_file")
-            .ok_or_else(|| "read_file tool not found")
-        {
-            Ok(tool) => tool,
-            Err(e) => return json_rpc_error_response(-32603, e, id),
+    if uri.starts_with(mcp_gateway::RESOURCE_SCHEME) {
+        let project_id = mcp_project_id(project_id_override, Some(&params));
+        return match read_upstream_resource(state, uri, &project_id).await {
+            Ok(contents) => JsonRpcResponse {
+                jsonrpc: "2.0".to_string(),
+                result: Some(json!({ "contents": contents })),
+                error: None,
+                id,
+            },
+            Err((code, message)) => json_rpc_error_response(code, message, id),
         };
+    }
 
-        match read_file.execute(json!({ "path": path })).await {
-            Ok(r) => r,
-            Err(e) => return json_rpc_error_response(-32603, e.to_string(), id),
-        }
+    let contents = if uri.starts_with(mcp_resources::SCHEME) {
+        read_project_resource(state, uri, project_id_override).await
+    } else if let Some(path) = uri.strip_prefix("file://") {
+        let project_id = mcp_project_id(project_id_override, Some(&params));
+        read_file_resource(state, uri, path, &project_id, caller).await
+    } else {
+        Err((-32602, "Invalid uri".to_string()))
     };
 
-    JsonRpcResponse {
-        jsonrpc: "2.0".to_string(),
-        result: Some(json!({
-            "contents": [
-                {
-                    "uri": uri,
-                    "mimeType": "text/plain",
-                    "text": result.content
-                }
-            ]
-        })),
-        error: None,
-        id,
+    match contents {
+        Ok(contents) => JsonRpcResponse {
+            jsonrpc: "2.0".to_string(),
+            result: Some(json!({ "contents": [contents] })),
+            error: None,
+            id,
+        },
+        Err((code, message)) => json_rpc_error_response(code, message, id),
     }
 }
 
-fn todo_tool_definitions() -> Vec<ToolDefinition> {
-    vec![
-        ToolDefinition {
-            name: "todo_add".to_string(),
-            description: "Add a todo item to the ordered list".to_string(),
-            input_schema: json!({
-                "type": "object",
-                "properties": {
-                    "title": {"type": "string"},
-                    "description": {"type": "string"},
-                    "position": {"type": "integer"},
-                    "project_id": {"type": "string"}
-                },
-                "required": ["title"]
-            }),
-        },
-        ToolDefinition {
-            name: "todo_remove".to_string(),
-            description: "Remove a todo item".to_string(),
-            input_schema: json!({
-                "type": "object",
-                "properties": {"id": {"type": "string"}},
-                "required": ["id"]
-            }),
-        },
-        ToolDefinition {
-            name: "todo_check".to_string(),
-            description: "Mark a todo item completed or not".to_string(),
-            input_schema: json!({
-                "type": "object",
-                "properties": {
-                    "id": {"type": "string"},
-                    "completed": {"type": "boolean"}
-                },
-                "required": ["id"]
-            }),
-        },
-        ToolDefinition {
-            name: "todo_list".to_string(),
-            description: "List all todos in order".to_string(),
-            input_schema: json!({
-                "type": "object",
-                "properties": {"project_id": {"type": "string"}}
-            }),
-        },
-        ToolDefinition {
-            name: "todo_get_next".to_string(),
-            description: "Get the next incomplete todo".to_string(),
-            input_schema: json!({
-                "type": "object",
-                "properties": {"project_id": {"type": "string"}}
-            }),
-        },
-        ToolDefinition {
-            name: "todo_insert".to_string(),
-            description: "Insert a todo at a specific position".to_string(),
-            input_schema: json!({
-                "type": "object",
-                "properties": {
-                    "title": {"type": "string"},
-                    "description": {"type": "string"},
-                    "position": {"type": "integer"}
-                },
-                "required": ["title", "position"]
-            }),
-        },
-        ToolDefinition {
-            name: "todo_move".to_string(),
-            description: "Move a todo to a new position".to_string(),
-            input_schema: json!({
-                "type": "object",
-                "properties": {"id": {"type": "string"}, "position": {"type": "integer"}},
-                "required": ["id", "position"]
-            }),
-        },
-    ]
-}
+/// Handle MCP resources/subscribe.
+///
+/// Subscriptions belong to the session; `notifications/resources/updated`
+/// arrives on its `GET` stream. The resource must be one the caller could
+/// read: an `aiharness://` resource of the project, a context file, or a file
+/// inside the project sandbox.
+async fn handle_mcp_resources_subscribe(
+    state: &HttpState,
+    id: Option<serde_json::Value>,
+    params: Option<serde_json::Value>,
+    project_id_override: Option<&str>,
+    caller: &Caller,
+    session: Option<&McpSession>,
+) -> JsonRpcResponse {
+    let Some(session) = session else {
+        return json_rpc_error_response(-32600, "Subscriptions require an MCP session", id);
+    };
+    let params = match require_params(params, id.clone()) {
+        Ok(p) => p,
+        Err(e) => return e,
+    };
+    let uri = match require_str_param(&params, "uri", id.clone()) {
+        Ok(uri) => uri,
+        Err(e) => return e,
+    };
 
-fn is_todo_tool(tool_name: &str) -> bool {
-    matches!(
-        tool_name,
-        "todo_add" | "todo_remove" | "todo_check" | "todo_list" | "todo_get_next" | "todo_insert" | "todo_move"
-    )
+    let watched = if uri.starts_with(mcp_resources::SCHEME) {
+        match mcp_resources::parse_uri(uri) {
+            Some((project_id, _)) if project_id_override.map_or(true, |p| p == project_id) => {
+                Watched::Project(project_id)
+            }
+            Some(_) => {
+                return json_rpc_error_response(
+                    -32602,
+                    format!("Resource belongs to another project: {uri}"),
+                    id,
+                )
+            }
+            None => return json_rpc_error_response(-32602, format!("Unknown resource: {uri}"), id),
+        }
+    } else if let Some(path) = uri.strip_prefix("file://") {
+        let project_id = mcp_project_id(project_id_override, Some(&params));
+        match check_file_resource(state, path, &project_id, caller).await {
+            Ok(resolved) => Watched::File(resolved),
+            Err((code, message)) => return json_rpc_error_response(code, message, id),
+        }
+    } else {
+        return json_rpc_error_response(-32602, "Invalid uri", id);
+    };
+
+    let fingerprint = resource_subscriptions::fingerprint(state, uri, &watched).await;
+    state
+        .read()
+        .await
+        .resource_subscriptions
+        .subscribe(&session.id, uri, watched, fingerprint);
+    JsonRpcResponse {
+        jsonrpc: "2.0".to_string(),
+        result: Some(json!({})),
+        error: None,
+        id,
+    }
 }
 
-fn build_tool_definitions() -> Vec<ToolDefinition> {
-    vec![
-        ToolDefinition {
-            name: "build_add_command".to_string(),
-            description: "Add a build command to the project.".to_string(),
-            input_schema: json!({
-                "type": "object",
-                "properties": {
-                    "name": { "type": "string" },
-                    "command": { "type": "string" },
-                    "working_dir": { "type": "string" }
-                },
-                "required": ["name", "command"]
-            }),
-        },
-        ToolDefinition {
-            name: "build_remove_command".to_string(),
-            description: "Remove a build command by id.".to_string(),
-            input_schema: json!({
-                "type": "object",
-                "properties": {
-                    "id": { "type": "string" }
-                },
-                "required": ["id"]
-            }),
-        },
-        ToolDefinition {
-            name: "build_list_commands".to_string(),
-            description: "List build commands for the project.".to_string(),
-            input_schema: json!({
-                "type": "object",
-                "properties": {}
-            }),
-        },
-        ToolDefinition {
-            name: "build_run_command".to_string(),
-            description: "Run a build command by id.".to_string(),
-            input_schema: json!({
-                "type": "object",
-                "properties": {
-                    "id": { "type": "string" }
-                },
-                "required": ["id"]
-            }),
-        },
-        ToolDefinition {
-            name: "build_set_default".to_string(),
-            description: "Set the default build command by id.".to_string(),
-            input_schema: json!({
-                "type": "object",
-                "properties": {
-                    "id": { "type": "string" }
-                },
-                "required": ["id"]
-            }),
-        },
-        ToolDefinition {
-            name: "build_get_default".to_string(),
-            description: "Get the default build command.".to_string(),
-            input_schema: json!({
-                "type": "object",
-                "properties": {}
-            }),
-        },
-    ]
-}
-
-fn is_build_tool(tool_name: &str) -> bool {
-    matches!(
-        tool_name,
-        "build_add_command"
-            | "build_remove_command"
-            | "build_list_commands"
-            | "build_run_command"
-            | "build_set_default"
-            | "build_get_default"
-    )
+/// Handle MCP resources/unsubscribe.
+async fn handle_mcp_resources_unsubscribe(
+    state: &HttpState,
+    id: Option<serde_json::Value>,
+    params: Option<serde_json::Value>,
+    session: Option<&McpSession>,
+) -> JsonRpcResponse {
+    let Some(session) = session else {
+        return json_rpc_error_response(-32600, "Subscriptions require an MCP session", id);
+    };
+    let params = match require_params(params, id.clone()) {
+        Ok(p) => p,
+        Err(e) => return e,
+    };
+    let uri = match require_str_param(&params, "uri", id.clone()) {
+        Ok(uri) => uri,
+        Err(e) => return e,
+    };
+
+    state
+        .read()
+        .await
+        .resource_subscriptions
+        .unsubscribe(&session.id, uri);
+    JsonRpcResponse {
+        jsonrpc: "2.0".to_string(),
+        result: Some(json!({})),
+        error: None,
+        id,
+    }
 }
 
-fn next_session_tool_definitions() -> Vec<ToolDefinition> {
-    vec![
-        ToolDefinition {
-            name: "next_session_read".to_string(),
-            description: "Read the next session briefing for the project.".to_string(),
-            input_schema: json!({
-                "type": "object",
-                "properties": {}
-            }),
-        },
-        ToolDefinition {
-            name: "next_session_write".to_string(),
-            description: "Write the next session briefing for the project.".to_string(),
-            input_schema: json!({
-                "type": "object",
-                "properties": { "content": { "type": "string" } },
-                "required": ["content"]
-            }),
-        },
-    ]
+/// Check that a `file://` resource may be watched: a context file, or a path
+/// inside the project sandbox, that policies allow reading. Returns the path
+/// to watch (sandbox paths resolved against the project root).
+async fn check_file_resource(
+    state: &HttpState,
+    path: &str,
+    project_id: &str,
+    caller: &Caller,
+) -> Result<std::path::PathBuf, (i32, String)> {
+    let ctx = state
+        .read()
+        .await
+        .tool_context(project_id, caller.clone())
+        .await
+        .map_err(|e| (-32603, e.to_string()))?;
+    tool_dispatch::check_tool_policy(state, &ctx, "read_file", &json!({ "path": path }))
+        .await
+        .map_err(|denied| (-32603, denied))?;
+
+    let is_context_file = ctx
+        .store
+        .context_store
+        .read()
+        .await
+        .contains(path)
+        .await
+        .unwrap_or(false);
+    if is_context_file {
+        return Ok(std::path::PathBuf::from(path));
+    }
+    ctx.sandbox
+        .resolve(path)
+        .map_err(|e| (-32602, e.to_string()))
 }
 
-fn is_next_session_tool(tool_name: &str) -> bool {
-    matches!(tool_name, "next_session_read" | "next_session_write")
+/// Handle MCP prompts/list: the project's prompt library plus global prompts.
+async fn handle_mcp_prompts_list(
+    state: &HttpState,
+    id: Option<serde_json::Value>,
+    params: Option<serde_json::Value>,
+    project_id_override: Option<&str>,
+) -> JsonRpcResponse {
+    let project_id = mcp_project_id(project_id_override, params.as_ref());
+    let state_read = state.read().await;
+    let store = match state_read.get_project_store(&project_id).await {
+        Ok(store) => store,
+        Err(e) => return json_rpc_error_response(-32603, e.to_string(), id),
+    };
+    let library = match prompts::library(&store, &state_read.prompts).await {
+        Ok(library) => library,
+        Err(e) => return json_rpc_error_response(-32603, e.to_string(), id),
+    };
+
+    let prompts = library
+        .into_iter()
+        .map(|prompt| {
+            let arguments = prompt
+                .arguments
+                .iter()
+                .map(|a| json!({ "name": a.name, "description": a.description, "required": a.required }))
+                .collect::<Vec<_>>();
+            json!({
+                "name": prompt.name,
+                "description": prompt.description,
+                "arguments": arguments,
+            })
+        })
+        .collect::<Vec<_>>();
+    JsonRpcResponse {
+        jsonrpc: "2.0".to_string(),
+        result: Some(json!({ "prompts": prompts })),
+        error: None,
+        id,
+    }
 }
 
-async fn execute_build_tool_call(
-    state: HttpState,
-    tool_name: &str,
-    arguments: serde_json::Value,
-    project_id: &str,
-) -> Result<String, String> {
-    match tool_name {
-        "build_add_command" => {
-            let name = arguments
-                .get("name")
-                .and_then(|v| v.as_str())
-                .ok_or_else(|| "Missing name".to_string())?;
-            let command = arguments
-                .get("command")
-                .and_then(|v| v.as_str())
-                .ok_or_else(|| "Missing command".to_string())?;
-            let working_dir = arguments
-                .get("working_dir")
-                .and_then(|v| v.as_str())
-                .map(|s| s.to_string());
-            let store = {
-                let state_read = state.read().await;
-                state_read
-                    .get_project_store(project_id)
-                    .await
-                    .map_err(|e| e.to_string())?
-            };
-            let store = store.build_command_store.read().await;
-            let command = store
-                .add(&name, &command, working_dir)
-                .await
-                .map_err(|e| e.to_string())?;
-            Ok(serde_json::to_string(&command).unwrap_or_default())
-        }
-        "build_remove_command" => {
-            let id = arguments
-                .get("id")
-                .and_then(|v| v.as_str())
-                .ok_or_else(|| "Missing id".to_string())?;
-            let store = {
-                let state_read = state.read().await;
-                state_read
-                    .get_project_store(project_id)
-                    .await
-                    .map_err(|e| e.to_string())?
-            };
-            let store = store.build_command_store.read().await;
-            store.remove(&id).await.map_err(|e| e.to_string())?;
-            Ok("ok".to_string())
-        }
-        "build_list_commands" => {
-            let store = {
-                let state_read = state.read().await;
-                state_read
-                    .get_project_store(project_id)
-                    .await
-                    .map_err(|e| e.to_string())?
-            };
-            let store = store.build_command_store.read().await;
-            let list = store.list().await.map_err(|e| e.to_string())?;
-            Ok(serde_json::to_string(&list).unwrap_or_default())
-        }
-        "build_run_command" => {
-            let id = arguments
-                .get("id")
-                .and_then(|v| v.as_str())
-                .ok_or_else(|| "Missing id".to_string())?;
-            let (command, root_path) = {
-                let state_read = state.read().await;
-                let project = state_read
-                    .project_registry
-                    .get_project(project_id)
-                    .await
-                    .map_err(|e| e.to_string())?
-                    .ok_or_else(|| "Project not found".to_string())?;
-                let store = state_read
-                    .get_project_store(project_id)
-                    .await
-                    .map_err(|e| e.to_string())?;
-                let store = store.build_command_store.read().await;
-                let command = store
-                    .get(&id)
-                    .await
-                    .map_err(|e| e.to_string())?
-                    .ok_or_else(|| "Build command not found".to_string())?;
-                let working_dir = command
-                    .working_dir
-                    .clone()
-                    .unwrap_or_else(|| project.root_path.clone());
-                (command.command, working_dir)
-            };
-            crate::run_shell_command(&command, &root_path).await
-        }
-        "build_set_default" => {
-            let id = arguments
-                .get("id")
-                .and_then(|v| v.as_str())
-                .ok_or_else(|| "Missing id".to_string())?;
-            let store = {
-                let state_read = state.read().await;
-                state_read
-                    .get_project_store(project_id)
-                    .await
-                    .map_err(|e| e.to_string())?
-            };
-            let store = store.build_command_store.read().await;
-            store.set_default(&id).await.map_err(|e| e.to_string())?;
-            Ok("ok".to_string())
-        }
-        "build_get_default" => {
-            let store = {
-                let state_read = state.read().await;
-                state_read
-                    .get_project_store(project_id)
-                    .await
-                    .map_err(|e| e.to_string())?
-            };
-            let store = store.build_command_store.read().await;
-            let command = store.get_default().await.map_err(|e| e.to_string())?;
-            Ok(serde_json::to_string(&command).unwrap_or_default())
+/// Handle MCP prompts/get: render a prompt as a single user message.
+async fn handle_mcp_prompts_get(
+    state: &HttpState,
+    id: Option<serde_json::Value>,
+    params: Option<serde_json::Value>,
+    project_id_override: Option<&str>,
+    caller: &Caller,
+) -> JsonRpcResponse {
+    let params = match require_params(params, id.clone()) {
+        Ok(p) => p,
+        Err(e) => return e,
+    };
+    let name = match require_str_param(&params, "name", id.clone()) {
+        Ok(name) => name,
+        Err(e) => return e,
+    };
+    let values: HashMap<String, String> = params
+        .get("arguments")
+        .and_then(|v| v.as_object())
+        .map(|arguments| {
+            arguments
+                .iter()
+                .map(|(key, value)| {
+                    let value = value.as_str().map_or_else(|| value.to_string(), str::to_string);
+                    (key.clone(), value)
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let project_id = mcp_project_id(project_id_override, Some(&params));
+
+    let (prompt, ctx) = {
+        let state_read = state.read().await;
+        let ctx = match state_read.tool_context(&project_id, caller.clone()).await {
+            Ok(ctx) => ctx,
+            Err(e) => return json_rpc_error_response(-32603, e.to_string(), id),
+        };
+        match prompts::find(&ctx.store, &state_read.prompts, name).await {
+            Ok(Some(prompt)) => (prompt, ctx),
+            Ok(None) => {
+                return json_rpc_error_response(-32602, format!("Unknown prompt: {name}"), id)
+            }
+            Err(e) => return json_rpc_error_response(-32603, e.to_string(), id),
         }
-        _ => Err(format!("Unknown build tool: {}", tool_name)),
+    };
+
+    match prompts::render(state, &prompt, &values, &ctx).await {
+        Ok(text) => JsonRpcResponse {
+            jsonrpc: "2.0".to_string(),
+            result: Some(json!({
+                "description": prompt.description,
+                "messages": [
+                    { "role": "user", "content": { "type": "text", "text": text } }
+                ]
+            })),
+            error: None,
+            id,
+        },
+        Err(e @ ToolError::InvalidArguments(_)) => json_rpc_error_response(-32602, e.to_string(), id),
+        Err(e) => json_rpc_error_response(-32603, e.to_string(), id),
     }
 }
 
-async fn execute_next_session_tool_call(
-    state: HttpState,
-    tool_name: &str,
-    arguments: serde_json::Value,
-    project_id: &str,
-) -> Result<String, String> {
+/// Read an `aiharness://{project}/...` resource.
+///
+/// On a project endpoint only that project's resources can be read.
+async fn read_project_resource(
+    state: &HttpState,
+    uri: &str,
+    project_id_override: Option<&str>,
+) -> Result<serde_json::Value, (i32, String)> {
+    let (project_id, resource) = mcp_resources::parse_uri(uri)
+        .ok_or_else(|| (-32602, format!("Unknown resource: {uri}")))?;
+    if project_id_override.is_some_and(|p| p != project_id) {
+        return Err((
+            -32602,
+            format!("Resource belongs to another project: {uri}"),
+        ));
+    }
+
     let store = {
         let state_read = state.read().await;
         state_read
+            .get_project_store(&project_id)
+            .await
+            .map_err(|e| (-32603, e.to_string()))?
+    };
+    let text = mcp_resources::read(&store, &resource)
+        .await
+        .map_err(|e| match e {
+            ContextError::NotInContext(_) => (-32002, format!("Resource not found: {uri}")),
+            e => (-32603, e.to_string()),
+        })?;
+    Ok(json!({ "uri": uri, "mimeType": resource.mime_type(), "text": text }))
+}
+
+/// Read an `upstream://{server}/...` resource of a project's upstream server.
+async fn read_upstream_resource(
+    state: &HttpState,
+    uri: &str,
+    project_id: &str,
+) -> Result<Vec<serde_json::Value>, (i32, String)> {
+    let (store, gateway) = {
+        let state_read = state.read().await;
+        let store = state_read
             .get_project_store(project_id)
             .await
-            .map_err(|e| e.to_string())?
+            .map_err(|e| (-32603, e.to_string()))?;
+        (store, state_read.mcp_gateway.clone())
     };
-    let store = store.next_session_store.read().await;
-    match tool_name {
-        "next_session_read" => {
-            let briefing = store.get().await.map_err(|e| e.to_string())?;
-            Ok(briefing.map(|b| b.content).unwrap_or_default())
-        }
-        "next_session_write" => {
-            let content = arguments
-                .get("content")
-                .and_then(|v| v.as_str())
-                .ok_or_else(|| "Missing content".to_string())?;
-            let briefing = store.set(content).await.map_err(|e| e.to_string())?;
-            Ok(briefing.content)
-        }
-        _ => Err(format!("Unknown next session tool: {}", tool_name)),
+    match gateway.read_resource(&store, uri).await {
+        Ok(Some(contents)) => Ok(contents),
+        Ok(None) => Err((-32002, format!("Resource not found: {uri}"))),
+        Err(e) => Err((-32603, e.to_string())),
     }
 }
 
-async fn execute_todo_tool_call(
-    state: HttpState,
-    tool_name: &str,
-    arguments: serde_json::Value,
+/// Read a `file://` resource for a project.
+///
+/// Context files are readable wherever they live; other paths must be inside
+/// the project sandbox. Reading is a `read_file` call as far as policies are
+/// concerned, and binary files are returned as base64 blob contents.
+async fn read_file_resource(
+    state: &HttpState,
+    uri: &str,
+    path: &str,
     project_id: &str,
-) -> Result<String, String> {
-    let store = {
+    caller: &Caller,
+) -> Result<serde_json::Value, (i32, String)> {
+    let (read_file, ctx) = {
         let state_read = state.read().await;
-        state_read
-            .get_project_store(project_id)
+        let read_file = state_read
+            .tool_registry
+            .get_shared("read_file")
+            .ok_or_else(|| (-32603, "read_file tool not found".to_string()))?;
+        let ctx = state_read
+            .tool_context(project_id, caller.clone())
             .await
-            .map_err(|e| e.to_string())?
+            .map_err(|e| (-32603, e.to_string()))?;
+        (read_file, ctx)
     };
+    let read_args = json!({ "path": path });
+    tool_dispatch::check_tool_policy(state, &ctx, "read_file", &read_args)
+        .await
+        .map_err(|denied| (-32603, denied))?;
 
-    match tool_name {
-        "todo_add" => {
-            let title = require_arg_string(&arguments, "title")?;
-            let description = arguments.get("description").and_then(|v| v.as_str()).map(|s| s.to_string());
-            let position = arguments.get("position").and_then(|v| v.as_i64());
-            let todo = store
-                .todo_store
-                .read()
-                .await
-                .add(&title, description, position)
-                .await
-                .map_err(|e| e.to_string())?;
-            Ok(serde_json::to_string_pretty(&todo).unwrap_or_else(|_| "{}".to_string()))
-        }
-        "todo_insert" => {
-            let title = require_arg_string(&arguments, "title")?;
-            let description = arguments.get("description").and_then(|v| v.as_str()).map(|s| s.to_string());
-            let position = require_arg_i64(&arguments, "position")?;
-            let todo = store
-                .todo_store
-                .read()
-                .await
-                .add(&title, description, Some(position))
-                .await
-                .map_err(|e| e.to_string())?;
-            Ok(serde_json::to_string_pretty(&todo).unwrap_or_else(|_| "{}".to_string()))
-        }
-        "todo_remove" => {
-            let id = require_arg_string(&arguments, "id")?;
-            store
-                .todo_store
-                .read()
-                .await
-                .remove(&id)
-                .await
-                .map_err(|e| e.to_string())?;
-            Ok("removed".to_string())
-        }
-        "todo_check" => {
-            let id = require_arg_string(&arguments, "id")?;
-            let completed = arguments.get("completed").and_then(|v| v.as_bool()).unwrap_or(true);
-            store
-                .todo_store
-                .read()
-                .await
-                .set_completed(&id, completed)
-                .await
-                .map_err(|e| e.to_string())?;
-            Ok("updated".to_string())
-        }
-        "todo_list" => {
-            let todos = store
-                .todo_store
-                .read()
-                .await
-                .list()
-                .await
-                .map_err(|e| e.to_string())?;
-            Ok(serde_json::to_string_pretty(&todos).unwrap_or_else(|_| "[]".to_string()))
-        }
-        "todo_get_next" => {
-            let todo = store
-                .todo_store
-                .read()
-                .await
-                .get_next()
-                .await
-                .map_err(|e| e.to_string())?;
-            Ok(serde_json::to_string_pretty(&todo).unwrap_or_else(|_| "null".to_string()))
-        }
-        "todo_move" => {
-            let id = require_arg_string(&arguments, "id")?;
-            let position = require_arg_i64(&arguments, "position")?;
-            store
-                .todo_store
-                .read()
-                .await
-                .move_to(&id, position)
-                .await
-                .map_err(|e| e.to_string())?;
-            Ok("moved".to_string())
+    let is_context_file = ctx
+        .store
+        .context_store
+        .read()
+        .await
+        .contains(path)
+        .await
+        .unwrap_or(false);
+    let read = |args: serde_json::Value| {
+        let read_file = read_file.clone();
+        let ctx = ctx.clone();
+        async move {
+            if is_context_file {
+                read_file.execute(args).await
+            } else {
+                read_file.execute_with_context(args, &ctx).await
+            }
         }
-        _ => Err("Unknown todo tool".to_string()),
+    };
+
+    let result = match read(read_args).await {
+        Ok(r) => r,
+        Err(ToolError::BinaryFile { .. }) => read(json!({ "path": path, "encoding": "base64" }))
+            .await
+            .map_err(|e| (-32603, e.to_string()))?,
+        Err(e) => return Err((-32603, e.to_string())),
+    };
+    Ok(resource_contents(uri, result))
+}
+
+/// Build one MCP resource contents entry from a `read_file` result.
+fn resource_contents(uri: &str, result: crate::tools::ToolResult) -> serde_json::Value {
+    let data = result.data.unwrap_or_default();
+    if data.get("encoding").and_then(|v| v.as_str()) == Some("base64") {
+        let mime_type = data
+            .get("mime_type")
+            .and_then(|v| v.as_str())
+            .unwrap_or("application/octet-stream");
+        json!({ "uri": uri, "mimeType": mime_type, "blob": result.content })
+    } else {
+        json!({ "uri": uri, "mimeType": "text/plain", "text": result.content })
     }
 }
 
@@ -1012,7 +1560,10 @@ async fn execute_todo_tool_call(
 mod tests {
     use super::*;
     use crate::app_state::AppState;
+    use crate::tool_policies;
+    use axum::body::Body;
     use tempfile::TempDir;
+    use tower::ServiceExt;
 
     #[test]
     fn parse_tool_call_body_defaults() {
@@ -1081,24 +1632,73 @@ mod tests {
             name: "t".to_string(),
             description: "d".to_string(),
             input_schema: json!({"type": "object"}),
+            output_schema: Some(json!({"type": "object"})),
+            annotations: crate::tools::ToolAnnotations::read_only("T"),
         }];
         let mapped = map_tools(&tools, "inputSchema");
         assert_eq!(mapped.len(), 1);
         assert!(mapped[0].get("inputSchema").is_some());
+        assert!(mapped[0].get("outputSchema").is_some());
+        assert_eq!(mapped[0]["annotations"]["readOnlyHint"], true);
+
+        let mapped = map_tools(&tools, "input_schema");
+        assert!(mapped[0].get("output_schema").is_some());
     }
 
     #[test]
     fn mcp_content_response_sets_error_flag() {
-        let response = mcp_content_response(Some(json!(1)), "oops".to_string(), true);
+        let response = mcp_content_response(Some(json!(1)), "oops", None, true);
         let result = response.result.unwrap();
         assert_eq!(result.get("isError").unwrap(), &json!(true));
+        assert!(result.get("structuredContent").is_none());
     }
 
     #[test]
-    fn handle_mcp_initialize_returns_protocol_version() {
-        let response = handle_mcp_initialize(Some(json!(1)));
-        let result = response.result.unwrap();
-        assert_eq!(result.get("protocolVersion").unwrap(), MCP_PROTOCOL_VERSION);
+    fn mcp_content_response_includes_structured_content() {
+        let data = json!({ "total_lines": 3 });
+        let response = mcp_content_response(Some(json!(1)), "a\nb\nc", Some(data.clone()), false);
+        assert_eq!(response.result.unwrap()["structuredContent"], data);
+    }
+
+    #[test]
+    fn handle_mcp_initialize_negotiates_protocol_version() {
+        let params = json!({ "protocolVersion": "2024-11-05" });
+        let response = handle_mcp_initialize(Some(json!(1)), Some(&params));
+        assert_eq!(response.result.unwrap()["protocolVersion"], "2024-11-05");
+
+        let params = json!({ "protocolVersion": "1999-01-01" });
+        let response = handle_mcp_initialize(Some(json!(1)), Some(&params));
+        assert_eq!(
+            response.result.unwrap()["protocolVersion"],
+            SUPPORTED_PROTOCOL_VERSIONS[0]
+        );
+    }
+
+    #[test]
+    fn events_params_default_to_default_project() {
+        let (project_id, query) = EventsParams::default().into_query();
+        assert_eq!(project_id, "default");
+        assert!(query.tool.is_none());
+    }
+
+    #[test]
+    fn resource_contents_uses_blob_for_base64() {
+        let result = crate::tools::ToolResult::success_with_data(
+            "iVBORw0K",
+            json!({ "encoding": "base64", "mime_type": "image/png" }),
+        );
+        let contents = resource_contents("file:///tmp/logo.png", result);
+        assert_eq!(contents["blob"], "iVBORw0K");
+        assert_eq!(contents["mimeType"], "image/png");
+        assert!(contents.get("text").is_none());
+    }
+
+    #[test]
+    fn resource_contents_uses_text_by_default() {
+        let result = crate::tools::ToolResult::success("hello");
+        let contents = resource_contents("file:///tmp/a.txt", result);
+        assert_eq!(contents["text"], "hello");
+        assert_eq!(contents["mimeType"], "text/plain");
     }
 
     #[tokio::test]
@@ -1110,9 +1710,946 @@ mod tests {
             .unwrap();
         let state = Arc::new(RwLock::new(state));
 
-        let response = handle_mcp_tools_list(&state, Some(json!(1))).await;
+        let response = handle_mcp_tools_list(&state, Some(json!(1)), None, None).await;
         let result = response.result.unwrap();
         let tools = result.get("tools").unwrap().as_array().unwrap();
         assert!(!tools.is_empty());
     }
+
+    #[tokio::test]
+    async fn resources_expose_project_data_scoped_to_the_project() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry_path = temp_dir.path().join("registry.db");
+        let state = AppState::new(registry_path.to_str().unwrap(), temp_dir.path())
+            .await
+            .unwrap();
+        let todo = {
+            let store = state.get_project_store("default").await.unwrap();
+            let todo = store.todo_store.read().await.add("ship", None, None).await.unwrap();
+            store.next_session_store.read().await.set("start here").await.unwrap();
+            todo
+        };
+        let state = Arc::new(RwLock::new(state));
+        let caller = Caller::App;
+
+        let listed = handle_mcp_resources_list(&state, Some(json!(1)), None, Some("default")).await;
+        let listed = listed.result.unwrap();
+        assert!(listed["resources"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|r| r["uri"] == "aiharness://default/todos"));
+
+        let read = |uri: String, project: Option<&'static str>| {
+            let state = state.clone();
+            let caller = caller.clone();
+            async move {
+                handle_mcp_resources_read(&state, Some(json!(2)), Some(json!({ "uri": uri })), project, &caller)
+                    .await
+            }
+        };
+
+        let todo_uri = format!("aiharness://default/todos/{}", todo.id);
+        let response = read(todo_uri.clone(), Some("default")).await;
+        let contents = &response.result.unwrap()["contents"][0];
+        assert_eq!(contents["mimeType"], "application/json");
+        assert!(contents["text"].as_str().unwrap().contains("ship"));
+
+        let response = read("aiharness://default/next-session".to_string(), None).await;
+        let contents = &response.result.unwrap()["contents"][0];
+        assert_eq!(contents["mimeType"], "text/markdown");
+        assert_eq!(contents["text"], "start here");
+
+        let response = read(todo_uri, Some("other")).await;
+        assert_eq!(response.error.unwrap().code, -32602);
+
+        let response = read("aiharness://default/todos/missing".to_string(), None).await;
+        assert_eq!(response.error.unwrap().code, -32002);
+    }
+
+    #[tokio::test]
+    async fn prompts_render_project_and_global_library() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry_path = temp_dir.path().join("registry.db");
+        let state = AppState::new(registry_path.to_str().unwrap(), temp_dir.path())
+            .await
+            .unwrap();
+        state
+            .prompts
+            .add(prompts::NewPrompt {
+                name: "plan".to_string(),
+                description: Some("Plan the work".to_string()),
+                arguments: vec![prompts::PromptArgument {
+                    name: "goal".to_string(),
+                    description: None,
+                    required: true,
+                    kind: prompts::PromptArgumentType::String,
+                }],
+                template: "Plan {{goal}}\n{{next_session}}".to_string(),
+            })
+            .unwrap();
+        let store = state.get_project_store("default").await.unwrap();
+        store.next_session_store.read().await.set("start here").await.unwrap();
+        let state = Arc::new(RwLock::new(state));
+
+        let listed = handle_mcp_prompts_list(&state, Some(json!(1)), None, None).await;
+        let listed = listed.result.unwrap();
+        assert_eq!(listed["prompts"][0]["name"], "plan");
+        assert_eq!(listed["prompts"][0]["arguments"][0]["required"], true);
+
+        let params = json!({ "name": "plan", "arguments": { "goal": "the release" } });
+        let got = handle_mcp_prompts_get(&state, Some(json!(2)), Some(params), None, &Caller::App).await;
+        let got = got.result.unwrap();
+        assert_eq!(got["messages"][0]["content"]["text"], "Plan the release\nstart here");
+
+        let params = json!({ "name": "plan" });
+        let missing = handle_mcp_prompts_get(&state, Some(json!(3)), Some(params), None, &Caller::App).await;
+        assert_eq!(missing.error.unwrap().code, -32602);
+        let params = json!({ "name": "nope" });
+        let unknown = handle_mcp_prompts_get(&state, Some(json!(4)), Some(params), None, &Caller::App).await;
+        assert_eq!(unknown.error.unwrap().code, -32602);
+    }
+
+    #[tokio::test]
+    async fn file_resources_outside_the_project_require_a_context_entry() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry_path = temp_dir.path().join("registry.db");
+        let state = AppState::new(registry_path.to_str().unwrap(), temp_dir.path())
+            .await
+            .unwrap();
+        let outside = TempDir::new().unwrap();
+        let shared = outside.path().join("shared.md");
+        let private = outside.path().join("private.md");
+        std::fs::write(&shared, "shared").unwrap();
+        std::fs::write(&private, "private").unwrap();
+        let shared = shared.to_str().unwrap().to_string();
+        {
+            let store = state.get_project_store("default").await.unwrap();
+            store.context_store.read().await.add_file(&shared).await.unwrap();
+        }
+        let state = Arc::new(RwLock::new(state));
+
+        let params = json!({ "uri": format!("file://{shared}") });
+        let response =
+            handle_mcp_resources_read(&state, Some(json!(1)), Some(params), Some("default"), &Caller::App).await;
+        assert_eq!(response.result.unwrap()["contents"][0]["text"], "shared");
+
+        let params = json!({ "uri": format!("file://{}", private.display()) });
+        let response =
+            handle_mcp_resources_read(&state, Some(json!(2)), Some(params), Some("default"), &Caller::App).await;
+        assert!(response.error.unwrap().message.contains("outside the project root"));
+    }
+
+    #[tokio::test]
+    async fn execute_tool_call_blocks_path_escape_and_records_security_event() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry_path = temp_dir.path().join("registry.db");
+        let state = AppState::new(registry_path.to_str().unwrap(), temp_dir.path())
+            .await
+            .unwrap();
+        let state = Arc::new(RwLock::new(state));
+        let outside = TempDir::new().unwrap();
+        let target = outside.path().join("pwned.txt");
+
+        let result = execute_tool_call(
+            state.clone(),
+            "write_file",
+            json!({ "path": target.to_str().unwrap(), "content": "x" }),
+            "default".to_string(),
+            Caller::App,
+        )
+        .await;
+
+        let error = result.err().unwrap();
+        assert!(error.contains("outside the project root"));
+        assert!(!target.exists());
+        let events = state.read().await.get_security_events().await;
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind, "path_escape");
+        assert_eq!(events[0].tool_name, "write_file");
+    }
+
+    #[tokio::test]
+    async fn execute_tool_call_persists_history_for_querying() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry_path = temp_dir.path().join("registry.db");
+        let state = AppState::new(registry_path.to_str().unwrap(), temp_dir.path())
+            .await
+            .unwrap();
+        let state = Arc::new(RwLock::new(state));
+
+        execute_tool_call(
+            state.clone(),
+            "write_file",
+            json!({ "path": "notes.txt", "content": "remember the milk" }),
+            "default".to_string(),
+            Caller::App,
+        )
+        .await
+        .unwrap();
+        let _ = execute_tool_call(
+            state.clone(),
+            "read_file",
+            json!({ "path": "missing.txt" }),
+            "default".to_string(),
+            Caller::App,
+        )
+        .await;
+
+        let params = EventsParams {
+            success: Some(false),
+            ..EventsParams::default()
+        };
+        let (project_id, query) = params.into_query();
+        let page = state.read().await.query_events(&project_id, &query).await.unwrap();
+        assert_eq!(page.total, 1);
+        assert_eq!(page.events[0].tool_name, "read_file");
+
+        let query = ToolCallQuery {
+            search: Some("milk".to_string()),
+            ..ToolCallQuery::default()
+        };
+        let page = state.read().await.query_events("default", &query).await.unwrap();
+        assert_eq!(page.total, 1);
+        assert_eq!(page.events[0].tool_name, "write_file");
+    }
+
+    #[test]
+    fn parse_bearer_token_requires_bearer_scheme() {
+        assert_eq!(parse_bearer_token("Bearer abc"), Some("abc"));
+        assert_eq!(parse_bearer_token("bearer  abc "), Some("abc"));
+        assert_eq!(parse_bearer_token("Basic abc"), None);
+        assert_eq!(parse_bearer_token("Bearer "), None);
+        assert_eq!(parse_bearer_token("abc"), None);
+    }
+
+    async fn router_with_token() -> (TempDir, Router, String) {
+        let temp_dir = TempDir::new().unwrap();
+        let registry_path = temp_dir.path().join("registry.db");
+        let state = AppState::new(registry_path.to_str().unwrap(), temp_dir.path())
+            .await
+            .unwrap();
+        let token = state.client_tokens.create("test").unwrap().token;
+        let router = create_router(Arc::new(RwLock::new(state)));
+        (temp_dir, router, token)
+    }
+
+    fn get_request(uri: &str, token: Option<&str>) -> axum::http::Request<Body> {
+        let mut builder = axum::http::Request::builder().uri(uri);
+        if let Some(token) = token {
+            builder = builder.header(header::AUTHORIZATION, format!("Bearer {token}"));
+        }
+        builder.body(Body::empty()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn router_requires_valid_token_except_for_health_check() {
+        let (_temp_dir, router, token) = router_with_token().await;
+
+        let health = router.clone().oneshot(get_request("/", None)).await.unwrap();
+        assert_eq!(health.status(), StatusCode::OK);
+
+        let missing = router.clone().oneshot(get_request("/tools", None)).await.unwrap();
+        assert_eq!(missing.status(), StatusCode::UNAUTHORIZED);
+
+        let wrong = router
+            .clone()
+            .oneshot(get_request("/tools", Some("aih_wrong")))
+            .await
+            .unwrap();
+        assert_eq!(wrong.status(), StatusCode::UNAUTHORIZED);
+
+        let ok = router.oneshot(get_request("/tools", Some(&token))).await.unwrap();
+        assert_eq!(ok.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn events_are_limited_to_projects_and_tools_a_client_may_use() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry_path = temp_dir.path().join("registry.db");
+        let state = AppState::new(registry_path.to_str().unwrap(), temp_dir.path())
+            .await
+            .unwrap();
+        let project_b = temp_dir.path().join("b");
+        state
+            .project_registry
+            .create_project_with_id("b".to_string(), "B", project_b.to_str().unwrap())
+            .await
+            .unwrap();
+        let client_a = state.client_tokens.create("a").unwrap();
+        let client_b = state.client_tokens.create("b").unwrap();
+        // Client A may not use project B at all, and only read files in the default project
+        for (project_id, rules) in [
+            ("b", tool_policies::ToolPolicyRules {
+                deny_tools: vec!["*".to_string()],
+                ..tool_policies::ToolPolicyRules::default()
+            }),
+            ("default", tool_policies::ToolPolicyRules {
+                allow_tools: vec!["read_file".to_string()],
+                ..tool_policies::ToolPolicyRules::default()
+            }),
+        ] {
+            state
+                .tool_policies
+                .add(tool_policies::NewToolPolicy {
+                    client_id: Some(client_a.client.id.clone()),
+                    project_id: Some(project_id.to_string()),
+                    rules,
+                })
+                .unwrap();
+        }
+        let state = Arc::new(RwLock::new(state));
+        for (tool, arguments, project_id) in [
+            ("write_file", json!({ "path": "secret.txt", "content": "b" }), "b"),
+            ("write_file", json!({ "path": "notes.txt", "content": "x" }), "default"),
+            ("read_file", json!({ "path": "notes.txt" }), "default"),
+        ] {
+            execute_tool_call(state.clone(), tool, arguments, project_id.to_string(), Caller::App)
+                .await
+                .unwrap();
+        }
+        let router = create_router(state.clone());
+
+        let events = |uri: &'static str, token: String| {
+            let router = router.clone();
+            async move {
+                let response = router.oneshot(get_request(uri, Some(&token))).await.unwrap();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                let page: serde_json::Value = serde_json::from_slice(&body).unwrap();
+                page["events"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|event| event["tool_name"].as_str().unwrap().to_string())
+                    .collect::<Vec<_>>()
+            }
+        };
+        assert!(events("/events?project=b", client_a.token.clone()).await.is_empty());
+        assert_eq!(events("/events?project=b", client_b.token.clone()).await, ["write_file"]);
+        assert_eq!(events("/events", client_a.token.clone()).await, ["read_file"]);
+        assert_eq!(events("/events", client_b.token.clone()).await, ["read_file", "write_file"]);
+
+        // The live stream is filtered the same way
+        let caller_a = Caller::Client {
+            id: client_a.client.id.clone(),
+            name: client_a.client.name.clone(),
+        };
+        let page = state.read().await.query_events("b", &ToolCallQuery::default()).await.unwrap();
+        let from_b = &page.events[0];
+        assert!(!event_visible(&state, &caller_a, "b", from_b).await);
+        assert!(!event_visible(&state, &caller_a, "default", from_b).await);
+        assert!(event_visible(&state, &Caller::App, "b", from_b).await);
+    }
+
+    fn mcp_request(
+        method: Method,
+        token: &str,
+        session: Option<&str>,
+        accept: &str,
+        body: Option<serde_json::Value>,
+    ) -> axum::http::Request<Body> {
+        let mut builder = axum::http::Request::builder()
+            .method(method)
+            .uri("/mcp")
+            .header(header::AUTHORIZATION, format!("Bearer {token}"))
+            .header(header::ACCEPT, accept)
+            .header(header::CONTENT_TYPE, "application/json");
+        if let Some(session) = session {
+            builder = builder.header(SESSION_HEADER, session);
+        }
+        let body = body.map_or_else(Body::empty, |b| Body::from(b.to_string()));
+        builder.body(body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn mcp_sessions_start_on_initialize_and_end_on_delete() {
+        let (_temp_dir, router, token) = router_with_token().await;
+        let initialize = json!({ "jsonrpc": "2.0", "method": "initialize", "id": 1 });
+
+        let response = router
+            .clone()
+            .oneshot(mcp_request(Method::POST, &token, None, "application/json", Some(initialize)))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let session = response
+            .headers()
+            .get(SESSION_HEADER)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string();
+
+        let notification = json!({ "jsonrpc": "2.0", "method": "notifications/initialized" });
+        let response = router
+            .clone()
+            .oneshot(mcp_request(Method::POST, &token, Some(&session), "application/json", Some(notification)))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+        let list = json!({ "jsonrpc": "2.0", "method": "tools/list", "id": 2 });
+        let unknown = router
+            .clone()
+            .oneshot(mcp_request(Method::POST, &token, Some("bogus"), "application/json", Some(list)))
+            .await
+            .unwrap();
+        assert_eq!(unknown.status(), StatusCode::NOT_FOUND);
+
+        let deleted = router
+            .clone()
+            .oneshot(mcp_request(Method::DELETE, &token, Some(&session), "application/json", None))
+            .await
+            .unwrap();
+        assert_eq!(deleted.status(), StatusCode::NO_CONTENT);
+        let again = router
+            .oneshot(mcp_request(Method::DELETE, &token, Some(&session), "application/json", None))
+            .await
+            .unwrap();
+        assert_eq!(again.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn mcp_sessions_only_work_on_the_endpoint_that_started_them() {
+        let (_temp_dir, router, token) = router_with_token().await;
+        let on = |uri: &str, mut request: axum::http::Request<Body>| {
+            *request.uri_mut() = uri.parse().unwrap();
+            request
+        };
+        let initialize = json!({ "jsonrpc": "2.0", "method": "initialize", "id": 1 });
+        let response = router
+            .clone()
+            .oneshot(on(
+                "/mcp/default",
+                mcp_request(Method::POST, &token, None, "application/json", Some(initialize)),
+            ))
+            .await
+            .unwrap();
+        let session = response.headers()[SESSION_HEADER].to_str().unwrap().to_string();
+
+        let list = json!({ "jsonrpc": "2.0", "method": "tools/list", "id": 2 });
+        for uri in ["/mcp/other", "/mcp"] {
+            let response = router
+                .clone()
+                .oneshot(on(
+                    uri,
+                    mcp_request(Method::POST, &token, Some(&session), "application/json", Some(list.clone())),
+                ))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::NOT_FOUND, "POST {uri}");
+        }
+        let stream = router
+            .clone()
+            .oneshot(on(
+                "/mcp/other",
+                mcp_request(Method::GET, &token, Some(&session), "text/event-stream", None),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(stream.status(), StatusCode::NOT_FOUND);
+        let deleted = router
+            .clone()
+            .oneshot(on(
+                "/mcp/other",
+                mcp_request(Method::DELETE, &token, Some(&session), "application/json", None),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(deleted.status(), StatusCode::NOT_FOUND);
+
+        let response = router
+            .oneshot(on(
+                "/mcp/default",
+                mcp_request(Method::POST, &token, Some(&session), "application/json", Some(list)),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn mcp_session_remembers_negotiated_protocol_version() {
+        let (_temp_dir, router, token) = router_with_token().await;
+        let initialize = json!({
+            "jsonrpc": "2.0",
+            "method": "initialize",
+            "params": { "protocolVersion": "2025-03-26" },
+            "id": 1
+        });
+        let response = router
+            .clone()
+            .oneshot(mcp_request(Method::POST, &token, None, "application/json", Some(initialize)))
+            .await
+            .unwrap();
+        let session = response.headers()[SESSION_HEADER].to_str().unwrap().to_string();
+
+        let ping = json!({ "jsonrpc": "2.0", "method": "ping", "id": 2 });
+        let mut request = mcp_request(Method::POST, &token, Some(&session), "application/json", Some(ping.clone()));
+        request
+            .headers_mut()
+            .insert(PROTOCOL_VERSION_HEADER, HeaderValue::from_static("2025-03-26"));
+        let response = router.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["result"], json!({}));
+
+        let mut request = mcp_request(Method::POST, &token, Some(&session), "application/json", Some(ping));
+        request
+            .headers_mut()
+            .insert(PROTOCOL_VERSION_HEADER, HeaderValue::from_static("2025-06-18"));
+        let response = router.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn mcp_batches_return_replies_for_requests_only() {
+        let (_temp_dir, router, token) = router_with_token().await;
+        let batch = json!([
+            { "jsonrpc": "2.0", "method": "ping", "id": 1 },
+            { "jsonrpc": "2.0", "method": "notifications/initialized" },
+            { "jsonrpc": "2.0", "method": "initialize", "id": 2 },
+            { "jsonrpc": "2.0", "method": "tools/list", "id": 3 }
+        ]);
+        let response = router
+            .clone()
+            .oneshot(mcp_request(Method::POST, &token, None, "application/json", Some(batch)))
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let replies: Vec<serde_json::Value> = serde_json::from_slice(&body).unwrap();
+        let ids: Vec<_> = replies.iter().map(|r| r["id"].clone()).collect();
+        assert_eq!(ids, vec![json!(1), json!(2), json!(3)]);
+        assert_eq!(replies[1]["error"]["code"], -32600);
+        assert!(replies[2]["result"]["tools"].is_array());
+
+        let notifications = json!([{ "jsonrpc": "2.0", "method": "notifications/initialized" }]);
+        let response = router
+            .oneshot(mcp_request(Method::POST, &token, None, "application/json", Some(notifications)))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+    }
+
+    #[tokio::test]
+    async fn mcp_tools_call_upgrades_to_sse_when_accepted() {
+        let (_temp_dir, router, token) = router_with_token().await;
+        let call = json!({
+            "jsonrpc": "2.0",
+            "method": "tools/call",
+            "params": { "name": "todo_list", "arguments": {} },
+            "id": 7
+        });
+
+        let response = router
+            .oneshot(mcp_request(
+                Method::POST,
+                &token,
+                None,
+                "application/json, text/event-stream",
+                Some(call),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE).unwrap(),
+            "text/event-stream"
+        );
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        let data = body
+            .lines()
+            .find_map(|line| line.strip_prefix("data: "))
+            .unwrap();
+        let message: serde_json::Value = serde_json::from_str(data).unwrap();
+        assert_eq!(message["id"], 7);
+        assert_eq!(message["result"]["isError"], false);
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[tokio::test]
+    async fn mcp_tools_call_streams_progress_and_honours_cancellation() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry_path = temp_dir.path().join("registry.db");
+        let state = AppState::new(registry_path.to_str().unwrap(), temp_dir.path())
+            .await
+            .unwrap();
+        let token = state.client_tokens.create("test").unwrap().token;
+        let (echo, sleep) = {
+            let store = state.get_project_store("default").await.unwrap();
+            let commands = store.build_command_store.read().await;
+            (
+                commands.add("echo", "echo one; echo two", None).await.unwrap(),
+                commands.add("sleep", "sleep 30", None).await.unwrap(),
+            )
+        };
+        let router = create_router(Arc::new(RwLock::new(state)));
+        let run = |id: &str, request_id: i64| {
+            json!({
+                "jsonrpc": "2.0",
+                "method": "tools/call",
+                "params": {
+                    "name": "build_run_command",
+                    "arguments": { "id": id },
+                    "_meta": { "progressToken": "p1" }
+                },
+                "id": request_id
+            })
+        };
+
+        let response = router
+            .clone()
+            .oneshot(mcp_request(
+                Method::POST,
+                &token,
+                None,
+                "application/json, text/event-stream",
+                Some(run(&echo.id, 1)),
+            ))
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let messages = String::from_utf8(body.to_vec())
+            .unwrap()
+            .lines()
+            .filter_map(|line| line.strip_prefix("data: "))
+            .map(|data| serde_json::from_str::<serde_json::Value>(data).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[0]["method"], "notifications/progress");
+        assert_eq!(messages[0]["params"]["progressToken"], "p1");
+        assert_eq!(messages[1]["params"]["progress"], 2.0);
+        assert_eq!(messages[1]["params"]["message"], "two");
+        assert_eq!(messages[2]["id"], 1);
+
+        let slow = tokio::spawn(router.clone().oneshot(mcp_request(
+            Method::POST,
+            &token,
+            None,
+            "application/json",
+            Some(run(&sleep.id, 2)),
+        )));
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        let cancel = json!({
+            "jsonrpc": "2.0",
+            "method": "notifications/cancelled",
+            "params": { "requestId": 2, "reason": "user gave up" }
+        });
+        let ack = router
+            .oneshot(mcp_request(Method::POST, &token, None, "application/json", Some(cancel)))
+            .await
+            .unwrap();
+        assert_eq!(ack.status(), StatusCode::ACCEPTED);
+
+        let response = tokio::time::timeout(std::time::Duration::from_secs(10), slow)
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let reply: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(reply["result"]["isError"], true);
+        assert_eq!(reply["result"]["content"][0]["text"], "Cancelled");
+    }
+
+    #[tokio::test]
+    async fn mcp_get_streams_server_messages_for_the_session() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry_path = temp_dir.path().join("registry.db");
+        let state = AppState::new(registry_path.to_str().unwrap(), temp_dir.path())
+            .await
+            .unwrap();
+        let created = state.client_tokens.create("test").unwrap();
+        let session = state.mcp_sessions.create(Some(&created.client.id), None, "2025-03-26");
+        let sessions = state.mcp_sessions.clone();
+        let router = create_router(Arc::new(RwLock::new(state)));
+
+        let missing = router
+            .clone()
+            .oneshot(mcp_request(Method::GET, &created.token, None, "text/event-stream", None))
+            .await
+            .unwrap();
+        assert_eq!(missing.status(), StatusCode::BAD_REQUEST);
+
+        let response = router
+            .oneshot(mcp_request(Method::GET, &created.token, Some(&session.id), "text/event-stream", None))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        sessions.send(&session.id, json!({ "jsonrpc": "2.0", "method": "notifications/message" }));
+        sessions.remove(&session.id, Some(&created.client.id), None);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert!(String::from_utf8(body.to_vec()).unwrap().contains("notifications/message"));
+    }
+
+    #[tokio::test]
+    async fn mcp_resource_subscriptions_require_a_session() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry_path = temp_dir.path().join("registry.db");
+        let state = AppState::new(registry_path.to_str().unwrap(), temp_dir.path())
+            .await
+            .unwrap();
+        let created = state.client_tokens.create("test").unwrap();
+        let session = state.mcp_sessions.create(Some(&created.client.id), None, "2025-03-26");
+        let mut messages = state.mcp_sessions.subscribe(&session.id, Some(&created.client.id), None).unwrap();
+        let subscriptions = state.resource_subscriptions.clone();
+        let root = state.get_project_store("default").await.unwrap().info.root_path.clone();
+        let state = Arc::new(RwLock::new(state));
+        let router = create_router(state.clone());
+        let call = |method: &str, uri: &str| {
+            json!({ "jsonrpc": "2.0", "method": method, "params": { "uri": uri }, "id": 1 })
+        };
+        let reply = |response: Response| async move {
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+        };
+
+        let uri = "aiharness://default/todos";
+        let stateless = router
+            .clone()
+            .oneshot(mcp_request(
+                Method::POST,
+                &created.token,
+                None,
+                "application/json",
+                Some(call("resources/subscribe", uri)),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(reply(stateless).await["error"]["code"], -32600);
+
+        let subscribed = router
+            .clone()
+            .oneshot(mcp_request(
+                Method::POST,
+                &created.token,
+                Some(&session.id),
+                "application/json",
+                Some(call("resources/subscribe", uri)),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(reply(subscribed).await["result"], json!({}));
+        assert_eq!(subscriptions.subscribed_uris(&session.id), vec![uri.to_string()]);
+
+        let outside = router
+            .clone()
+            .oneshot(mcp_request(
+                Method::POST,
+                &created.token,
+                Some(&session.id),
+                "application/json",
+                Some(call("resources/subscribe", "file:///etc/passwd")),
+            ))
+            .await
+            .unwrap();
+        assert!(reply(outside).await["error"].is_object());
+
+        // A relative file URI is watched inside the project, not the server's cwd
+        let notes = std::path::Path::new(&root).join("watched-notes.md");
+        std::fs::write(&notes, "one").unwrap();
+        let relative = router
+            .clone()
+            .oneshot(mcp_request(
+                Method::POST,
+                &created.token,
+                Some(&session.id),
+                "application/json",
+                Some(call("resources/subscribe", "file://watched-notes.md")),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(reply(relative).await["result"], json!({}));
+        std::fs::write(&notes, "two, longer").unwrap();
+        subscriptions.poll(&state).await;
+        let updated = messages.try_recv().unwrap();
+        assert_eq!(updated["params"]["uri"], "file://watched-notes.md");
+
+        router
+            .oneshot(mcp_request(
+                Method::POST,
+                &created.token,
+                Some(&session.id),
+                "application/json",
+                Some(call("resources/unsubscribe", uri)),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(subscriptions.subscribed_uris(&session.id), vec!["file://watched-notes.md".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn router_only_allows_app_origins() {
+        let (_temp_dir, router, _token) = router_with_token().await;
+        let with_origin = |origin: &str| {
+            axum::http::Request::builder()
+                .uri("/")
+                .header(header::ORIGIN, origin)
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let allowed = router
+            .clone()
+            .oneshot(with_origin("tauri://localhost"))
+            .await
+            .unwrap();
+        assert_eq!(
+            allowed.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(),
+            "tauri://localhost"
+        );
+
+        let dev_server = router
+            .clone()
+            .oneshot(with_origin("http://localhost:1420"))
+            .await
+            .unwrap();
+        assert_eq!(
+            dev_server.headers().contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN),
+            cfg!(debug_assertions)
+        );
+
+        let foreign = router.oneshot(with_origin("https://evil.example")).await.unwrap();
+        assert!(foreign.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+    }
+
+    #[tokio::test]
+    async fn tools_call_denied_by_client_policy_returns_is_error() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry_path = temp_dir.path().join("registry.db");
+        let state = AppState::new(registry_path.to_str().unwrap(), temp_dir.path())
+            .await
+            .unwrap();
+        let client = state.client_tokens.create("reader").unwrap().client;
+        state
+            .tool_policies
+            .add(tool_policies::NewToolPolicy {
+                client_id: Some(client.id.clone()),
+                project_id: Some("default".to_string()),
+                rules: tool_policies::ToolPolicyRules {
+                    preset: Some(tool_policies::PolicyPreset::ReadOnly),
+                    ..tool_policies::ToolPolicyRules::default()
+                },
+            })
+            .unwrap();
+        let state = Arc::new(RwLock::new(state));
+
+        let params = json!({
+            "name": "write_file",
+            "arguments": { "path": "blocked.txt", "content": "x" }
+        });
+        let caller = Caller::Client {
+            id: client.id.clone(),
+            name: client.name.clone(),
+        };
+        let response =
+            handle_mcp_tools_call(
+                &state,
+                Some(json!(1)),
+                Some(params.clone()),
+                Some("default"),
+                &caller,
+                McpOutbound::default(),
+            )
+                .await;
+        let result = response.result.unwrap();
+        assert_eq!(result["isError"], true);
+        assert!(result["content"][0]["text"]
+            .as_str()
+            .unwrap()
+            .starts_with("Denied by policy"));
+        assert!(!temp_dir.path().join("blocked.txt").exists());
+
+        let events = state.read().await.get_security_events().await;
+        assert_eq!(events[0].kind, "policy_denied");
+
+        // Other clients are unaffected
+        let other = Caller::Client {
+            id: "someone-else".to_string(),
+            name: "other".to_string(),
+        };
+        let response =
+            handle_mcp_tools_call(
+                &state,
+                Some(json!(2)),
+                Some(params),
+                Some("default"),
+                &other,
+                McpOutbound::default(),
+            )
+                .await;
+        assert_eq!(response.result.unwrap()["isError"], false);
+    }
+
+    #[tokio::test]
+    async fn tool_call_waits_for_approval_and_records_decision() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry_path = temp_dir.path().join("registry.db");
+        let state = AppState::new(registry_path.to_str().unwrap(), temp_dir.path())
+            .await
+            .unwrap();
+        state
+            .approval_rules
+            .add(crate::approvals::NewApprovalRule {
+                tool: "write_file".to_string(),
+                ..crate::approvals::NewApprovalRule::default()
+            })
+            .unwrap();
+        let mut approvals = state.subscribe_approvals();
+        let state = Arc::new(RwLock::new(state));
+
+        let call = |path: &'static str| {
+            let state = state.clone();
+            tokio::spawn(async move {
+                execute_tool_call(
+                    state,
+                    "write_file",
+                    json!({ "path": path, "content": "x" }),
+                    "default".to_string(),
+                    Caller::Client {
+                        id: "agent".to_string(),
+                        name: "agent".to_string(),
+                    },
+                )
+                .await
+            })
+        };
+
+        let denied = call("denied.txt");
+        let request = approvals.recv().await.unwrap();
+        assert_eq!(request.tool_name, "write_file");
+        state
+            .read()
+            .await
+            .approvals
+            .resolve(&request.id, false, Some("no".to_string()))
+            .unwrap();
+        assert_eq!(denied.await.unwrap().err().unwrap(), "Denied by user: no");
+
+        let approved = call("approved.txt");
+        let request = approvals.recv().await.unwrap();
+        state.read().await.approvals.resolve(&request.id, true, None).unwrap();
+        assert!(approved.await.unwrap().is_ok());
+
+        let page = state
+            .read()
+            .await
+            .query_events("default", &ToolCallQuery::default())
+            .await
+            .unwrap();
+        let decisions: Vec<_> = page
+            .events
+            .iter()
+            .map(|e| e.approval.as_ref().unwrap().decision)
+            .collect();
+        assert_eq!(
+            decisions,
+            vec![
+                crate::approvals::ApprovalDecision::Approved,
+                crate::approvals::ApprovalDecision::Denied
+            ]
+        );
+    }
 }
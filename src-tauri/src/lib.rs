@@ -1,7 +1,8 @@
 //! AIHarness - Single binary with built-in HTTP server
 //! 
 //! Architecture:
-//! - One process: GUI + HTTP server + tools
+//! - One process: GUI + HTTP server + tools (or `serve --headless` without
+//!   the GUI, see [`headless`])
 //! - Shared state: context, tool registry, event history
 //! - Events flow directly from tool execution to UI
 
@@ -14,11 +15,6 @@ use std::sync::Mutex;
 /// Simple file logger for debugging
 static DEBUG_LOG: Mutex<Option<std::fs::File>> = Mutex::new(None);
 
-#[tauri::command]
-fn debug_log_cmd(msg: String) {
-    debug_log(&msg);
-}
-
 pub fn debug_log(msg: &str) {
     let path = "/Users/danbaker/Projects/AIHarness/aiharness_debug.log";
     let mut guard = DEBUG_LOG.lock().unwrap();
@@ -36,36 +32,46 @@ pub fn debug_log(msg: &str) {
     }
 }
 
+pub mod allowed_roots;
 pub mod app_state;
+pub mod approvals;
 pub mod build_commands;
+pub mod client_tokens;
 pub mod context;
 pub mod context_notes;
+pub mod discovery;
 pub mod error;
+#[cfg(feature = "gui")]
+mod gui;
+pub mod headless;
+pub mod headless_config;
 pub mod http_server;
+pub mod mcp_client;
 pub mod mcp_config;
+pub mod mcp_gateway;
 pub mod mcp_proxy;
+pub mod mcp_resources;
+pub mod mcp_sessions;
 pub mod next_session;
 pub mod projects;
+pub mod processes;
+pub mod prompts;
+pub mod resource_subscriptions;
+pub mod terminals;
 pub mod todos;
+pub mod tool_dispatch;
+pub mod tool_history;
+pub mod tool_policies;
 pub mod tools;
+pub mod upstream_servers;
 
 #[cfg(test)]
 mod tests;
 
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
-use tauri::{Manager, Emitter};
-use tokio::sync::RwLock;
 
 pub use app_state::AppState;
 
-/// Common arguments struct for project-scoped commands
-#[derive(Debug, Deserialize)]
-struct ProjectArgs {
-    #[serde(default)]
-    project_id: Option<String>,
-}
-
 /// Tool call event for frontend
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToolCallEvent {
@@ -77,6 +83,23 @@ pub struct ToolCallEvent {
     pub success: bool,
     pub content: String,
     pub duration_ms: u64,
+    /// Human approval decision, for calls that required one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub approval: Option<approvals::ApprovalRecord>,
+    /// The caller cancelled the call before it finished
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub cancelled: bool,
+}
+
+/// Security event (e.g. a blocked sandbox escape) for frontend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecurityEvent {
+    pub id: String,
+    pub timestamp: String,
+    pub kind: String,
+    pub tool_name: String,
+    pub project_id: String,
+    pub detail: String,
 }
 
 /// Raw log event for debugging
@@ -152,6 +175,25 @@ pub struct BuildCommandInfo {
     pub created_at: String,
 }
 
+/// Allowed extra root for frontend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AllowedRootInfo {
+    pub id: String,
+    pub path: String,
+    pub added_at: String,
+}
+
+/// HTTP client token for frontend (the token itself only appears on creation)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClientTokenInfo {
+    pub id: String,
+    pub name: String,
+    pub created_at: String,
+    pub last_used_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
+
 /// Todo item for frontend
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TodoItemInfo {
@@ -164,1192 +206,54 @@ pub struct TodoItemInfo {
     pub updated_at: String,
 }
 
-/// Start the HTTP server
-#[tauri::command]
-async fn start_server(
-    state: tauri::State<'_, Arc<RwLock<AppState>>>,
-    app_handle: tauri::AppHandle,
-) -> Result<ServerStatus, String> {
-    // Check if already running
-    {
-        let state_read = state.read().await;
-        if state_read.is_server_running().await {
-            let port = state_read.get_port().await;
-            return Ok(ServerStatus { running: true, port });
+/// Run the application: the stdio proxy (`--mcp-stdio-proxy`), the headless
+/// server (`serve --headless`), or the Tauri GUI.
+///
+/// # Panics
+///
+/// Panics if the tokio runtime can't be started
+pub fn run() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    if args.iter().any(|arg| arg == "--mcp-stdio-proxy") {
+        // stdout carries the MCP messages, so logs go to stderr
+        tracing_subscriber::fmt().with_writer(std::io::stderr).init();
+        let runtime = tokio::runtime::Runtime::new().expect("tokio runtime");
+        if let Err(e) = runtime.block_on(mcp_proxy::run_stdio_proxy()) {
+            eprintln!("{e}");
         }
+        return;
     }
-    
-    // Get port
-    let port = {
-        let state_read = state.read().await;
-        state_read.get_port().await
-    };
-    
-    // Clone the Arc for the server
-    let server_state = Arc::clone(&state);
-    
-    // Start HTTP server
-    let handle = http_server::start_http_server(server_state, port)
-        .await
-        .map_err(|e| format!("Failed to start server: {}", e))?;
-    
-    // Store the handle
-    {
-    let state_write = state.write().await;
-    state_write.set_server_handle(handle).await;
-    }
-    
-    // Emit startup event
-    let startup_event = RawLogEvent {
-        timestamp: chrono::Utc::now().to_rfc3339(),
-        source: "server".to_string(),
-        message: format!("HTTP server started on port {}", port),
-    };
-    app_handle.emit("raw-log", &startup_event).ok();
-    
-    tracing::info!("HTTP server started on port {}", port);
-    
-    Ok(ServerStatus { running: true, port })
-}
-
-/// Stop the HTTP server
-#[tauri::command]
-async fn stop_server(
-    state: tauri::State<'_, Arc<RwLock<AppState>>>,
-) -> Result<ServerStatus, String> {
-    let state = state.write().await;
-    state.stop_server().await;
-    Ok(ServerStatus { running: false, port: 0 })
-}
-
-/// Get server status
-#[tauri::command]
-async fn get_server_status(
-    state: tauri::State<'_, Arc<RwLock<AppState>>>,
-) -> Result<ServerStatus, String> {
-    let state = state.read().await;
-    let running = state.is_server_running().await;
-    let port = state.get_port().await;
-    Ok(ServerStatus { running, port })
-}
-
-/// Execute a tool directly
-#[tauri::command]
-async fn execute_tool(
-    state: tauri::State<'_, Arc<RwLock<AppState>>>,
-    app_handle: tauri::AppHandle,
-    tool_name: String,
-    arguments: serde_json::Value,
-    project_id: Option<String>,
-) -> Result<String, String> {
-    use std::time::Instant;
-    use uuid::Uuid;
-    
-    let state = state.read().await;
-    let start = Instant::now();
-    let call_id = Uuid::new_v4().to_string();
-    let timestamp = chrono::Utc::now().to_rfc3339();
-    
-    // Get tool
-    let tool = state.tool_registry
-        .get(&tool_name)
-        .ok_or_else(|| format!("Tool not found: {}", tool_name))?;
-    
-    // Execute
-    let result = tool.execute(arguments.clone()).await;
-    let duration_ms = start.elapsed().as_millis() as u64;
-    
-    // Create and record event
-    let project_id = project_id.unwrap_or_else(|| "default".to_string());
-    let event = match &result {
-        Ok(output) => ToolCallEvent {
-            id: call_id.clone(),
-            timestamp: timestamp.clone(),
-            tool_name: tool_name.clone(),
-            project_id: project_id.clone(),
-            arguments: arguments.clone(),
-            success: true,
-            content: output.content.clone(),
-            duration_ms,
-        },
-        Err(e) => ToolCallEvent {
-            id: call_id.clone(),
-            timestamp: timestamp.clone(),
-            tool_name: tool_name.clone(),
-            project_id: project_id.clone(),
-            arguments: arguments.clone(),
-            success: false,
-            content: e.to_string(),
-            duration_ms,
-        },
-    };
-    
-    // Record event (broadcasts to UI)
-    state.record_event(event).await;
-    
-    // Also emit raw log event
-    let raw_event = RawLogEvent {
-        timestamp,
-        source: "tool".to_string(),
-        message: serde_json::json!({
-            "event": "tool_call_end",
-            "id": call_id,
-            "tool_name": tool_name,
-            "success": result.is_ok(),
-            "duration_ms": duration_ms
-        }).to_string(),
-    };
-    app_handle.emit("raw-log", &raw_event).ok();
-    
-    match result {
-        Ok(output) => Ok(output.content),
-        Err(e) => Err(e.to_string()),
-    }
-}
-
-/// Get event history
-#[tauri::command]
-async fn get_event_history(
-    state: tauri::State<'_, Arc<RwLock<AppState>>>,
-    project_id: Option<String>,
-) -> Result<Vec<ToolCallEvent>, String> {
-    let state = state.read().await;
-    let history = state.get_history().await;
-    let project_id = project_id.unwrap_or_else(|| "default".to_string());
-    Ok(history
-        .into_iter()
-        .filter(|event| event.project_id == project_id)
-        .collect())
-}
-
-#[tauri::command]
-async fn list_projects(
-    state: tauri::State<'_, Arc<RwLock<AppState>>>,
-) -> Result<Vec<ProjectInfo>, String> {
-    let state = state.read().await;
-    let projects = state
-        .project_registry
-        .list_projects()
-        .await
-        .map_err(|e| e.to_string())?;
-    
-    // Debug: Log all projects and their db_paths
-    for p in &projects {
-        tracing::info!(
-            "Project: id={}, name={}, root_path={}, db_path={}",
-            p.id, p.name, p.root_path, p.db_path
-        );
-    }
-    
-    Ok(projects
-        .into_iter()
-        .map(|p| ProjectInfo {
-            id: p.id,
-            name: p.name,
-            root_path: p.root_path,
-            db_path: p.db_path,
-            created_at: p.created_at.to_rfc3339(),
-            updated_at: p.updated_at.to_rfc3339(),
-        })
-        .collect())
-}
-
-#[tauri::command]
-async fn create_project(
-    state: tauri::State<'_, Arc<RwLock<AppState>>>,
-    name: String,
-    root_path: String,
-) -> Result<ProjectInfo, String> {
-    let state = state.read().await;
-    let project = state
-        .project_registry
-        .create_project(&name, &root_path)
-        .await
-        .map_err(|e| e.to_string())?;
-    Ok(ProjectInfo {
-        id: project.id,
-        name: project.name,
-        root_path: project.root_path,
-        db_path: project.db_path,
-        created_at: project.created_at.to_rfc3339(),
-        updated_at: project.updated_at.to_rfc3339(),
-    })
-}
-
-#[tauri::command]
-async fn list_todos(
-    state: tauri::State<'_, Arc<RwLock<AppState>>>,
-    args: ProjectArgs,
-) -> Result<Vec<TodoItemInfo>, String> {
-    let project_id = args.project_id.unwrap_or_else(|| "default".to_string());
-    debug_log(&format!("=== list_todos START === project_id={}", project_id));
-    
-    let store = {
-        let state_read = state.read().await;
-        state_read
-            .get_project_store(&project_id)
-            .await
-            .map_err(|e| e.to_string())?
-    };
-    
-    debug_log(&format!(
-        "list_todos: GOT STORE project_id={} db_path={}",
-        project_id, store.info.db_path
-    ));
-    
-    let todos = store
-        .todo_store
-        .read()
-        .await
-        .list()
-        .await
-        .map_err(|e| e.to_string())?;
-    
-    // Log each todo
-    for todo in &todos {
-        debug_log(&format!(
-            "list_todos: project_id={} todo_id={} title={}",
-            project_id, todo.id, todo.title
-        ));
-    }
-    
-    debug_log(&format!(
-        "=== list_todos END === project_id={} count={}",
-        project_id, todos.len()
-    ));
-    
-    let items = todos.into_iter().map(todo_info_from).collect();
-    Ok(items)
-}
-
-#[derive(Debug, Deserialize)]
-struct AddTodoArgs {
-    title: String,
-    description: Option<String>,
-    position: Option<i64>,
-    #[serde(default)]
-    project_id: Option<String>,
-}
-
-#[tauri::command]
-async fn add_todo(
-    state: tauri::State<'_, Arc<RwLock<AppState>>>,
-    args: AddTodoArgs,
-) -> Result<TodoItemInfo, String> {
-    let project_id = args.project_id.unwrap_or_else(|| "default".to_string());
-    let title = args.title;
-    let description = args.description;
-    let position = args.position;
-    let store = {
-        let state_read = state.read().await;
-        state_read
-            .get_project_store(&project_id)
-            .await
-            .map_err(|e| e.to_string())?
-    };
-    
-    // Debug logging
-    tracing::info!(
-        "add_todo: project_id={}, db_path={}, title={}",
-        project_id, store.info.db_path, title
-    );
-    
-    let todo = store
-        .todo_store
-        .read()
-        .await
-        .add(&title, description, position)
-        .await
-        .map_err(|e| e.to_string())?;
-    let info = todo_info_from(todo);
-    Ok(info)
-}
-
-#[derive(Debug, Deserialize)]
-struct SetTodoCompletedArgs {
-    id: String,
-    completed: bool,
-    #[serde(default)]
-    project_id: Option<String>,
-}
-
-#[tauri::command]
-async fn set_todo_completed(
-    state: tauri::State<'_, Arc<RwLock<AppState>>>,
-    args: SetTodoCompletedArgs,
-) -> Result<(), String> {
-    let project_id = args.project_id.unwrap_or_else(|| "default".to_string());
-    let id = args.id;
-    let completed = args.completed;
-    let store = {
-        let state_read = state.read().await;
-        state_read
-            .get_project_store(&project_id)
-            .await
-            .map_err(|e| e.to_string())?
-    };
-    let result = store
-        .todo_store
-        .read()
-        .await
-        .set_completed(&id, completed)
-        .await
-        .map_err(|e| e.to_string());
-    result
-}
-
-#[derive(Debug, Deserialize)]
-struct RemoveTodoArgs {
-    id: String,
-    #[serde(default)]
-    project_id: Option<String>,
-}
-
-#[tauri::command]
-async fn remove_todo(
-    state: tauri::State<'_, Arc<RwLock<AppState>>>,
-    args: RemoveTodoArgs,
-) -> Result<(), String> {
-    let project_id = args.project_id.unwrap_or_else(|| "default".to_string());
-    let id = args.id;
-    let store = {
-        let state_read = state.read().await;
-        state_read
-            .get_project_store(&project_id)
-            .await
-            .map_err(|e| e.to_string())?
-    };
-    let result = store
-        .todo_store
-        .read()
-        .await
-        .remove(&id)
-        .await
-        .map_err(|e| e.to_string());
-    result
-}
-
-#[tauri::command]
-async fn move_todo(
-    state: tauri::State<'_, Arc<RwLock<AppState>>>,
-    id: String,
-    position: i64,
-    project_id: Option<String>,
-) -> Result<(), String> {
-    let project_id = project_id.unwrap_or_else(|| "default".to_string());
-    let store = {
-        let state_read = state.read().await;
-        state_read
-            .get_project_store(&project_id)
-            .await
-            .map_err(|e| e.to_string())?
-    };
-    let result = store
-        .todo_store
-        .read()
-        .await
-        .move_to(&id, position)
-        .await
-        .map_err(|e| e.to_string());
-    result
-}
-
-#[tauri::command]
-async fn get_next_todo(
-    state: tauri::State<'_, Arc<RwLock<AppState>>>,
-    project_id: Option<String>,
-) -> Result<Option<TodoItemInfo>, String> {
-    let project_id = project_id.unwrap_or_else(|| "default".to_string());
-    let store = {
-        let state_read = state.read().await;
-        state_read
-            .get_project_store(&project_id)
-            .await
-            .map_err(|e| e.to_string())?
-    };
-    let todo = store
-        .todo_store
-        .read()
-        .await
-        .get_next()
-        .await
-        .map_err(|e| e.to_string())?;
-    let info = todo.map(todo_info_from);
-    Ok(info)
-}
-
-fn todo_info_from(todo: crate::todos::TodoItem) -> TodoItemInfo {
-    TodoItemInfo {
-        id: todo.id,
-        title: todo.title,
-        description: todo.description,
-        completed: todo.completed,
-        position: todo.position,
-        created_at: todo.created_at.to_rfc3339(),
-        updated_at: todo.updated_at.to_rfc3339(),
-    }
-}
-
-/// Add context file
-#[tauri::command]
-async fn add_context_file(
-    state: tauri::State<'_, Arc<RwLock<AppState>>>,
-    path: String,
-    project_id: Option<String>,
-) -> Result<ContextFileInfo, String> {
-    let project_id = project_id.unwrap_or_else(|| "default".to_string());
-    let store = {
-        let state_read = state.read().await;
-        state_read
-            .get_project_store(&project_id)
-            .await
-            .map_err(|e| e.to_string())?
-    };
-    let store = store.context_store.read().await;
-    let file = store.add_file(&path).await.map_err(|e| e.to_string())?;
-    
-    let name = std::path::Path::new(&file.path)
-        .file_name()
-        .and_then(|n| n.to_str())
-        .unwrap_or("unknown")
-        .to_string();
-    
-    Ok(ContextFileInfo {
-        id: file.id,
-        path: file.path,
-        name,
-        added_at: file.added_at.to_rfc3339(),
-        last_read_at: file.last_read_at.map(|d| d.to_rfc3339()),
-    })
-}
 
-#[derive(Debug, Deserialize)]
-struct RemoveContextFileArgs {
-    path: String,
-    #[serde(default)]
-    project_id: Option<String>,
-}
-
-/// Remove context file
-#[tauri::command]
-async fn remove_context_file(
-    state: tauri::State<'_, Arc<RwLock<AppState>>>,
-    args: RemoveContextFileArgs,
-) -> Result<(), String> {
-    let project_id = args.project_id.unwrap_or_else(|| "default".to_string());
-    let store = {
-        let state_read = state.read().await;
-        state_read
-            .get_project_store(&project_id)
-            .await
-            .map_err(|e| e.to_string())?
-    };
-    let store = store.context_store.read().await;
-    store.remove_file(&args.path).await.map_err(|e| e.to_string())
-}
-
-/// List context files
-#[tauri::command]
-async fn list_context_files(
-    state: tauri::State<'_, Arc<RwLock<AppState>>>,
-    project_id: Option<String>,
-) -> Result<Vec<ContextFileInfo>, String> {
-    let project_id = project_id.unwrap_or_else(|| "default".to_string());
-    let store = {
-        let state_read = state.read().await;
-        state_read
-            .get_project_store(&project_id)
-            .await
-            .map_err(|e| e.to_string())?
-    };
-    let store = store.context_store.read().await;
-    let files = store.list_files().await.map_err(|e| e.to_string())?;
-    
-    let infos: Vec<ContextFileInfo> = files
-        .into_iter()
-        .map(|file| {
-            let name = std::path::Path::new(&file.path)
-                .file_name()
-                .and_then(|n| n.to_str())
-                .unwrap_or("unknown")
-                .to_string();
-            
-            ContextFileInfo {
-                id: file.id,
-                path: file.path,
-                name,
-                added_at: file.added_at.to_rfc3339(),
-                last_read_at: file.last_read_at.map(|d| d.to_rfc3339()),
+    if headless::is_requested(&args) {
+        headless::init_logging();
+        let code = match headless::ServeOptions::parse(&args) {
+            Ok(options) => {
+                let runtime = tokio::runtime::Runtime::new().expect("tokio runtime");
+                match runtime.block_on(headless::serve(options)) {
+                    Ok(()) => 0,
+                    Err(e) => {
+                        tracing::error!("Headless server failed: {e:#}");
+                        1
+                    }
+                }
+            }
+            Err(e) => {
+                eprintln!("{}\n\n{}", e, headless::USAGE);
+                2
             }
-        })
-        .collect();
-    
-    Ok(infos)
-}
-
-/// List context notes
-#[tauri::command]
-async fn list_context_notes(
-    state: tauri::State<'_, Arc<RwLock<AppState>>>,
-    project_id: Option<String>,
-) -> Result<Vec<ContextNoteInfo>, String> {
-    let project_id = project_id.unwrap_or_else(|| "default".to_string());
-    let store = {
-        let state_read = state.read().await;
-        state_read
-            .get_project_store(&project_id)
-            .await
-            .map_err(|e| e.to_string())?
-    };
-    let store = store.context_note_store.read().await;
-    let notes = store.list().await.map_err(|e| e.to_string())?;
-    Ok(notes.into_iter().map(context_note_info_from).collect())
-}
-
-/// Add context note
-#[tauri::command]
-async fn add_context_note(
-    state: tauri::State<'_, Arc<RwLock<AppState>>>,
-    project_id: Option<String>,
-    content: String,
-    position: Option<i64>,
-) -> Result<ContextNoteInfo, String> {
-    let project_id = project_id.unwrap_or_else(|| "default".to_string());
-    let store = {
-        let state_read = state.read().await;
-        state_read
-            .get_project_store(&project_id)
-            .await
-            .map_err(|e| e.to_string())?
-    };
-    let store = store.context_note_store.read().await;
-    let note = store
-        .add(&content, position)
-        .await
-        .map_err(|e| e.to_string())?;
-    Ok(context_note_info_from(note))
-}
-
-#[derive(Debug, Deserialize)]
-struct UpdateContextNoteArgs {
-    #[serde(default)]
-    project_id: Option<String>,
-    id: String,
-    content: String,
-}
-
-/// Update context note
-#[tauri::command]
-async fn update_context_note(
-    state: tauri::State<'_, Arc<RwLock<AppState>>>,
-    args: UpdateContextNoteArgs,
-) -> Result<(), String> {
-    let project_id = args.project_id.unwrap_or_else(|| "default".to_string());
-    let store = {
-        let state_read = state.read().await;
-        state_read
-            .get_project_store(&project_id)
-            .await
-            .map_err(|e| e.to_string())?
-    };
-    let store = store.context_note_store.read().await;
-    store.update(&args.id, &args.content).await.map_err(|e| e.to_string())
-}
-
-#[derive(Debug, Deserialize)]
-struct RemoveContextNoteArgs {
-    #[serde(default)]
-    project_id: Option<String>,
-    id: String,
-}
-
-/// Remove context note
-#[tauri::command]
-async fn remove_context_note(
-    state: tauri::State<'_, Arc<RwLock<AppState>>>,
-    args: RemoveContextNoteArgs,
-) -> Result<(), String> {
-    let project_id = args.project_id.unwrap_or_else(|| "default".to_string());
-    let store = {
-        let state_read = state.read().await;
-        state_read
-            .get_project_store(&project_id)
-            .await
-            .map_err(|e| e.to_string())?
-    };
-    let store = store.context_note_store.read().await;
-    store.remove(&args.id).await.map_err(|e| e.to_string())
-}
-
-/// Move context note
-#[derive(Debug, Deserialize)]
-struct MoveContextNoteArgs {
-    #[serde(default)]
-    project_id: Option<String>,
-    id: String,
-    position: i64,
-}
-
-#[tauri::command]
-async fn move_context_note(
-    state: tauri::State<'_, Arc<RwLock<AppState>>>,
-    args: MoveContextNoteArgs,
-) -> Result<(), String> {
-    let project_id = args.project_id.unwrap_or_else(|| "default".to_string());
-    let store = {
-        let state_read = state.read().await;
-        state_read
-            .get_project_store(&project_id)
-            .await
-            .map_err(|e| e.to_string())?
-    };
-    let store = store.context_note_store.read().await;
-    store.move_to(&args.id, args.position).await.map_err(|e| e.to_string())
-}
-
-fn context_note_info_from(note: crate::context_notes::ContextNote) -> ContextNoteInfo {
-    ContextNoteInfo {
-        id: note.id,
-        content: note.content,
-        position: note.position,
-        created_at: note.created_at.to_rfc3339(),
-        updated_at: note.updated_at.to_rfc3339(),
-    }
-}
-
-/// List build commands
-#[tauri::command]
-async fn list_build_commands(
-    state: tauri::State<'_, Arc<RwLock<AppState>>>,
-    project_id: Option<String>,
-) -> Result<Vec<BuildCommandInfo>, String> {
-    let project_id = project_id.unwrap_or_else(|| "default".to_string());
-    let store = {
-        let state_read = state.read().await;
-        state_read
-            .get_project_store(&project_id)
-            .await
-            .map_err(|e| e.to_string())?
-    };
-    let store = store.build_command_store.read().await;
-    let commands = store.list().await.map_err(|e| e.to_string())?;
-    Ok(commands
-        .into_iter()
-        .map(build_command_info_from)
-        .collect())
-}
-
-/// Add build command
-#[tauri::command]
-async fn add_build_command(
-    state: tauri::State<'_, Arc<RwLock<AppState>>>,
-    project_id: Option<String>,
-    name: String,
-    command: String,
-    working_dir: Option<String>,
-) -> Result<BuildCommandInfo, String> {
-    let project_id = project_id.unwrap_or_else(|| "default".to_string());
-    let store = {
-        let state_read = state.read().await;
-        state_read
-            .get_project_store(&project_id)
-            .await
-            .map_err(|e| e.to_string())?
-    };
-    let store = store.build_command_store.read().await;
-    let command = store
-        .add(&name, &command, working_dir)
-        .await
-        .map_err(|e| e.to_string())?;
-    Ok(build_command_info_from(command))
-}
-
-#[derive(Debug, Deserialize)]
-struct RemoveBuildCommandArgs {
-    #[serde(default)]
-    project_id: Option<String>,
-    id: String,
-}
-
-/// Remove build command
-#[tauri::command]
-async fn remove_build_command(
-    state: tauri::State<'_, Arc<RwLock<AppState>>>,
-    args: RemoveBuildCommandArgs,
-) -> Result<(), String> {
-    let project_id = args.project_id.unwrap_or_else(|| "default".to_string());
-    let store = {
-        let state_read = state.read().await;
-        state_read
-            .get_project_store(&project_id)
-            .await
-            .map_err(|e| e.to_string())?
-    };
-    let store = store.build_command_store.read().await;
-    store.remove(&args.id).await.map_err(|e| e.to_string())
-}
-
-#[derive(Debug, Deserialize)]
-struct RunBuildCommandArgs {
-    #[serde(default)]
-    project_id: Option<String>,
-    id: String,
-}
-
-/// Run build command
-#[tauri::command]
-async fn run_build_command(
-    state: tauri::State<'_, Arc<RwLock<AppState>>>,
-    args: RunBuildCommandArgs,
-) -> Result<String, String> {
-    let project_id = args.project_id.unwrap_or_else(|| "default".to_string());
-    let id = args.id;
-    debug_log(&format!("=== run_build_command START === project_id={} command_id={}", project_id, id));
-    let (command, root_path) = {
-        let state_read = state.read().await;
-        let project = state_read
-            .project_registry
-            .get_project(&project_id)
-            .await
-            .map_err(|e| e.to_string())?
-            .ok_or_else(|| format!("Unknown project: {}", project_id))?;
-        let store = state_read
-            .get_project_store(&project_id)
-            .await
-            .map_err(|e| e.to_string())?;
-        let store = store.build_command_store.read().await;
-        let command = store
-            .get(&id)
-            .await
-            .map_err(|e| e.to_string())?
-            .ok_or_else(|| "Build command not found".to_string())?;
-        let working_dir = command
-            .working_dir
-            .clone()
-            .unwrap_or_else(|| project.root_path.clone());
-        debug_log(&format!("run_build_command: project_id={} command='{}' working_dir='{}'", 
-            project_id, command.command, working_dir));
-        (command.command, working_dir)
-    };
-
-    let result = run_shell_command(&command, &root_path).await;
-    debug_log(&format!("=== run_build_command END === project_id={} result={:?}", project_id, result.is_ok()));
-    result
-}
-
-#[derive(Debug, Deserialize)]
-struct SetDefaultBuildCommandArgs {
-    #[serde(default)]
-    project_id: Option<String>,
-    id: String,
-}
-
-/// Set default build command
-#[tauri::command]
-async fn set_default_build_command(
-    state: tauri::State<'_, Arc<RwLock<AppState>>>,
-    args: SetDefaultBuildCommandArgs,
-) -> Result<(), String> {
-    let project_id = args.project_id.unwrap_or_else(|| "default".to_string());
-    let store = {
-        let state_read = state.read().await;
-        state_read
-            .get_project_store(&project_id)
-            .await
-            .map_err(|e| e.to_string())?
-    };
-    let store = store.build_command_store.read().await;
-    store.set_default(&args.id).await.map_err(|e| e.to_string())
-}
-
-#[derive(Debug, Deserialize)]
-struct GetDefaultBuildCommandArgs {
-    #[serde(default)]
-    project_id: Option<String>,
-}
-
-/// Get default build command
-#[tauri::command]
-async fn get_default_build_command(
-    state: tauri::State<'_, Arc<RwLock<AppState>>>,
-    args: GetDefaultBuildCommandArgs,
-) -> Result<Option<BuildCommandInfo>, String> {
-    let project_id = args.project_id.unwrap_or_else(|| "default".to_string());
-    let store = {
-        let state_read = state.read().await;
-        state_read
-            .get_project_store(&project_id)
-            .await
-            .map_err(|e| e.to_string())?
-    };
-    let store = store.build_command_store.read().await;
-    let command = store.get_default().await.map_err(|e| e.to_string())?;
-    Ok(command.map(build_command_info_from))
-}
-
-fn build_command_info_from(command: crate::build_commands::BuildCommand) -> BuildCommandInfo {
-    BuildCommandInfo {
-        id: command.id,
-        name: command.name,
-        command: command.command,
-        working_dir: command.working_dir,
-        is_default: command.is_default,
-        created_at: command.created_at.to_rfc3339(),
-    }
-}
-
-pub(crate) async fn run_shell_command(command: &str, working_dir: &str) -> Result<String, String> {
-    #[cfg(target_os = "windows")]
-    let mut cmd = {
-        let mut cmd = tokio::process::Command::new("cmd");
-        cmd.arg("/C").arg(command);
-        cmd
-    };
-
-    #[cfg(not(target_os = "windows"))]
-    let mut cmd = {
-        let mut cmd = tokio::process::Command::new("sh");
-        cmd.arg("-lc").arg(command);
-        cmd
-    };
-
-    cmd.current_dir(working_dir);
-
-    let output = cmd
-        .output()
-        .await
-        .map_err(|e| format!("Failed to run command: {}", e))?;
-
-    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-
-    let combined = if stderr.is_empty() {
-        stdout.clone()
-    } else if stdout.is_empty() {
-        stderr.clone()
-    } else {
-        format!("{}\n{}", stdout, stderr)
-    };
-
-    if output.status.success() {
-        Ok(combined)
-    } else {
-        Err(format!("Command failed ({}): {}", output.status, combined))
-    }
-}
-
-/// List a project's directory contents (relative to project root).
-#[tauri::command]
-async fn list_project_directory(
-    state: tauri::State<'_, Arc<RwLock<AppState>>>,
-    project_id: Option<String>,
-    sub_path: Option<String>,
-) -> Result<DirectoryListingInfo, String> {
-    let project_id = project_id.unwrap_or_else(|| "default".to_string());
-    let project = {
-        let state_read = state.read().await;
-        state_read
-            .project_registry
-            .get_project(&project_id)
-            .await
-            .map_err(|e| e.to_string())?
-    }
-    .ok_or_else(|| format!("Unknown project: {}", project_id))?;
-
-    let root = std::path::PathBuf::from(&project.root_path);
-    let requested = sub_path.unwrap_or_else(|| "".to_string());
-    let requested_path = root.join(requested);
-    let canonical = std::fs::canonicalize(&requested_path)
-        .map_err(|e| format!("Invalid path: {}", e))?;
-
-    if !canonical.starts_with(&root) {
-        return Err("Path is outside project root".to_string());
-    }
-
-    list_directory_impl(&canonical, Some(&root)).await
-}
-
-/// List any absolute directory contents.
-#[tauri::command]
-async fn list_directory(path: String) -> Result<DirectoryListingInfo, String> {
-    let requested = std::path::PathBuf::from(path);
-    let canonical = std::fs::canonicalize(&requested)
-        .map_err(|e| format!("Invalid path: {}", e))?;
-    if !canonical.is_absolute() {
-        return Err("Path must be absolute".to_string());
-    }
-    list_directory_impl(&canonical, None).await
-}
-
-async fn list_directory_impl(
-    path: &std::path::Path,
-    root: Option<&std::path::Path>,
-) -> Result<DirectoryListingInfo, String> {
-    if !path.is_dir() {
-        return Err("Path is not a directory".to_string());
-    }
-
-    let mut entries = tokio::fs::read_dir(path)
-        .await
-        .map_err(|e| format!("Failed to read directory: {}", e))?;
-    let mut dirs: Vec<DirectoryEntryInfo> = Vec::new();
-    let mut files: Vec<DirectoryEntryInfo> = Vec::new();
-
-    while let Some(entry) = entries
-        .next_entry()
-        .await
-        .map_err(|e| format!("Failed to read directory: {}", e))?
-    {
-        let file_name = entry.file_name().to_string_lossy().to_string();
-        let entry_path = entry.path();
-        let metadata = entry
-            .metadata()
-            .await
-            .map_err(|e| format!("Failed to read metadata: {}", e))?;
-        let is_dir = metadata.is_dir();
-        let entry_info = DirectoryEntryInfo {
-            name: file_name,
-            path: entry_path.to_string_lossy().to_string(),
-            is_dir,
         };
-
-        if is_dir {
-            dirs.push(entry_info);
-        } else {
-            files.push(entry_info);
-        }
-    }
-
-    dirs.sort_by(|a, b| a.name.cmp(&b.name));
-    files.sort_by(|a, b| a.name.cmp(&b.name));
-
-    let mut entries = Vec::with_capacity(dirs.len() + files.len());
-    entries.extend(dirs);
-    entries.extend(files);
-
-    let parent_path = path
-        .parent()
-        .and_then(|parent| match root {
-            Some(root) if parent.starts_with(root) => Some(parent),
-            Some(_) => None,
-            None => Some(parent),
-        })
-        .map(|parent| parent.to_string_lossy().to_string());
-
-    Ok(DirectoryListingInfo {
-        path: path.to_string_lossy().to_string(),
-        parent_path,
-        entries,
-    })
-}
-
-/// Get the user's home directory
-#[tauri::command]
-async fn get_home_directory() -> Result<String, String> {
-    dirs::home_dir()
-        .map(|p| p.to_string_lossy().to_string())
-        .ok_or_else(|| "Could not determine home directory".to_string())
-}
-
-/// Get list of supported AI tools for MCP configuration
-#[tauri::command]
-async fn get_mcp_supported_tools() -> Result<Vec<mcp_config::AiToolInfo>, String> {
-    Ok(mcp_config::get_mcp_config_info())
-}
-
-/// Configure MCP for a specific AI tool and project
-#[tauri::command(rename_all = "snake_case")]
-async fn configure_mcp_for_tool(
-    tool: String,
-    project_id: String,
-    port: u16,
-) -> Result<mcp_config::McpSetupResult, String> {
-    let ai_tool = match tool.as_str() {
-        "claude" => mcp_config::AiTool::Claude,
-        "kimi" => mcp_config::AiTool::Kimi,
-        "gemini" => mcp_config::AiTool::Gemini,
-        "codex" => mcp_config::AiTool::Codex,
-        _ => return Err(format!("Unknown AI tool: {}", tool)),
-    };
-
-    mcp_config::configure_mcp(ai_tool, &project_id, port)
-        .await
-        .map_err(|e| e.to_string())
-}
-
-/// Configure MCP for all supported AI tools
-#[tauri::command(rename_all = "snake_case")]
-async fn configure_mcp_for_all_tools(
-    project_id: String,
-    port: u16,
-) -> Result<Vec<mcp_config::McpSetupResult>, String> {
-    let tools = vec![
-        mcp_config::AiTool::Claude,
-        mcp_config::AiTool::Kimi,
-        mcp_config::AiTool::Gemini,
-        mcp_config::AiTool::Codex,
-    ];
-
-    let mut results = Vec::new();
-
-    for tool in tools {
-        let result = mcp_config::configure_mcp(tool, &project_id, port).await;
-        results.push(result.unwrap_or_else(|e| mcp_config::McpSetupResult {
-            success: false,
-            message: format!("Error: {}", e),
-            config_path: None,
-        }));
+        std::process::exit(code);
     }
 
-    Ok(results)
-}
-
-/// Run the Tauri application
-pub fn run() {
     tracing_subscriber::fmt::init();
 
-    let is_stdio_proxy_mode = std::env::args().any(|arg| arg == "--mcp-stdio-proxy");
-    if is_stdio_proxy_mode {
-        tauri::async_runtime::block_on(async {
-            if let Err(e) = mcp_proxy::run_stdio_proxy().await {
-                eprintln!("{}", e);
-            }
-        });
-        return;
-    }
-    
-    tauri::Builder::default()
-        .plugin(tauri_plugin_shell::init())
-        .setup(|app| {
-            let handle = app.handle().clone();
-            
-            tauri::async_runtime::block_on(async move {
-                let app_dir = handle.path().app_data_dir().unwrap();
-                std::fs::create_dir_all(&app_dir).ok();
-                
-                let registry_path = app_dir.join("registry.db");
-                
-                match AppState::new(registry_path.to_str().unwrap(), &app_dir).await {
-                    Ok(state) => {
-                        tracing::info!("App state initialized");
-                        let state = Arc::new(RwLock::new(state));
-                        
-                        // Subscribe to events and forward to Tauri
-                        let mut rx = {
-                            let state_read = state.read().await;
-                            state_read.subscribe()
-                        };
-                        let app_handle = handle.clone();
-                        tauri::async_runtime::spawn(async move {
-                            while let Ok(event) = rx.recv().await {
-                                // Emit to Tauri UI
-                                let _ = app_handle.emit("tool-call", &event);
-                                
-                                // Also emit raw log
-                                let raw_event = RawLogEvent {
-                                    timestamp: event.timestamp.clone(),
-                                    source: "tool".to_string(),
-                                    message: serde_json::json!({
-                                        "event": "tool_call_end",
-                                        "id": &event.id,
-                                        "tool_name": &event.tool_name,
-                                        "success": event.success,
-                                        "duration_ms": event.duration_ms
-                                    }).to_string(),
-                                };
-                                let _ = app_handle.emit("raw-log", &raw_event);
-                            }
-                        });
-                        
-                        handle.manage(state.clone());
-
-                        let app_handle = handle.clone();
-                        tauri::async_runtime::spawn(async move {
-                            let port = {
-                                let state_read = state.read().await;
-                                state_read.get_port().await
-                            };
-
-                            let already_running = {
-                                let state_read = state.read().await;
-                                state_read.is_server_running().await
-                            };
+    #[cfg(feature = "gui")]
+    gui::run();
 
-                            if already_running {
-                                return;
-                            }
-
-                            match http_server::start_http_server(state.clone(), port).await {
-                                Ok(server_handle) => {
-                                    {
-                                        let state_write = state.write().await;
-                                        state_write.set_server_handle(server_handle).await;
-                                    }
-
-                                    let startup_event = RawLogEvent {
-                                        timestamp: chrono::Utc::now().to_rfc3339(),
-                                        source: "server".to_string(),
-                                        message: format!("HTTP server auto-started on port {}", port),
-                                    };
-                                    let _ = app_handle.emit("raw-log", &startup_event);
-                                }
-                                Err(e) => {
-                                    tracing::error!("Failed to auto-start HTTP server: {}", e);
-                                    let error_event = RawLogEvent {
-                                        timestamp: chrono::Utc::now().to_rfc3339(),
-                                        source: "server".to_string(),
-                                        message: format!("HTTP server auto-start failed: {}", e),
-                                    };
-                                    let _ = app_handle.emit("raw-log", &error_event);
-                                }
-                            }
-                        });
-                    }
-                    Err(e) => {
-                        tracing::error!("Failed to initialize: {}", e);
-                    }
-                }
-            });
-            
-            Ok(())
-        })
-        .plugin(tauri_plugin_dialog::init())
-        .invoke_handler(tauri::generate_handler![
-            start_server,
-            stop_server,
-            get_server_status,
-            execute_tool,
-            get_event_history,
-            list_projects,
-            create_project,
-            add_context_file,
-            remove_context_file,
-            list_context_files,
-            list_context_notes,
-            add_context_note,
-            update_context_note,
-            remove_context_note,
-            move_context_note,
-            list_project_directory,
-            list_directory,
-            get_home_directory,
-            list_build_commands,
-            add_build_command,
-            remove_build_command,
-            run_build_command,
-            set_default_build_command,
-            get_default_build_command,
-            list_todos,
-            add_todo,
-            set_todo_completed,
-            remove_todo,
-            move_todo,
-            get_next_todo,
-            get_mcp_supported_tools,
-            configure_mcp_for_tool,
-            configure_mcp_for_all_tools,
-            debug_log_cmd,
-        ])
-        .run(tauri::generate_context!())
-        .expect("error running app");
+    #[cfg(not(feature = "gui"))]
+    {
+        eprintln!("This build has no GUI.\n\n{}", headless::USAGE);
+        std::process::exit(2);
+    }
 }
@@ -0,0 +1,540 @@
+//! Minimal MCP client for upstream servers.
+//!
+//! Speaks JSON-RPC to a child process over stdin/stdout (one message per
+//! line) or to a Streamable HTTP endpoint (JSON or SSE replies). Requests from
+//! the server other than `ping` are answered with "method not found", and its
+//! notifications are ignored.
+
+use crate::{error::ToolError, mcp_proxy::take_sse_messages};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin};
+use tokio::sync::oneshot;
+use tokio_util::sync::CancellationToken;
+
+/// Protocol version requested on `initialize`
+pub const CLIENT_PROTOCOL_VERSION: &str = "2025-06-18";
+
+/// How long list/read requests may take (tool calls are not limited)
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Most pages fetched from a paginated list
+const MAX_PAGES: usize = 20;
+
+type Pending = Arc<Mutex<HashMap<u64, oneshot::Sender<Value>>>>;
+
+enum Transport {
+    Stdio {
+        /// Kept so the process is killed when the client is dropped
+        _child: Child,
+        stdin: Arc<tokio::sync::Mutex<ChildStdin>>,
+        pending: Pending,
+        closed: Arc<AtomicBool>,
+    },
+    Http {
+        client: reqwest::Client,
+        url: String,
+        session_id: Mutex<Option<String>>,
+        /// Set when the server no longer knows the session
+        expired: AtomicBool,
+    },
+}
+
+/// A connection to one upstream MCP server
+pub struct McpClient {
+    /// Server name, used in error messages
+    name: String,
+    transport: Transport,
+    next_id: AtomicU64,
+}
+
+impl McpClient {
+    /// Launch a stdio server in `working_dir` and initialize it
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the process cannot be started or does not complete
+    /// the `initialize` handshake
+    pub async fn connect_stdio(
+        name: &str,
+        command: &str,
+        args: &[String],
+        env: &BTreeMap<String, String>,
+        working_dir: &Path,
+    ) -> Result<Self, ToolError> {
+        let mut child = tokio::process::Command::new(command)
+            .args(args)
+            .envs(env)
+            .current_dir(working_dir)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| upstream_error(name, format!("Failed to launch '{command}': {e}")))?;
+
+        let (Some(stdin), Some(stdout), Some(stderr)) =
+            (child.stdin.take(), child.stdout.take(), child.stderr.take())
+        else {
+            return Err(upstream_error(name, "Failed to open the server's stdio"));
+        };
+        let stdin = Arc::new(tokio::sync::Mutex::new(stdin));
+        let pending = Pending::default();
+        let closed = Arc::new(AtomicBool::new(false));
+
+        tokio::spawn(read_stdio_messages(
+            BufReader::new(stdout),
+            stdin.clone(),
+            pending.clone(),
+            closed.clone(),
+        ));
+        let server = name.to_string();
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stderr).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                tracing::debug!("[upstream {server}] {line}");
+            }
+        });
+
+        let client = Self {
+            name: name.to_string(),
+            transport: Transport::Stdio {
+                _child: child,
+                stdin,
+                pending,
+                closed,
+            },
+            next_id: AtomicU64::new(1),
+        };
+        client.initialize().await?;
+        Ok(client)
+    }
+
+    /// Connect to a Streamable HTTP server and initialize a session
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the server cannot be reached or rejects `initialize`
+    pub async fn connect_http(name: &str, url: &str) -> Result<Self, ToolError> {
+        let client = Self {
+            name: name.to_string(),
+            transport: Transport::Http {
+                client: reqwest::Client::new(),
+                url: url.to_string(),
+                session_id: Mutex::new(None),
+                expired: AtomicBool::new(false),
+            },
+            next_id: AtomicU64::new(1),
+        };
+        client.initialize().await?;
+        Ok(client)
+    }
+
+    async fn initialize(&self) -> Result<(), ToolError> {
+        self.request(
+            "initialize",
+            json!({
+                "protocolVersion": CLIENT_PROTOCOL_VERSION,
+                "capabilities": {},
+                "clientInfo": { "name": "aiharness", "version": env!("CARGO_PKG_VERSION") }
+            }),
+        )
+        .await?;
+        self.notify("notifications/initialized", None).await
+    }
+
+    /// Whether the connection can still be used: a stdio server is running, or
+    /// an HTTP server has not answered 404 to the session
+    pub fn is_alive(&self) -> bool {
+        match &self.transport {
+            Transport::Stdio { closed, .. } => !closed.load(Ordering::Relaxed),
+            Transport::Http { expired, .. } => !expired.load(Ordering::Relaxed),
+        }
+    }
+
+    /// The server's tools, across all pages
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails
+    pub async fn list_tools(&self) -> Result<Vec<Value>, ToolError> {
+        self.list_all("tools/list", "tools").await
+    }
+
+    /// The server's resources, across all pages
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails
+    pub async fn list_resources(&self) -> Result<Vec<Value>, ToolError> {
+        self.list_all("resources/list", "resources").await
+    }
+
+    /// Read a resource; returns the `contents` array
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails
+    pub async fn read_resource(&self, uri: &str) -> Result<Vec<Value>, ToolError> {
+        let result = self.request("resources/read", json!({ "uri": uri })).await?;
+        Ok(result
+            .get("contents")
+            .and_then(|c| c.as_array())
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    /// Call a tool and return the raw `tools/call` result
+    ///
+    /// If `cancel` fires, or the returned future is dropped before the reply,
+    /// the server is sent `notifications/cancelled`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or is cancelled
+    pub async fn call_tool(
+        &self,
+        name: &str,
+        arguments: Value,
+        cancel: &CancellationToken,
+    ) -> Result<Value, ToolError> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut call = PendingCall {
+            client: self,
+            id,
+            finished: false,
+        };
+        let params = json!({ "name": name, "arguments": arguments });
+        let result = tokio::select! {
+            result = self.exchange(id, "tools/call", params) => result,
+            () = cancel.cancelled() => return Err(ToolError::Cancelled),
+        };
+        call.finished = true;
+        result
+    }
+
+    async fn list_all(&self, method: &str, key: &str) -> Result<Vec<Value>, ToolError> {
+        let mut items = Vec::new();
+        let mut cursor: Option<String> = None;
+        for _ in 0..MAX_PAGES {
+            let params = cursor.map_or_else(|| json!({}), |cursor| json!({ "cursor": cursor }));
+            let result = self.request(method, params).await?;
+            if let Some(page) = result.get(key).and_then(|v| v.as_array()) {
+                items.extend(page.iter().cloned());
+            }
+            cursor = result
+                .get("nextCursor")
+                .and_then(|v| v.as_str())
+                .map(str::to_string);
+            if cursor.is_none() {
+                break;
+            }
+        }
+        Ok(items)
+    }
+
+    /// Send a request and wait (at most [`REQUEST_TIMEOUT`]) for its result
+    async fn request(&self, method: &str, params: Value) -> Result<Value, ToolError> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let exchange = self.exchange(id, method, params);
+        let Ok(result) = tokio::time::timeout(REQUEST_TIMEOUT, exchange).await else {
+            self.forget(id);
+            return Err(self.error(format!("{method} timed out")));
+        };
+        result
+    }
+
+    async fn exchange(&self, id: u64, method: &str, params: Value) -> Result<Value, ToolError> {
+        let message = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        let response = match &self.transport {
+            Transport::Stdio { stdin, pending, .. } => {
+                let (sender, receiver) = oneshot::channel();
+                pending
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .insert(id, sender);
+                if let Err(e) = write_message(stdin, &message).await {
+                    self.forget(id);
+                    return Err(self.error(format!("Failed to send {method}: {e}")));
+                }
+                receiver
+                    .await
+                    .map_err(|_| self.error("Server exited before replying"))?
+            }
+            Transport::Http { .. } => self.post(&message, Some(id)).await?,
+        };
+
+        if let Some(error) = response.get("error") {
+            let message = error
+                .get("message")
+                .and_then(|m| m.as_str())
+                .unwrap_or("Unknown error");
+            return Err(self.error(message));
+        }
+        Ok(response.get("result").cloned().unwrap_or(Value::Null))
+    }
+
+    async fn notify(&self, method: &str, params: Option<Value>) -> Result<(), ToolError> {
+        let mut message = json!({ "jsonrpc": "2.0", "method": method });
+        if let Some(params) = params {
+            message["params"] = params;
+        }
+        match &self.transport {
+            Transport::Stdio { stdin, .. } => write_message(stdin, &message)
+                .await
+                .map_err(|e| self.error(format!("Failed to send {method}: {e}"))),
+            Transport::Http { .. } => self.post(&message, None).await.map(|_| ()),
+        }
+    }
+
+    /// POST a message; for a request, return the reply with its id
+    async fn post(&self, message: &Value, id: Option<u64>) -> Result<Value, ToolError> {
+        let Transport::Http {
+            client,
+            url,
+            session_id,
+            expired,
+        } = &self.transport
+        else {
+            return Err(self.error("Not an HTTP connection"));
+        };
+
+        let mut builder = client
+            .post(url)
+            .header(reqwest::header::ACCEPT, "application/json, text/event-stream")
+            .json(message);
+        let current = session_id
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        let has_session = current.is_some();
+        if let Some(current) = current {
+            builder = builder.header("mcp-session-id", current);
+        }
+        let mut resp = builder.send().await.map_err(|e| self.error(e.to_string()))?;
+        if has_session && resp.status() == reqwest::StatusCode::NOT_FOUND {
+            // The session is gone; the gateway reconnects on next use
+            expired.store(true, Ordering::Relaxed);
+            return Err(self.error("Session expired"));
+        }
+        if let Some(issued) = resp
+            .headers()
+            .get("mcp-session-id")
+            .and_then(|v| v.to_str().ok())
+        {
+            *session_id.lock().unwrap_or_else(PoisonError::into_inner) = Some(issued.to_string());
+        }
+        if !resp.status().is_success() {
+            return Err(self.error(format!("HTTP {}", resp.status())));
+        }
+        let Some(id) = id else {
+            return Ok(Value::Null);
+        };
+
+        let is_sse = resp
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.starts_with("text/event-stream"));
+        if !is_sse {
+            return resp.json().await.map_err(|e| self.error(e.to_string()));
+        }
+
+        let mut buffer = String::new();
+        while let Some(chunk) = resp.chunk().await.map_err(|e| self.error(e.to_string()))? {
+            buffer.push_str(&String::from_utf8_lossy(&chunk));
+            for data in take_sse_messages(&mut buffer) {
+                let Ok(message) = serde_json::from_str::<Value>(&data) else {
+                    continue;
+                };
+                if message.get("method").is_none() && message.get("id") == Some(&json!(id)) {
+                    return Ok(message);
+                }
+            }
+        }
+        Err(self.error("Stream ended before the reply"))
+    }
+
+    /// Tell the server a request was cancelled, without waiting
+    fn cancel_in_background(&self, id: u64) {
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let message = json!({
+            "jsonrpc": "2.0",
+            "method": "notifications/cancelled",
+            "params": { "requestId": id, "reason": "Cancelled by the client" }
+        });
+        match &self.transport {
+            Transport::Stdio { stdin, .. } => {
+                let stdin = stdin.clone();
+                runtime.spawn(async move {
+                    let _ = write_message(&stdin, &message).await;
+                });
+            }
+            Transport::Http {
+                client,
+                url,
+                session_id,
+                ..
+            } => {
+                let mut builder = client.post(url).json(&message);
+                let current = session_id
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .clone();
+                if let Some(current) = current {
+                    builder = builder.header("mcp-session-id", current);
+                }
+                runtime.spawn(async move {
+                    let _ = builder.send().await;
+                });
+            }
+        }
+    }
+
+    /// Stop waiting for a request's reply
+    fn forget(&self, id: u64) {
+        if let Transport::Stdio { pending, .. } = &self.transport {
+            pending
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .remove(&id);
+        }
+    }
+
+    fn error(&self, message: impl Into<String>) -> ToolError {
+        upstream_error(&self.name, message)
+    }
+}
+
+/// A tool call awaiting its reply; cancelled on the server if dropped first
+struct PendingCall<'a> {
+    client: &'a McpClient,
+    id: u64,
+    finished: bool,
+}
+
+impl Drop for PendingCall<'_> {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.client.forget(self.id);
+            self.client.cancel_in_background(self.id);
+        }
+    }
+}
+
+fn upstream_error(server: &str, message: impl Into<String>) -> ToolError {
+    ToolError::Upstream {
+        server: server.to_string(),
+        message: message.into(),
+    }
+}
+
+async fn write_message(
+    stdin: &tokio::sync::Mutex<ChildStdin>,
+    message: &Value,
+) -> std::io::Result<()> {
+    let mut line = message.to_string();
+    line.push('\n');
+    let mut stdin = stdin.lock().await;
+    stdin.write_all(line.as_bytes()).await?;
+    stdin.flush().await
+}
+
+/// Route a stdio server's messages: replies to their waiting requests, `ping`
+/// answered, other server requests refused. Marks the connection closed at EOF.
+async fn read_stdio_messages(
+    stdout: BufReader<tokio::process::ChildStdout>,
+    stdin: Arc<tokio::sync::Mutex<ChildStdin>>,
+    pending: Pending,
+    closed: Arc<AtomicBool>,
+) {
+    let mut lines = stdout.lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        let Ok(message) = serde_json::from_str::<Value>(&line) else {
+            continue;
+        };
+        let id = message.get("id").cloned().filter(|id| !id.is_null());
+        match (message.get("method").and_then(|m| m.as_str()), id) {
+            (Some(method), Some(id)) => {
+                let reply = if method == "ping" {
+                    json!({ "jsonrpc": "2.0", "id": id, "result": {} })
+                } else {
+                    json!({
+                        "jsonrpc": "2.0",
+                        "id": id,
+                        "error": { "code": -32601, "message": format!("Method not found: {method}") }
+                    })
+                };
+                let _ = write_message(&stdin, &reply).await;
+            }
+            (None, Some(id)) => {
+                let waiter = id.as_u64().and_then(|id| {
+                    pending
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .remove(&id)
+                });
+                if let Some(waiter) = waiter {
+                    let _ = waiter.send(message);
+                }
+            }
+            // Notifications from the server and malformed messages are dropped
+            (_, None) => {}
+        }
+    }
+    closed.store(true, Ordering::Relaxed);
+    pending
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http::HeaderMap, http::StatusCode, response::IntoResponse, routing::post, Json, Router};
+
+    /// A server that knows no sessions
+    async fn forgetful(headers: HeaderMap, Json(message): Json<Value>) -> axum::response::Response {
+        if headers.contains_key("mcp-session-id") {
+            return StatusCode::NOT_FOUND.into_response();
+        }
+        Json(json!({ "jsonrpc": "2.0", "id": message["id"], "result": {} })).into_response()
+    }
+
+    #[tokio::test]
+    async fn http_connection_dies_when_its_session_is_lost() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/mcp", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            axum::serve(listener, Router::new().route("/mcp", post(forgetful)))
+                .await
+                .unwrap();
+        });
+
+        // A connection whose session the server has since dropped
+        let client = McpClient {
+            name: "remote".to_string(),
+            transport: Transport::Http {
+                client: reqwest::Client::new(),
+                url,
+                session_id: Mutex::new(Some("s1".to_string())),
+                expired: AtomicBool::new(false),
+            },
+            next_id: AtomicU64::new(1),
+        };
+        assert!(client.is_alive());
+        let error = client.list_tools().await.unwrap_err();
+        assert_eq!(error.to_string(), "Upstream server 'remote': Session expired");
+        assert!(!client.is_alive());
+        server.abort();
+    }
+}
@@ -5,7 +5,7 @@
 use crate::error::ContextError;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 /// Supported AI tools for MCP configuration
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -115,21 +115,29 @@ pub struct McpSetupResult {
 }
 
 /// Configure MCP for a specific AI tool and project
+///
+/// `token` is the client bearer token the tool will authenticate with.
 pub async fn configure_mcp(
     tool: AiTool,
     project_id: &str,
     server_port: u16,
+    token: &str,
 ) -> Result<McpSetupResult, ContextError> {
     let binary_path = detect_aiharness_binary()?;
 
     match tool {
-        AiTool::Claude => configure_claude(project_id, &binary_path).await,
-        AiTool::Kimi => configure_kimi(project_id, server_port).await,
-        AiTool::Gemini => configure_gemini(project_id, server_port).await,
-        AiTool::Codex => configure_codex(project_id, server_port).await,
+        AiTool::Claude => configure_claude(project_id, server_port, token, &binary_path).await,
+        AiTool::Kimi => configure_kimi(project_id, server_port, token).await,
+        AiTool::Gemini => configure_gemini(project_id, server_port, token).await,
+        AiTool::Codex => configure_codex(project_id, server_port, token).await,
     }
 }
 
+/// `Authorization` header value for a client token
+fn bearer_header(token: &str) -> String {
+    format!("Bearer {token}")
+}
+
 /// Detect the AIHarness binary path
 /// 
 /// This handles multiple scenarios:
@@ -223,28 +231,42 @@ fn find_claude_binary() -> Result<PathBuf, ContextError> {
 
 /// Configure Claude Code using CLI command
 /// 
-/// Command: claude mcp add --transport stdio <name> -- <binary> --mcp-stdio-proxy --project <project_id>
-async fn configure_claude(project_id: &str, binary_path: &PathBuf) -> Result<McpSetupResult, ContextError> {
+/// Command: `claude mcp add --transport stdio -e AIH_PORT=.. -e AIH_PROJECT_ID=.. -e AIH_TOKEN=.. <name> -- <binary> --mcp-stdio-proxy`
+async fn configure_claude(
+    project_id: &str,
+    server_port: u16,
+    token: &str,
+    binary_path: &Path,
+) -> Result<McpSetupResult, ContextError> {
     let server_name = format!("aiharness-{}", project_id);
     let binary_str = binary_path.to_string_lossy();
     
     // Find the Claude binary
     let claude_path = find_claude_binary()?;
 
-    // Build the command: claude mcp add --transport stdio <name> -- <binary> --mcp-stdio-proxy
+    // The proxy reads its port, project and token from the environment Claude launches it with
+    let port_env = format!("AIH_PORT={server_port}");
+    let project_env = format!("AIH_PROJECT_ID={project_id}");
+    let token_env = format!("AIH_TOKEN={token}");
+
+    // Build the command: claude mcp add --transport stdio -e ... <name> -- <binary> --mcp-stdio-proxy
     let output = tokio::process::Command::new(&claude_path)
         .args(&[
             "mcp",
             "add",
             "--transport",
             "stdio",
+            "-e",
+            &port_env,
+            "-e",
+            &project_env,
+            "-e",
+            &token_env,
             &server_name,
             "--",
             &binary_str,
             "--mcp-stdio-proxy",
         ])
-        .env("AIH_PORT", "8787")
-        .env("AIH_PROJECT_ID", project_id)
         .output()
         .await
         .map_err(|e| ContextError::Config(format!("Failed to run claude command: {}", e)))?;
@@ -275,7 +297,11 @@ async fn configure_claude(project_id: &str, binary_path: &PathBuf) -> Result<Mcp
 }
 
 /// Configure Kimi CLI using file-based config
-async fn configure_kimi(project_id: &str, server_port: u16) -> Result<McpSetupResult, ContextError> {
+async fn configure_kimi(
+    project_id: &str,
+    server_port: u16,
+    token: &str,
+) -> Result<McpSetupResult, ContextError> {
     let config_path = match AiTool::Kimi.config_path()? {
         Some(p) => p,
         None => return Err(ContextError::Config("No config path for Kimi".to_string())),
@@ -289,7 +315,8 @@ async fn configure_kimi(project_id: &str, server_port: u16) -> Result<McpSetupRe
         "mcpServers": {
             server_name.clone(): {
                 "url": server_url,
-                "transport": "http"
+                "transport": "http",
+                "headers": { "Authorization": bearer_header(token) }
             }
         }
     });
@@ -326,9 +353,13 @@ async fn configure_kimi(project_id: &str, server_port: u16) -> Result<McpSetupRe
 /// Configure Gemini CLI using file-based config
 /// 
 /// Config location: ~/.gemini/settings.json
-/// Format: { "mcpServers": { "name": { "url": "..." } } }
+/// Format: { "mcpServers": { "name": { "url": "...", "headers": { ... } } } }
 /// Docs: https://geminicli.com/docs/tools/mcp-server/
-async fn configure_gemini(project_id: &str, server_port: u16) -> Result<McpSetupResult, ContextError> {
+async fn configure_gemini(
+    project_id: &str,
+    server_port: u16,
+    token: &str,
+) -> Result<McpSetupResult, ContextError> {
     let config_path = match AiTool::Gemini.config_path()? {
         Some(p) => p,
         None => return Err(ContextError::Config("No config path for Gemini".to_string())),
@@ -341,7 +372,8 @@ async fn configure_gemini(project_id: &str, server_port: u16) -> Result<McpSetup
     let config = serde_json::json!({
         "mcpServers": {
             server_name.clone(): {
-                "url": server_url
+                "url": server_url,
+                "headers": { "Authorization": bearer_header(token) }
             }
         }
     });
@@ -382,8 +414,14 @@ async fn configure_gemini(project_id: &str, server_port: u16) -> Result<McpSetup
 ///   mcpServers:
 ///     name:
 ///       url: https://...
+///       headers:
+///         Authorization: Bearer ...
 /// Docs: https://developers.openai.com/codex/mcp/
-async fn configure_codex(project_id: &str, server_port: u16) -> Result<McpSetupResult, ContextError> {
+async fn configure_codex(
+    project_id: &str,
+    server_port: u16,
+    token: &str,
+) -> Result<McpSetupResult, ContextError> {
     let config_path = match AiTool::Codex.config_path()? {
         Some(p) => p,
         None => return Err(ContextError::Config("No config path for Codex".to_string())),
@@ -400,7 +438,7 @@ async fn configure_codex(project_id: &str, server_port: u16) -> Result<McpSetupR
     };
 
     // Merge YAML configs
-    let merged = merge_codex_config(existing_yaml, &server_name, &server_url)?;
+    let merged = merge_codex_config(existing_yaml, &server_name, &server_url, token)?;
 
     // Ensure parent directory exists
     if let Some(parent) = config_path.parent() {
@@ -426,6 +464,7 @@ fn merge_codex_config(
     existing: Option<String>,
     server_name: &str,
     server_url: &str,
+    token: &str,
 ) -> Result<String, ContextError> {
     use serde_yaml::Value;
 
@@ -453,6 +492,15 @@ fn merge_codex_config(
         Value::String("url".to_string()),
         Value::String(server_url.to_string()),
     );
+    let mut headers = serde_yaml::Mapping::new();
+    headers.insert(
+        Value::String("Authorization".to_string()),
+        Value::String(bearer_header(token)),
+    );
+    server_config.insert(
+        Value::String("headers".to_string()),
+        Value::Mapping(headers),
+    );
     
     server_mapping.insert(
         Value::String(server_name.to_string()),
@@ -560,4 +608,18 @@ mod tests {
         assert!(servers.get("existing").is_some());
         assert!(servers.get("new").is_some());
     }
+
+    #[test]
+    fn merge_codex_config_adds_server_with_auth_header() {
+        let existing = Some("mcpServers:\n  other:\n    url: http://other\n".to_string());
+
+        let merged = merge_codex_config(existing, "aiharness-p1", "http://127.0.0.1:8787/mcp/p1", "aih_t")
+            .unwrap();
+        let parsed: serde_yaml::Value = serde_yaml::from_str(&merged).unwrap();
+
+        let servers = &parsed["mcpServers"];
+        assert!(servers.get("other").is_some());
+        assert_eq!(servers["aiharness-p1"]["url"], "http://127.0.0.1:8787/mcp/p1");
+        assert_eq!(servers["aiharness-p1"]["headers"]["Authorization"], "Bearer aih_t");
+    }
 }
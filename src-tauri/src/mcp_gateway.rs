@@ -0,0 +1,537 @@
+//! Gateway to a project's upstream MCP servers.
+//!
+//! Enabled servers are connected on first use and the connection is kept
+//! until the server is removed, disabled or reconfigured (or a stdio server
+//! exits). Each server connects under its own lock, so a slow server does not
+//! hold up the others, and its tool list is fetched once per connection. Their
+//! tools are listed as `{server}__{tool}` and run through the
+//! normal dispatcher as [`UpstreamTool`]s, so policies, approvals and history
+//! apply to them like built-ins. Their resources are listed as
+//! `upstream://{server}/{uri}`.
+
+use crate::{
+    error::ToolError,
+    mcp_client::McpClient,
+    projects::ProjectStore,
+    tools::{Tool, ToolAnnotations, ToolContext, ToolResult},
+    upstream_servers::{UpstreamServer, UpstreamTransport},
+};
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex, PoisonError};
+use tokio::sync::OnceCell;
+
+/// Separates the server name from the tool name
+pub const TOOL_SEPARATOR: &str = "__";
+
+/// Prefix of namespaced upstream resource URIs
+pub const RESOURCE_SCHEME: &str = "upstream://";
+
+struct Connection {
+    /// Configuration the connection was made with
+    server: UpstreamServer,
+    client: Arc<McpClient>,
+    /// The server's `tools/list`, fetched on first use
+    tools: OnceCell<Vec<Value>>,
+}
+
+/// A server's connection, locked while it is being made
+type Slot = Arc<tokio::sync::Mutex<Option<Arc<Connection>>>>;
+
+/// Live connections to upstream servers, by server id
+#[derive(Default)]
+pub struct McpGateway {
+    connections: Mutex<HashMap<String, Slot>>,
+}
+
+impl McpGateway {
+    /// Tools of a project's enabled upstream servers
+    ///
+    /// Servers that cannot be reached are skipped (and logged).
+    pub async fn tools(&self, store: &ProjectStore) -> Vec<UpstreamTool> {
+        let mut tools = Vec::new();
+        for server in enabled_servers(store).await {
+            match self.server_tools(store, &server).await {
+                Ok(server_tools) => tools.extend(server_tools),
+                Err(e) => tracing::warn!("Skipping upstream server tools: {e}"),
+            }
+        }
+        tools.sort_by(|a, b| a.name.cmp(&b.name));
+        tools
+    }
+
+    /// Look up a namespaced tool (`{server}__{tool}`)
+    ///
+    /// Returns `Ok(None)` if no enabled server of the project has that tool.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the server cannot be reached
+    pub async fn tool(&self, store: &ProjectStore, name: &str) -> Result<Option<UpstreamTool>, ToolError> {
+        let Some((server_name, _)) = name.split_once(TOOL_SEPARATOR) else {
+            return Ok(None);
+        };
+        let Some(server) = enabled_server(store, server_name).await else {
+            return Ok(None);
+        };
+        let tools = self.server_tools(store, &server).await?;
+        Ok(tools.into_iter().find(|tool| tool.name == name))
+    }
+
+    /// Resources of a project's enabled upstream servers, with namespaced URIs
+    ///
+    /// Servers that cannot be reached are skipped (and logged).
+    pub async fn resources(&self, store: &ProjectStore) -> Vec<Value> {
+        let mut resources = Vec::new();
+        for server in enabled_servers(store).await {
+            let listed = match self.connection(store, &server).await {
+                Ok(connection) => connection.client.list_resources().await,
+                Err(e) => Err(e),
+            };
+            match listed {
+                Ok(listed) => resources.extend(listed.into_iter().filter_map(|mut resource| {
+                    let uri = resource.get("uri")?.as_str()?;
+                    resource["uri"] = json!(resource_uri(&server.name, uri));
+                    Some(resource)
+                })),
+                Err(e) => tracing::warn!("Skipping upstream server resources: {e}"),
+            }
+        }
+        resources
+    }
+
+    /// Read a namespaced resource (`upstream://{server}/{uri}`)
+    ///
+    /// Returns the `contents` with namespaced URIs, or `Ok(None)` if the URI
+    /// names no enabled server of the project.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the server cannot be reached or the read fails
+    pub async fn read_resource(&self, store: &ProjectStore, uri: &str) -> Result<Option<Vec<Value>>, ToolError> {
+        let Some((server_name, upstream_uri)) = parse_resource_uri(uri) else {
+            return Ok(None);
+        };
+        let Some(server) = enabled_server(store, server_name).await else {
+            return Ok(None);
+        };
+        let connection = self.connection(store, &server).await?;
+        let contents = connection.client.read_resource(upstream_uri).await?;
+        Ok(Some(
+            contents
+                .into_iter()
+                .map(|mut content| {
+                    if let Some(inner) = content.get("uri").and_then(|u| u.as_str()) {
+                        content["uri"] = json!(resource_uri(&server.name, inner));
+                    }
+                    content
+                })
+                .collect(),
+        ))
+    }
+
+    /// Close a server's connection (stopping a stdio server)
+    pub async fn disconnect(&self, server_id: &str) {
+        let slot = self.lock().remove(server_id);
+        if let Some(slot) = slot {
+            slot.lock().await.take();
+        }
+    }
+
+    async fn server_tools(&self, store: &ProjectStore, server: &UpstreamServer) -> Result<Vec<UpstreamTool>, ToolError> {
+        let connection = self.connection(store, server).await?;
+        let listed = connection
+            .tools
+            .get_or_try_init(|| connection.client.list_tools())
+            .await?;
+        Ok(listed
+            .iter()
+            .filter_map(|tool| UpstreamTool::new(&server.name, connection.client.clone(), tool))
+            .collect())
+    }
+
+    /// The server's connection, made (again) if it is missing, stale or closed
+    ///
+    /// Only this server's slot stays locked while connecting.
+    async fn connection(&self, store: &ProjectStore, server: &UpstreamServer) -> Result<Arc<Connection>, ToolError> {
+        let slot = self.lock().entry(server.id.clone()).or_default().clone();
+        let mut slot = slot.lock().await;
+        if let Some(connection) = slot.as_ref() {
+            if connection.server == *server && connection.client.is_alive() {
+                return Ok(connection.clone());
+            }
+        }
+
+        // Drop the stale connection before starting its replacement
+        slot.take();
+        let client = connect(server, Path::new(&store.info.root_path)).await?;
+        let connection = Arc::new(Connection {
+            server: server.clone(),
+            client: Arc::new(client),
+            tools: OnceCell::new(),
+        });
+        *slot = Some(connection.clone());
+        Ok(connection)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Slot>> {
+        self.connections
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+async fn connect(server: &UpstreamServer, root: &Path) -> Result<McpClient, ToolError> {
+    match server.transport {
+        UpstreamTransport::Stdio => {
+            let command = server.command.as_deref().unwrap_or_default();
+            McpClient::connect_stdio(&server.name, command, &server.args, &server.env, root).await
+        }
+        UpstreamTransport::Http => {
+            let url = server.url.as_deref().unwrap_or_default();
+            McpClient::connect_http(&server.name, url).await
+        }
+    }
+}
+
+async fn enabled_servers(store: &ProjectStore) -> Vec<UpstreamServer> {
+    match store.upstream_server_store.read().await.list() {
+        Ok(servers) => servers.into_iter().filter(|s| s.enabled).collect(),
+        Err(e) => {
+            tracing::warn!("Failed to list upstream servers: {e}");
+            Vec::new()
+        }
+    }
+}
+
+async fn enabled_server(store: &ProjectStore, name: &str) -> Option<UpstreamServer> {
+    let store = store.upstream_server_store.read().await;
+    store.get_by_name(name).ok().flatten().filter(|s| s.enabled)
+}
+
+/// Namespaced URI of an upstream resource
+#[must_use]
+pub fn resource_uri(server: &str, uri: &str) -> String {
+    format!("{RESOURCE_SCHEME}{server}/{uri}")
+}
+
+/// Split a namespaced URI into server name and upstream URI
+#[must_use]
+pub fn parse_resource_uri(uri: &str) -> Option<(&str, &str)> {
+    let (server, upstream_uri) = uri.strip_prefix(RESOURCE_SCHEME)?.split_once('/')?;
+    (!server.is_empty() && !upstream_uri.is_empty()).then_some((server, upstream_uri))
+}
+
+/// A tool of an upstream server, called through its connection
+pub struct UpstreamTool {
+    name: String,
+    description: String,
+    server: String,
+    /// Name of the tool on the upstream server
+    remote_name: String,
+    input_schema: Value,
+    output_schema: Option<Value>,
+    annotations: ToolAnnotations,
+    client: Arc<McpClient>,
+}
+
+impl UpstreamTool {
+    /// Wrap a tool from an upstream `tools/list` result
+    fn new(server: &str, client: Arc<McpClient>, tool: &Value) -> Option<Self> {
+        let remote_name = tool.get("name")?.as_str()?;
+        let description = tool
+            .get("description")
+            .and_then(|d| d.as_str())
+            .unwrap_or_default();
+        Some(Self {
+            name: format!("{server}{TOOL_SEPARATOR}{remote_name}"),
+            description: format!("[{server}] {description}").trim_end().to_string(),
+            server: server.to_string(),
+            remote_name: remote_name.to_string(),
+            input_schema: tool
+                .get("inputSchema")
+                .cloned()
+                .unwrap_or_else(|| json!({ "type": "object" })),
+            output_schema: tool.get("outputSchema").cloned(),
+            annotations: tool
+                .get("annotations")
+                .and_then(|a| serde_json::from_value(a.clone()).ok())
+                .unwrap_or_default(),
+            client,
+        })
+    }
+}
+
+#[async_trait]
+impl Tool for UpstreamTool {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn input_schema(&self) -> Value {
+        self.input_schema.clone()
+    }
+
+    fn output_schema(&self) -> Option<Value> {
+        self.output_schema.clone()
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        self.annotations.clone()
+    }
+
+    async fn execute(&self, _args: Value) -> Result<ToolResult, ToolError> {
+        Err(ToolError::ProjectRequired(self.name.clone()))
+    }
+
+    async fn execute_with_context(&self, args: Value, ctx: &ToolContext) -> Result<ToolResult, ToolError> {
+        let result = self
+            .client
+            .call_tool(&self.remote_name, args, &ctx.cancel)
+            .await?;
+        tool_result(&self.server, &result)
+    }
+}
+
+/// Convert an upstream `tools/call` result: text content joined by lines,
+/// `structuredContent` as data, and `isError` as an error.
+fn tool_result(server: &str, result: &Value) -> Result<ToolResult, ToolError> {
+    let text = result
+        .get("content")
+        .and_then(|c| c.as_array())
+        .map(|items| {
+            items
+                .iter()
+                .map(|item| match item.get("type").and_then(|t| t.as_str()) {
+                    Some("text") => item.get("text").and_then(|t| t.as_str()).unwrap_or_default().to_string(),
+                    Some("resource") => item
+                        .get("resource")
+                        .and_then(|r| r.get("text").or_else(|| r.get("uri")))
+                        .and_then(|t| t.as_str())
+                        .unwrap_or_default()
+                        .to_string(),
+                    Some(kind) => format!(
+                        "[{} {}]",
+                        kind,
+                        item.get("mimeType").and_then(|m| m.as_str()).unwrap_or_default()
+                    ),
+                    None => String::new(),
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .unwrap_or_default();
+
+    if result
+        .get("isError")
+        .and_then(Value::as_bool)
+        .unwrap_or(false)
+    {
+        return Err(ToolError::Upstream {
+            server: server.to_string(),
+            message: text,
+        });
+    }
+    Ok(match result.get("structuredContent") {
+        Some(data) => ToolResult::success_with_data(text, data.clone()),
+        None => ToolResult::success(text),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app_state::AppState;
+    use crate::tool_dispatch::{self, ToolCall};
+    use crate::tools::{Caller, ProgressReporter};
+    use crate::upstream_servers::NewUpstreamServer;
+    use std::collections::BTreeMap;
+    use tempfile::TempDir;
+    use tokio::sync::RwLock;
+    use tokio_util::sync::CancellationToken;
+
+    /// A stdio MCP server with an `echo` tool and one resource
+    const STUB_SERVER: &str = r#"
+while IFS= read -r line; do
+  id=$(printf '%s\n' "$line" | sed -n 's/.*"id":\([0-9][0-9]*\).*/\1/p')
+  case "$line" in
+    *'"method":"initialize"'*)
+      printf '{"jsonrpc":"2.0","id":%s,"result":{"protocolVersion":"2025-06-18","capabilities":{"tools":{},"resources":{}},"serverInfo":{"name":"stub","version":"1"}}}\n' "$id" ;;
+    *'"method":"tools/list"'*)
+      echo tools/list >> "$0.log"
+      printf '{"jsonrpc":"2.0","id":%s,"result":{"tools":[{"name":"echo","description":"Echo text","inputSchema":{"type":"object","properties":{"text":{"type":"string"}}},"annotations":{"readOnlyHint":true}}]}}\n' "$id" ;;
+    *'"method":"tools/call"'*)
+      text=$(printf '%s\n' "$line" | sed -n 's/.*"text":"\([^"]*\)".*/\1/p')
+      if [ "$text" = "fail" ]; then
+        printf '{"jsonrpc":"2.0","id":%s,"result":{"content":[{"type":"text","text":"refused"}],"isError":true}}\n' "$id"
+      else
+        printf '{"jsonrpc":"2.0","id":%s,"result":{"content":[{"type":"text","text":"echo: %s"}],"structuredContent":{"echoed":"%s"}}}\n' "$id" "$text" "$text"
+      fi ;;
+    *'"method":"resources/list"'*)
+      printf '{"jsonrpc":"2.0","id":%s,"result":{"resources":[{"uri":"stub://greeting","name":"greeting","mimeType":"text/plain"}]}}\n' "$id" ;;
+    *'"method":"resources/read"'*)
+      printf '{"jsonrpc":"2.0","id":%s,"result":{"contents":[{"uri":"stub://greeting","text":"hello"}]}}\n' "$id" ;;
+    *)
+      if [ -n "$id" ]; then
+        printf '{"jsonrpc":"2.0","id":%s,"error":{"code":-32601,"message":"Method not found"}}\n' "$id"
+      fi ;;
+  esac
+done
+"#;
+
+    async fn state_with_stub(temp_dir: &TempDir) -> (Arc<RwLock<AppState>>, Arc<ProjectStore>) {
+        let registry_path = temp_dir.path().join("registry.db");
+        let state = AppState::new(registry_path.to_str().unwrap(), temp_dir.path())
+            .await
+            .unwrap();
+        let script = temp_dir.path().join("stub.sh");
+        std::fs::write(&script, STUB_SERVER).unwrap();
+
+        let store = state.get_project_store("default").await.unwrap();
+        store
+            .upstream_server_store
+            .read()
+            .await
+            .add(NewUpstreamServer {
+                name: "stub".to_string(),
+                transport: UpstreamTransport::Stdio,
+                command: Some("sh".to_string()),
+                args: vec![script.to_string_lossy().to_string()],
+                env: BTreeMap::default(),
+                url: None,
+            })
+            .unwrap();
+        (Arc::new(RwLock::new(state)), store)
+    }
+
+    fn call(tool_name: &str, arguments: Value) -> ToolCall {
+        ToolCall {
+            tool_name: tool_name.to_string(),
+            arguments,
+            project_id: "default".to_string(),
+            caller: Caller::App,
+            progress: ProgressReporter::default(),
+            cancel: CancellationToken::new(),
+        }
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[tokio::test]
+    async fn upstream_tools_are_namespaced_and_dispatched() {
+        let temp_dir = TempDir::new().unwrap();
+        let (state, store) = state_with_stub(&temp_dir).await;
+        let gateway = state.read().await.mcp_gateway.clone();
+
+        let tools = gateway.tools(&store).await;
+        assert_eq!(tools.len(), 1);
+        let definition = tools[0].definition();
+        assert_eq!(definition.name, "stub__echo");
+        assert_eq!(definition.description, "[stub] Echo text");
+        assert_eq!(definition.annotations.read_only_hint, Some(true));
+
+        let outcome = tool_dispatch::dispatch(&state, call("stub__echo", json!({ "text": "hi" }))).await;
+        let result = outcome.result.unwrap();
+        assert_eq!(result.content, "echo: hi");
+        assert_eq!(result.data.unwrap()["echoed"], "hi");
+
+        let failed = tool_dispatch::dispatch(&state, call("stub__echo", json!({ "text": "fail" }))).await;
+        assert_eq!(failed.result.unwrap_err(), "Upstream server 'stub': refused");
+
+        let missing = tool_dispatch::dispatch(&state, call("stub__nope", json!({}))).await;
+        assert_eq!(missing.result.unwrap_err(), "Tool not found: stub__nope");
+
+        let history = state.read().await.get_history().await;
+        assert_eq!(history.len(), 3);
+        assert!(history.iter().all(|event| event.tool_name.starts_with("stub__")));
+
+        {
+            let servers = store.upstream_server_store.read().await;
+            let server = servers.get_by_name("stub").unwrap().unwrap();
+            servers.set_enabled(&server.id, false).unwrap();
+            gateway.disconnect(&server.id).await;
+        }
+        assert!(gateway.tools(&store).await.is_empty());
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[tokio::test]
+    async fn tools_are_listed_once_per_connection_without_waiting_on_other_servers() {
+        let temp_dir = TempDir::new().unwrap();
+        let (state, store) = state_with_stub(&temp_dir).await;
+        let gateway = state.read().await.mcp_gateway.clone();
+        // A server that never answers `initialize`
+        store
+            .upstream_server_store
+            .read()
+            .await
+            .add(NewUpstreamServer {
+                name: "hung".to_string(),
+                transport: UpstreamTransport::Stdio,
+                command: Some("sh".to_string()),
+                args: vec!["-c".to_string(), "sleep 60".to_string()],
+                env: BTreeMap::default(),
+                url: None,
+            })
+            .unwrap();
+
+        let hung = tokio::spawn({
+            let (gateway, store) = (gateway.clone(), store.clone());
+            async move { gateway.tool(&store, "hung__anything").await.map(|_| ()) }
+        });
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+
+        let lookup = async {
+            for _ in 0..3 {
+                assert!(gateway.tool(&store, "stub__echo").await.unwrap().is_some());
+            }
+        };
+        tokio::time::timeout(std::time::Duration::from_secs(10), lookup)
+            .await
+            .expect("a hung server blocked another server's lookup");
+        hung.abort();
+
+        let log = std::fs::read_to_string(temp_dir.path().join("stub.sh.log")).unwrap();
+        assert_eq!(log.lines().count(), 1);
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[tokio::test]
+    async fn upstream_resources_are_namespaced() {
+        let temp_dir = TempDir::new().unwrap();
+        let (state, store) = state_with_stub(&temp_dir).await;
+        let gateway = state.read().await.mcp_gateway.clone();
+
+        let resources = gateway.resources(&store).await;
+        assert_eq!(resources[0]["uri"], "upstream://stub/stub://greeting");
+
+        let contents = gateway
+            .read_resource(&store, "upstream://stub/stub://greeting")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(contents[0]["text"], "hello");
+        assert_eq!(contents[0]["uri"], "upstream://stub/stub://greeting");
+
+        assert!(gateway
+            .read_resource(&store, "upstream://other/x")
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn parse_resource_uri_splits_at_the_server_name() {
+        assert_eq!(
+            parse_resource_uri("upstream://files/file:///tmp/a.txt"),
+            Some(("files", "file:///tmp/a.txt"))
+        );
+        assert_eq!(parse_resource_uri("upstream://files"), None);
+        assert_eq!(parse_resource_uri("file:///tmp/a.txt"), None);
+    }
+}
@@ -1,22 +1,66 @@
 //! MCP stdio proxy mode
 //!
 //! Forwards JSON-RPC over stdio to the running app's HTTP MCP endpoint.
-
+//! The `Mcp-Session-Id` issued on `initialize` is sent with later requests,
+//! and the session is ended when stdin closes.
+//!
+//! Requests after `initialize` are forwarded concurrently, so a client can
+//! send `notifications/cancelled` while a tool call is still running. Replies
+//! the server streams as SSE (tool calls with their progress notifications)
+//! are written to stdout message by message.
+//!
+//! The server doesn't have to be up. The port comes from `AIH_PORT`, the
+//! runtime discovery file, or the default. While the server can't be reached,
+//! requests wait for it (retrying with backoff, up to `AIH_CONNECT_TIMEOUT`
+//! seconds) and `initialize` is answered locally. With `AIH_AUTO_LAUNCH=1` the
+//! proxy starts a headless server (`serve --headless`) itself, and starts it
+//! again if it exits. When the server comes back (or forgets the
+//! session after a restart) the client's `initialize` is replayed to open a
+//! new session before the waiting requests are sent.
+
+use crate::discovery;
 use serde_json::Value;
 use std::error::Error;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::mpsc;
+use tokio::task::JoinSet;
+use tokio::time::Instant;
 
 const DEFAULT_PORT: u16 = 8787;
 
-fn mcp_url(port: u16) -> String {
-    // Check for project ID from Claude MCP environment variable
-    match resolve_project_id() {
-        Some(pid) => {
-            format!("http://127.0.0.1:{}/mcp/{}", port, pid)
-        }
-        _ => {
-            format!("http://127.0.0.1:{}/mcp", port)
-        }
+/// Header carrying the MCP session id
+const SESSION_HEADER: &str = "mcp-session-id";
+
+/// How long a request waits for the server before failing
+const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How long `initialize` waits for the server before it is answered locally
+const INITIALIZE_WAIT: Duration = Duration::from_secs(5);
+
+/// First and longest pause between connection attempts
+const INITIAL_BACKOFF: Duration = Duration::from_millis(250);
+const MAX_BACKOFF: Duration = Duration::from_secs(5);
+
+/// Health checks fail fast so a hung port doesn't stall reconnects
+const HEALTH_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Times a request is sent before giving up on a flapping server
+const MAX_ATTEMPTS: usize = 3;
+
+/// Request id of a replayed `initialize` (its reply isn't shown to the client)
+const REPLAY_ID: &str = "aiharness-proxy-initialize";
+
+/// Lines queued for stdout
+type Output = mpsc::UnboundedSender<String>;
+
+fn mcp_url(port: u16, project_id: Option<&str>) -> String {
+    match project_id {
+        Some(pid) => format!("http://127.0.0.1:{port}/mcp/{pid}"),
+        None => format!("http://127.0.0.1:{port}/mcp"),
     }
 }
 
@@ -24,20 +68,27 @@ fn health_url(port: u16) -> String {
     format!("http://127.0.0.1:{}/", port)
 }
 
-/// Run MCP stdio proxy mode, forwarding requests to the running HTTP server.
+/// Run MCP stdio proxy mode, forwarding requests to the HTTP server.
 pub async fn run_stdio_proxy() -> Result<(), Box<dyn Error>> {
-    let port = resolve_port();
-    let client = reqwest::Client::new();
-
-    ensure_server_available(&client, port).await?;
+    let upstream = Arc::new(Upstream::from_env());
+
+    let (out, mut out_rx) = mpsc::unbounded_channel::<String>();
+    let writer = tokio::spawn(async move {
+        let mut stdout = tokio::io::stdout();
+        while let Some(line) = out_rx.recv().await {
+            if let Err(e) = write_line(&mut stdout, &line).await {
+                eprintln!("Failed writing to stdout: {e}");
+                break;
+            }
+        }
+    });
 
-    let stdin = tokio::io::stdin();
-    let stdout = tokio::io::stdout();
-    let reader = BufReader::new(stdin);
+    let reader = BufReader::new(tokio::io::stdin());
     let mut lines = reader.lines();
-    let mut stdout = stdout;
+    let mut in_flight = JoinSet::new();
 
     while let Some(line) = lines.next_line().await? {
+        while in_flight.try_join_next().is_some() {}
         if line.trim().is_empty() {
             continue;
         }
@@ -45,35 +96,53 @@ pub async fn run_stdio_proxy() -> Result<(), Box<dyn Error>> {
         let request = match parse_json_rpc_line(&line) {
             Ok(value) => value,
             Err(error_json) => {
-                write_line(&mut stdout, &error_json).await?;
+                let _ = out.send(error_json);
                 continue;
             }
         };
 
-        let is_notification = is_notification(&request);
-        let response = forward_request(&client, port, &request).await;
-
-        if is_notification {
-            continue;
+        // initialize finishes first so later requests carry the session id
+        if is_initialize(&request) {
+            proxy_message(&upstream, &request, &out).await;
+        } else {
+            let (upstream, out) = (upstream.clone(), out.clone());
+            in_flight.spawn(async move {
+                proxy_message(&upstream, &request, &out).await;
+            });
         }
+    }
+    while in_flight.join_next().await.is_some() {}
 
-        let output = match response {
-            Ok(text) => text,
-            Err(error_json) => error_json,
-        };
+    upstream.end_session().await;
 
-        write_line(&mut stdout, &output).await?;
+    drop(out);
+    let _ = writer.await;
+    Ok(())
+}
+
+/// Forward one message and queue its reply (if it gets one) for stdout.
+async fn proxy_message(upstream: &Upstream, request: &Value, out: &Output) {
+    let is_notification = is_notification(request);
+    let response = upstream.forward(request, out).await;
+
+    if is_notification {
+        return;
     }
 
-    Ok(())
+    let output = match response {
+        Ok(text) => text,
+        Err(error_json) => error_json,
+    };
+    if output.trim().is_empty() {
+        return;
+    }
+
+    let _ = out.send(output);
 }
 
-/// Resolve the HTTP server port from env or default.
-fn resolve_port() -> u16 {
-    std::env::var("AIH_PORT")
-        .ok()
-        .and_then(|p| p.parse().ok())
-        .unwrap_or(DEFAULT_PORT)
+/// Resolve the HTTP server port from env, if set.
+fn resolve_port() -> Option<u16> {
+    std::env::var("AIH_PORT").ok().and_then(|p| p.parse().ok())
 }
 
 /// Resolve the project ID from env or return None.
@@ -81,19 +150,377 @@ fn resolve_project_id() -> Option<String> {
     std::env::var("AIH_PROJECT_ID").ok().filter(|s| !s.is_empty())
 }
 
-/// Validate that the HTTP server is reachable before proxying.
-async fn ensure_server_available(client: &reqwest::Client, port: u16) -> Result<(), Box<dyn Error>> {
-    let health = health_url(port);
-    if client.get(health).send().await.is_err() {
-        let msg = format!(
-            "AIHarness HTTP server not found on port {}. Start the app first.",
-            port
-        );
-        eprintln!("{}", msg);
-        return Err(msg.into());
+/// Resolve the client bearer token from env or return None.
+fn resolve_token() -> Option<String> {
+    std::env::var("AIH_TOKEN").ok().filter(|s| !s.is_empty())
+}
+
+/// Resolve how long requests wait for the server (`AIH_CONNECT_TIMEOUT`, seconds).
+fn resolve_connect_timeout() -> Duration {
+    std::env::var("AIH_CONNECT_TIMEOUT")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .map_or(DEFAULT_CONNECT_TIMEOUT, Duration::from_secs)
+}
+
+/// Whether the proxy may start a server when none is running (`AIH_AUTO_LAUNCH`).
+fn resolve_auto_launch() -> bool {
+    std::env::var("AIH_AUTO_LAUNCH").is_ok_and(|v| matches!(v.as_str(), "1" | "true" | "yes"))
+}
+
+/// Ports to try, in order: `AIH_PORT`, the discovery file's, then the default.
+fn candidate_ports(env_port: Option<u16>, discovered: Option<u16>) -> Vec<u16> {
+    let mut ports = Vec::new();
+    for port in [env_port, discovered, Some(DEFAULT_PORT)].into_iter().flatten() {
+        if !ports.contains(&port) {
+            ports.push(port);
+        }
     }
+    ports
+}
 
-    Ok(())
+/// Connection to the app's HTTP server, shared by in-flight requests
+struct Upstream {
+    client: reqwest::Client,
+    project_id: Option<String>,
+    token: Option<String>,
+    env_port: Option<u16>,
+    data_dir: Option<PathBuf>,
+    connect_timeout: Duration,
+    initialize_wait: Duration,
+    auto_launch: bool,
+    /// Port of the server while it is known to be reachable
+    port: Mutex<Option<u16>>,
+    /// Held while looking for the server, so waiting requests resume in order
+    reconnect: tokio::sync::Mutex<()>,
+    /// The headless server the proxy launched, if any
+    launched: Mutex<Option<std::process::Child>>,
+    /// Session id issued by the server
+    session: Mutex<Option<String>>,
+    /// The client's `initialize`, replayed to open a new session
+    initialize: tokio::sync::Mutex<Option<Value>>,
+    /// The server has no session for the client yet: `initialize` was answered
+    /// locally, or the connection or session was lost
+    resync: AtomicBool,
+}
+
+/// Why a request didn't get a reply from the server
+enum ForwardError {
+    /// The server couldn't be reached; the request wasn't delivered
+    Unreachable,
+    /// The server no longer knows the session; carries its reply
+    SessionExpired(String),
+    /// A JSON-RPC error payload for the client
+    Reply(String),
+}
+
+impl Upstream {
+    fn from_env() -> Self {
+        Self::new(
+            resolve_port(),
+            discovery::default_data_dir(),
+            resolve_project_id(),
+            resolve_token(),
+            resolve_connect_timeout(),
+            resolve_auto_launch(),
+        )
+    }
+
+    fn new(
+        env_port: Option<u16>,
+        data_dir: Option<PathBuf>,
+        project_id: Option<String>,
+        token: Option<String>,
+        connect_timeout: Duration,
+        auto_launch: bool,
+    ) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            project_id,
+            token,
+            env_port,
+            data_dir,
+            connect_timeout,
+            initialize_wait: INITIALIZE_WAIT.min(connect_timeout),
+            auto_launch,
+            port: Mutex::new(None),
+            reconnect: tokio::sync::Mutex::new(()),
+            launched: Mutex::new(None),
+            session: Mutex::new(None),
+            initialize: tokio::sync::Mutex::new(None),
+            resync: AtomicBool::new(false),
+        }
+    }
+
+    fn session(&self) -> Option<String> {
+        self.session
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    fn set_session(&self, session: Option<String>) {
+        *self.session.lock().unwrap_or_else(PoisonError::into_inner) = session;
+    }
+
+    fn current_port(&self) -> Option<u16> {
+        *self.port.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Forward a message, waiting for the server and reopening the session
+    /// as needed. `initialize` is answered locally if the server stays down.
+    async fn forward(&self, request: &Value, out: &Output) -> Result<String, String> {
+        let initialize = is_initialize(request);
+        if initialize {
+            *self.initialize.lock().await = Some(request.clone());
+        }
+        let wait = if initialize { self.initialize_wait } else { self.connect_timeout };
+
+        let mut last_reply = None;
+        for _ in 0..MAX_ATTEMPTS {
+            let port = match self.connect(wait).await {
+                Ok(port) => port,
+                Err(_) if initialize => {
+                    eprintln!("AIHarness HTTP server not reachable yet; answering initialize locally");
+                    self.resync.store(true, Ordering::SeqCst);
+                    return Ok(local_initialize(request));
+                }
+                Err(message) => return Err(error_json(request, -32603, &message)),
+            };
+
+            let result = if initialize {
+                self.resync.store(false, Ordering::SeqCst);
+                forward_request(self, port, request, out).await
+            } else {
+                match self.ensure_session(port).await {
+                    Ok(()) => forward_request(self, port, request, out).await,
+                    Err(e) => Err(e),
+                }
+            };
+
+            match result {
+                Ok(text) => return Ok(text),
+                Err(ForwardError::Unreachable) => self.lost(port),
+                Err(ForwardError::SessionExpired(reply)) => {
+                    self.set_session(None);
+                    self.resync.store(true, Ordering::SeqCst);
+                    last_reply = Some(reply);
+                }
+                Err(ForwardError::Reply(json)) => return Err(json),
+            }
+        }
+
+        Err(last_reply.unwrap_or_else(|| {
+            error_json(request, -32603, "AIHarness HTTP server keeps dropping the connection")
+        }))
+    }
+
+    /// Find a reachable server, retrying with backoff until `wait` runs out.
+    async fn connect(&self, wait: Duration) -> Result<u16, String> {
+        if let Some(port) = self.current_port() {
+            return Ok(port);
+        }
+        let _gate = self.reconnect.lock().await;
+        if let Some(port) = self.current_port() {
+            return Ok(port);
+        }
+
+        let deadline = Instant::now() + wait;
+        let mut backoff = INITIAL_BACKOFF;
+        loop {
+            let discovered = self
+                .data_dir
+                .as_deref()
+                .and_then(discovery::read)
+                .map(|info| info.port);
+            let ports = candidate_ports(self.env_port, discovered);
+            for &port in &ports {
+                if self.is_healthy(port).await {
+                    *self.port.lock().unwrap_or_else(PoisonError::into_inner) = Some(port);
+                    return Ok(port);
+                }
+            }
+
+            if self.auto_launch {
+                self.launch_unless_running();
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                let ports = ports.iter().map(u16::to_string).collect::<Vec<_>>().join(", ");
+                let hint = if self.auto_launch { "" } else { " (or set AIH_AUTO_LAUNCH=1)" };
+                return Err(format!(
+                    "AIHarness HTTP server not found on port {ports}. Start the app first{hint}."
+                ));
+            }
+            tokio::time::sleep(backoff.min(deadline - now)).await;
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+    }
+
+    /// Whether an AIHarness server (not just anything) answers on `port`.
+    async fn is_healthy(&self, port: u16) -> bool {
+        let response = self
+            .client
+            .get(health_url(port))
+            .timeout(HEALTH_TIMEOUT)
+            .send()
+            .await;
+        match response {
+            Ok(response) if response.status().is_success() => response
+                .text()
+                .await
+                .is_ok_and(|body| body == crate::http_server::HEALTH_MESSAGE),
+            _ => false,
+        }
+    }
+
+    /// Launch a headless server, unless the one launched before is still
+    /// starting up (or running but unreachable).
+    fn launch_unless_running(&self) {
+        let mut launched = self.launched.lock().unwrap_or_else(PoisonError::into_inner);
+        if !is_running(&mut launched) {
+            *launched = launch_server(self.env_port, self.data_dir.as_deref());
+        }
+    }
+
+    /// Forget a server that stopped answering; its session went with it.
+    fn lost(&self, port: u16) {
+        let mut current = self.port.lock().unwrap_or_else(PoisonError::into_inner);
+        if *current == Some(port) {
+            *current = None;
+            self.set_session(None);
+            self.resync.store(true, Ordering::SeqCst);
+            eprintln!("Lost connection to AIHarness HTTP server on port {port}; waiting for it");
+        }
+    }
+
+    /// Replay the client's `initialize` if the server has no session for it.
+    async fn ensure_session(&self, port: u16) -> Result<(), ForwardError> {
+        let initialize = self.initialize.lock().await;
+        if !self.resync.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+        let Some(request) = initialize.as_ref() else {
+            return Ok(());
+        };
+
+        let mut replay = request.clone();
+        replay["id"] = Value::from(REPLAY_ID);
+        // Replies to the replay aren't for the client
+        let (sink, _) = mpsc::unbounded_channel();
+        forward_request(self, port, &replay, &sink).await?;
+        let initialized = serde_json::json!({
+            "jsonrpc": "2.0",
+            "method": "notifications/initialized"
+        });
+        forward_request(self, port, &initialized, &sink).await?;
+        self.resync.store(false, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// End the MCP session on the server (best effort).
+    async fn end_session(&self) {
+        let (Some(port), Some(session_id)) = (self.current_port(), self.session()) else {
+            return;
+        };
+        let mut builder = self
+            .client
+            .delete(mcp_url(port, self.project_id.as_deref()))
+            .header(SESSION_HEADER, session_id);
+        if let Some(token) = &self.token {
+            builder = builder.bearer_auth(token);
+        }
+        if let Err(e) = builder.send().await {
+            eprintln!("Failed to end MCP session: {e}");
+        }
+    }
+}
+
+/// Whether a launched server is still running; an exited one is reaped.
+fn is_running(launched: &mut Option<std::process::Child>) -> bool {
+    let Some(child) = launched else {
+        return false;
+    };
+    match child.try_wait() {
+        Ok(None) => true,
+        Ok(Some(status)) => {
+            eprintln!("The headless AIHarness server exited ({status}); launching it again");
+            *launched = None;
+            false
+        }
+        Err(e) => {
+            eprintln!("Failed to check on the headless AIHarness server: {e}");
+            *launched = None;
+            false
+        }
+    }
+}
+
+/// Start a headless server in the background, on `AIH_PORT` (if set) and in
+/// the data directory the proxy reads the discovery file from.
+fn launch_server(
+    port: Option<u16>,
+    data_dir: Option<&std::path::Path>,
+) -> Option<std::process::Child> {
+    let exe = match std::env::current_exe() {
+        Ok(exe) => exe,
+        Err(e) => {
+            eprintln!("Failed to locate the AIHarness executable: {e}");
+            return None;
+        }
+    };
+    let mut command = std::process::Command::new(&exe);
+    command.args(["serve", "--headless"]);
+    if let Some(port) = port {
+        command.arg("--port").arg(port.to_string());
+    }
+    if let Some(data_dir) = data_dir {
+        command.arg("--data-dir").arg(data_dir);
+    }
+    // stdout is the MCP channel, so the server must not write to it
+    let spawned = command
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn();
+    match spawned {
+        Ok(child) => {
+            eprintln!("Launched a headless AIHarness server (pid {})", child.id());
+            Some(child)
+        }
+        Err(e) => {
+            eprintln!("Failed to launch {}: {}", exe.display(), e);
+            None
+        }
+    }
+}
+
+/// The reply to `initialize` while the server is down.
+fn local_initialize(request: &Value) -> String {
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "result": crate::http_server::initialize_result(request.get("params")),
+        "id": request.get("id").cloned().unwrap_or(Value::Null)
+    })
+    .to_string()
+}
+
+/// A JSON-RPC error payload answering `request`.
+fn error_json(request: &Value, code: i64, message: &str) -> String {
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "error": {
+            "code": code,
+            "message": message
+        },
+        "id": request.get("id").cloned().unwrap_or(Value::Null)
+    })
+    .to_string()
+}
+
+fn is_initialize(request: &Value) -> bool {
+    request.get("method").and_then(|m| m.as_str()) == Some("initialize")
 }
 
 /// Parse a JSON-RPC request line or return a JSON-RPC error payload.
@@ -111,47 +538,132 @@ fn parse_json_rpc_line(line: &str) -> Result<Value, String> {
     })
 }
 
-/// Determine whether a JSON-RPC request is a notification (no id).
+/// Determine whether a JSON-RPC message needs no reply: a notification (no id),
+/// or a batch made only of notifications.
 fn is_notification(request: &Value) -> bool {
-    request.get("id").is_none() || request.get("id") == Some(&Value::Null)
+    match request {
+        Value::Array(batch) => batch.iter().all(is_notification),
+        _ => request.get("id").is_none() || request.get("id") == Some(&Value::Null),
+    }
 }
 
 /// Forward a JSON-RPC request to the HTTP MCP endpoint.
+///
+/// Records the session id returned by `initialize` and sends it on later
+/// requests. Messages of an SSE reply are queued on `out` as they arrive, and
+/// an empty string is returned.
 async fn forward_request(
-    client: &reqwest::Client,
+    upstream: &Upstream,
     port: u16,
     request: &Value,
-) -> Result<String, String> {
-    let response = client
-        .post(mcp_url(port))
-        .json(request)
-        .send()
-        .await;
+    out: &Output,
+) -> Result<String, ForwardError> {
+    let mut builder = upstream
+        .client
+        .post(mcp_url(port, upstream.project_id.as_deref()))
+        .header(reqwest::header::ACCEPT, "application/json, text/event-stream")
+        .json(request);
+    if let Some(token) = &upstream.token {
+        builder = builder.bearer_auth(token);
+    }
+    let current = upstream.session();
+    if let Some(current) = &current {
+        builder = builder.header(SESSION_HEADER, current);
+    }
+    let response = builder.send().await;
+
+    if let Ok(resp) = &response {
+        if let Some(issued) = resp.headers().get(SESSION_HEADER).and_then(|v| v.to_str().ok()) {
+            upstream.set_session(Some(issued.to_string()));
+        }
+    }
 
     match response {
+        Ok(resp) if is_event_stream(&resp) => stream_events(resp, out)
+            .await
+            .map(|()| String::new())
+            .map_err(|e| {
+                ForwardError::Reply(error_json(
+                    request,
+                    -32603,
+                    &format!("Failed reading response: {e}"),
+                ))
+            }),
+        Ok(resp) if resp.status() == reqwest::StatusCode::UNAUTHORIZED => {
+            Err(ForwardError::Reply(error_json(
+                request,
+                -32001,
+                "Unauthorized: set AIH_TOKEN to a client token created in AIHarness",
+            )))
+        }
+        Ok(resp) if resp.status() == reqwest::StatusCode::NOT_FOUND && current.is_some() => {
+            let text = resp.text().await.unwrap_or_default();
+            Err(ForwardError::SessionExpired(text))
+        }
         Ok(resp) => resp.text().await.map_err(|e| {
-            serde_json::json!({
-                "jsonrpc": "2.0",
-                "error": {
-                    "code": -32603,
-                    "message": format!("Failed reading response: {}", e)
-                },
-                "id": null
-            })
-            .to_string()
+            ForwardError::Reply(error_json(
+                request,
+                -32603,
+                &format!("Failed reading response: {e}"),
+            ))
         }),
-        Err(e) => Err(
-            serde_json::json!({
-                "jsonrpc": "2.0",
-                "error": {
-                    "code": -32603,
-                    "message": format!("HTTP MCP proxy error: {}", e)
-                },
-                "id": null
-            })
-            .to_string(),
-        ),
+        Err(e) if e.is_connect() => Err(ForwardError::Unreachable),
+        Err(e) => Err(ForwardError::Reply(error_json(
+            request,
+            -32603,
+            &format!("HTTP MCP proxy error: {e}"),
+        ))),
+    }
+}
+
+/// Whether the server answered with an SSE stream
+fn is_event_stream(resp: &reqwest::Response) -> bool {
+    resp.headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("text/event-stream"))
+}
+
+/// Queue each message of an SSE reply on `out` as it arrives.
+async fn stream_events(mut resp: reqwest::Response, out: &Output) -> Result<(), reqwest::Error> {
+    let mut buffer = String::new();
+    while let Some(chunk) = resp.chunk().await? {
+        buffer.push_str(&String::from_utf8_lossy(&chunk));
+        for message in take_sse_messages(&mut buffer) {
+            let _ = out.send(message);
+        }
     }
+    buffer.push_str("\n\n");
+    for message in take_sse_messages(&mut buffer) {
+        let _ = out.send(message);
+    }
+    Ok(())
+}
+
+/// Remove the complete events from an SSE buffer, returning their data.
+///
+/// Events without data (keep-alive comments) are skipped; an incomplete
+/// trailing event stays in the buffer.
+pub(crate) fn take_sse_messages(buffer: &mut String) -> Vec<String> {
+    let normalized = buffer.replace("\r\n", "\n");
+    let Some(end) = normalized.rfind("\n\n") else {
+        *buffer = normalized;
+        return Vec::new();
+    };
+    let (complete, rest) = normalized.split_at(end + 2);
+    let messages = complete
+        .split("\n\n")
+        .filter_map(|event| {
+            let data = event
+                .lines()
+                .filter_map(|line| line.strip_prefix("data:"))
+                .map(|data| data.strip_prefix(' ').unwrap_or(data))
+                .collect::<Vec<_>>();
+            (!data.is_empty()).then(|| data.join("\n"))
+        })
+        .collect();
+    *buffer = rest.to_string();
+    messages
 }
 
 /// Write a single line response to stdout.
@@ -169,27 +681,35 @@ mod tests {
     #[test]
     fn resolve_port_defaults() {
         std::env::remove_var("AIH_PORT");
-        assert_eq!(resolve_port(), DEFAULT_PORT);
+        assert_eq!(resolve_port(), None);
+        assert_eq!(candidate_ports(resolve_port(), None), vec![DEFAULT_PORT]);
     }
 
     #[test]
     fn resolve_port_from_env() {
         std::env::set_var("AIH_PORT", "9001");
-        assert_eq!(resolve_port(), 9001);
+        assert_eq!(resolve_port(), Some(9001));
         std::env::remove_var("AIH_PORT");
     }
 
+    #[test]
+    fn candidate_ports_prefer_env_then_discovery() {
+        assert_eq!(candidate_ports(Some(9001), Some(9002)), vec![9001, 9002, DEFAULT_PORT]);
+        assert_eq!(candidate_ports(None, Some(9002)), vec![9002, DEFAULT_PORT]);
+        assert_eq!(candidate_ports(Some(DEFAULT_PORT), Some(DEFAULT_PORT)), vec![DEFAULT_PORT]);
+    }
+
     #[test]
     fn mcp_url_without_project_id() {
-        std::env::remove_var("AIH_PROJECT_ID");
-        assert_eq!(mcp_url(8787), "http://127.0.0.1:8787/mcp");
+        assert_eq!(mcp_url(8787, None), "http://127.0.0.1:8787/mcp");
     }
 
     #[test]
     fn mcp_url_with_project_id() {
-        std::env::set_var("AIH_PROJECT_ID", "my-project-123");
-        assert_eq!(mcp_url(8787), "http://127.0.0.1:8787/mcp/my-project-123");
-        std::env::remove_var("AIH_PROJECT_ID");
+        assert_eq!(
+            mcp_url(8787, Some("my-project-123")),
+            "http://127.0.0.1:8787/mcp/my-project-123"
+        );
     }
 
     #[test]
@@ -206,6 +726,16 @@ mod tests {
         std::env::remove_var("AIH_PROJECT_ID");
     }
 
+    #[test]
+    fn resolve_token_from_env() {
+        std::env::set_var("AIH_TOKEN", "aih_secret");
+        assert_eq!(resolve_token(), Some("aih_secret".to_string()));
+        std::env::set_var("AIH_TOKEN", "");
+        assert_eq!(resolve_token(), None);
+        std::env::remove_var("AIH_TOKEN");
+        assert_eq!(resolve_token(), None);
+    }
+
     #[test]
     fn parse_json_rpc_line_rejects_invalid() {
         let err = parse_json_rpc_line("{bad json").unwrap_err();
@@ -237,4 +767,139 @@ mod tests {
         let value = serde_json::json!({"jsonrpc":"2.0","method":"tools/list","id": 1});
         assert!(!is_notification(&value));
     }
+
+    #[test]
+    fn take_sse_messages_keeps_incomplete_events() {
+        let mut buffer = ": keep-alive\n\ndata: {\"a\":1}\r\n\r\ndata: {\"b\"".to_string();
+        assert_eq!(take_sse_messages(&mut buffer), vec![r#"{"a":1}"#.to_string()]);
+        assert_eq!(buffer, r#"data: {"b""#);
+
+        buffer.push_str(":2}\n\n");
+        assert_eq!(take_sse_messages(&mut buffer), vec![r#"{"b":2}"#.to_string()]);
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn is_notification_checks_every_batch_entry() {
+        let notifications = serde_json::json!([
+            {"jsonrpc":"2.0","method":"notifications/initialized"}
+        ]);
+        assert!(is_notification(&notifications));
+
+        let mixed = serde_json::json!([
+            {"jsonrpc":"2.0","method":"notifications/initialized"},
+            {"jsonrpc":"2.0","method":"ping","id": 1}
+        ]);
+        assert!(!is_notification(&mixed));
+    }
+
+    #[tokio::test]
+    async fn requests_wait_for_the_server_and_reopen_lost_sessions() {
+        use crate::AppState;
+        use tokio::sync::RwLock;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let registry_path = temp_dir.path().join("registry.db");
+        let state = AppState::new(registry_path.to_str().unwrap(), temp_dir.path())
+            .await
+            .unwrap();
+        let created = state.client_tokens.create("proxy").unwrap();
+        let sessions = state.mcp_sessions.clone();
+        let port = {
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            listener.local_addr().unwrap().port()
+        };
+        state.set_port(port).await;
+        let state = Arc::new(RwLock::new(state));
+
+        // The port is only known from the discovery file written on start
+        let upstream = Arc::new(Upstream {
+            initialize_wait: Duration::from_millis(300),
+            ..Upstream::new(
+                None,
+                Some(temp_dir.path().to_path_buf()),
+                None,
+                Some(created.token.clone()),
+                Duration::from_secs(20),
+                false,
+            )
+        });
+        let (out, _out_rx) = mpsc::unbounded_channel();
+
+        let initialize = serde_json::json!({
+            "jsonrpc": "2.0",
+            "method": "initialize",
+            "params": { "protocolVersion": "2025-03-26" },
+            "id": 1
+        });
+        let reply: Value =
+            serde_json::from_str(&upstream.forward(&initialize, &out).await.unwrap()).unwrap();
+        assert_eq!(reply["id"], 1);
+        assert_eq!(reply["result"]["protocolVersion"], "2025-03-26");
+        assert!(upstream.session().is_none());
+
+        let list = serde_json::json!({ "jsonrpc": "2.0", "method": "tools/list", "id": 2 });
+        let pending = tokio::spawn({
+            let (upstream, out, list) = (upstream.clone(), out.clone(), list.clone());
+            async move { upstream.forward(&list, &out).await }
+        });
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        assert!(!pending.is_finished());
+
+        let handle = crate::http_server::start_http_server(state.clone(), port)
+            .await
+            .unwrap();
+        state.read().await.set_server_handle(handle).await;
+
+        let reply: Value = serde_json::from_str(&pending.await.unwrap().unwrap()).unwrap();
+        assert_eq!(reply["id"], 2);
+        assert!(reply["result"]["tools"].as_array().is_some_and(|t| !t.is_empty()));
+        let first_session = upstream.session().expect("replayed initialize opens a session");
+
+        // The server forgets the session (as after a restart): it is reopened
+        assert!(sessions.remove(&first_session, Some(&created.client.id), None));
+        let reply: Value =
+            serde_json::from_str(&upstream.forward(&list, &out).await.unwrap()).unwrap();
+        assert_eq!(reply["id"], 2);
+        let second_session = upstream.session().unwrap();
+        assert_ne!(first_session, second_session);
+        assert!(sessions.get(&second_session, Some(&created.client.id), None).is_some());
+
+        state.read().await.stop_server().await;
+    }
+
+    #[tokio::test]
+    async fn other_servers_on_the_port_are_not_taken_for_the_app() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let app = axum::Router::new().route("/", axum::routing::get(|| async { "OK" }));
+        let server = tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let upstream = Upstream::new(Some(port), None, None, None, Duration::from_millis(300), false);
+        assert!(!upstream.is_healthy(port).await);
+        assert!(upstream.connect(Duration::from_millis(300)).await.is_err());
+        server.abort();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn exited_servers_are_launched_again() {
+        let mut launched = None;
+        assert!(!is_running(&mut launched));
+
+        launched = Some(std::process::Command::new("sleep").arg("30").spawn().unwrap());
+        assert!(is_running(&mut launched));
+        launched.as_mut().unwrap().kill().unwrap();
+        launched.as_mut().unwrap().wait().unwrap();
+        assert!(!is_running(&mut launched));
+        assert!(launched.is_none());
+
+        let mut exited = Some(std::process::Command::new("true").spawn().unwrap());
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while is_running(&mut exited) {
+            assert!(std::time::Instant::now() < deadline);
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        assert!(exited.is_none());
+    }
 }
@@ -0,0 +1,230 @@
+//! Project data exposed as MCP resources.
+//!
+//! Each project's todo list, context notes, next-session briefing and build
+//! commands are addressable as `aiharness://{project}/...` URIs; single todos
+//! and build commands are reachable through resource templates.
+
+use crate::{error::ContextError, projects::ProjectStore};
+use serde_json::json;
+
+/// URI scheme for project resources
+pub const SCHEME: &str = "aiharness://";
+
+const JSON_MIME: &str = "application/json";
+const MARKDOWN_MIME: &str = "text/markdown";
+
+/// A resource inside one project
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProjectResource {
+    Todos,
+    Todo(String),
+    Notes,
+    NextSession,
+    BuildCommands,
+    BuildCommand(String),
+}
+
+impl ProjectResource {
+    /// Path of the resource below the project (`todos`, `todos/{id}`, ...)
+    #[must_use]
+    pub fn path(&self) -> String {
+        match self {
+            Self::Todos => "todos".to_string(),
+            Self::Todo(id) => format!("todos/{id}"),
+            Self::Notes => "notes".to_string(),
+            Self::NextSession => "next-session".to_string(),
+            Self::BuildCommands => "build-commands".to_string(),
+            Self::BuildCommand(id) => format!("build-commands/{id}"),
+        }
+    }
+
+    /// Full `aiharness://` URI of the resource in `project_id`
+    #[must_use]
+    pub fn uri(&self, project_id: &str) -> String {
+        format!("{}{}/{}", SCHEME, project_id, self.path())
+    }
+
+    /// MIME type of the resource contents
+    #[must_use]
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            Self::Notes | Self::NextSession => MARKDOWN_MIME,
+            _ => JSON_MIME,
+        }
+    }
+}
+
+/// Split an `aiharness://{project}/{path}` URI into its project and resource
+#[must_use]
+pub fn parse_uri(uri: &str) -> Option<(String, ProjectResource)> {
+    let rest = uri.strip_prefix(SCHEME)?;
+    let (project_id, path) = rest.split_once('/')?;
+    if project_id.is_empty() {
+        return None;
+    }
+    let resource = match path.split('/').collect::<Vec<_>>().as_slice() {
+        ["todos"] => ProjectResource::Todos,
+        ["todos", id] if !id.is_empty() => ProjectResource::Todo((*id).to_string()),
+        ["notes"] => ProjectResource::Notes,
+        ["next-session"] => ProjectResource::NextSession,
+        ["build-commands"] => ProjectResource::BuildCommands,
+        ["build-commands", id] if !id.is_empty() => ProjectResource::BuildCommand((*id).to_string()),
+        _ => return None,
+    };
+    Some((project_id.to_string(), resource))
+}
+
+/// `resources/list` entries for a project's fixed resources
+#[must_use]
+pub fn list(project_id: &str) -> Vec<serde_json::Value> {
+    [
+        (ProjectResource::Todos, "Todo list", "Ordered todo list"),
+        (ProjectResource::Notes, "Context notes", "Project context notes"),
+        (
+            ProjectResource::NextSession,
+            "Next session briefing",
+            "Briefing left for the next session",
+        ),
+        (
+            ProjectResource::BuildCommands,
+            "Build commands",
+            "Saved build commands",
+        ),
+    ]
+    .into_iter()
+    .map(|(resource, name, description)| {
+        json!({
+            "uri": resource.uri(project_id),
+            "name": name,
+            "description": description,
+            "mimeType": resource.mime_type(),
+        })
+    })
+    .collect()
+}
+
+/// `resources/templates/list` entries
+#[must_use]
+pub fn templates() -> Vec<serde_json::Value> {
+    vec![
+        json!({
+            "uriTemplate": format!("{SCHEME}{{project}}/todos/{{id}}"),
+            "name": "Todo item",
+            "description": "A single todo item",
+            "mimeType": JSON_MIME,
+        }),
+        json!({
+            "uriTemplate": format!("{SCHEME}{{project}}/build-commands/{{id}}"),
+            "name": "Build command",
+            "description": "A single saved build command",
+            "mimeType": JSON_MIME,
+        }),
+    ]
+}
+
+/// Read a resource's contents as text
+///
+/// # Errors
+///
+/// Returns `ContextError::NotInContext` for unknown items, or a store error
+pub async fn read(store: &ProjectStore, resource: &ProjectResource) -> Result<String, ContextError> {
+    let text = match resource {
+        ProjectResource::Todos => {
+            let todos = store.todo_store.read().await.list().await?;
+            to_json(&todos)
+        }
+        ProjectResource::Todo(id) => {
+            let todos = store.todo_store.read().await.list().await?;
+            let todo = todos
+                .into_iter()
+                .find(|todo| &todo.id == id)
+                .ok_or_else(|| ContextError::NotInContext(id.clone()))?;
+            to_json(&todo)
+        }
+        ProjectResource::Notes => {
+            let notes = store.context_note_store.read().await.list().await?;
+            let mut text = "# Context notes\n\n".to_string();
+            for note in notes {
+                text.push_str("- ");
+                text.push_str(&note.content);
+                text.push('\n');
+            }
+            text
+        }
+        ProjectResource::NextSession => store
+            .next_session_store
+            .read()
+            .await
+            .get()
+            .await?
+            .map(|briefing| briefing.content)
+            .unwrap_or_default(),
+        ProjectResource::BuildCommands => {
+            let commands = store.build_command_store.read().await.list().await?;
+            to_json(&commands)
+        }
+        ProjectResource::BuildCommand(id) => {
+            let command = store
+                .build_command_store
+                .read()
+                .await
+                .get(id)
+                .await?
+                .ok_or_else(|| ContextError::NotInContext(id.clone()))?;
+            to_json(&command)
+        }
+    };
+    Ok(text)
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> String {
+    serde_json::to_string_pretty(value).unwrap_or_else(|_| "null".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::context::test_context;
+    use tempfile::TempDir;
+
+    #[test]
+    fn parse_uri_round_trips() {
+        for resource in [
+            ProjectResource::Todos,
+            ProjectResource::Todo("t1".to_string()),
+            ProjectResource::Notes,
+            ProjectResource::NextSession,
+            ProjectResource::BuildCommands,
+            ProjectResource::BuildCommand("b1".to_string()),
+        ] {
+            let uri = resource.uri("alpha");
+            assert_eq!(parse_uri(&uri), Some(("alpha".to_string(), resource)));
+        }
+    }
+
+    #[test]
+    fn parse_uri_rejects_unknown_paths() {
+        assert_eq!(parse_uri("aiharness://alpha/secrets"), None);
+        assert_eq!(parse_uri("aiharness://alpha/todos/"), None);
+        assert_eq!(parse_uri("aiharness:///todos"), None);
+        assert_eq!(parse_uri("file:///tmp/a"), None);
+    }
+
+    #[tokio::test]
+    async fn read_renders_notes_as_markdown_and_todos_as_json() {
+        let temp = TempDir::new().unwrap();
+        let ctx = test_context(temp.path()).await;
+        ctx.store.context_note_store.read().await.add("uses sqlite", None).await.unwrap();
+        let todo = ctx.store.todo_store.read().await.add("ship", None, None).await.unwrap();
+
+        let notes = read(&ctx.store, &ProjectResource::Notes).await.unwrap();
+        assert!(notes.contains("- uses sqlite"));
+
+        let item = read(&ctx.store, &ProjectResource::Todo(todo.id.clone())).await.unwrap();
+        let item: serde_json::Value = serde_json::from_str(&item).unwrap();
+        assert_eq!(item["title"], "ship");
+
+        let missing = read(&ctx.store, &ProjectResource::Todo("nope".to_string())).await;
+        assert!(missing.is_err());
+    }
+}
@@ -0,0 +1,422 @@
+//! Sessions for the MCP Streamable HTTP transport.
+//!
+//! A session starts with `initialize` (the server returns its id in the
+//! `Mcp-Session-Id` header), is bound to the client token that created it and
+//! ends on `DELETE`. Each session has a channel for server-initiated messages,
+//! delivered to the client over its `GET` SSE stream. Sessions with no request
+//! and no open stream for the idle timeout are ended by a background reaper,
+//! since clients often go away without a `DELETE`.
+//!
+//! Requests still being served are tracked by session (or, for stateless
+//! requests, by client) and JSON-RPC id so a `notifications/cancelled` from the
+//! same session can stop them. Request ids are only unique within a session, so
+//! two sessions of one client may use the same id.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError, Weak};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
+
+/// Header carrying the session id
+pub const SESSION_HEADER: &str = "mcp-session-id";
+
+/// Header carrying the negotiated protocol version on requests after `initialize`
+pub const PROTOCOL_VERSION_HEADER: &str = "mcp-protocol-version";
+
+/// Server-initiated messages buffered per session
+const MESSAGE_BUFFER: usize = 64;
+
+/// How long a session may go unused before it is ended
+pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+/// How often the reaper looks for idle sessions
+const REAP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// A live MCP session
+#[derive(Debug, Clone, Serialize)]
+pub struct McpSession {
+    pub id: String,
+    /// Client token id the session belongs to
+    pub client_id: Option<String>,
+    /// Project from the endpoint path, if any
+    pub project_id: Option<String>,
+    /// Protocol version negotiated on `initialize`
+    pub protocol_version: String,
+    pub created_at: DateTime<Utc>,
+}
+
+struct SessionEntry {
+    session: McpSession,
+    sender: broadcast::Sender<serde_json::Value>,
+    /// Last request or stream from the client
+    last_seen: Instant,
+}
+
+impl SessionEntry {
+    /// Whether the session was started by `client_id` on `project_id`'s endpoint
+    fn belongs_to(&self, client_id: Option<&str>, project_id: Option<&str>) -> bool {
+        self.session.client_id.as_deref() == client_id
+            && self.session.project_id.as_deref() == project_id
+    }
+
+    /// Whether the client is idle: no open stream and nothing seen for `timeout`
+    fn idle(&self, timeout: Duration) -> bool {
+        self.sender.receiver_count() == 0 && self.last_seen.elapsed() >= timeout
+    }
+}
+
+/// Live sessions (in memory; they do not survive a restart)
+pub struct McpSessionManager {
+    sessions: Mutex<HashMap<String, SessionEntry>>,
+    idle_timeout: Duration,
+    reaper_started: AtomicBool,
+}
+
+impl McpSessionManager {
+    #[must_use]
+    pub fn new(idle_timeout: Duration) -> Self {
+        Self {
+            sessions: Mutex::new(HashMap::new()),
+            idle_timeout,
+            reaper_started: AtomicBool::new(false),
+        }
+    }
+
+    /// Start a session for a client
+    pub fn create(
+        self: &Arc<Self>,
+        client_id: Option<&str>,
+        project_id: Option<&str>,
+        protocol_version: &str,
+    ) -> McpSession {
+        let session = McpSession {
+            id: uuid::Uuid::new_v4().simple().to_string(),
+            client_id: client_id.map(str::to_string),
+            project_id: project_id.map(str::to_string),
+            protocol_version: protocol_version.to_string(),
+            created_at: Utc::now(),
+        };
+        let (sender, _) = broadcast::channel(MESSAGE_BUFFER);
+        self.lock().insert(
+            session.id.clone(),
+            SessionEntry {
+                session: session.clone(),
+                sender,
+                last_seen: Instant::now(),
+            },
+        );
+        self.start_reaper();
+        session
+    }
+
+    /// Look up a session, only if it belongs to `client_id` and was started
+    /// on the same endpoint (`project_id` from the path), marking it used
+    pub fn get(
+        &self,
+        id: &str,
+        client_id: Option<&str>,
+        project_id: Option<&str>,
+    ) -> Option<McpSession> {
+        self.lock()
+            .get_mut(id)
+            .filter(|entry| entry.belongs_to(client_id, project_id))
+            .map(|entry| {
+                entry.last_seen = Instant::now();
+                entry.session.clone()
+            })
+    }
+
+    /// Receive the session's server-initiated messages
+    pub fn subscribe(
+        &self,
+        id: &str,
+        client_id: Option<&str>,
+        project_id: Option<&str>,
+    ) -> Option<broadcast::Receiver<serde_json::Value>> {
+        self.lock()
+            .get_mut(id)
+            .filter(|entry| entry.belongs_to(client_id, project_id))
+            .map(|entry| {
+                entry.last_seen = Instant::now();
+                entry.sender.subscribe()
+            })
+    }
+
+    /// Send a message to one session's stream
+    ///
+    /// Returns false if the session is unknown.
+    pub fn send(&self, id: &str, message: serde_json::Value) -> bool {
+        match self.lock().get(id) {
+            Some(entry) => {
+                // No open stream is fine; the message is simply dropped
+                let _ = entry.sender.send(message);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Send a message to every session's stream
+    pub fn broadcast(&self, message: &serde_json::Value) {
+        for entry in self.lock().values() {
+            let _ = entry.sender.send(message.clone());
+        }
+    }
+
+    /// End a session owned by `client_id` on `project_id`'s endpoint; open
+    /// streams close
+    ///
+    /// Returns false if there was no such session.
+    pub fn remove(&self, id: &str, client_id: Option<&str>, project_id: Option<&str>) -> bool {
+        let mut sessions = self.lock();
+        let owned = sessions
+            .get(id)
+            .is_some_and(|entry| entry.belongs_to(client_id, project_id));
+        owned && sessions.remove(id).is_some()
+    }
+
+    /// All live sessions
+    pub fn list(&self) -> Vec<McpSession> {
+        let mut sessions = self
+            .lock()
+            .values()
+            .map(|entry| entry.session.clone())
+            .collect::<Vec<_>>();
+        sessions.sort_by_key(|s| s.created_at);
+        sessions
+    }
+
+    /// End sessions unused for the idle timeout, returning their ids.
+    ///
+    /// A session with an open stream is in use. When it closes, the session
+    /// counts as seen then.
+    pub fn reap_idle(&self) -> Vec<String> {
+        let mut sessions = self.lock();
+        for entry in sessions.values_mut() {
+            if entry.sender.receiver_count() > 0 {
+                entry.last_seen = Instant::now();
+            }
+        }
+        let idle: Vec<_> = sessions
+            .iter()
+            .filter(|(_, entry)| entry.idle(self.idle_timeout))
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in &idle {
+            tracing::info!("Ending idle MCP session {id}");
+            sessions.remove(id);
+        }
+        idle
+    }
+
+    fn start_reaper(self: &Arc<Self>) {
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        if self.reaper_started.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let manager: Weak<Self> = Arc::downgrade(self);
+        runtime.spawn(async move {
+            loop {
+                tokio::time::sleep(REAP_INTERVAL).await;
+                let Some(manager) = manager.upgrade() else {
+                    break;
+                };
+                manager.reap_idle();
+            }
+        });
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, SessionEntry>> {
+        self.sessions.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Default for McpSessionManager {
+    fn default() -> Self {
+        Self::new(DEFAULT_IDLE_TIMEOUT)
+    }
+}
+
+/// Who a request id belongs to
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum RequestOwner {
+    Session(String),
+    /// Stateless request, scoped to the client token
+    Client(Option<String>),
+}
+
+impl RequestOwner {
+    fn new(session_id: Option<&str>, client_id: Option<&str>) -> Self {
+        match session_id {
+            Some(id) => Self::Session(id.to_string()),
+            None => Self::Client(client_id.map(str::to_string)),
+        }
+    }
+}
+
+type RequestKey = (RequestOwner, String);
+
+/// Requests being served, by session (or client) and request id
+#[derive(Default)]
+pub struct InFlightRequests {
+    requests: Mutex<HashMap<RequestKey, CancellationToken>>,
+}
+
+/// A tracked request; it stops being tracked when dropped
+pub struct InFlightRequest {
+    requests: Arc<InFlightRequests>,
+    key: RequestKey,
+    /// Cancelled by a matching `notifications/cancelled`
+    pub cancel: CancellationToken,
+}
+
+impl InFlightRequests {
+    /// Track a request so the client can cancel it
+    ///
+    /// `session_id` must already be checked to belong to `client_id`.
+    pub fn start(
+        self: &Arc<Self>,
+        session_id: Option<&str>,
+        client_id: Option<&str>,
+        request_id: &serde_json::Value,
+    ) -> InFlightRequest {
+        let key = (RequestOwner::new(session_id, client_id), request_id.to_string());
+        let cancel = CancellationToken::new();
+        self.lock().insert(key.clone(), cancel.clone());
+        InFlightRequest {
+            requests: self.clone(),
+            key,
+            cancel,
+        }
+    }
+
+    /// Cancel a request of a session (or, without one, of a client)
+    ///
+    /// Returns false if it is not (or no longer) running.
+    pub fn cancel(
+        &self,
+        session_id: Option<&str>,
+        client_id: Option<&str>,
+        request_id: &serde_json::Value,
+    ) -> bool {
+        let key = (RequestOwner::new(session_id, client_id), request_id.to_string());
+        match self.lock().get(&key) {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<RequestKey, CancellationToken>> {
+        self.requests.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Drop for InFlightRequest {
+    fn drop(&mut self) {
+        self.requests.lock().remove(&self.key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn sessions_are_bound_to_their_client_and_project() {
+        let manager = Arc::new(McpSessionManager::default());
+        let session = manager.create(Some("client-a"), Some("default"), "2025-03-26");
+        let project = Some("default");
+
+        assert!(manager.get(&session.id, Some("client-a"), project).is_some());
+        assert!(manager.get(&session.id, Some("client-b"), project).is_none());
+        assert!(manager.get(&session.id, Some("client-a"), Some("other")).is_none());
+        assert!(manager.get(&session.id, Some("client-a"), None).is_none());
+        assert!(manager.subscribe(&session.id, Some("client-a"), Some("other")).is_none());
+        assert!(!manager.remove(&session.id, Some("client-a"), Some("other")));
+        assert!(!manager.remove(&session.id, Some("client-b"), project));
+        assert!(manager.remove(&session.id, Some("client-a"), project));
+        assert!(manager.get(&session.id, Some("client-a"), project).is_none());
+    }
+
+    #[tokio::test]
+    async fn send_reaches_subscribers_until_removed() {
+        let manager = Arc::new(McpSessionManager::default());
+        let session = manager.create(Some("c"), None, "2025-03-26");
+        let mut rx = manager.subscribe(&session.id, Some("c"), None).unwrap();
+
+        assert!(manager.send(&session.id, json!({ "method": "ping" })));
+        assert_eq!(rx.recv().await.unwrap()["method"], "ping");
+
+        manager.remove(&session.id, Some("c"), None);
+        assert!(rx.recv().await.is_err());
+        assert!(!manager.send(&session.id, json!({})));
+    }
+
+    #[tokio::test]
+    async fn idle_sessions_are_reaped() {
+        let manager = Arc::new(McpSessionManager::new(Duration::from_millis(50)));
+        let idle = manager.create(Some("c"), None, "2025-03-26");
+        let used = manager.create(Some("c"), None, "2025-03-26");
+        let streaming = manager.create(Some("c"), None, "2025-03-26");
+        let rx = manager.subscribe(&streaming.id, Some("c"), None).unwrap();
+        assert!(manager.reap_idle().is_empty());
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert!(manager.get(&used.id, Some("c"), None).is_some());
+        assert_eq!(manager.reap_idle(), vec![idle.id.clone()]);
+        assert!(manager.get(&idle.id, Some("c"), None).is_none());
+
+        // Once its stream closes, the streaming session is idle from then on
+        drop(rx);
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        let mut reaped = manager.reap_idle();
+        reaped.sort();
+        let mut expected = vec![used.id, streaming.id];
+        expected.sort();
+        assert_eq!(reaped, expected);
+        assert!(manager.list().is_empty());
+    }
+
+    #[test]
+    fn in_flight_requests_are_cancelled_only_by_their_client() {
+        let requests = Arc::new(InFlightRequests::default());
+        let request = requests.start(None, Some("client-a"), &json!(7));
+
+        assert!(!requests.cancel(None, Some("client-b"), &json!(7)));
+        assert!(!requests.cancel(None, Some("client-a"), &json!("7")));
+        assert!(!request.cancel.is_cancelled());
+
+        assert!(requests.cancel(None, Some("client-a"), &json!(7)));
+        assert!(request.cancel.is_cancelled());
+
+        drop(request);
+        assert!(!requests.cancel(None, Some("client-a"), &json!(7)));
+    }
+
+    #[test]
+    fn sessions_of_one_client_keep_their_request_ids_apart() {
+        let requests = Arc::new(InFlightRequests::default());
+        let first = requests.start(Some("session-1"), Some("client"), &json!(1));
+        let second = requests.start(Some("session-2"), Some("client"), &json!(1));
+
+        assert!(requests.cancel(Some("session-1"), Some("client"), &json!(1)));
+        assert!(first.cancel.is_cancelled());
+        assert!(!second.cancel.is_cancelled());
+
+        // Untracking one session's request leaves the other's in place
+        drop(first);
+        assert!(!requests.cancel(None, Some("client"), &json!(1)));
+        assert!(requests.cancel(Some("session-2"), Some("client"), &json!(1)));
+        assert!(second.cancel.is_cancelled());
+    }
+}
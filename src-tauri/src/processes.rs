@@ -0,0 +1,725 @@
+//! Background processes
+//!
+//! Long-running commands an agent starts and keeps working alongside: dev
+//! servers, `cargo watch`, test watchers. Each project's [`ProcessManager`]
+//! (on its `ProjectStore`) runs them through the platform shell, keeps the
+//! latest lines of their output in a ring buffer and broadcasts a
+//! [`ProcessEvent`] whenever one starts, exits or is stopped.
+//!
+//! On Unix every process leads its own process group, so stopping it also
+//! stops whatever it spawned (e.g. the node server behind `npm run dev`).
+
+use crate::tools::shell::{own_process_group, shell_command, signal_group, Signal};
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::sync::{broadcast, watch};
+use tokio_util::sync::CancellationToken;
+
+/// Output lines kept per process
+pub const MAX_LOG_LINES: usize = 5000;
+
+/// Processes (running or finished) kept per project
+pub const MAX_PROCESSES_PER_PROJECT: usize = 16;
+
+/// How long a process gets to exit after SIGTERM before it is killed
+pub const DEFAULT_STOP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Longer lines are cut to this many characters
+const MAX_LINE_CHARS: usize = 4096;
+
+/// What to run
+#[derive(Debug, Clone)]
+pub struct ProcessSpec {
+    /// Name to refer to the process by (default: the command)
+    pub name: Option<String>,
+    pub command: String,
+    pub cwd: PathBuf,
+    pub env: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProcessStatus {
+    Running,
+    /// Asked to stop, not exited yet
+    Stopping,
+    /// Exited on its own
+    Exited,
+    /// Exited after being stopped
+    Stopped,
+}
+
+impl ProcessStatus {
+    #[must_use]
+    pub fn is_active(self) -> bool {
+        matches!(self, Self::Running | Self::Stopping)
+    }
+}
+
+/// A background process and its current state
+#[derive(Debug, Clone, Serialize)]
+pub struct ProcessInfo {
+    pub id: String,
+    pub name: String,
+    pub command: String,
+    pub cwd: String,
+    pub status: ProcessStatus,
+    pub pid: Option<u32>,
+    /// `None` while running, or if the process was killed by a signal
+    pub exit_code: Option<i32>,
+    pub started_at: DateTime<Utc>,
+    pub exited_at: Option<DateTime<Utc>>,
+    /// Times the process was restarted
+    pub restarts: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogStream {
+    Stdout,
+    Stderr,
+}
+
+/// A line of process output
+#[derive(Debug, Clone, Serialize)]
+pub struct LogLine {
+    /// Increases by one per line over the process's lifetime (restarts included)
+    pub seq: u64,
+    pub stream: LogStream,
+    pub text: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Which log lines to return
+#[derive(Debug, Clone)]
+pub struct LogQuery {
+    /// Only lines after this sequence number
+    pub since: Option<u64>,
+    /// At most this many of the newest matching lines
+    pub tail: usize,
+    pub grep: Option<Regex>,
+    pub stream: Option<LogStream>,
+}
+
+/// Log lines matching a [`LogQuery`]
+#[derive(Debug, Clone, Serialize)]
+pub struct LogPage {
+    pub lines: Vec<LogLine>,
+    /// Sequence number of the newest line logged; pass as `since` to get
+    /// only what comes after
+    pub cursor: u64,
+    /// Matching lines left out by `tail`
+    pub omitted: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProcessEventKind {
+    Started,
+    Exited,
+    Stopped,
+    Removed,
+}
+
+/// A process changed state (for the UI)
+#[derive(Debug, Clone, Serialize)]
+pub struct ProcessEvent {
+    pub project_id: String,
+    pub kind: ProcessEventKind,
+    pub process: ProcessInfo,
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+struct RunState {
+    status: ProcessStatus,
+    pid: Option<u32>,
+    exit_code: Option<i32>,
+    started_at: DateTime<Utc>,
+    exited_at: Option<DateTime<Utc>>,
+    restarts: u32,
+}
+
+struct LogBuffer {
+    lines: VecDeque<LogLine>,
+    next_seq: u64,
+}
+
+struct ManagedProcess {
+    id: String,
+    name: String,
+    command: String,
+    cwd: PathBuf,
+    env: BTreeMap<String, String>,
+    state: watch::Sender<RunState>,
+    logs: Mutex<LogBuffer>,
+    /// Cancelled to kill the current run outright
+    kill: Mutex<CancellationToken>,
+}
+
+impl ManagedProcess {
+    fn info(&self) -> ProcessInfo {
+        let state = self.state.borrow();
+        ProcessInfo {
+            id: self.id.clone(),
+            name: self.name.clone(),
+            command: self.command.clone(),
+            cwd: self.cwd.to_string_lossy().to_string(),
+            status: state.status,
+            pid: state.pid,
+            exit_code: state.exit_code,
+            started_at: state.started_at,
+            exited_at: state.exited_at,
+            restarts: state.restarts,
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        self.state.borrow().status.is_active()
+    }
+
+    fn logs(&self) -> MutexGuard<'_, LogBuffer> {
+        self.logs.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn log(&self, stream: LogStream, mut text: String) {
+        if text.len() > MAX_LINE_CHARS {
+            let mut end = MAX_LINE_CHARS;
+            while !text.is_char_boundary(end) {
+                end -= 1;
+            }
+            text.truncate(end);
+        }
+        let mut logs = self.logs();
+        let seq = logs.next_seq;
+        logs.next_seq += 1;
+        logs.lines.push_back(LogLine {
+            seq,
+            stream,
+            text,
+            timestamp: Utc::now(),
+        });
+        if logs.lines.len() > MAX_LOG_LINES {
+            logs.lines.pop_front();
+        }
+    }
+}
+
+/// A project's background processes
+pub struct ProcessManager {
+    project_id: String,
+    processes: Mutex<Vec<Arc<ManagedProcess>>>,
+    sender: broadcast::Sender<ProcessEvent>,
+}
+
+impl ProcessManager {
+    #[must_use]
+    pub fn new(project_id: &str) -> Self {
+        let (sender, _) = broadcast::channel(100);
+        Self {
+            project_id: project_id.to_string(),
+            processes: Mutex::new(Vec::new()),
+            sender,
+        }
+    }
+
+    /// Subscribe to process state changes
+    pub fn subscribe(&self) -> broadcast::Receiver<ProcessEvent> {
+        self.sender.subscribe()
+    }
+
+    /// Start a command in the background.
+    ///
+    /// A finished process with the same name is replaced; the oldest finished
+    /// process is dropped when the project is at its limit.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a process with this name is running, the project
+    /// has too many processes running, or the command can't be started
+    pub fn start(&self, spec: ProcessSpec) -> Result<ProcessInfo, String> {
+        let name = spec
+            .name
+            .unwrap_or_else(|| spec.command.trim().chars().take(60).collect());
+        let process = Arc::new(ManagedProcess {
+            id: uuid::Uuid::new_v4().to_string(),
+            name,
+            command: spec.command,
+            cwd: spec.cwd,
+            env: spec.env,
+            state: watch::Sender::new(RunState {
+                status: ProcessStatus::Running,
+                pid: None,
+                exit_code: None,
+                started_at: Utc::now(),
+                exited_at: None,
+                restarts: 0,
+            }),
+            logs: Mutex::new(LogBuffer {
+                lines: VecDeque::new(),
+                next_seq: 1,
+            }),
+            kill: Mutex::new(CancellationToken::new()),
+        });
+
+        let removed = {
+            let mut processes = self.lock();
+            if processes
+                .iter()
+                .any(|p| p.name == process.name && p.is_active())
+            {
+                return Err(format!(
+                    "A process named '{}' is already running; stop or restart it",
+                    process.name
+                ));
+            }
+            let mut removed: Vec<_> = Vec::new();
+            if let Some(index) = processes.iter().position(|p| p.name == process.name) {
+                removed.push(processes.remove(index));
+            }
+            if processes.len() >= MAX_PROCESSES_PER_PROJECT {
+                let index = processes
+                    .iter()
+                    .position(|p| !p.is_active())
+                    .ok_or_else(|| {
+                        format!(
+                            "{} processes are already running; stop one first",
+                            processes.len()
+                        )
+                    })?;
+                removed.push(processes.remove(index));
+            }
+            self.launch(&process)?;
+            processes.push(process.clone());
+            removed
+        };
+
+        for old in &removed {
+            self.emit(ProcessEventKind::Removed, old);
+        }
+        self.emit(ProcessEventKind::Started, &process);
+        Ok(process.info())
+    }
+
+    /// Every process, running or finished, oldest first
+    #[must_use]
+    pub fn list(&self) -> Vec<ProcessInfo> {
+        self.lock().iter().map(|p| p.info()).collect()
+    }
+
+    /// A process by id or name
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no process matches
+    pub fn get(&self, id: &str) -> Result<ProcessInfo, String> {
+        Ok(self.find(id)?.info())
+    }
+
+    /// A process's buffered output.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no process matches
+    pub fn logs(&self, id: &str, query: &LogQuery) -> Result<LogPage, String> {
+        let process = self.find(id)?;
+        let logs = process.logs();
+        let mut lines: Vec<_> = logs
+            .lines
+            .iter()
+            .filter(|line| query.since.map_or(true, |since| line.seq > since))
+            .filter(|line| query.stream.map_or(true, |stream| line.stream == stream))
+            .filter(|line| query.grep.as_ref().map_or(true, |re| re.is_match(&line.text)))
+            .cloned()
+            .collect();
+        let omitted = lines.len().saturating_sub(query.tail);
+        lines.drain(..omitted);
+        Ok(LogPage {
+            lines,
+            cursor: logs.next_seq - 1,
+            omitted,
+        })
+    }
+
+    /// Stop a process: SIGTERM (on Unix), then a kill after `timeout`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no process matches
+    pub async fn stop(&self, id: &str, timeout: Duration) -> Result<ProcessInfo, String> {
+        let process = self.find(id)?;
+        halt(&process, timeout).await;
+        Ok(process.info())
+    }
+
+    /// Stop a process if it is running, then start its command again.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no process matches or the command can't be started
+    pub async fn restart(&self, id: &str, timeout: Duration) -> Result<ProcessInfo, String> {
+        let process = self.find(id)?;
+        halt(&process, timeout).await;
+        // Someone else may have restarted it while it was stopping
+        if !process.is_active() {
+            process.state.send_modify(|state| state.restarts += 1);
+            self.launch(&process)?;
+            self.emit(ProcessEventKind::Started, &process);
+        }
+        Ok(process.info())
+    }
+
+    /// Stop every running process (when the app shuts down).
+    pub async fn stop_all(&self, timeout: Duration) {
+        let running: Vec<_> = self.lock().iter().filter(|p| p.is_active()).cloned().collect();
+        futures::future::join_all(running.iter().map(|p| halt(p, timeout))).await;
+    }
+
+    /// Spawn `process`'s command and the task that collects its output and
+    /// exit status.
+    fn launch(&self, process: &Arc<ManagedProcess>) -> Result<(), String> {
+        let mut cmd = shell_command(&process.command);
+        cmd.current_dir(&process.cwd)
+            .envs(&process.env)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        own_process_group(&mut cmd);
+
+        let mut child = cmd
+            .spawn()
+            .map_err(|e| format!("Failed to start '{}': {}", process.command, e))?;
+        let pid = child.id();
+        let kill = CancellationToken::new();
+        *process.kill.lock().unwrap_or_else(PoisonError::into_inner) = kill.clone();
+        process.state.send_modify(|state| {
+            state.status = ProcessStatus::Running;
+            state.pid = pid;
+            state.exit_code = None;
+            state.started_at = Utc::now();
+            state.exited_at = None;
+        });
+
+        let readers = [
+            child
+                .stdout
+                .take()
+                .map(|out| tokio::spawn(read_lines(out, LogStream::Stdout, process.clone()))),
+            child
+                .stderr
+                .take()
+                .map(|err| tokio::spawn(read_lines(err, LogStream::Stderr, process.clone()))),
+        ];
+        let process = process.clone();
+        let project_id = self.project_id.clone();
+        let sender = self.sender.clone();
+        tokio::spawn(async move {
+            let status = tokio::select! {
+                status = child.wait() => status.ok(),
+                () = kill.cancelled() => {
+                    signal_group(pid, Signal::Kill);
+                    let _ = child.kill().await;
+                    child.wait().await.ok()
+                }
+            };
+            // Output still in the pipes (unless something else holds them open)
+            for reader in readers.into_iter().flatten() {
+                let _ = tokio::time::timeout(Duration::from_secs(1), reader).await;
+            }
+
+            let mut kind = ProcessEventKind::Exited;
+            process.state.send_modify(|state| {
+                if state.status == ProcessStatus::Stopping {
+                    state.status = ProcessStatus::Stopped;
+                    kind = ProcessEventKind::Stopped;
+                } else {
+                    state.status = ProcessStatus::Exited;
+                }
+                state.exit_code = status.and_then(|s| s.code());
+                state.exited_at = Some(Utc::now());
+            });
+            let _ = sender.send(ProcessEvent {
+                project_id,
+                kind,
+                process: process.info(),
+                timestamp: Utc::now(),
+            });
+        });
+        Ok(())
+    }
+
+    fn find(&self, id: &str) -> Result<Arc<ManagedProcess>, String> {
+        let processes = self.lock();
+        processes
+            .iter()
+            .find(|p| p.id == id)
+            .or_else(|| processes.iter().rev().find(|p| p.name == id))
+            .cloned()
+            .ok_or_else(|| format!("No process '{}' in project {}", id, self.project_id))
+    }
+
+    fn emit(&self, kind: ProcessEventKind, process: &ManagedProcess) {
+        let _ = self.sender.send(ProcessEvent {
+            project_id: self.project_id.clone(),
+            kind,
+            process: process.info(),
+            timestamp: Utc::now(),
+        });
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<Arc<ManagedProcess>>> {
+        self.processes.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Ask a running process to stop and wait for it to exit, killing it if it
+/// is still running after `timeout`.
+async fn halt(process: &ManagedProcess, timeout: Duration) {
+    let mut state = process.state.subscribe();
+    let mut stopping = false;
+    process.state.send_if_modified(|state| {
+        stopping = state.status == ProcessStatus::Running;
+        if stopping {
+            state.status = ProcessStatus::Stopping;
+        }
+        stopping
+    });
+    if !process.is_active() {
+        return;
+    }
+
+    // (If it was already stopping, someone else sent the signal)
+    if stopping {
+        let pid = process.state.borrow().pid;
+        if cfg!(unix) && pid.is_some() {
+            signal_group(pid, Signal::Terminate);
+        } else {
+            process.kill.lock().unwrap_or_else(PoisonError::into_inner).cancel();
+        }
+    }
+    let exited = state.wait_for(|state| !state.status.is_active());
+    if tokio::time::timeout(timeout, exited).await.is_err() {
+        process.kill.lock().unwrap_or_else(PoisonError::into_inner).cancel();
+        let _ = state.wait_for(|state| !state.status.is_active()).await;
+    }
+}
+
+/// Append a process's output to its log, line by line.
+///
+/// Lines end at `\n`, `\r\n` or a lone `\r` (progress output redraws a line
+/// with `\r`). A line reaching `MAX_LINE_CHARS` bytes is logged as it stands
+/// and the rest continues on the next, so output without line breaks is never
+/// held in memory.
+async fn read_lines(mut stream: impl AsyncRead + Unpin, kind: LogStream, process: Arc<ManagedProcess>) {
+    let mut chunk = vec![0u8; 8192];
+    let mut line = Vec::with_capacity(MAX_LINE_CHARS);
+    let mut after_cr = false;
+    // The line was just split for length, so a line end now adds no line
+    let mut just_split = false;
+    loop {
+        let read = match stream.read(&mut chunk).await {
+            Ok(0) | Err(_) => break,
+            Ok(read) => read,
+        };
+        for &byte in &chunk[..read] {
+            let ends_line = byte == b'\n' || byte == b'\r';
+            let crlf = byte == b'\n' && after_cr;
+            after_cr = byte == b'\r';
+            if ends_line {
+                let already_logged = crlf || (just_split && line.is_empty());
+                if !already_logged {
+                    process.log(kind, take_line(&mut line));
+                }
+                just_split = false;
+                continue;
+            }
+            line.push(byte);
+            just_split = line.len() >= MAX_LINE_CHARS;
+            if just_split {
+                process.log(kind, take_line(&mut line));
+            }
+        }
+    }
+    if !line.is_empty() {
+        process.log(kind, String::from_utf8_lossy(&line).into_owned());
+    }
+}
+
+/// Take the buffered line as text, keeping back a UTF-8 character cut off at
+/// its end for the next line.
+fn take_line(line: &mut Vec<u8>) -> String {
+    let complete = match std::str::from_utf8(line) {
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        _ => line.len(),
+    };
+    let rest = line.split_off(complete);
+    let text = String::from_utf8_lossy(line).into_owned();
+    *line = rest;
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn spec(temp_dir: &TempDir, name: &str, command: &str) -> ProcessSpec {
+        ProcessSpec {
+            name: Some(name.to_string()),
+            command: command.to_string(),
+            cwd: temp_dir.path().to_path_buf(),
+            env: BTreeMap::from([("GREETING".to_string(), "hello".to_string())]),
+        }
+    }
+
+    async fn wait_for_exit(manager: &ProcessManager, id: &str) -> ProcessInfo {
+        for _ in 0..100 {
+            let info = manager.get(id).unwrap();
+            if !info.status.is_active() {
+                return info;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        panic!("process {id} did not exit");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn processes_log_output_and_report_exit() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = ProcessManager::new("p1");
+        let mut events = manager.subscribe();
+
+        let info = manager
+            .start(spec(
+                &temp_dir,
+                "greet",
+                "echo \"$GREETING\"; echo warn >&2; echo done; exit 4",
+            ))
+            .unwrap();
+        assert_eq!(info.status, ProcessStatus::Running);
+        let info = wait_for_exit(&manager, "greet").await;
+        assert_eq!(info.status, ProcessStatus::Exited);
+        assert_eq!(info.exit_code, Some(4));
+        assert_eq!(events.recv().await.unwrap().kind, ProcessEventKind::Started);
+        assert_eq!(events.recv().await.unwrap().kind, ProcessEventKind::Exited);
+
+        let all = LogQuery {
+            since: None,
+            tail: 100,
+            grep: None,
+            stream: None,
+        };
+        let page = manager.logs(&info.id, &all).unwrap();
+        let mut texts: Vec<_> = page.lines.iter().map(|l| l.text.as_str()).collect();
+        texts.sort_unstable();
+        assert_eq!(texts, vec!["done", "hello", "warn"]);
+        assert_eq!(page.cursor, 3);
+
+        let stderr = LogQuery {
+            stream: Some(LogStream::Stderr),
+            ..all.clone()
+        };
+        assert_eq!(manager.logs("greet", &stderr).unwrap().lines[0].text, "warn");
+        let grep = LogQuery {
+            grep: Some(Regex::new("^d").unwrap()),
+            ..all.clone()
+        };
+        assert_eq!(manager.logs("greet", &grep).unwrap().lines.len(), 1);
+        let newer = LogQuery {
+            since: Some(3),
+            ..all
+        };
+        assert!(manager.logs("greet", &newer).unwrap().lines.is_empty());
+
+        // Restarting runs the command again, keeping the log
+        let info = manager.restart("greet", DEFAULT_STOP_TIMEOUT).await.unwrap();
+        assert_eq!(info.restarts, 1);
+        wait_for_exit(&manager, "greet").await;
+        assert_eq!(manager.logs("greet", &newer).unwrap().lines.len(), 3);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn long_and_carriage_return_lines_are_split() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = ProcessManager::new("p1");
+        manager
+            .start(spec(
+                &temp_dir,
+                "progress",
+                "printf 'step 1\\rstep 2\\r\\ndone\\n'; head -c 9000 /dev/zero | tr '\\0' x; echo",
+            ))
+            .unwrap();
+        let info = wait_for_exit(&manager, "progress").await;
+
+        let all = LogQuery {
+            since: None,
+            tail: 100,
+            grep: None,
+            stream: None,
+        };
+        let lines = manager.logs(&info.id, &all).unwrap().lines;
+        let texts: Vec<_> = lines.iter().map(|l| l.text.as_str()).collect();
+        assert_eq!(texts[..3], ["step 1", "step 2", "done"]);
+        let lengths: Vec<_> = texts[3..].iter().map(|t| t.len()).collect();
+        assert_eq!(lengths, vec![MAX_LINE_CHARS, MAX_LINE_CHARS, 9000 - 2 * MAX_LINE_CHARS]);
+    }
+
+    #[test]
+    fn take_line_keeps_a_split_character_for_the_next_line() {
+        let mut line = "ab\u{e9}".as_bytes()[..3].to_vec();
+        assert_eq!(take_line(&mut line), "ab");
+        line.extend_from_slice(&"\u{e9}".as_bytes()[1..]);
+        assert_eq!(take_line(&mut line), "\u{e9}");
+        assert!(line.is_empty());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn stop_terminates_the_process_group() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = ProcessManager::new("p1");
+
+        manager
+            .start(spec(&temp_dir, "server", "sleep 30 & echo $! > child.pid; wait"))
+            .unwrap();
+        assert!(manager.start(spec(&temp_dir, "server", "true")).is_err());
+        let pid_file = temp_dir.path().join("child.pid");
+        for _ in 0..100 {
+            if std::fs::read_to_string(&pid_file).is_ok_and(|s| s.ends_with('\n')) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let child: i32 = std::fs::read_to_string(&pid_file).unwrap().trim().parse().unwrap();
+
+        let info = manager.stop("server", DEFAULT_STOP_TIMEOUT).await.unwrap();
+        assert_eq!(info.status, ProcessStatus::Stopped);
+        // Gone, or a zombie waiting for init to reap it
+        let alive = || {
+            std::fs::read_to_string(format!("/proc/{child}/stat"))
+                .is_ok_and(|stat| !stat.contains(") Z"))
+        };
+        for _ in 0..50 {
+            if !alive() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert!(!alive());
+
+        // Stopping again is a no-op; a finished process's name can be reused
+        manager.stop("server", DEFAULT_STOP_TIMEOUT).await.unwrap();
+        manager.start(spec(&temp_dir, "server", "true")).unwrap();
+        assert_eq!(manager.list().len(), 1);
+    }
+}
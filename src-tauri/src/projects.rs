@@ -1,13 +1,13 @@
 //! Project registry and per-project storage.
 
-use crate::{build_commands::BuildCommandStore, context::ContextStore, context_notes::ContextNoteStore, error::ContextError, next_session::NextSessionBriefingStore, todos::TodoStore};
+use crate::{allowed_roots::AllowedRootStore, build_commands::BuildCommandStore, context::ContextStore, context_notes::ContextNoteStore, error::ContextError, next_session::NextSessionBriefingStore, processes::{ProcessEvent, ProcessManager}, prompts::PromptStore, todos::TodoStore, tool_history::ToolCallHistoryStore, upstream_servers::UpstreamServerStore};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{broadcast, RwLock};
 
 /// Project metadata stored in the registry.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -34,6 +34,12 @@ impl ProjectRegistry {
         Ok(registry)
     }
 
+    /// Path of the registry database
+    #[must_use]
+    pub fn db_path(&self) -> &str {
+        &self.db_path
+    }
+
     fn get_db(&self) -> Result<rusqlite::Connection, ContextError> {
         Ok(rusqlite::Connection::open(&self.db_path)?)
     }
@@ -165,6 +171,12 @@ pub struct ProjectStore {
     pub next_session_store: Arc<RwLock<NextSessionBriefingStore>>,
     pub build_command_store: Arc<RwLock<BuildCommandStore>>,
     pub todo_store: Arc<RwLock<TodoStore>>,
+    pub allowed_root_store: Arc<RwLock<AllowedRootStore>>,
+    pub tool_call_store: Arc<RwLock<ToolCallHistoryStore>>,
+    pub prompt_store: Arc<RwLock<PromptStore>>,
+    pub upstream_server_store: Arc<RwLock<UpstreamServerStore>>,
+    /// Background processes started in the project
+    pub processes: Arc<ProcessManager>,
 }
 
 impl ProjectStore {
@@ -176,6 +188,10 @@ impl ProjectStore {
         let next_session_store = NextSessionBriefingStore::new(&info.db_path).await?;
         let build_command_store = BuildCommandStore::new(&info.db_path).await?;
         let todo_store = TodoStore::new(&info.db_path).await?;
+        let allowed_root_store = AllowedRootStore::new(&info.db_path)?;
+        let tool_call_store = ToolCallHistoryStore::new(&info.db_path)?;
+        let prompt_store = PromptStore::new(&info.db_path)?;
+        let upstream_server_store = UpstreamServerStore::new(&info.db_path)?;
         
         let store = Self {
             info: info.clone(),
@@ -184,6 +200,11 @@ impl ProjectStore {
             next_session_store: Arc::new(RwLock::new(next_session_store)),
             build_command_store: Arc::new(RwLock::new(build_command_store)),
             todo_store: Arc::new(RwLock::new(todo_store)),
+            allowed_root_store: Arc::new(RwLock::new(allowed_root_store)),
+            tool_call_store: Arc::new(RwLock::new(tool_call_store)),
+            prompt_store: Arc::new(RwLock::new(prompt_store)),
+            upstream_server_store: Arc::new(RwLock::new(upstream_server_store)),
+            processes: Arc::new(ProcessManager::new(&info.id)),
         };
         
         tracing::info!(
@@ -196,14 +217,19 @@ impl ProjectStore {
 }
 
 /// Cache project stores in memory.
+///
+/// Process events from every cached store are forwarded to one channel.
 pub struct ProjectStoreCache {
     stores: RwLock<HashMap<String, Arc<ProjectStore>>>,
+    process_events: broadcast::Sender<ProcessEvent>,
 }
 
 impl ProjectStoreCache {
     pub fn new() -> Self {
+        let (process_events, _) = broadcast::channel(100);
         Self {
             stores: RwLock::new(HashMap::new()),
+            process_events,
         }
     }
 
@@ -211,12 +237,36 @@ impl ProjectStoreCache {
         self.stores.read().await.get(project_id).cloned()
     }
 
+    /// Every cached store
+    pub async fn all(&self) -> Vec<Arc<ProjectStore>> {
+        self.stores.read().await.values().cloned().collect()
+    }
+
     pub async fn insert(&self, store: Arc<ProjectStore>) {
+        let mut events = store.processes.subscribe();
+        let sender = self.process_events.clone();
+        tokio::spawn(async move {
+            loop {
+                match events.recv().await {
+                    Ok(event) => {
+                        let _ = sender.send(event);
+                    }
+                    Err(broadcast::error::RecvError::Lagged(_)) => {}
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+
         self.stores
             .write()
             .await
             .insert(store.info.id.clone(), store);
     }
+
+    /// Subscribe to process events from every project
+    pub fn subscribe_processes(&self) -> broadcast::Receiver<ProcessEvent> {
+        self.process_events.subscribe()
+    }
 }
 
 fn ensure_project_db_path(root: &Path) -> Result<String, ContextError> {
@@ -0,0 +1,627 @@
+//! Prompt library exposed to MCP clients as prompts.
+//!
+//! Prompts live in each project's DB; the same table in the registry DB holds
+//! a global library shared by every project (a project prompt shadows a global
+//! one with the same name). A prompt's template is plain text with
+//! placeholders:
+//!
+//! - `{{name}}` - value of the argument `name`
+//! - `{{notes}}` - the project's context notes, one `- ` line each
+//! - `{{next_session}}` - the next session briefing
+//! - `{{file:path}}` - contents of a file (relative to the project root, or a
+//!   context file), read as `read_file` would be: subject to the caller's tool
+//!   policies and the same size limit
+
+use crate::{
+    app_state::AppState,
+    error::ContextError,
+    error::ToolError,
+    projects::ProjectStore,
+    tool_dispatch,
+    tools::{file::MAX_FILE_SIZE, ToolContext},
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+/// Type an argument value must parse as (MCP passes every value as a string)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PromptArgumentType {
+    #[default]
+    String,
+    Number,
+    Boolean,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PromptArgument {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub required: bool,
+    #[serde(default, rename = "type")]
+    pub kind: PromptArgumentType,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Prompt {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub arguments: Vec<PromptArgument>,
+    pub template: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Input for creating or replacing a prompt
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct NewPrompt {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub arguments: Vec<PromptArgument>,
+    pub template: String,
+}
+
+pub struct PromptStore {
+    db_path: String,
+}
+
+impl PromptStore {
+    /// Open the store, creating its table if needed
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database can't be opened
+    pub fn new(db_path: &str) -> Result<Self, ContextError> {
+        let store = Self {
+            db_path: db_path.to_string(),
+        };
+        store.init_schema()?;
+        Ok(store)
+    }
+
+    fn get_db(&self) -> Result<rusqlite::Connection, ContextError> {
+        Ok(rusqlite::Connection::open(&self.db_path)?)
+    }
+
+    fn init_schema(&self) -> Result<(), ContextError> {
+        let db = self.get_db()?;
+        db.execute(
+            "CREATE TABLE IF NOT EXISTS prompts (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL UNIQUE,
+                description TEXT,
+                arguments TEXT NOT NULL,
+                template TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+        Ok(())
+    }
+
+    /// All prompts, sorted by name
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database query fails
+    pub fn list(&self) -> Result<Vec<Prompt>, ContextError> {
+        self.query("ORDER BY name ASC", None)
+    }
+
+    /// The prompt called `name`, if any
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database query fails
+    pub fn get_by_name(&self, name: &str) -> Result<Option<Prompt>, ContextError> {
+        Ok(self.query("WHERE name = ?1", Some(name))?.into_iter().next())
+    }
+
+    fn query(&self, filter: &str, param: Option<&str>) -> Result<Vec<Prompt>, ContextError> {
+        let db = self.get_db()?;
+        let mut stmt = db.prepare(&format!(
+            "SELECT id, name, description, arguments, template, created_at, updated_at
+             FROM prompts {filter}"
+        ))?;
+
+        let map_row = |row: &rusqlite::Row<'_>| {
+            Ok(Prompt {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                description: row.get(2)?,
+                arguments: serde_json::from_str(&row.get::<_, String>(3)?).unwrap_or_default(),
+                template: row.get(4)?,
+                created_at: row
+                    .get::<_, String>(5)?
+                    .parse()
+                    .unwrap_or_else(|_| Utc::now()),
+                updated_at: row
+                    .get::<_, String>(6)?
+                    .parse()
+                    .unwrap_or_else(|_| Utc::now()),
+            })
+        };
+        let rows = match param {
+            Some(param) => stmt.query_map([param], map_row)?,
+            None => stmt.query_map([], map_row)?,
+        };
+
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| ContextError::Database(e.to_string()))
+    }
+
+    /// Add a prompt after validating its name, arguments and template.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the prompt is invalid, its name is taken, or it can't
+    /// be saved
+    pub fn add(&self, prompt: NewPrompt) -> Result<Prompt, ContextError> {
+        validate(&prompt)?;
+        if self.get_by_name(&prompt.name)?.is_some() {
+            return Err(ContextError::Config(format!("Prompt already exists: {}", prompt.name)));
+        }
+
+        let id = uuid::Uuid::new_v4().to_string();
+        let now = Utc::now();
+        let db = self.get_db()?;
+        db.execute(
+            "INSERT INTO prompts (id, name, description, arguments, template, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            rusqlite::params![
+                id,
+                prompt.name,
+                prompt.description,
+                arguments_json(&prompt.arguments),
+                prompt.template,
+                now.to_rfc3339(),
+                now.to_rfc3339()
+            ],
+        )?;
+
+        Ok(Prompt {
+            id,
+            name: prompt.name,
+            description: prompt.description,
+            arguments: prompt.arguments,
+            template: prompt.template,
+            created_at: now,
+            updated_at: now,
+        })
+    }
+
+    /// Replace a prompt's name, description, arguments and template.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the prompt is invalid, its name is taken, or no prompt
+    /// has this id
+    pub fn update(&self, id: &str, prompt: &NewPrompt) -> Result<(), ContextError> {
+        validate(prompt)?;
+        if let Some(existing) = self.get_by_name(&prompt.name)? {
+            if existing.id != id {
+                return Err(ContextError::Config(format!("Prompt already exists: {}", prompt.name)));
+            }
+        }
+
+        let db = self.get_db()?;
+        let rows = db.execute(
+            "UPDATE prompts SET name = ?1, description = ?2, arguments = ?3, template = ?4, updated_at = ?5
+             WHERE id = ?6",
+            rusqlite::params![
+                prompt.name,
+                prompt.description,
+                arguments_json(&prompt.arguments),
+                prompt.template,
+                Utc::now().to_rfc3339(),
+                id
+            ],
+        )?;
+        if rows == 0 {
+            return Err(ContextError::NotInContext(id.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Delete a prompt
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no prompt has this id
+    pub fn remove(&self, id: &str) -> Result<(), ContextError> {
+        let db = self.get_db()?;
+        let rows = db.execute("DELETE FROM prompts WHERE id = ?1", [id])?;
+        if rows == 0 {
+            return Err(ContextError::NotInContext(id.to_string()));
+        }
+        Ok(())
+    }
+}
+
+fn arguments_json(arguments: &[PromptArgument]) -> String {
+    serde_json::to_string(arguments).unwrap_or_else(|_| "[]".to_string())
+}
+
+/// Prompts available in a project: its own, then global ones it doesn't shadow
+///
+/// # Errors
+///
+/// Returns an error if either store can't be read
+pub async fn library(store: &ProjectStore, global: &PromptStore) -> Result<Vec<Prompt>, ContextError> {
+    let mut prompts = store.prompt_store.read().await.list()?;
+    for prompt in global.list()? {
+        if !prompts.iter().any(|p| p.name == prompt.name) {
+            prompts.push(prompt);
+        }
+    }
+    prompts.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(prompts)
+}
+
+/// Look a prompt up by name, project library first
+///
+/// # Errors
+///
+/// Returns an error if either store can't be read
+pub async fn find(
+    store: &ProjectStore,
+    global: &PromptStore,
+    name: &str,
+) -> Result<Option<Prompt>, ContextError> {
+    match store.prompt_store.read().await.get_by_name(name)? {
+        Some(prompt) => Ok(Some(prompt)),
+        None => global.get_by_name(name),
+    }
+}
+
+/// A `{{...}}` placeholder in a template
+#[derive(Debug, PartialEq, Eq)]
+enum Placeholder<'a> {
+    Argument(&'a str),
+    Notes,
+    NextSession,
+    File(&'a str),
+}
+
+fn parse_placeholder(inner: &str) -> Placeholder<'_> {
+    let inner = inner.trim();
+    match inner {
+        "notes" => Placeholder::Notes,
+        "next_session" => Placeholder::NextSession,
+        _ => match inner.strip_prefix("file:") {
+            Some(path) => Placeholder::File(path.trim()),
+            None => Placeholder::Argument(inner),
+        },
+    }
+}
+
+/// Split a template into literal text and placeholders
+fn segments(template: &str) -> Vec<Result<&str, Placeholder<'_>>> {
+    let mut segments = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        segments.push(Ok(&rest[..start]));
+        segments.push(Err(parse_placeholder(&rest[start + 2..start + 2 + len])));
+        rest = &rest[start + 2 + len + 2..];
+    }
+    segments.push(Ok(rest));
+    segments
+}
+
+fn validate(prompt: &NewPrompt) -> Result<(), ContextError> {
+    let is_identifier = |name: &str| {
+        !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    };
+    if !is_identifier(&prompt.name) {
+        return Err(ContextError::Config(format!("Invalid prompt name: '{}'", prompt.name)));
+    }
+    for (i, argument) in prompt.arguments.iter().enumerate() {
+        if !is_identifier(&argument.name) || matches!(argument.name.as_str(), "notes" | "next_session") {
+            return Err(ContextError::Config(format!("Invalid argument name: '{}'", argument.name)));
+        }
+        if prompt.arguments[..i].iter().any(|a| a.name == argument.name) {
+            return Err(ContextError::Config(format!("Duplicate argument: {}", argument.name)));
+        }
+    }
+    for segment in segments(&prompt.template) {
+        match segment {
+            Err(Placeholder::Argument(name))
+                if !prompt.arguments.iter().any(|a| a.name == name) =>
+            {
+                return Err(ContextError::Config(format!(
+                    "Unknown placeholder: {{{{{name}}}}}"
+                )));
+            }
+            Err(Placeholder::File("")) => {
+                return Err(ContextError::Config("File placeholder needs a path".to_string()));
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Check argument values against a prompt's declared arguments
+fn check_arguments<S: BuildHasher>(
+    prompt: &Prompt,
+    values: &HashMap<String, String, S>,
+) -> Result<(), ToolError> {
+    for argument in &prompt.arguments {
+        let Some(value) = values.get(&argument.name) else {
+            if argument.required {
+                return Err(ToolError::InvalidArguments(format!(
+                    "Missing '{}' argument",
+                    argument.name
+                )));
+            }
+            continue;
+        };
+        let valid = match argument.kind {
+            PromptArgumentType::String => true,
+            PromptArgumentType::Number => value.trim().parse::<f64>().is_ok(),
+            PromptArgumentType::Boolean => matches!(value.trim(), "true" | "false"),
+        };
+        if !valid {
+            return Err(ToolError::InvalidArguments(format!(
+                "Argument '{}' must be a {}",
+                argument.name,
+                match argument.kind {
+                    PromptArgumentType::Number => "number",
+                    _ => "boolean",
+                }
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Render a prompt's template for a project.
+///
+/// Missing optional arguments render as empty text. Files must be context
+/// files of the project or inside its sandbox, and readable under the
+/// caller's tool policies.
+///
+/// # Errors
+///
+/// Returns `ToolError::InvalidArguments` for missing or mistyped arguments,
+/// or the error from reading an included file
+pub async fn render<S: BuildHasher>(
+    state: &Arc<RwLock<AppState>>,
+    prompt: &Prompt,
+    values: &HashMap<String, String, S>,
+    ctx: &ToolContext,
+) -> Result<String, ToolError> {
+    check_arguments(prompt, values)?;
+
+    let mut text = String::new();
+    for segment in segments(&prompt.template) {
+        match segment {
+            Ok(literal) => text.push_str(literal),
+            Err(Placeholder::Argument(name)) => {
+                text.push_str(values.get(name).map(String::as_str).unwrap_or_default());
+            }
+            Err(Placeholder::Notes) => {
+                let notes = ctx.store.context_note_store.read().await.list().await?;
+                let lines = notes
+                    .iter()
+                    .map(|note| format!("- {}", note.content))
+                    .collect::<Vec<_>>();
+                text.push_str(&lines.join("\n"));
+            }
+            Err(Placeholder::NextSession) => {
+                let briefing = ctx.store.next_session_store.read().await.get().await?;
+                text.push_str(&briefing.map(|b| b.content).unwrap_or_default());
+            }
+            Err(Placeholder::File(path)) => text.push_str(&read_file(state, path, ctx).await?),
+        }
+    }
+    Ok(text)
+}
+
+async fn read_file(state: &Arc<RwLock<AppState>>, path: &str, ctx: &ToolContext) -> Result<String, ToolError> {
+    tool_dispatch::enforce_tool_policy(state, ctx, "read_file", &json!({ "path": path })).await?;
+    let is_context_file = ctx.store.context_store.read().await.contains(path).await?;
+    let resolved = if is_context_file {
+        std::path::PathBuf::from(path)
+    } else {
+        ctx.sandbox.resolve(path)?
+    };
+    let size = tokio::fs::metadata(&resolved).await?.len();
+    if size > MAX_FILE_SIZE {
+        return Err(ToolError::FileTooLarge {
+            path: path.to_string(),
+            size,
+            max_size: MAX_FILE_SIZE,
+        });
+    }
+    Ok(tokio::fs::read_to_string(&resolved).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tool_policies::{NewToolPolicy, ToolPolicyRules};
+    use crate::tools::{context::test_context, Caller};
+    use tempfile::TempDir;
+
+    async fn create_state(temp: &TempDir) -> Arc<RwLock<AppState>> {
+        let registry_path = temp.path().join("registry.db");
+        let state = AppState::new(registry_path.to_str().unwrap(), temp.path())
+            .await
+            .unwrap();
+        Arc::new(RwLock::new(state))
+    }
+
+    fn review_prompt() -> NewPrompt {
+        NewPrompt {
+            name: "review".to_string(),
+            description: Some("Review a change".to_string()),
+            arguments: vec![
+                PromptArgument {
+                    name: "focus".to_string(),
+                    description: None,
+                    required: true,
+                    kind: PromptArgumentType::String,
+                },
+                PromptArgument {
+                    name: "depth".to_string(),
+                    description: None,
+                    required: false,
+                    kind: PromptArgumentType::Number,
+                },
+            ],
+            template: "Review {{focus}} (depth {{ depth }}).\nNotes:\n{{notes}}\n---\n{{file:README.md}}"
+                .to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn add_validates_names_and_placeholders() {
+        let temp = TempDir::new().unwrap();
+        let store = PromptStore::new(temp.path().join("p.db").to_str().unwrap()).unwrap();
+
+        store.add(review_prompt()).unwrap();
+        let duplicate = store.add(review_prompt());
+        assert!(matches!(duplicate, Err(ContextError::Config(_))));
+
+        let unknown = store.add(NewPrompt {
+            name: "plan".to_string(),
+            template: "Plan {{goal}}".to_string(),
+            ..NewPrompt::default()
+        });
+        assert!(matches!(unknown, Err(ContextError::Config(_))));
+        assert_eq!(store.list().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn render_fills_arguments_notes_and_files() {
+        let temp = TempDir::new().unwrap();
+        let state = create_state(&temp).await;
+        let ctx = test_context(temp.path()).await;
+        std::fs::write(temp.path().join("README.md"), "readme body").unwrap();
+        ctx.store
+            .context_note_store
+            .read()
+            .await
+            .add("uses sqlite", None)
+            .await
+            .unwrap();
+        let prompt = ctx
+            .store
+            .prompt_store
+            .read()
+            .await
+            .add(review_prompt())
+            .unwrap();
+
+        let values = HashMap::from([("focus".to_string(), "the parser".to_string())]);
+        let text = render(&state, &prompt, &values, &ctx).await.unwrap();
+        assert_eq!(
+            text,
+            "Review the parser (depth ).\nNotes:\n- uses sqlite\n---\nreadme body"
+        );
+
+        let missing = render(&state, &prompt, &HashMap::new(), &ctx).await;
+        assert!(matches!(missing, Err(ToolError::InvalidArguments(_))));
+        let mistyped = HashMap::from([
+            ("focus".to_string(), "x".to_string()),
+            ("depth".to_string(), "deep".to_string()),
+        ]);
+        let mistyped = render(&state, &prompt, &mistyped, &ctx).await;
+        assert!(matches!(mistyped, Err(ToolError::InvalidArguments(_))));
+    }
+
+    #[tokio::test]
+    async fn included_files_follow_read_policies_and_size_limit() {
+        let temp = TempDir::new().unwrap();
+        let state = create_state(&temp).await;
+        let mut ctx = test_context(temp.path()).await;
+        ctx.caller = Caller::Client {
+            id: "c1".to_string(),
+            name: "test".to_string(),
+        };
+        std::fs::write(temp.path().join(".env"), "SECRET=1").unwrap();
+        let too_big = "x".repeat(usize::try_from(MAX_FILE_SIZE).unwrap() + 1);
+        std::fs::write(temp.path().join("big.txt"), too_big).unwrap();
+        state
+            .read()
+            .await
+            .tool_policies
+            .add(NewToolPolicy {
+                client_id: None,
+                project_id: None,
+                rules: ToolPolicyRules {
+                    deny_paths: vec![".env".to_string()],
+                    ..ToolPolicyRules::default()
+                },
+            })
+            .unwrap();
+        let include = |path: &str| Prompt {
+            id: "p".to_string(),
+            name: "include".to_string(),
+            description: None,
+            arguments: Vec::new(),
+            template: format!("{{{{file:{path}}}}}"),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+
+        let denied = render(&state, &include(".env"), &HashMap::new(), &ctx).await;
+        assert!(matches!(denied, Err(ToolError::PolicyDenied(_))));
+        let too_large = render(&state, &include("big.txt"), &HashMap::new(), &ctx).await;
+        assert!(matches!(too_large, Err(ToolError::FileTooLarge { .. })));
+
+        // The app itself is not subject to policies
+        ctx.caller = Caller::App;
+        let text = render(&state, &include(".env"), &HashMap::new(), &ctx).await.unwrap();
+        assert_eq!(text, "SECRET=1");
+    }
+
+    #[tokio::test]
+    async fn project_prompts_shadow_global_ones() {
+        let temp = TempDir::new().unwrap();
+        let ctx = test_context(temp.path()).await;
+        let global = PromptStore::new(temp.path().join("registry.db").to_str().unwrap()).unwrap();
+        global.add(review_prompt()).unwrap();
+        global
+            .add(NewPrompt {
+                name: "plan".to_string(),
+                template: "Plan the next step".to_string(),
+                ..NewPrompt::default()
+            })
+            .unwrap();
+        ctx.store
+            .prompt_store
+            .read()
+            .await
+            .add(NewPrompt {
+                name: "review".to_string(),
+                template: "Project review".to_string(),
+                ..NewPrompt::default()
+            })
+            .unwrap();
+
+        let prompts = library(&ctx.store, &global).await.unwrap();
+        let names = prompts.iter().map(|p| p.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["plan", "review"]);
+        let review = find(&ctx.store, &global, "review").await.unwrap().unwrap();
+        assert_eq!(review.template, "Project review");
+    }
+}
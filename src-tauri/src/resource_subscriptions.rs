@@ -0,0 +1,311 @@
+//! MCP resource subscriptions and the watcher that serves them.
+//!
+//! Sessions subscribe to resource URIs. A background task polls every
+//! subscribed resource (file metadata for `file://` context files, contents for
+//! `aiharness://` project data) and sends `notifications/resources/updated`
+//! to the subscribers when it changes. It also watches each session's context
+//! file list and sends `notifications/resources/list_changed` when files are
+//! added or removed.
+
+use crate::{app_state::AppState, mcp_resources, mcp_sessions::McpSessionManager};
+use serde_json::json;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, SystemTime};
+use tokio::sync::RwLock;
+
+/// How often subscribed resources are checked
+pub const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Snapshot used to detect a change
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Fingerprint {
+    /// Modification time and size of a file (`None` once it is gone)
+    File(Option<(SystemTime, u64)>),
+    /// Hash of a project resource's contents (`None` if it cannot be read)
+    Content(Option<u64>),
+}
+
+/// What a subscribed URI refers to
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Watched {
+    /// A `file://` resource, by its sandbox-resolved path (a relative URI
+    /// means different files in different projects)
+    File(PathBuf),
+    /// An `aiharness://` resource of a project
+    Project(String),
+}
+
+struct Subscription {
+    sessions: HashSet<String>,
+    fingerprint: Fingerprint,
+}
+
+type SubscriptionKey = (String, Watched);
+
+/// Resource subscriptions of all MCP sessions
+#[derive(Default)]
+pub struct ResourceSubscriptions {
+    by_uri: Mutex<HashMap<SubscriptionKey, Subscription>>,
+    /// Last seen context file list per project
+    context_lists: Mutex<HashMap<String, Vec<String>>>,
+}
+
+impl ResourceSubscriptions {
+    /// Subscribe a session to a resource
+    pub fn subscribe(&self, session_id: &str, uri: &str, watched: Watched, fingerprint: Fingerprint) {
+        let mut by_uri = self.lock();
+        let subscription = by_uri
+            .entry((uri.to_string(), watched))
+            .or_insert_with(|| Subscription {
+                sessions: HashSet::new(),
+                fingerprint,
+            });
+        subscription.sessions.insert(session_id.to_string());
+    }
+
+    /// Remove a session's subscription to a resource
+    pub fn unsubscribe(&self, session_id: &str, uri: &str) {
+        self.lock().retain(|(subscribed, _), subscription| {
+            if subscribed == uri {
+                subscription.sessions.remove(session_id);
+            }
+            !subscription.sessions.is_empty()
+        });
+    }
+
+    /// URIs a session is subscribed to
+    pub fn subscribed_uris(&self, session_id: &str) -> Vec<String> {
+        let mut uris = self
+            .lock()
+            .iter()
+            .filter(|(_, s)| s.sessions.contains(session_id))
+            .map(|((uri, _), _)| uri.clone())
+            .collect::<Vec<_>>();
+        uris.sort();
+        uris
+    }
+
+    /// Check every subscribed resource and context file list once, notifying
+    /// sessions of changes. Subscriptions of ended sessions are dropped.
+    ///
+    /// The state is only locked to look things up, never while a resource is
+    /// read.
+    pub async fn poll(&self, state: &RwLock<AppState>) {
+        let sessions = state.read().await.mcp_sessions.clone();
+        let sessions = &*sessions;
+        let live = sessions.list();
+        let live_ids = live.iter().map(|s| s.id.clone()).collect::<HashSet<_>>();
+
+        let targets = {
+            let mut by_uri = self.lock();
+            by_uri.retain(|_, subscription| {
+                subscription.sessions.retain(|id| live_ids.contains(id));
+                !subscription.sessions.is_empty()
+            });
+            by_uri.keys().cloned().collect::<Vec<_>>()
+        };
+        for key in targets {
+            let current = fingerprint(state, &key.0, &key.1).await;
+            let notify = {
+                let mut by_uri = self.lock();
+                match by_uri.get_mut(&key) {
+                    Some(subscription) if subscription.fingerprint != current => {
+                        subscription.fingerprint = current;
+                        subscription.sessions.iter().cloned().collect::<Vec<_>>()
+                    }
+                    _ => Vec::new(),
+                }
+            };
+            let message = json!({
+                "jsonrpc": "2.0",
+                "method": "notifications/resources/updated",
+                "params": { "uri": key.0 }
+            });
+            for session_id in notify {
+                sessions.send(&session_id, message.clone());
+            }
+        }
+
+        let mut projects = HashMap::<String, Vec<String>>::new();
+        for session in live {
+            let project_id = session.project_id.unwrap_or_else(|| "default".to_string());
+            projects.entry(project_id).or_default().push(session.id);
+        }
+        self.lock_lists().retain(|project_id, _| projects.contains_key(project_id));
+        for (project_id, session_ids) in projects {
+            self.check_context_list(state, sessions, &project_id, &session_ids)
+                .await;
+        }
+    }
+
+    async fn check_context_list(
+        &self,
+        state: &RwLock<AppState>,
+        sessions: &McpSessionManager,
+        project_id: &str,
+        session_ids: &[String],
+    ) {
+        let store = state.read().await.get_project_store(project_id).await;
+        let Ok(store) = store else {
+            return;
+        };
+        let Ok(files) = store.context_store.read().await.list_files().await else {
+            return;
+        };
+        let mut paths = files.into_iter().map(|f| f.path).collect::<Vec<_>>();
+        paths.sort();
+
+        let previous = self.lock_lists().insert(project_id.to_string(), paths.clone());
+        if previous.is_some_and(|previous| previous != paths) {
+            let message = json!({
+                "jsonrpc": "2.0",
+                "method": "notifications/resources/list_changed"
+            });
+            for session_id in session_ids {
+                sessions.send(session_id, message.clone());
+            }
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<SubscriptionKey, Subscription>> {
+        self.by_uri.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn lock_lists(&self) -> std::sync::MutexGuard<'_, HashMap<String, Vec<String>>> {
+        self.context_lists
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Current fingerprint of a subscribable resource
+pub async fn fingerprint(state: &RwLock<AppState>, uri: &str, watched: &Watched) -> Fingerprint {
+    let project_id = match watched {
+        Watched::File(path) => {
+            let metadata = tokio::fs::metadata(path).await.ok();
+            return Fingerprint::File(
+                metadata.and_then(|m| Some((m.modified().ok()?, m.len()))),
+            );
+        }
+        Watched::Project(project_id) => project_id,
+    };
+
+    let Some((_, resource)) = mcp_resources::parse_uri(uri) else {
+        return Fingerprint::Content(None);
+    };
+    let store = state.read().await.get_project_store(project_id).await;
+    let Ok(store) = store else {
+        return Fingerprint::Content(None);
+    };
+    let text = mcp_resources::read(&store, &resource).await.ok();
+    Fingerprint::Content(text.map(|text| {
+        let mut hasher = DefaultHasher::new();
+        text.hash(&mut hasher);
+        hasher.finish()
+    }))
+}
+
+/// Poll subscriptions forever (runs alongside the HTTP server)
+pub async fn watch(state: Arc<RwLock<AppState>>) {
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+        let subscriptions = state.read().await.resource_subscriptions.clone();
+        subscriptions.poll(&state).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    async fn create_state(temp_dir: &TempDir) -> RwLock<AppState> {
+        let registry_path = temp_dir.path().join("registry.db");
+        RwLock::new(
+            AppState::new(registry_path.to_str().unwrap(), temp_dir.path())
+                .await
+                .unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn poll_notifies_subscribers_when_project_data_changes() {
+        let temp_dir = TempDir::new().unwrap();
+        let state = create_state(&temp_dir).await;
+        let app = state.read().await;
+        let session = app.mcp_sessions.create(Some("c"), Some("default"), "2025-03-26");
+        let mut rx = app.mcp_sessions.subscribe(&session.id, Some("c"), Some("default")).unwrap();
+
+        let uri = "aiharness://default/todos";
+        let watched = Watched::Project("default".to_string());
+        let subscriptions = app.resource_subscriptions.clone();
+        subscriptions.subscribe(&session.id, uri, watched.clone(), fingerprint(&state, uri, &watched).await);
+        subscriptions.poll(&state).await;
+        assert!(rx.try_recv().is_err());
+
+        let store = app.get_project_store("default").await.unwrap();
+        store.todo_store.read().await.add("new", None, None).await.unwrap();
+        subscriptions.poll(&state).await;
+        let message = rx.try_recv().unwrap();
+        assert_eq!(message["method"], "notifications/resources/updated");
+        assert_eq!(message["params"]["uri"], uri);
+
+        subscriptions.unsubscribe(&session.id, uri);
+        store.todo_store.read().await.add("another", None, None).await.unwrap();
+        subscriptions.poll(&state).await;
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn poll_notifies_when_context_files_are_added() {
+        let temp_dir = TempDir::new().unwrap();
+        let state = create_state(&temp_dir).await;
+        let app = state.read().await;
+        let session = app.mcp_sessions.create(Some("c"), None, "2025-03-26");
+        let mut rx = app.mcp_sessions.subscribe(&session.id, Some("c"), None).unwrap();
+        let subscriptions = app.resource_subscriptions.clone();
+        subscriptions.poll(&state).await;
+
+        let file = temp_dir.path().join("notes.md");
+        std::fs::write(&file, "hi").unwrap();
+        let store = app.get_project_store("default").await.unwrap();
+        store
+            .context_store
+            .read()
+            .await
+            .add_file(file.to_str().unwrap())
+            .await
+            .unwrap();
+        subscriptions.poll(&state).await;
+        assert_eq!(
+            rx.try_recv().unwrap()["method"],
+            "notifications/resources/list_changed"
+        );
+    }
+
+    #[tokio::test]
+    async fn subscriptions_of_ended_sessions_are_dropped() {
+        let temp_dir = TempDir::new().unwrap();
+        let state = create_state(&temp_dir).await;
+        let app = state.read().await;
+        let session = app.mcp_sessions.create(Some("c"), None, "2025-03-26");
+        let subscriptions = app.resource_subscriptions.clone();
+        subscriptions.subscribe(
+            &session.id,
+            "file:///nope",
+            Watched::File(PathBuf::from("/nope")),
+            Fingerprint::File(None),
+        );
+        assert_eq!(subscriptions.subscribed_uris(&session.id).len(), 1);
+
+        app.mcp_sessions.remove(&session.id, Some("c"), None);
+        subscriptions.poll(&state).await;
+        assert!(subscriptions.subscribed_uris(&session.id).is_empty());
+    }
+}
@@ -0,0 +1,781 @@
+//! Persistent terminal sessions
+//!
+//! A terminal is a shell on a pseudo-terminal that stays open across tool
+//! calls, so `cd`, activated virtualenvs and exported variables carry over
+//! from one command to the next (unlike `run_command`). Output is kept in a
+//! bounded buffer addressed by byte cursors, so callers read it
+//! incrementally. Everything typed into and printed by a terminal is
+//! broadcast as a [`TerminalEvent`] so the UI can mirror it.
+//!
+//! Terminals nobody has sent to or read from for the idle timeout are closed
+//! by a background reaper.
+
+use chrono::{DateTime, Utc};
+use portable_pty::{native_pty_system, ChildKiller, CommandBuilder, MasterPty, PtySize};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, Weak};
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, Notify};
+
+/// How long a terminal may go unused before it is closed
+pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+/// Open terminals allowed per project
+pub const MAX_TERMINALS_PER_PROJECT: usize = 8;
+
+/// Output kept per terminal; older output is dropped
+const OUTPUT_BUFFER_BYTES: usize = 1024 * 1024;
+
+/// How often the reaper looks for idle terminals
+const REAP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// A read stops once output has been quiet this long
+const SETTLE_TIME: Duration = Duration::from_millis(150);
+
+/// How to start a terminal
+#[derive(Debug, Clone)]
+pub struct TerminalOptions {
+    /// Program to run (default: the user's login shell)
+    pub shell: Option<String>,
+    pub cwd: PathBuf,
+    pub env: BTreeMap<String, String>,
+    pub cols: u16,
+    pub rows: u16,
+}
+
+/// An open terminal
+#[derive(Debug, Clone, Serialize)]
+pub struct TerminalInfo {
+    pub id: String,
+    pub project_id: String,
+    /// The program running in the terminal
+    pub shell: String,
+    pub cwd: String,
+    pub cols: u16,
+    pub rows: u16,
+    pub created_at: DateTime<Utc>,
+    /// Cursor just past the newest output
+    pub cursor: u64,
+    pub exited: bool,
+    pub exit_code: Option<u32>,
+}
+
+/// Output read from a terminal
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TerminalOutput {
+    pub output: String,
+    /// Where the next read continues
+    pub cursor: u64,
+    /// Bytes between the requested cursor and the oldest output still kept
+    pub dropped: u64,
+    /// More output is available past `cursor`
+    pub more: bool,
+    pub exited: bool,
+    pub exit_code: Option<u32>,
+}
+
+/// What happened in a terminal
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TerminalEventKind {
+    Opened { shell: String, cwd: String },
+    /// Input sent to the terminal
+    Input { data: String },
+    /// Output printed by the terminal (with its escape sequences)
+    Output { data: String },
+    /// The shell exited
+    Exited { exit_code: Option<u32> },
+    /// The terminal was closed (`reason` is `closed`, `idle` or `shutdown`)
+    Closed { reason: String },
+}
+
+/// Terminal activity for the UI
+#[derive(Debug, Clone, Serialize)]
+pub struct TerminalEvent {
+    pub terminal_id: String,
+    pub project_id: String,
+    pub timestamp: DateTime<Utc>,
+    #[serde(flatten)]
+    pub kind: TerminalEventKind,
+}
+
+/// Output and exit status, shared with the reader and waiter threads
+struct Shared {
+    terminal_id: String,
+    project_id: String,
+    state: Mutex<OutputState>,
+    changed: Notify,
+    events: broadcast::Sender<TerminalEvent>,
+}
+
+struct OutputState {
+    buffer: VecDeque<u8>,
+    /// Cursor of the first byte in `buffer`
+    start: u64,
+    /// Where a read without a cursor continues
+    read_cursor: u64,
+    exited: bool,
+    /// `None` while running, or if the exit status couldn't be read
+    exit_code: Option<u32>,
+}
+
+impl OutputState {
+    fn end(&self) -> u64 {
+        self.start + self.buffer.len() as u64
+    }
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, OutputState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn emit(&self, kind: TerminalEventKind) {
+        let _ = self.events.send(TerminalEvent {
+            terminal_id: self.terminal_id.clone(),
+            project_id: self.project_id.clone(),
+            timestamp: Utc::now(),
+            kind,
+        });
+    }
+
+    fn push_output(&self, data: &[u8]) {
+        {
+            let mut state = self.lock();
+            state.buffer.extend(data);
+            let excess = state.buffer.len().saturating_sub(OUTPUT_BUFFER_BYTES);
+            if excess > 0 {
+                state.buffer.drain(..excess);
+                state.start += excess as u64;
+            }
+        }
+        self.changed.notify_waiters();
+    }
+
+    fn set_exit(&self, exit_code: Option<u32>) {
+        {
+            let mut state = self.lock();
+            state.exited = true;
+            state.exit_code = exit_code;
+        }
+        self.changed.notify_waiters();
+        self.emit(TerminalEventKind::Exited { exit_code });
+    }
+}
+
+struct Terminal {
+    id: String,
+    project_id: String,
+    shell: String,
+    cwd: String,
+    cols: u16,
+    rows: u16,
+    created_at: DateTime<Utc>,
+    last_used: Mutex<Instant>,
+    shared: Arc<Shared>,
+    writer: Mutex<Box<dyn Write + Send>>,
+    killer: Mutex<Box<dyn ChildKiller + Send + Sync>>,
+    /// Keeps the pseudo-terminal open
+    _master: Mutex<Box<dyn MasterPty + Send>>,
+}
+
+impl Terminal {
+    fn touch(&self) {
+        *self.last_used.lock().unwrap_or_else(PoisonError::into_inner) = Instant::now();
+    }
+
+    fn idle_for(&self) -> Duration {
+        self.last_used
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .elapsed()
+    }
+
+    fn kill(&self) {
+        let _ = self
+            .killer
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .kill();
+    }
+
+    fn info(&self) -> TerminalInfo {
+        let state = self.shared.lock();
+        TerminalInfo {
+            id: self.id.clone(),
+            project_id: self.project_id.clone(),
+            shell: self.shell.clone(),
+            cwd: self.cwd.clone(),
+            cols: self.cols,
+            rows: self.rows,
+            created_at: self.created_at,
+            cursor: state.end(),
+            exited: state.exited,
+            exit_code: state.exit_code,
+        }
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        self.kill();
+    }
+}
+
+/// Open terminals across all projects
+pub struct TerminalManager {
+    terminals: Mutex<HashMap<String, Arc<Terminal>>>,
+    sender: broadcast::Sender<TerminalEvent>,
+    idle_timeout: Duration,
+    reaper_started: AtomicBool,
+}
+
+impl TerminalManager {
+    #[must_use]
+    pub fn new(idle_timeout: Duration) -> Self {
+        let (sender, _) = broadcast::channel(256);
+        Self {
+            terminals: Mutex::new(HashMap::new()),
+            sender,
+            idle_timeout,
+            reaper_started: AtomicBool::new(false),
+        }
+    }
+
+    /// Subscribe to terminal activity
+    pub fn subscribe(&self) -> broadcast::Receiver<TerminalEvent> {
+        self.sender.subscribe()
+    }
+
+    /// Start a shell on a new pseudo-terminal.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the project has too many terminals open or the
+    /// shell can't be started
+    pub fn open(
+        self: &Arc<Self>,
+        project_id: &str,
+        options: &TerminalOptions,
+    ) -> Result<TerminalInfo, String> {
+        let open = self
+            .lock()
+            .values()
+            .filter(|t| t.project_id == project_id)
+            .count();
+        if open >= MAX_TERMINALS_PER_PROJECT {
+            return Err(format!(
+                "Project {project_id} already has {open} terminals open; close one first"
+            ));
+        }
+
+        let pair = native_pty_system()
+            .openpty(PtySize {
+                rows: options.rows,
+                cols: options.cols,
+                pixel_width: 0,
+                pixel_height: 0,
+            })
+            .map_err(|e| format!("Failed to open a pseudo-terminal: {e}"))?;
+
+        let mut cmd = match &options.shell {
+            Some(shell) => CommandBuilder::new(shell),
+            None => CommandBuilder::new_default_prog(),
+        };
+        let shell = options.shell.clone().unwrap_or_else(|| cmd.get_shell());
+        cmd.cwd(&options.cwd);
+        cmd.env("TERM", "xterm-256color");
+        for (name, value) in &options.env {
+            cmd.env(name, value);
+        }
+
+        let mut child = pair
+            .slave
+            .spawn_command(cmd)
+            .map_err(|e| format!("Failed to start {shell}: {e}"))?;
+        drop(pair.slave);
+        let killer = child.clone_killer();
+        let reader = pair.master.try_clone_reader();
+        let writer = pair.master.take_writer();
+        let (reader, writer) = match (reader, writer) {
+            (Ok(reader), Ok(writer)) => (reader, writer),
+            (Err(e), _) | (_, Err(e)) => {
+                let _ = child.kill();
+                return Err(format!("Failed to attach to the terminal: {e}"));
+            }
+        };
+
+        let id = uuid::Uuid::new_v4().to_string();
+        let shared = Arc::new(Shared {
+            terminal_id: id.clone(),
+            project_id: project_id.to_string(),
+            state: Mutex::new(OutputState {
+                buffer: VecDeque::new(),
+                start: 0,
+                read_cursor: 0,
+                exited: false,
+                exit_code: None,
+            }),
+            changed: Notify::new(),
+            events: self.sender.clone(),
+        });
+
+        let output = shared.clone();
+        std::thread::spawn(move || read_output(reader, &output));
+        let exit = shared.clone();
+        std::thread::spawn(move || {
+            let exit_code = child.wait().ok().map(|status| status.exit_code());
+            exit.set_exit(exit_code);
+        });
+
+        let terminal = Arc::new(Terminal {
+            id: id.clone(),
+            project_id: project_id.to_string(),
+            shell: shell.clone(),
+            cwd: options.cwd.to_string_lossy().to_string(),
+            cols: options.cols,
+            rows: options.rows,
+            created_at: Utc::now(),
+            last_used: Mutex::new(Instant::now()),
+            shared,
+            writer: Mutex::new(writer),
+            killer: Mutex::new(killer),
+            _master: Mutex::new(pair.master),
+        });
+        let info = terminal.info();
+        terminal.shared.emit(TerminalEventKind::Opened {
+            shell,
+            cwd: info.cwd.clone(),
+        });
+        self.lock().insert(id, terminal);
+        self.start_reaper();
+        Ok(info)
+    }
+
+    /// Write `input` to a terminal, returning the cursor its output starts at.
+    ///
+    /// This blocks if the terminal isn't consuming its input.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the terminal doesn't exist or has exited
+    pub fn send(&self, project_id: &str, id: &str, input: &str) -> Result<u64, String> {
+        let terminal = self.get(project_id, id)?;
+        terminal.touch();
+        let cursor = {
+            let state = terminal.shared.lock();
+            if state.exited {
+                return Err(format!("Terminal {id} has exited"));
+            }
+            state.end()
+        };
+
+        let mut writer = terminal.writer.lock().unwrap_or_else(PoisonError::into_inner);
+        writer
+            .write_all(input.as_bytes())
+            .and_then(|()| writer.flush())
+            .map_err(|e| format!("Failed to write to terminal {id}: {e}"))?;
+        drop(writer);
+
+        terminal.shared.emit(TerminalEventKind::Input {
+            data: input.to_string(),
+        });
+        Ok(cursor)
+    }
+
+    /// Read a terminal's output from `cursor` (default: where the previous
+    /// read stopped).
+    ///
+    /// Waits up to `wait` for new output, then until the output has been quiet
+    /// briefly, so a command's output usually arrives in one read. At most
+    /// `max_bytes` are returned.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the terminal doesn't exist
+    pub async fn read(
+        &self,
+        project_id: &str,
+        id: &str,
+        cursor: Option<u64>,
+        wait: Duration,
+        max_bytes: usize,
+    ) -> Result<TerminalOutput, String> {
+        let terminal = self.get(project_id, id)?;
+        terminal.touch();
+        let shared = &terminal.shared;
+        let cursor = cursor.unwrap_or_else(|| shared.lock().read_cursor);
+        let deadline = Instant::now() + wait;
+
+        loop {
+            let notified = shared.changed.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+
+            let (end, exited) = {
+                let state = shared.lock();
+                (state.end(), state.exited)
+            };
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let pending = end.saturating_sub(cursor);
+            if exited || remaining.is_zero() || pending >= max_bytes as u64 {
+                break;
+            }
+            let limit = if pending > 0 {
+                SETTLE_TIME.min(remaining)
+            } else {
+                remaining
+            };
+            if tokio::time::timeout(limit, notified).await.is_err() && pending > 0 {
+                break;
+            }
+        }
+
+        let mut state = shared.lock();
+        let end = state.end();
+        let (from, dropped) = if cursor < state.start {
+            (state.start, state.start - cursor)
+        } else {
+            (cursor.min(end), 0)
+        };
+        let offset = usize::try_from(from - state.start).unwrap_or(usize::MAX);
+        let available = usize::try_from(end - from).unwrap_or(usize::MAX);
+        let take = available.min(max_bytes);
+        let bytes: Vec<u8> = state.buffer.range(offset..offset + take).copied().collect();
+        let exited = state.exited;
+        let len = if exited && take == available {
+            bytes.len()
+        } else {
+            complete_utf8_len(&bytes)
+        };
+        let next = from + len as u64;
+        state.read_cursor = next;
+
+        Ok(TerminalOutput {
+            output: String::from_utf8_lossy(&bytes[..len]).into_owned(),
+            cursor: next,
+            dropped,
+            more: next < end,
+            exited,
+            exit_code: state.exit_code,
+        })
+    }
+
+    /// Close a terminal, killing its shell.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the terminal doesn't exist
+    pub fn close(&self, project_id: &str, id: &str) -> Result<(), String> {
+        self.get(project_id, id)?;
+        if let Some(terminal) = self.lock().remove(id) {
+            Self::shut(&terminal, "closed");
+        }
+        Ok(())
+    }
+
+    /// Open terminals, optionally only a project's, oldest first
+    #[must_use]
+    pub fn list(&self, project_id: Option<&str>) -> Vec<TerminalInfo> {
+        let mut terminals: Vec<_> = self
+            .lock()
+            .values()
+            .filter(|t| project_id.map_or(true, |p| t.project_id == p))
+            .map(|t| t.info())
+            .collect();
+        terminals.sort_by_key(|t| t.created_at);
+        terminals
+    }
+
+    /// Close terminals unused for the idle timeout, returning their ids.
+    pub fn reap_idle(&self) -> Vec<String> {
+        let idle: Vec<_> = {
+            let mut terminals = self.lock();
+            let ids: Vec<_> = terminals
+                .values()
+                .filter(|t| t.idle_for() >= self.idle_timeout)
+                .map(|t| t.id.clone())
+                .collect();
+            ids.iter().filter_map(|id| terminals.remove(id)).collect()
+        };
+        for terminal in &idle {
+            tracing::info!("Closing idle terminal {} ({})", terminal.id, terminal.project_id);
+            Self::shut(terminal, "idle");
+        }
+        idle.iter().map(|t| t.id.clone()).collect()
+    }
+
+    /// Close every terminal (when the app shuts down).
+    pub fn close_all(&self) {
+        let terminals: Vec<_> = self.lock().drain().map(|(_, t)| t).collect();
+        for terminal in &terminals {
+            Self::shut(terminal, "shutdown");
+        }
+    }
+
+    fn get(&self, project_id: &str, id: &str) -> Result<Arc<Terminal>, String> {
+        self.lock()
+            .get(id)
+            .filter(|t| t.project_id == project_id)
+            .cloned()
+            .ok_or_else(|| format!("No terminal {id} in project {project_id}"))
+    }
+
+    fn shut(terminal: &Terminal, reason: &str) {
+        terminal.kill();
+        terminal.shared.emit(TerminalEventKind::Closed {
+            reason: reason.to_string(),
+        });
+    }
+
+    fn start_reaper(self: &Arc<Self>) {
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        if self.reaper_started.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let manager: Weak<Self> = Arc::downgrade(self);
+        runtime.spawn(async move {
+            loop {
+                tokio::time::sleep(REAP_INTERVAL).await;
+                let Some(manager) = manager.upgrade() else {
+                    break;
+                };
+                manager.reap_idle();
+            }
+        });
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, Arc<Terminal>>> {
+        self.terminals.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Default for TerminalManager {
+    fn default() -> Self {
+        Self::new(DEFAULT_IDLE_TIMEOUT)
+    }
+}
+
+/// Copy a terminal's output into its buffer and out as events until the
+/// pseudo-terminal closes.
+fn read_output(mut reader: Box<dyn Read + Send>, shared: &Shared) {
+    let mut chunk = vec![0u8; 8192];
+    // An incomplete UTF-8 character held back from the last event
+    let mut pending = Vec::new();
+    loop {
+        let n = match reader.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(_) => break,
+        };
+        shared.push_output(&chunk[..n]);
+
+        pending.extend_from_slice(&chunk[..n]);
+        let len = complete_utf8_len(&pending);
+        if len > 0 {
+            let data = String::from_utf8_lossy(&pending[..len]).into_owned();
+            pending.drain(..len);
+            shared.emit(TerminalEventKind::Output { data });
+        }
+    }
+}
+
+/// Length of `bytes` without a trailing multi-byte character that is still
+/// missing bytes.
+fn complete_utf8_len(bytes: &[u8]) -> usize {
+    for (back, &byte) in bytes.iter().rev().take(4).enumerate() {
+        if byte & 0xC0 == 0x80 {
+            continue;
+        }
+        let width = match byte {
+            0xC0..=0xDF => 2,
+            0xE0..=0xEF => 3,
+            0xF0..=0xF7 => 4,
+            _ => 1,
+        };
+        return if back + 1 < width {
+            bytes.len() - back - 1
+        } else {
+            bytes.len()
+        };
+    }
+    bytes.len()
+}
+
+/// Terminal output as plain text: escape sequences (colors, cursor
+/// movement, titles) and control characters removed, `\r\n` turned into `\n`.
+#[must_use]
+pub fn strip_ansi(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\x1b' => match chars.next() {
+                // CSI: parameters up to a final byte in @..~
+                Some('[') => {
+                    for c in chars.by_ref() {
+                        if ('@'..='~').contains(&c) {
+                            break;
+                        }
+                    }
+                }
+                // OSC: up to BEL or ESC \
+                Some(']') => {
+                    while let Some(c) = chars.next() {
+                        if c == '\x07' {
+                            break;
+                        }
+                        if c == '\x1b' {
+                            chars.next();
+                            break;
+                        }
+                    }
+                }
+                // Character set designation
+                Some('(' | ')') => {
+                    chars.next();
+                }
+                _ => {}
+            },
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' | '\t' | '\r' => out.push(c),
+            c if c.is_control() => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn strip_ansi_and_utf8_boundaries() {
+        assert_eq!(
+            strip_ansi("\x1b]0;title\x07\x1b[1;32mok\x1b[0m\r\n$ \x1b(Bdone\x07"),
+            "ok\n$ done"
+        );
+
+        let text = "aé€".as_bytes();
+        assert_eq!(complete_utf8_len(text), text.len());
+        assert_eq!(complete_utf8_len(&text[..text.len() - 1]), 3);
+        assert_eq!(complete_utf8_len(&text[..2]), 1);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn terminal_keeps_shell_state_between_sends() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::create_dir(temp_dir.path().join("sub")).unwrap();
+        let manager = Arc::new(TerminalManager::default());
+        let mut events = manager.subscribe();
+
+        let info = manager
+            .open(
+                "p1",
+                &TerminalOptions {
+                    shell: Some("sh".to_string()),
+                    cwd: temp_dir.path().to_path_buf(),
+                    env: BTreeMap::from([("GREETING".to_string(), "hello".to_string())]),
+                    cols: 80,
+                    rows: 24,
+                },
+            )
+            .unwrap();
+        assert!(matches!(
+            events.recv().await.unwrap().kind,
+            TerminalEventKind::Opened { .. }
+        ));
+
+        manager
+            .send("p1", &info.id, "cd sub && export NAME=world\r")
+            .unwrap();
+        let cursor = manager
+            .send("p1", &info.id, "echo \"$GREETING $NAME from $(basename $PWD)\"\r")
+            .unwrap();
+        let mut output = String::new();
+        for _ in 0..50 {
+            let read = manager
+                .read("p1", &info.id, None, Duration::from_millis(200), 64 * 1024)
+                .await
+                .unwrap();
+            output.push_str(&read.output);
+            if output.contains("hello world from sub") {
+                break;
+            }
+        }
+        assert!(output.contains("hello world from sub"), "{output:?}");
+
+        // Reading again from a cursor repeats the output
+        let again = manager
+            .read("p1", &info.id, Some(cursor), Duration::ZERO, 64 * 1024)
+            .await
+            .unwrap();
+        assert!(again.output.contains("hello world from sub"));
+
+        // Another project can't see the terminal
+        assert!(manager.send("p2", &info.id, "ls\r").is_err());
+        assert_eq!(manager.list(Some("p2")).len(), 0);
+
+        manager.send("p1", &info.id, "exit 3\r").unwrap();
+        let mut exit = None;
+        for _ in 0..50 {
+            let read = manager
+                .read("p1", &info.id, None, Duration::from_millis(200), 64 * 1024)
+                .await
+                .unwrap();
+            if read.exited {
+                exit = read.exit_code;
+                break;
+            }
+        }
+        assert_eq!(exit, Some(3));
+        assert!(manager.send("p1", &info.id, "ls\r").is_err());
+
+        manager.close("p1", &info.id).unwrap();
+        assert!(manager.list(None).is_empty());
+        assert!(manager.close("p1", &info.id).is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn idle_terminals_are_reaped() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = Arc::new(TerminalManager::new(Duration::from_millis(50)));
+        let mut events = manager.subscribe();
+        let info = manager
+            .open(
+                "p1",
+                &TerminalOptions {
+                    shell: Some("sh".to_string()),
+                    cwd: temp_dir.path().to_path_buf(),
+                    env: BTreeMap::new(),
+                    cols: 80,
+                    rows: 24,
+                },
+            )
+            .unwrap();
+        assert!(manager.reap_idle().is_empty());
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(manager.reap_idle(), vec![info.id.clone()]);
+        assert!(manager.list(None).is_empty());
+
+        let closed = loop {
+            let event = events.recv().await.unwrap();
+            if let TerminalEventKind::Closed { reason } = event.kind {
+                break reason;
+            }
+        };
+        assert_eq!(closed, "idle");
+    }
+}
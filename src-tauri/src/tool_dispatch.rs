@@ -0,0 +1,541 @@
+//! Single dispatcher for tool calls from HTTP, MCP and the app UI
+//!
+//! Resolves the tool (a built-in, or a tool of one of the project's upstream
+//! MCP servers) and its project context, applies client policies and
+//! approval gates, runs the tool and records the call in history. A call can
+//! carry a progress reporter and a cancellation token; a cancelled call stops
+//! the running tool and is recorded as cancelled.
+
+use crate::{
+    app_state::AppState,
+    approvals::{ApprovalRecord, ApprovalRequest},
+    error::ToolError,
+    mcp_gateway,
+    tool_policies::{self, PolicyRequest},
+    tools::{Caller, ProgressReporter, Tool, ToolContext, ToolResult},
+    ToolCallEvent,
+};
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::RwLock;
+use tokio_util::sync::CancellationToken;
+
+/// A tool call to dispatch
+#[derive(Debug, Clone)]
+pub struct ToolCall {
+    pub tool_name: String,
+    pub arguments: serde_json::Value,
+    pub project_id: String,
+    pub caller: Caller,
+    /// Receives progress from tools that report it
+    pub progress: ProgressReporter,
+    /// Cancelling it stops the tool run
+    pub cancel: CancellationToken,
+}
+
+/// What came of a dispatched call
+#[derive(Debug, Clone)]
+pub struct ToolCallOutcome {
+    /// The event recorded in history
+    pub event: ToolCallEvent,
+    /// Tool output, or the error message
+    pub result: Result<ToolResult, String>,
+}
+
+/// Run a tool call end to end and record it.
+///
+/// Calls from HTTP/MCP clients are checked against tool policies and approval
+/// rules first; calls from the app UI are not. The state lock is not held
+/// while the tool runs or while waiting for approval.
+pub async fn dispatch(state: &Arc<RwLock<AppState>>, call: ToolCall) -> ToolCallOutcome {
+    let start = Instant::now();
+    let timestamp = chrono::Utc::now().to_rfc3339();
+
+    let (result, approval) = run(state, &call).await;
+    let duration_ms = u64::try_from(start.elapsed().as_millis()).unwrap_or(u64::MAX);
+
+    let event = ToolCallEvent {
+        id: uuid::Uuid::new_v4().to_string(),
+        timestamp,
+        tool_name: call.tool_name,
+        project_id: call.project_id,
+        arguments: call.arguments,
+        success: result.is_ok(),
+        content: match &result {
+            Ok(output) => output.content.clone(),
+            Err(e) => e.clone(),
+        },
+        duration_ms,
+        approval,
+        cancelled: result.is_err() && call.cancel.is_cancelled(),
+    };
+    state.read().await.record_event(event.clone()).await;
+
+    ToolCallOutcome { event, result }
+}
+
+async fn run(
+    state: &Arc<RwLock<AppState>>,
+    call: &ToolCall,
+) -> (Result<ToolResult, String>, Option<ApprovalRecord>) {
+    let (tool, gateway, mut ctx) = {
+        let state_read = state.read().await;
+        let tool = state_read.tool_registry.get_shared(&call.tool_name);
+        let is_upstream = call.tool_name.contains(mcp_gateway::TOOL_SEPARATOR);
+        if tool.is_none() && !is_upstream {
+            return (Err(ToolError::NotFound(call.tool_name.clone()).to_string()), None);
+        }
+        match state_read.tool_context(&call.project_id, call.caller.clone()).await {
+            Ok(ctx) => (tool, state_read.mcp_gateway.clone(), ctx),
+            Err(e) => return (Err(e.to_string()), None),
+        }
+    };
+    let tool: Arc<dyn Tool> = match tool {
+        Some(tool) => tool,
+        None => match gateway.tool(&ctx.store, &call.tool_name).await {
+            Ok(Some(tool)) => Arc::new(tool),
+            Ok(None) => return (Err(ToolError::NotFound(call.tool_name.clone()).to_string()), None),
+            Err(e) => return (Err(e.to_string()), None),
+        },
+    };
+
+    // Dropping the gate on cancel also withdraws a pending approval request
+    let (gate, approval) = tokio::select! {
+        gated = gate_tool_call(state, &ctx, &call.tool_name, &call.arguments) => gated,
+        () = call.cancel.cancelled() => return (Err(ToolError::Cancelled.to_string()), None),
+    };
+    if let Err(denied) = gate {
+        return (Err(denied), approval);
+    }
+
+    if call.cancel.is_cancelled() {
+        return (Err(ToolError::Cancelled.to_string()), approval);
+    }
+    ctx.progress = call.progress.clone();
+    ctx.cancel = call.cancel.clone();
+    let result = tokio::select! {
+        result = tool.execute_with_context(call.arguments.clone(), &ctx) => result,
+        () = call.cancel.cancelled() => Err(ToolError::Cancelled),
+    };
+    match result {
+        Ok(output) => (Ok(output), approval),
+        Err(ToolError::Cancelled) => (Err(ToolError::Cancelled.to_string()), approval),
+        Err(e) => {
+            state
+                .read()
+                .await
+                .record_tool_error(&call.tool_name, &call.project_id, &e)
+                .await;
+            (Err(e.to_string()), approval)
+        }
+    }
+}
+
+/// Run the policy check and, if an approval rule matches, wait for a human decision.
+///
+/// Returns whether the call may proceed, plus the approval decision if one was needed.
+async fn gate_tool_call(
+    state: &Arc<RwLock<AppState>>,
+    ctx: &ToolContext,
+    tool_name: &str,
+    arguments: &serde_json::Value,
+) -> (Result<(), String>, Option<ApprovalRecord>) {
+    if let Err(denied) = check_tool_policy(state, ctx, tool_name, arguments).await {
+        return (Err(denied), None);
+    }
+    match await_approval(state, ctx, tool_name, arguments).await {
+        Ok(Some(record)) if !record.is_approved() => {
+            let message = record.denial_message();
+            (Err(message), Some(record))
+        }
+        Ok(record) => (Ok(()), record),
+        Err(e) => (Err(e), None),
+    }
+}
+
+/// Suspend a client call that matches an approval rule until it is approved,
+/// denied or times out.
+///
+/// The state lock is released while waiting.
+async fn await_approval(
+    state: &Arc<RwLock<AppState>>,
+    ctx: &ToolContext,
+    tool_name: &str,
+    arguments: &serde_json::Value,
+) -> Result<Option<ApprovalRecord>, String> {
+    let Some(client_id) = ctx.caller.client_id() else {
+        return Ok(None);
+    };
+    let (queue, request) = {
+        let state_read = state.read().await;
+        let rules = state_read
+            .approval_rules
+            .for_project(&ctx.project_id)
+            .map_err(|e| e.to_string())?;
+        if rules.is_empty() {
+            return Ok(None);
+        }
+        let request = policy_request(ctx, tool_name, arguments).await;
+        let Some(rule) = rules.iter().find(|rule| rule.matches(&request)) else {
+            return Ok(None);
+        };
+        let approval = ApprovalRequest {
+            id: uuid::Uuid::new_v4().to_string(),
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            tool_name: tool_name.to_string(),
+            project_id: ctx.project_id.clone(),
+            arguments: arguments.clone(),
+            client_id: Some(client_id.to_string()),
+            rule_id: rule.id.clone(),
+            command: request.command,
+        };
+        (state_read.approvals.clone(), approval)
+    };
+
+    Ok(Some(queue.request(request).await))
+}
+
+/// Check a client call against the tool policies that apply to it.
+///
+/// Calls from the app UI are not subject to policies. Denials are recorded
+/// as security events.
+pub(crate) async fn check_tool_policy(
+    state: &Arc<RwLock<AppState>>,
+    ctx: &ToolContext,
+    tool_name: &str,
+    arguments: &serde_json::Value,
+) -> Result<(), String> {
+    enforce_tool_policy(state, ctx, tool_name, arguments)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// [`check_tool_policy`], for callers that report a `ToolError`
+pub(crate) async fn enforce_tool_policy(
+    state: &Arc<RwLock<AppState>>,
+    ctx: &ToolContext,
+    tool_name: &str,
+    arguments: &serde_json::Value,
+) -> Result<(), ToolError> {
+    let Some(client_id) = ctx.caller.client_id() else {
+        return Ok(());
+    };
+    let state_read = state.read().await;
+    let policies = state_read
+        .tool_policies
+        .for_call(Some(client_id), &ctx.project_id)?;
+    if policies.is_empty() {
+        return Ok(());
+    }
+
+    let request = policy_request(ctx, tool_name, arguments).await;
+    if let Err(reason) = tool_policies::evaluate(&policies, &request) {
+        let error = ToolError::PolicyDenied(reason);
+        state_read
+            .record_tool_error(tool_name, &ctx.project_id, &error)
+            .await;
+        return Err(error);
+    }
+    Ok(())
+}
+
+/// Describe a call for the policy engine: the paths it touches, the
+/// command it would run (resolving build command ids) and the environment
+/// variables and stdin it gives that command.
+async fn policy_request(
+    ctx: &ToolContext,
+    tool_name: &str,
+    arguments: &serde_json::Value,
+) -> PolicyRequest {
+    let single = tool_policies::PATH_ARG_KEYS
+        .iter()
+        .filter_map(|key| arguments.get(*key).and_then(|v| v.as_str()));
+    let listed = tool_policies::PATH_LIST_ARG_KEYS
+        .iter()
+        .filter_map(|key| arguments.get(*key).and_then(|v| v.as_array()))
+        .flatten()
+        .filter_map(|v| v.as_str());
+    let paths = single
+        .chain(listed)
+        .map(|path| resolved_policy_path(ctx, path))
+        .collect();
+
+    let mut command = arguments
+        .get("command")
+        .and_then(|v| v.as_str())
+        .map(ToString::to_string);
+    if tool_name == "terminal_open" {
+        // The program a terminal runs is checked like a command
+        command = arguments
+            .get("shell")
+            .and_then(|v| v.as_str())
+            .map(ToString::to_string);
+    }
+    if tool_name == "terminal_send" {
+        // What's typed into a terminal is checked like a command line
+        command = arguments
+            .get("input")
+            .and_then(|v| v.as_str())
+            .map(ToString::to_string);
+    }
+    if tool_name == "build_run_command" {
+        if let Some(id) = arguments.get("id").and_then(|v| v.as_str()) {
+            let store = ctx.store.build_command_store.read().await;
+            command = store.get(id).await.ok().flatten().map(|c| c.command);
+        }
+    }
+
+    // Environment overrides and stdin can change what a command runs
+    let env = arguments
+        .get("env")
+        .and_then(|v| v.as_object())
+        .map(|env| env.keys().cloned().collect())
+        .unwrap_or_default();
+    let stdin = arguments.get("stdin").is_some_and(|v| !v.is_null());
+
+    PolicyRequest {
+        tool_name: tool_name.to_string(),
+        paths,
+        command,
+        env,
+        stdin,
+    }
+}
+
+/// Where a path argument really points (following symlinks), relative to the
+/// project root; the lexical form if it can't be resolved.
+fn resolved_policy_path(ctx: &ToolContext, path: &str) -> String {
+    match ctx.sandbox.resolve(path) {
+        Ok(resolved) => tool_policies::policy_path(&resolved.to_string_lossy(), Some(ctx.root())),
+        Err(_) => tool_policies::policy_path(path, Some(std::path::Path::new(ctx.root_path()))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    async fn create_state(temp_dir: &TempDir) -> Arc<RwLock<AppState>> {
+        let registry_path = temp_dir.path().join("registry.db");
+        let state = AppState::new(registry_path.to_str().unwrap(), temp_dir.path())
+            .await
+            .unwrap();
+        Arc::new(RwLock::new(state))
+    }
+
+    fn call(tool_name: &str, arguments: serde_json::Value, caller: Caller) -> ToolCall {
+        ToolCall {
+            tool_name: tool_name.to_string(),
+            arguments,
+            project_id: "default".to_string(),
+            caller,
+            progress: ProgressReporter::default(),
+            cancel: CancellationToken::new(),
+        }
+    }
+
+    fn client(id: &str) -> Caller {
+        Caller::Client {
+            id: id.to_string(),
+            name: "test".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn dispatch_runs_project_tools_and_records_events() {
+        let temp_dir = TempDir::new().unwrap();
+        let state = create_state(&temp_dir).await;
+
+        let outcome = dispatch(&state, call("todo_add", json!({ "title": "ship it" }), Caller::App)).await;
+        assert!(outcome.result.is_ok());
+        assert!(outcome.event.success);
+
+        let outcome = dispatch(&state, call("todo_list", json!({}), client("c1"))).await;
+        assert!(outcome.result.unwrap().content.contains("ship it"));
+
+        let outcome = dispatch(&state, call("no_such_tool", json!({}), Caller::App)).await;
+        assert_eq!(outcome.result.unwrap_err(), "Tool not found: no_such_tool");
+
+        let history = state.read().await.get_history().await;
+        assert_eq!(history.len(), 3);
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[tokio::test]
+    async fn cancelled_calls_stop_and_are_recorded() {
+        let temp_dir = TempDir::new().unwrap();
+        let state = create_state(&temp_dir).await;
+
+        let command = {
+            let state_read = state.read().await;
+            let store = state_read.get_project_store("default").await.unwrap();
+            let store = store.build_command_store.read().await;
+            store.add("slow", "sleep 30", None).await.unwrap()
+        };
+
+        let slow = call("build_run_command", json!({ "id": command.id }), Caller::App);
+        let token = slow.cancel.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            token.cancel();
+        });
+        let start = Instant::now();
+        let outcome = dispatch(&state, slow).await;
+        assert!(start.elapsed() < std::time::Duration::from_secs(10));
+        assert_eq!(outcome.result.unwrap_err(), "Cancelled");
+        assert!(outcome.event.cancelled);
+
+        let history = state.read().await.get_history().await;
+        assert!(history[0].cancelled);
+
+        let finished = dispatch(&state, call("todo_list", json!({}), Caller::App)).await;
+        assert!(!finished.event.cancelled);
+    }
+
+    #[tokio::test]
+    async fn cancelling_a_call_withdraws_its_approval_request() {
+        let temp_dir = TempDir::new().unwrap();
+        let state = create_state(&temp_dir).await;
+        state
+            .read()
+            .await
+            .approval_rules
+            .add(crate::approvals::NewApprovalRule {
+                project_id: None,
+                tool: "todo_add".to_string(),
+                path: None,
+                command: None,
+            })
+            .unwrap();
+
+        let gated = call("todo_add", json!({ "title": "x" }), client("c1"));
+        let token = gated.cancel.clone();
+        let waiting = tokio::spawn({
+            let state = state.clone();
+            async move { dispatch(&state, gated).await }
+        });
+        let approvals = state.read().await.approvals.clone();
+        for _ in 0..100 {
+            if !approvals.pending().is_empty() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert_eq!(approvals.pending().len(), 1);
+
+        token.cancel();
+        let outcome = tokio::time::timeout(std::time::Duration::from_secs(5), waiting)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(outcome.event.cancelled);
+        assert!(approvals.pending().is_empty());
+    }
+
+    #[tokio::test]
+    async fn policies_apply_to_clients_but_not_the_app() {
+        let temp_dir = TempDir::new().unwrap();
+        let state = create_state(&temp_dir).await;
+        state
+            .read()
+            .await
+            .tool_policies
+            .add(tool_policies::NewToolPolicy {
+                client_id: None,
+                project_id: None,
+                rules: tool_policies::ToolPolicyRules {
+                    deny_tools: vec!["todo_add".to_string()],
+                    ..tool_policies::ToolPolicyRules::default()
+                },
+            })
+            .unwrap();
+
+        let denied = dispatch(&state, call("todo_add", json!({ "title": "x" }), client("c1"))).await;
+        assert!(denied.result.unwrap_err().starts_with("Denied by policy"));
+
+        let allowed = dispatch(&state, call("todo_add", json!({ "title": "x" }), Caller::App)).await;
+        assert!(allowed.result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn policy_request_resolves_paths_and_build_commands() {
+        let temp_dir = TempDir::new().unwrap();
+        let state = create_state(&temp_dir).await;
+        let ctx = state
+            .read()
+            .await
+            .tool_context("default", Caller::App)
+            .await
+            .unwrap();
+        let command = {
+            let store = ctx.store.build_command_store.read().await;
+            store.add("test", "cargo test", None).await.unwrap()
+        };
+
+        let request = policy_request(
+            &ctx,
+            "read_file",
+            &json!({ "path": format!("{}/src/./lib.rs", ctx.root_path()) }),
+        )
+        .await;
+        assert_eq!(request.paths, vec!["src/lib.rs".to_string()]);
+
+        let request = policy_request(
+            &ctx,
+            "git_diff",
+            &json!({ "paths": ["secrets/.env", "src/lib.rs"] }),
+        )
+        .await;
+        assert_eq!(request.paths, vec!["secrets/.env".to_string(), "src/lib.rs".to_string()]);
+
+        let request = policy_request(&ctx, "build_run_command", &json!({ "id": command.id })).await;
+        assert_eq!(request.command.as_deref(), Some("cargo test"));
+
+        let request = policy_request(
+            &ctx,
+            "process_start",
+            &json!({ "command": "cargo watch", "env": { "RUSTC_WRAPPER": "./evil" } }),
+        )
+        .await;
+        assert_eq!(request.env, vec!["RUSTC_WRAPPER".to_string()]);
+        assert!(!request.stdin);
+        let request =
+            policy_request(&ctx, "run_command", &json!({ "command": "cat", "stdin": "x" })).await;
+        assert!(request.env.is_empty());
+        assert!(request.stdin);
+
+        let request =
+            policy_request(&ctx, "terminal_send", &json!({ "id": "t1", "input": "rm -rf target" })).await;
+        assert_eq!(request.command.as_deref(), Some("rm -rf target"));
+
+        let request =
+            policy_request(&ctx, "terminal_open", &json!({ "shell": "/usr/bin/python3" })).await;
+        assert_eq!(request.command.as_deref(), Some("/usr/bin/python3"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn policy_request_follows_symlinks() {
+        let temp_dir = TempDir::new().unwrap();
+        let state = create_state(&temp_dir).await;
+        let ctx = state
+            .read()
+            .await
+            .tool_context("default", Caller::App)
+            .await
+            .unwrap();
+        std::fs::create_dir(ctx.root().join("secrets")).unwrap();
+        std::fs::write(ctx.root().join("secrets/key"), "k").unwrap();
+        std::os::unix::fs::symlink(ctx.root().join("secrets"), ctx.root().join("link")).unwrap();
+
+        let request = policy_request(&ctx, "read_file", &json!({ "path": "link/key" })).await;
+        assert_eq!(request.paths, vec!["secrets/key".to_string()]);
+        let rules = crate::tool_policies::ToolPolicyRules {
+            deny_paths: vec!["secrets/**".to_string()],
+            ..Default::default()
+        };
+        assert!(rules.check(&request).is_err());
+    }
+}
@@ -0,0 +1,468 @@
+//! Persistent tool-call history for projects.
+//!
+//! Every tool call is written to the project's `project.db`, with an FTS5
+//! index over tool names, arguments and output for full-text search.
+
+use crate::{build_commands::ensure_column, error::ContextError, ToolCallEvent};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Events kept per project before the oldest are pruned
+pub const DEFAULT_MAX_EVENTS: usize = 10_000;
+/// Output stored per call; the rest is cut off
+pub const MAX_STORED_CONTENT: usize = 64 * 1024;
+/// Page size when a query does not set `limit`
+pub const DEFAULT_PAGE_SIZE: usize = 100;
+/// Largest page a query may request
+pub const MAX_PAGE_SIZE: usize = 1000;
+
+/// Filters and pagination for a history query
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ToolCallQuery {
+    /// Only calls to this tool
+    #[serde(default)]
+    pub tool: Option<String>,
+    /// Only calls to one of these tools (set by the server to hide tools a
+    /// client's policy denies, never by the client)
+    #[serde(skip)]
+    pub tools: Option<Vec<String>>,
+    /// Only calls at or after this RFC 3339 timestamp (or date prefix)
+    #[serde(default)]
+    pub since: Option<String>,
+    /// Only successful (`true`) or failed (`false`) calls
+    #[serde(default)]
+    pub success: Option<bool>,
+    /// Full-text search over tool name, arguments and output
+    #[serde(default)]
+    pub search: Option<String>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+    #[serde(default)]
+    pub offset: Option<usize>,
+}
+
+/// One page of history, newest first
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolCallPage {
+    pub events: Vec<ToolCallEvent>,
+    /// Number of events matching the filters, across all pages
+    pub total: usize,
+    pub limit: usize,
+    pub offset: usize,
+}
+
+pub struct ToolCallHistoryStore {
+    db_path: String,
+    max_events: usize,
+    /// Calls recorded since the store was opened, to prune every so often
+    recorded: AtomicUsize,
+}
+
+impl ToolCallHistoryStore {
+    /// Open the store with the default retention
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database can't be opened
+    pub fn new(db_path: &str) -> Result<Self, ContextError> {
+        Self::with_retention(db_path, DEFAULT_MAX_EVENTS)
+    }
+
+    /// Open the store, keeping at most `max_events` calls
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database can't be opened
+    pub fn with_retention(db_path: &str, max_events: usize) -> Result<Self, ContextError> {
+        let store = Self {
+            db_path: db_path.to_string(),
+            max_events,
+            recorded: AtomicUsize::new(0),
+        };
+        store.init_schema()?;
+        Ok(store)
+    }
+
+    fn get_db(&self) -> Result<rusqlite::Connection, ContextError> {
+        Ok(rusqlite::Connection::open(&self.db_path)?)
+    }
+
+    fn init_schema(&self) -> Result<(), ContextError> {
+        let db = self.get_db()?;
+        db.execute_batch(
+            "CREATE TABLE IF NOT EXISTS tool_calls (
+                id TEXT PRIMARY KEY,
+                timestamp TEXT NOT NULL,
+                tool_name TEXT NOT NULL,
+                project_id TEXT NOT NULL,
+                arguments TEXT NOT NULL,
+                success INTEGER NOT NULL,
+                content TEXT NOT NULL,
+                duration_ms INTEGER NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_tool_calls_timestamp ON tool_calls(timestamp);
+            CREATE INDEX IF NOT EXISTS idx_tool_calls_tool ON tool_calls(tool_name);
+            CREATE VIRTUAL TABLE IF NOT EXISTS tool_calls_fts USING fts5(
+                tool_name, arguments, content,
+                content = 'tool_calls', content_rowid = 'rowid'
+            );
+            CREATE TRIGGER IF NOT EXISTS tool_calls_fts_insert AFTER INSERT ON tool_calls BEGIN
+                INSERT INTO tool_calls_fts(rowid, tool_name, arguments, content)
+                VALUES (new.rowid, new.tool_name, new.arguments, new.content);
+            END;
+            CREATE TRIGGER IF NOT EXISTS tool_calls_fts_delete AFTER DELETE ON tool_calls BEGIN
+                INSERT INTO tool_calls_fts(tool_calls_fts, rowid, tool_name, arguments, content)
+                VALUES ('delete', old.rowid, old.tool_name, old.arguments, old.content);
+            END;",
+        )?;
+        ensure_column(&db, "tool_calls", "approval", "TEXT")?;
+        ensure_column(&db, "tool_calls", "cancelled", "INTEGER NOT NULL DEFAULT 0")?;
+        Ok(())
+    }
+
+    /// Store a tool call, with its output cut to [`MAX_STORED_CONTENT`].
+    ///
+    /// Calls beyond the retention limit are pruned on the first call and then
+    /// every tenth of the limit (at most every 100 calls), so the table may
+    /// briefly hold a few more than `max_events`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the call can't be saved
+    pub fn record(&self, event: &ToolCallEvent) -> Result<(), ContextError> {
+        let db = self.get_db()?;
+        db.execute(
+            "INSERT INTO tool_calls
+             (id, timestamp, tool_name, project_id, arguments, success, content, duration_ms, approval,
+              cancelled)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            rusqlite::params![
+                event.id,
+                event.timestamp,
+                event.tool_name,
+                event.project_id,
+                event.arguments.to_string(),
+                i64::from(event.success),
+                truncate_content(&event.content),
+                i64::try_from(event.duration_ms).unwrap_or(i64::MAX),
+                event
+                    .approval
+                    .as_ref()
+                    .and_then(|approval| serde_json::to_string(approval).ok()),
+                i64::from(event.cancelled),
+            ],
+        )?;
+
+        let prune_every = (self.max_events / 10).clamp(1, 100);
+        if self.recorded.fetch_add(1, Ordering::Relaxed) % prune_every == 0 {
+            // Rows are inserted in call order, so the newest rowids are kept
+            db.execute(
+                "DELETE FROM tool_calls WHERE rowid < (
+                    SELECT rowid FROM tool_calls ORDER BY rowid DESC LIMIT 1 OFFSET ?1
+                )",
+                [i64::try_from(self.max_events.saturating_sub(1)).unwrap_or(i64::MAX)],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Names of the tools with recorded calls
+    ///
+    /// # Errors
+    ///
+    /// Returns `ContextError` if the database can't be read
+    pub fn tool_names(&self) -> Result<Vec<String>, ContextError> {
+        let db = self.get_db()?;
+        let mut stmt = db.prepare("SELECT DISTINCT tool_name FROM tool_calls ORDER BY tool_name")?;
+        let names = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| ContextError::Database(e.to_string()))?;
+        Ok(names)
+    }
+
+    /// Find calls matching `query`, newest first.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database query fails
+    pub fn query(&self, query: &ToolCallQuery) -> Result<ToolCallPage, ContextError> {
+        let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+        let offset = query.offset.unwrap_or(0);
+
+        let mut clauses = Vec::new();
+        let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
+        if let Some(tool) = &query.tool {
+            params.push(Box::new(tool.clone()));
+            clauses.push(format!("tool_name = ?{}", params.len()));
+        }
+        if let Some(tools) = &query.tools {
+            let placeholders: Vec<String> = tools
+                .iter()
+                .map(|tool| {
+                    params.push(Box::new(tool.clone()));
+                    format!("?{}", params.len())
+                })
+                .collect();
+            clauses.push(format!("tool_name IN ({})", placeholders.join(", ")));
+        }
+        if let Some(since) = &query.since {
+            params.push(Box::new(normalize_timestamp(since)));
+            clauses.push(format!("timestamp >= ?{}", params.len()));
+        }
+        if let Some(success) = query.success {
+            params.push(Box::new(i64::from(success)));
+            clauses.push(format!("success = ?{}", params.len()));
+        }
+        if let Some(search) = query.search.as_deref().map(fts_phrase).filter(|s| !s.is_empty()) {
+            params.push(Box::new(search));
+            clauses.push(format!(
+                "rowid IN (SELECT rowid FROM tool_calls_fts WHERE tool_calls_fts MATCH ?{})",
+                params.len()
+            ));
+        }
+        let filter = if clauses.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", clauses.join(" AND "))
+        };
+
+        let db = self.get_db()?;
+        let total: i64 = db.query_row(
+            &format!("SELECT COUNT(*) FROM tool_calls {filter}"),
+            rusqlite::params_from_iter(params.iter()),
+            |row| row.get(0),
+        )?;
+
+        let mut stmt = db.prepare(&format!(
+            "SELECT id, timestamp, tool_name, project_id, arguments, success, content, duration_ms,
+                    approval, cancelled
+             FROM tool_calls {filter}
+             ORDER BY timestamp DESC, rowid DESC
+             LIMIT {limit} OFFSET {offset}"
+        ))?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(params.iter()), |row| {
+            Ok(ToolCallEvent {
+                id: row.get(0)?,
+                timestamp: row.get(1)?,
+                tool_name: row.get(2)?,
+                project_id: row.get(3)?,
+                arguments: serde_json::from_str(&row.get::<_, String>(4)?)
+                    .unwrap_or(serde_json::Value::Null),
+                success: row.get::<_, i64>(5)? != 0,
+                content: row.get(6)?,
+                duration_ms: u64::try_from(row.get::<_, i64>(7)?).unwrap_or(0),
+                approval: row
+                    .get::<_, Option<String>>(8)?
+                    .and_then(|json| serde_json::from_str(&json).ok()),
+                cancelled: row.get::<_, i64>(9)? != 0,
+            })
+        })?;
+        let events = rows
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| ContextError::Database(e.to_string()))?;
+
+        Ok(ToolCallPage {
+            events,
+            total: usize::try_from(total).unwrap_or(0),
+            limit,
+            offset,
+        })
+    }
+}
+
+/// Cut stored output to [`MAX_STORED_CONTENT`] bytes (on a character
+/// boundary), noting how much was left out.
+fn truncate_content(content: &str) -> std::borrow::Cow<'_, str> {
+    if content.len() <= MAX_STORED_CONTENT {
+        return content.into();
+    }
+    let mut end = MAX_STORED_CONTENT;
+    while !content.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!(
+        "{}\n[... {} more bytes not stored]",
+        &content[..end],
+        content.len() - end
+    )
+    .into()
+}
+
+/// Convert parseable timestamps to the stored UTC RFC 3339 form; pass others through.
+fn normalize_timestamp(value: &str) -> String {
+    chrono::DateTime::parse_from_rfc3339(value).map_or_else(
+        |_| value.to_string(),
+        |t| t.with_timezone(&chrono::Utc).to_rfc3339(),
+    )
+}
+
+/// Quote each search term so user input is never parsed as FTS5 syntax
+fn fts_phrase(search: &str) -> String {
+    search
+        .split_whitespace()
+        .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn create_store(temp: &TempDir, max_events: usize) -> ToolCallHistoryStore {
+        let db_path = temp.path().join("project.db");
+        ToolCallHistoryStore::with_retention(db_path.to_str().unwrap(), max_events).unwrap()
+    }
+
+    fn event(id: &str, minute: u32, tool: &str, success: bool, content: &str) -> ToolCallEvent {
+        ToolCallEvent {
+            id: id.to_string(),
+            timestamp: format!("2026-01-01T00:{minute:02}:00+00:00"),
+            tool_name: tool.to_string(),
+            project_id: "p1".to_string(),
+            arguments: serde_json::json!({ "path": format!("{id}.txt") }),
+            success,
+            content: content.to_string(),
+            duration_ms: 5,
+            approval: None,
+            cancelled: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn record_then_query_returns_newest_first() {
+        let temp = TempDir::new().unwrap();
+        let store = create_store(&temp, DEFAULT_MAX_EVENTS);
+        store
+            .record(&event("a", 1, "read_file", true, "one"))
+            .unwrap();
+        store
+            .record(&event("b", 2, "write_file", true, "two"))
+            .unwrap();
+
+        let page = store.query(&ToolCallQuery::default()).unwrap();
+        assert_eq!(page.total, 2);
+        assert_eq!(page.events[0].id, "b");
+        assert_eq!(page.events[1].arguments["path"], "a.txt");
+    }
+
+    #[tokio::test]
+    async fn query_filters_and_paginates() {
+        let temp = TempDir::new().unwrap();
+        let store = create_store(&temp, DEFAULT_MAX_EVENTS);
+        store.record(&event("a", 1, "read_file", true, "")).unwrap();
+        store
+            .record(&event("b", 2, "read_file", false, ""))
+            .unwrap();
+        store.record(&event("c", 3, "read_file", true, "")).unwrap();
+        store
+            .record(&event("d", 4, "write_file", true, ""))
+            .unwrap();
+
+        let query = ToolCallQuery {
+            tool: Some("read_file".to_string()),
+            success: Some(true),
+            ..ToolCallQuery::default()
+        };
+        let page = store.query(&query).unwrap();
+        assert_eq!(page.events.iter().map(|e| e.id.as_str()).collect::<Vec<_>>(), ["c", "a"]);
+
+        let query = ToolCallQuery {
+            tools: Some(vec!["write_file".to_string(), "list_directory".to_string()]),
+            ..ToolCallQuery::default()
+        };
+        let page = store.query(&query).unwrap();
+        assert_eq!(page.events.iter().map(|e| e.id.as_str()).collect::<Vec<_>>(), ["d"]);
+        assert_eq!(store.tool_names().unwrap(), ["read_file", "write_file"]);
+
+        let query = ToolCallQuery {
+            since: Some("2026-01-01T00:02:00Z".to_string()),
+            limit: Some(1),
+            offset: Some(1),
+            ..ToolCallQuery::default()
+        };
+        let page = store.query(&query).unwrap();
+        assert_eq!(page.total, 3);
+        assert_eq!(page.events.len(), 1);
+        assert_eq!(page.events[0].id, "c");
+    }
+
+    #[tokio::test]
+    async fn query_searches_arguments_and_output() {
+        let temp = TempDir::new().unwrap();
+        let store = create_store(&temp, DEFAULT_MAX_EVENTS);
+        store
+            .record(&event("config", 1, "read_file", true, "port = 8787"))
+            .unwrap();
+        store
+            .record(&event("notes", 2, "read_file", true, "hello"))
+            .unwrap();
+
+        let by_output = ToolCallQuery {
+            search: Some("8787".to_string()),
+            ..ToolCallQuery::default()
+        };
+        let page = store.query(&by_output).unwrap();
+        assert_eq!(page.events.len(), 1);
+        assert_eq!(page.events[0].id, "config");
+
+        // Stray quotes are treated as text, not FTS5 syntax
+        let by_argument = ToolCallQuery {
+            search: Some("notes \"txt".to_string()),
+            ..ToolCallQuery::default()
+        };
+        let page = store.query(&by_argument).unwrap();
+        assert_eq!(page.total, 1);
+        assert_eq!(page.events[0].id, "notes");
+    }
+
+    #[tokio::test]
+    async fn record_prunes_beyond_retention_limit() {
+        let temp = TempDir::new().unwrap();
+        let store = create_store(&temp, 2);
+        store
+            .record(&event("a", 1, "read_file", true, "old"))
+            .unwrap();
+        store.record(&event("b", 2, "read_file", true, "")).unwrap();
+        store.record(&event("c", 3, "read_file", true, "")).unwrap();
+
+        let page = store.query(&ToolCallQuery::default()).unwrap();
+        assert_eq!(page.events.iter().map(|e| e.id.as_str()).collect::<Vec<_>>(), ["c", "b"]);
+
+        let search = ToolCallQuery {
+            search: Some("old".to_string()),
+            ..ToolCallQuery::default()
+        };
+        assert_eq!(store.query(&search).unwrap().total, 0);
+    }
+
+    #[tokio::test]
+    async fn record_prunes_in_batches_and_truncates_output() {
+        let temp = TempDir::new().unwrap();
+        let store = create_store(&temp, 20);
+        for minute in 0..22 {
+            store
+                .record(&event(&minute.to_string(), minute, "read_file", true, ""))
+                .unwrap();
+        }
+        // Pruned on the 21st call (every 2), so the 22nd is extra until the next
+        assert_eq!(store.query(&ToolCallQuery::default()).unwrap().total, 21);
+        store
+            .record(&event("last", 30, "read_file", true, ""))
+            .unwrap();
+        let page = store.query(&ToolCallQuery::default()).unwrap();
+        assert_eq!(page.total, 20);
+        assert_eq!(page.events.last().unwrap().id, "3");
+
+        let long = "é".repeat(MAX_STORED_CONTENT);
+        store
+            .record(&event("long", 40, "read_file", true, &long))
+            .unwrap();
+        let page = store.query(&ToolCallQuery::default()).unwrap();
+        let stored = &page.events[0].content;
+        assert!(stored.len() < MAX_STORED_CONTENT + 100);
+        assert!(stored.ends_with(&format!("[... {} more bytes not stored]", long.len() - MAX_STORED_CONTENT)));
+    }
+}
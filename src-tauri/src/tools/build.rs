@@ -0,0 +1,482 @@
+//! Build command tools for AIHarness
+//!
+//! Manage and run the calling project's saved build commands.
+
+use super::shell::{own_process_group, shell_command, GroupKillGuard};
+use super::{require_str, ProgressReporter, Tool, ToolAnnotations, ToolContext, ToolResult};
+use crate::error::ToolError;
+use async_trait::async_trait;
+use serde_json::json;
+use std::process::Stdio;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, BufReader};
+
+/// Run a shell command in `working_dir`, returning combined stdout/stderr.
+///
+/// # Errors
+///
+/// Returns the output (with exit status) if the command fails, or a message
+/// if it cannot be started
+pub async fn run_shell_command(command: &str, working_dir: &str) -> Result<String, String> {
+    run_shell_command_with_progress(command, working_dir, &ProgressReporter::default()).await
+}
+
+/// Like [`run_shell_command`], reporting each line of output as progress.
+///
+/// The command and everything it started are killed if the returned future
+/// is dropped, which is how a cancelled call stops a running command.
+///
+/// # Errors
+///
+/// Returns the output (with exit status) if the command fails, or a message
+/// if it cannot be started
+pub async fn run_shell_command_with_progress(
+    command: &str,
+    working_dir: &str,
+    progress: &ProgressReporter,
+) -> Result<String, String> {
+    let mut cmd = shell_command(command);
+    cmd.current_dir(working_dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    own_process_group(&mut cmd);
+
+    let mut child = cmd
+        .spawn()
+        .map_err(|e| format!("Failed to run command: {e}"))?;
+    let group = GroupKillGuard::new(child.id());
+    let mut stdout_reader = child.stdout.take().map(BufReader::new);
+    let mut stderr_reader = child.stderr.take().map(BufReader::new);
+
+    let mut stdout = Vec::new();
+    let mut stderr = Vec::new();
+    // Partial lines stay in these across iterations (`read_until` is cancel safe)
+    let mut stdout_line = Vec::new();
+    let mut stderr_line = Vec::new();
+    let mut lines = 0usize;
+    while stdout_reader.is_some() || stderr_reader.is_some() {
+        let (read, from_stdout) = tokio::select! {
+            read = read_line(&mut stdout_reader, &mut stdout_line), if stdout_reader.is_some() => (read, true),
+            read = read_line(&mut stderr_reader, &mut stderr_line), if stderr_reader.is_some() => (read, false),
+        };
+        let (output, line) = if from_stdout {
+            (&mut stdout, &mut stdout_line)
+        } else {
+            (&mut stderr, &mut stderr_line)
+        };
+        if !matches!(read, Ok(n) if n > 0) {
+            output.append(line);
+            if from_stdout {
+                stdout_reader = None;
+            } else {
+                stderr_reader = None;
+            }
+            continue;
+        }
+        lines += 1;
+        if progress.is_enabled() {
+            let text = String::from_utf8_lossy(line).trim_end().to_string();
+            progress.report_count(lines, Some(text));
+        }
+        output.append(line);
+    }
+    let status = child
+        .wait()
+        .await
+        .map_err(|e| format!("Failed to run command: {e}"))?;
+    group.disarm();
+
+    let stdout = String::from_utf8_lossy(&stdout).to_string();
+    let stderr = String::from_utf8_lossy(&stderr).to_string();
+
+    let combined = if stderr.is_empty() {
+        stdout.clone()
+    } else if stdout.is_empty() {
+        stderr.clone()
+    } else {
+        format!("{stdout}\n{stderr}")
+    };
+
+    if status.success() {
+        Ok(combined)
+    } else {
+        Err(format!("Command failed ({status}): {combined}"))
+    }
+}
+
+/// Read one line (with its newline) from an output pipe that is still open
+async fn read_line<R: AsyncBufRead + Unpin>(
+    reader: &mut Option<R>,
+    line: &mut Vec<u8>,
+) -> std::io::Result<usize> {
+    match reader {
+        Some(reader) => reader.read_until(b'\n', line).await,
+        None => Ok(0),
+    }
+}
+
+fn id_schema() -> serde_json::Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "id": { "type": "string" }
+        },
+        "required": ["id"]
+    })
+}
+
+fn empty_schema() -> serde_json::Value {
+    json!({
+        "type": "object",
+        "properties": {}
+    })
+}
+
+pub struct BuildAddCommandTool;
+
+#[async_trait]
+impl Tool for BuildAddCommandTool {
+    fn name(&self) -> &'static str {
+        "build_add_command"
+    }
+
+    fn description(&self) -> &'static str {
+        "Add a build command to the project."
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "name": { "type": "string" },
+                "command": { "type": "string" },
+                "working_dir": { "type": "string" }
+            },
+            "required": ["name", "command"]
+        })
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        ToolAnnotations::write("Add build command", false, false)
+    }
+
+    async fn execute(&self, _args: serde_json::Value) -> Result<ToolResult, ToolError> {
+        Err(ToolError::ProjectRequired(self.name().to_string()))
+    }
+
+    async fn execute_with_context(
+        &self,
+        args: serde_json::Value,
+        ctx: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        let name = require_str(&args, "name")?;
+        let command = require_str(&args, "command")?;
+        let working_dir = args
+            .get("working_dir")
+            .and_then(|v| v.as_str())
+            .map(ToString::to_string);
+        let command = ctx
+            .store
+            .build_command_store
+            .read()
+            .await
+            .add(name, command, working_dir)
+            .await?;
+        Ok(ToolResult::success(
+            serde_json::to_string(&command).unwrap_or_default(),
+        ))
+    }
+}
+
+pub struct BuildRemoveCommandTool;
+
+#[async_trait]
+impl Tool for BuildRemoveCommandTool {
+    fn name(&self) -> &'static str {
+        "build_remove_command"
+    }
+
+    fn description(&self) -> &'static str {
+        "Remove a build command by id."
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        id_schema()
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        ToolAnnotations::write("Remove build command", true, true)
+    }
+
+    async fn execute(&self, _args: serde_json::Value) -> Result<ToolResult, ToolError> {
+        Err(ToolError::ProjectRequired(self.name().to_string()))
+    }
+
+    async fn execute_with_context(
+        &self,
+        args: serde_json::Value,
+        ctx: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        let id = require_str(&args, "id")?;
+        ctx.store.build_command_store.read().await.remove(id).await?;
+        Ok(ToolResult::success("ok"))
+    }
+}
+
+pub struct BuildListCommandsTool;
+
+#[async_trait]
+impl Tool for BuildListCommandsTool {
+    fn name(&self) -> &'static str {
+        "build_list_commands"
+    }
+
+    fn description(&self) -> &'static str {
+        "List build commands for the project."
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        empty_schema()
+    }
+
+    fn output_schema(&self) -> Option<serde_json::Value> {
+        Some(json!({
+            "type": "object",
+            "properties": {
+                "commands": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "id": { "type": "string" },
+                            "name": { "type": "string" },
+                            "command": { "type": "string" },
+                            "working_dir": { "type": ["string", "null"] },
+                            "is_default": { "type": "boolean" },
+                            "created_at": { "type": "string" }
+                        },
+                        "required": ["id", "name", "command", "is_default"]
+                    }
+                }
+            },
+            "required": ["commands"]
+        }))
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        ToolAnnotations::read_only("List build commands")
+    }
+
+    async fn execute(&self, _args: serde_json::Value) -> Result<ToolResult, ToolError> {
+        Err(ToolError::ProjectRequired(self.name().to_string()))
+    }
+
+    async fn execute_with_context(
+        &self,
+        _args: serde_json::Value,
+        ctx: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        let list = ctx.store.build_command_store.read().await.list().await?;
+        Ok(ToolResult::success_with_data(
+            serde_json::to_string(&list).unwrap_or_default(),
+            json!({ "commands": list }),
+        ))
+    }
+}
+
+pub struct BuildRunCommandTool;
+
+#[async_trait]
+impl Tool for BuildRunCommandTool {
+    fn name(&self) -> &'static str {
+        "build_run_command"
+    }
+
+    fn description(&self) -> &'static str {
+        "Run a build command by id. Each line of output is reported as progress."
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        id_schema()
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        ToolAnnotations::write("Run build command", true, false)
+    }
+
+    async fn execute(&self, _args: serde_json::Value) -> Result<ToolResult, ToolError> {
+        Err(ToolError::ProjectRequired(self.name().to_string()))
+    }
+
+    async fn execute_with_context(
+        &self,
+        args: serde_json::Value,
+        ctx: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        let id = require_str(&args, "id")?;
+        let command = ctx
+            .store
+            .build_command_store
+            .read()
+            .await
+            .get(id)
+            .await?
+            .ok_or_else(|| ToolError::InvalidArguments(format!("Build command not found: {id}")))?;
+        let working_dir = command
+            .working_dir
+            .unwrap_or_else(|| ctx.root_path().to_string());
+        let output = run_shell_command_with_progress(&command.command, &working_dir, &ctx.progress)
+            .await
+            .map_err(ToolError::CommandFailed)?;
+        Ok(ToolResult::success(output))
+    }
+}
+
+pub struct BuildSetDefaultTool;
+
+#[async_trait]
+impl Tool for BuildSetDefaultTool {
+    fn name(&self) -> &'static str {
+        "build_set_default"
+    }
+
+    fn description(&self) -> &'static str {
+        "Set the default build command by id."
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        id_schema()
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        ToolAnnotations::write("Set default build command", false, true)
+    }
+
+    async fn execute(&self, _args: serde_json::Value) -> Result<ToolResult, ToolError> {
+        Err(ToolError::ProjectRequired(self.name().to_string()))
+    }
+
+    async fn execute_with_context(
+        &self,
+        args: serde_json::Value,
+        ctx: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        let id = require_str(&args, "id")?;
+        ctx.store.build_command_store.read().await.set_default(id).await?;
+        Ok(ToolResult::success("ok"))
+    }
+}
+
+pub struct BuildGetDefaultTool;
+
+#[async_trait]
+impl Tool for BuildGetDefaultTool {
+    fn name(&self) -> &'static str {
+        "build_get_default"
+    }
+
+    fn description(&self) -> &'static str {
+        "Get the default build command."
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        empty_schema()
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        ToolAnnotations::read_only("Get default build command")
+    }
+
+    async fn execute(&self, _args: serde_json::Value) -> Result<ToolResult, ToolError> {
+        Err(ToolError::ProjectRequired(self.name().to_string()))
+    }
+
+    async fn execute_with_context(
+        &self,
+        _args: serde_json::Value,
+        ctx: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        let command = ctx.store.build_command_store.read().await.get_default().await?;
+        Ok(ToolResult::success(
+            serde_json::to_string(&command).unwrap_or_default(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::context::test_context;
+    use tempfile::TempDir;
+
+    #[cfg(not(target_os = "windows"))]
+    #[tokio::test]
+    async fn build_run_command_runs_in_project_root() {
+        let temp = TempDir::new().unwrap();
+        std::fs::write(temp.path().join("marker.txt"), "here").unwrap();
+        let ctx = test_context(temp.path()).await;
+
+        let added = BuildAddCommandTool
+            .execute_with_context(json!({ "name": "cat", "command": "cat marker.txt" }), &ctx)
+            .await
+            .unwrap();
+        let command: serde_json::Value = serde_json::from_str(&added.content).unwrap();
+
+        let result = BuildRunCommandTool
+            .execute_with_context(json!({ "id": command["id"] }), &ctx)
+            .await
+            .unwrap();
+        assert_eq!(result.content, "here");
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[tokio::test]
+    async fn run_shell_command_reports_output_lines_as_progress() {
+        let temp = TempDir::new().unwrap();
+        let updates = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = updates.clone();
+        let progress = ProgressReporter::new(move |update| sink.lock().unwrap().push(update));
+
+        let output = run_shell_command_with_progress(
+            "echo one; echo two; echo oops >&2",
+            temp.path().to_str().unwrap(),
+            &progress,
+        )
+        .await
+        .unwrap();
+        assert_eq!(output, "one\ntwo\n\noops\n");
+
+        let updates = updates.lock().unwrap();
+        assert_eq!(updates.len(), 3);
+        assert!((updates[2].progress - 3.0).abs() < f64::EPSILON);
+        assert!(updates.iter().any(|u| u.message.as_deref() == Some("oops")));
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[tokio::test]
+    async fn build_run_command_reports_failures() {
+        let temp = TempDir::new().unwrap();
+        let ctx = test_context(temp.path()).await;
+        let command = ctx
+            .store
+            .build_command_store
+            .read()
+            .await
+            .add("fail", "exit 3", None)
+            .await
+            .unwrap();
+
+        let err = BuildRunCommandTool
+            .execute_with_context(json!({ "id": command.id }), &ctx)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ToolError::CommandFailed(_)));
+
+        let err = BuildRunCommandTool
+            .execute_with_context(json!({ "id": "missing" }), &ctx)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Build command not found"));
+    }
+}
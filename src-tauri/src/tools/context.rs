@@ -0,0 +1,147 @@
+//! Execution context for project-scoped tool calls
+//!
+//! Carries the project a call runs against (its stores and path sandbox),
+//! who made the call, the app's terminal sessions, and the call's progress
+//! reporter and cancellation token.
+
+use super::PathSandbox;
+use crate::projects::ProjectStore;
+use crate::terminals::TerminalManager;
+use std::path::Path;
+use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+
+/// Who is calling a tool
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Caller {
+    /// The app's own UI
+    App,
+    /// An HTTP/MCP client authenticated by a client token
+    Client { id: String, name: String },
+}
+
+impl Caller {
+    /// Client token id, if the call came from an HTTP/MCP client
+    #[must_use]
+    pub fn client_id(&self) -> Option<&str> {
+        match self {
+            Self::App => None,
+            Self::Client { id, .. } => Some(id),
+        }
+    }
+}
+
+/// A progress update from a running tool
+#[derive(Debug, Clone, PartialEq)]
+pub struct Progress {
+    /// Work done so far (increases with every update)
+    pub progress: f64,
+    /// Total work, if known
+    pub total: Option<f64>,
+    pub message: Option<String>,
+}
+
+/// Sends a running call's progress to whoever asked for it
+///
+/// The default reporter discards updates.
+#[derive(Clone, Default)]
+pub struct ProgressReporter {
+    sink: Option<Arc<dyn Fn(Progress) + Send + Sync>>,
+}
+
+impl ProgressReporter {
+    /// Reporter that hands every update to `sink`
+    pub fn new(sink: impl Fn(Progress) + Send + Sync + 'static) -> Self {
+        Self {
+            sink: Some(Arc::new(sink)),
+        }
+    }
+
+    /// Whether anyone receives the updates
+    #[must_use]
+    pub fn is_enabled(&self) -> bool {
+        self.sink.is_some()
+    }
+
+    /// Send an update (a no-op if nobody is listening)
+    pub fn report(&self, progress: f64, total: Option<f64>, message: Option<String>) {
+        if let Some(sink) = &self.sink {
+            sink(Progress {
+                progress,
+                total,
+                message,
+            });
+        }
+    }
+
+    /// Send a count of lines, files or bytes done so far, with no known total
+    pub fn report_count(&self, done: usize, message: Option<String>) {
+        // Counts up to u32::MAX convert exactly; larger ones stay there
+        let done = u32::try_from(done).unwrap_or(u32::MAX);
+        self.report(f64::from(done), None, message);
+    }
+}
+
+impl std::fmt::Debug for ProgressReporter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProgressReporter")
+            .field("enabled", &self.is_enabled())
+            .finish()
+    }
+}
+
+/// Everything a tool needs to run against a project
+#[derive(Clone)]
+pub struct ToolContext {
+    /// Project the call runs against
+    pub project_id: String,
+    /// The project's stores (todos, build commands, briefing, ...)
+    pub store: Arc<ProjectStore>,
+    /// File sandbox for the project root and its allowed roots
+    pub sandbox: PathSandbox,
+    /// Who made the call
+    pub caller: Caller,
+    /// Persistent terminal sessions (shared by all projects)
+    pub terminals: Arc<TerminalManager>,
+    /// Where long-running tools report progress
+    pub progress: ProgressReporter,
+    /// Cancelled when the caller gives up on the call
+    pub cancel: CancellationToken,
+}
+
+impl ToolContext {
+    /// The project root as registered (not canonicalized)
+    #[must_use]
+    pub fn root_path(&self) -> &str {
+        &self.store.info.root_path
+    }
+
+    /// The canonical project root
+    #[must_use]
+    pub fn root(&self) -> &Path {
+        self.sandbox.root()
+    }
+}
+
+/// Context for a throwaway project rooted at `root`, for tool tests
+#[cfg(test)]
+pub(crate) async fn test_context(root: &Path) -> ToolContext {
+    let now = chrono::Utc::now();
+    let info = crate::projects::ProjectInfo {
+        id: "test".to_string(),
+        name: "Test".to_string(),
+        root_path: root.to_string_lossy().to_string(),
+        db_path: root.join("project.db").to_string_lossy().to_string(),
+        created_at: now,
+        updated_at: now,
+    };
+    ToolContext {
+        project_id: info.id.clone(),
+        store: Arc::new(ProjectStore::new(info).await.unwrap()),
+        sandbox: PathSandbox::new(root, &[]).unwrap(),
+        caller: Caller::App,
+        terminals: Arc::new(TerminalManager::default()),
+        progress: ProgressReporter::default(),
+        cancel: CancellationToken::new(),
+    }
+}
@@ -0,0 +1,201 @@
+//! Content sniffing and text decoding for file tools
+//!
+//! Detects binary files (with a MIME type) and decodes text stored as
+//! UTF-8, UTF-16 (with BOM) or Latin-1.
+
+use encoding_rs::{Encoding, UTF_8, WINDOWS_1252};
+
+/// Bytes inspected when sniffing a file
+pub const SNIFF_LEN: usize = 8192;
+
+/// MIME type reported for binaries without a recognizable signature
+const FALLBACK_BINARY_MIME: &str = "application/octet-stream";
+
+/// What a file's leading bytes look like
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sniffed {
+    /// Text in the given encoding
+    Text(&'static Encoding),
+    /// Binary content with its MIME type
+    Binary(&'static str),
+}
+
+/// Classify content from its first bytes.
+///
+/// A byte-order mark wins; otherwise a known binary signature or a NUL byte
+/// marks the content as binary. Remaining content is UTF-8 when it decodes
+/// cleanly (ignoring a sequence cut off by the sample), else Latin-1.
+#[must_use]
+pub fn sniff(bytes: &[u8]) -> Sniffed {
+    let sample = &bytes[..bytes.len().min(SNIFF_LEN)];
+
+    if let Some((encoding, _)) = Encoding::for_bom(sample) {
+        return Sniffed::Text(encoding);
+    }
+    if let Some(kind) = infer::get(sample) {
+        if kind.matcher_type() != infer::MatcherType::Text {
+            return Sniffed::Binary(kind.mime_type());
+        }
+    }
+    if sample.contains(&0) {
+        return Sniffed::Binary(FALLBACK_BINARY_MIME);
+    }
+
+    match std::str::from_utf8(sample) {
+        Ok(_) => Sniffed::Text(UTF_8),
+        Err(e) if e.error_len().is_none() && sample.len() == SNIFF_LEN => Sniffed::Text(UTF_8),
+        Err(_) => Sniffed::Text(WINDOWS_1252),
+    }
+}
+
+/// Decode a whole file's bytes, or return the MIME type if it is binary.
+///
+/// Unlike [`sniff`], the UTF-8 check covers all of `bytes`, so a file whose
+/// invalid bytes come after the sample still decodes as Latin-1.
+///
+/// # Errors
+///
+/// Returns the detected MIME type when the content is binary
+pub fn decode(bytes: &[u8]) -> Result<(String, &'static Encoding), &'static str> {
+    let encoding = match sniff(bytes) {
+        Sniffed::Binary(mime) => return Err(mime),
+        Sniffed::Text(encoding) => match fallback(bytes, encoding) {
+            Some(fallback) if std::str::from_utf8(bytes).is_err() => fallback,
+            _ => encoding,
+        },
+    };
+
+    let (text, _) = encoding.decode_with_bom_removal(bytes);
+    Ok((text.into_owned(), encoding))
+}
+
+/// The encoding a file really is if bytes past its sniffed start turn out not
+/// to be valid in the sniffed `encoding`: Latin-1 for UTF-8 without a BOM.
+///
+/// `head` is the file's first bytes, as passed to [`sniff`].
+#[must_use]
+pub fn fallback(head: &[u8], encoding: &'static Encoding) -> Option<&'static Encoding> {
+    (encoding == UTF_8 && Encoding::for_bom(head).is_none()).then_some(WINDOWS_1252)
+}
+
+/// Decode a byte range of a file whose start sniffed as `encoding`.
+///
+/// A character cut at either edge of the range becomes U+FFFD; any other
+/// invalid UTF-8 means the file is in its `fallback` encoding after all.
+/// `at_start` ranges drop a byte-order mark.
+#[must_use]
+pub fn decode_range(
+    bytes: &[u8],
+    encoding: &'static Encoding,
+    fallback: Option<&'static Encoding>,
+    at_start: bool,
+) -> (String, &'static Encoding) {
+    let encoding = match fallback {
+        Some(fallback) if !is_utf8_between_cuts(bytes) => fallback,
+        _ => encoding,
+    };
+    let text = if at_start {
+        encoding.decode_with_bom_removal(bytes).0
+    } else {
+        encoding.decode_without_bom_handling(bytes).0
+    };
+    (text.into_owned(), encoding)
+}
+
+/// Whether `bytes` is valid UTF-8 apart from characters cut off at either end
+fn is_utf8_between_cuts(bytes: &[u8]) -> bool {
+    // A cut at the start leaves up to three continuation bytes
+    let start = bytes
+        .iter()
+        .take(3)
+        .take_while(|byte| *byte & 0xC0 == 0x80)
+        .count();
+    match std::str::from_utf8(&bytes[start..]) {
+        Ok(_) => true,
+        Err(e) => e.error_len().is_none(),
+    }
+}
+
+/// User-facing name for a detected encoding
+#[must_use]
+pub fn encoding_label(encoding: &'static Encoding) -> &'static str {
+    if encoding == WINDOWS_1252 {
+        "latin1"
+    } else {
+        encoding.name()
+    }
+}
+
+/// Best-effort MIME type for any content, text or binary
+#[must_use]
+pub fn mime_type(bytes: &[u8]) -> &'static str {
+    match sniff(bytes) {
+        Sniffed::Binary(mime) => mime,
+        Sniffed::Text(_) => infer::get(bytes).map_or("text/plain", |kind| kind.mime_type()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use encoding_rs::{UTF_16BE, UTF_16LE};
+
+    #[test]
+    fn sniff_detects_utf8_text() {
+        assert_eq!(sniff("héllo\n".as_bytes()), Sniffed::Text(UTF_8));
+    }
+
+    #[test]
+    fn sniff_detects_png_as_binary() {
+        let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+        assert_eq!(sniff(png), Sniffed::Binary("image/png"));
+    }
+
+    #[test]
+    fn sniff_treats_nul_bytes_as_binary() {
+        assert_eq!(sniff(b"abc\0def"), Sniffed::Binary(FALLBACK_BINARY_MIME));
+    }
+
+    #[test]
+    fn decode_handles_utf16_with_bom() {
+        let mut bytes = vec![0xFF, 0xFE];
+        for unit in "hi\n".encode_utf16() {
+            bytes.extend_from_slice(&unit.to_le_bytes());
+        }
+        let (text, encoding) = decode(&bytes).unwrap();
+        assert_eq!(text, "hi\n");
+        assert_eq!(encoding, UTF_16LE);
+
+        let mut bytes = vec![0xFE, 0xFF];
+        for unit in "ok".encode_utf16() {
+            bytes.extend_from_slice(&unit.to_be_bytes());
+        }
+        assert_eq!(decode(&bytes).unwrap(), ("ok".to_string(), UTF_16BE));
+    }
+
+    #[test]
+    fn decode_falls_back_to_latin1() {
+        let (text, encoding) = decode(b"caf\xe9").unwrap();
+        assert_eq!(text, "café");
+        assert_eq!(encoding_label(encoding), "latin1");
+    }
+
+    #[test]
+    fn decode_range_replaces_cut_characters_but_detects_latin1() {
+        let latin1 = fallback(b"plain", UTF_8);
+        assert_eq!(latin1, Some(WINDOWS_1252));
+        // "é" cut in half at both edges stays UTF-8
+        let (text, encoding) = decode_range(b"\xa9t\xc3", UTF_8, latin1, false);
+        assert_eq!((text.as_str(), encoding), ("\u{FFFD}t\u{FFFD}", UTF_8));
+        let (text, encoding) = decode_range(b"caf\xe9 au lait", UTF_8, latin1, false);
+        assert_eq!((text.as_str(), encoding), ("café au lait", WINDOWS_1252));
+        // With a BOM the file is UTF-8 whatever follows
+        assert_eq!(fallback(b"\xef\xbb\xbfhi", UTF_8), None);
+        assert_eq!(decode_range(b"\xef\xbb\xbfhi", UTF_8, None, true).0, "hi");
+    }
+
+    #[test]
+    fn decode_rejects_binary() {
+        assert_eq!(decode(b"%PDF-1.7\n\0\0"), Err("application/pdf"));
+    }
+}
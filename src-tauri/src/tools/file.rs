@@ -1,12 +1,53 @@
 //! File system tools for AIHarness
 
-use super::{Tool, ToolResult};
+use super::{
+    encoding, sandbox::PathSandbox, ProgressReporter, Tool, ToolAnnotations, ToolContext, ToolResult,
+};
 use crate::error::ToolError;
 use async_trait::async_trait;
+use ignore::{overrides::OverrideBuilder, WalkBuilder};
+use regex::{Regex, RegexBuilder};
+use serde::Serialize;
 use serde_json::json;
-use std::path::Path;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use tokio_util::sync::CancellationToken;
+
+pub(crate) const MAX_FILE_SIZE: u64 = 1024 * 1024; // 1MB limit
+
+/// Resolve a tool path, confining it to the project sandbox when one is given.
+///
+/// Without a sandbox the path must be absolute and is used as-is.
+fn resolve_path(path_str: &str, sandbox: Option<&PathSandbox>) -> Result<PathBuf, ToolError> {
+    if let Some(sandbox) = sandbox {
+        return sandbox.resolve(path_str);
+    }
+
+    let path = Path::new(path_str);
+    if !path.is_absolute() {
+        return Err(ToolError::InvalidPath(format!(
+            "Path must be absolute: {path_str}"
+        )));
+    }
+    Ok(path.to_path_buf())
+}
 
-const MAX_FILE_SIZE: u64 = 1024 * 1024; // 1MB limit
+/// Whether a walk may descend into or read an entry.
+///
+/// Symlinks are only followed when they resolve inside the sandbox.
+async fn entry_in_sandbox(entry: &tokio::fs::DirEntry, sandbox: Option<&PathSandbox>) -> bool {
+    let Some(sandbox) = sandbox else {
+        return true;
+    };
+    match entry.file_type().await {
+        Ok(file_type) if file_type.is_symlink() => match tokio::fs::canonicalize(entry.path()).await {
+            Ok(target) => sandbox.contains(&target),
+            Err(_) => false,
+        },
+        Ok(_) => true,
+        Err(_) => false,
+    }
+}
 
 /// Tool for reading file contents
 pub struct ReadFileTool;
@@ -18,8 +59,10 @@ impl Tool for ReadFileTool {
     }
 
     fn description(&self) -> &str {
-        "Read the contents of a file. Returns the file content as text. \
-         Will not read binary files. Limited to 1MB."
+        "Read the contents of a file as text. Whole-file reads are limited to 1MB; \
+         use 'offset'/'limit' to page through lines or 'byte_offset'/'byte_length' \
+         for a byte range of larger files. UTF-16 and Latin-1 text is decoded; binary \
+         files are rejected unless 'encoding' is 'base64'."
     }
 
     fn input_schema(&self) -> serde_json::Value {
@@ -28,27 +71,92 @@ impl Tool for ReadFileTool {
             "properties": {
                 "path": {
                     "type": "string",
-                    "description": "The absolute path to the file to read"
+                    "description": "The path to the file to read (absolute, or relative to the project root)"
+                },
+                "offset": {
+                    "type": "integer",
+                    "description": "First line to return (1-based)",
+                    "default": 1
+                },
+                "limit": {
+                    "type": "integer",
+                    "description": "Maximum number of lines to return",
+                    "default": DEFAULT_LINE_LIMIT
+                },
+                "line_numbers": {
+                    "type": "boolean",
+                    "description": "Prefix each line with its line number",
+                    "default": false
+                },
+                "byte_offset": {
+                    "type": "integer",
+                    "description": "Read a byte range starting here instead of lines"
+                },
+                "byte_length": {
+                    "type": "integer",
+                    "description": "Number of bytes to read in byte-range mode (max 1MB)"
+                },
+                "encoding": {
+                    "type": "string",
+                    "enum": ["text", "base64"],
+                    "description": "Return decoded text, or base64 of the raw bytes (for images and other binaries)",
+                    "default": "text"
                 }
             },
             "required": ["path"]
         })
     }
 
+    fn output_schema(&self) -> Option<serde_json::Value> {
+        Some(json!({
+            "type": "object",
+            "properties": {
+                "total_lines": { "type": "integer" },
+                "start_line": { "type": "integer" },
+                "end_line": { "type": ["integer", "null"] },
+                "lines_returned": { "type": "integer" },
+                "has_more": { "type": "boolean" },
+                "truncated": { "type": "boolean" },
+                "byte_offset": { "type": "integer" },
+                "bytes_read": { "type": "integer" },
+                "total_bytes": { "type": "integer" },
+                "size": { "type": "integer" },
+                "encoding": { "type": "string" },
+                "mime_type": { "type": "string" }
+            }
+        }))
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        ToolAnnotations::read_only("Read file")
+    }
+
     async fn execute(&self, args: serde_json::Value) -> Result<ToolResult, ToolError> {
+        self.run(args, None).await
+    }
+
+    async fn execute_sandboxed(
+        &self,
+        args: serde_json::Value,
+        sandbox: &PathSandbox,
+    ) -> Result<ToolResult, ToolError> {
+        self.run(args, Some(sandbox)).await
+    }
+}
+
+impl ReadFileTool {
+    async fn run(
+        &self,
+        args: serde_json::Value,
+        sandbox: Option<&PathSandbox>,
+    ) -> Result<ToolResult, ToolError> {
         let path_str = args
             .get("path")
             .and_then(|v| v.as_str())
             .ok_or_else(|| ToolError::InvalidArguments("Missing 'path' parameter".to_string()))?;
 
-        let path = Path::new(path_str);
-        
-        // Validate path is absolute
-        if !path.is_absolute() {
-            return Err(ToolError::InvalidPath(
-                format!("Path must be absolute: {}", path_str)
-            ));
-        }
+        let path = resolve_path(path_str, sandbox)?;
+        let path = path.as_path();
 
         // Check file exists and get metadata
         let metadata = tokio::fs::metadata(path).await.map_err(ToolError::from)?;
@@ -59,6 +167,42 @@ impl Tool for ReadFileTool {
             ));
         }
 
+        let base64 = match args.get("encoding").and_then(|v| v.as_str()) {
+            None | Some("text") => false,
+            Some("base64") => true,
+            Some(other) => {
+                return Err(ToolError::InvalidArguments(format!(
+                    "Unknown encoding '{other}': expected 'text' or 'base64'"
+                )))
+            }
+        };
+
+        let count = |key: &str| args.get(key).and_then(serde_json::Value::as_u64);
+        if count("byte_offset").is_some() || count("byte_length").is_some() {
+            return read_byte_range(
+                path,
+                path_str,
+                metadata.len(),
+                count("byte_offset").unwrap_or(0),
+                count("byte_length").unwrap_or(MAX_FILE_SIZE).min(MAX_FILE_SIZE),
+                base64,
+            )
+            .await;
+        }
+
+        let line_numbers = args
+            .get("line_numbers")
+            .and_then(serde_json::Value::as_bool)
+            .unwrap_or(false);
+        if !base64 && (count("offset").is_some() || count("limit").is_some() || line_numbers) {
+            let window = LineWindow {
+                offset: count("offset").map_or(1, |n| usize::try_from(n).unwrap_or(usize::MAX)).max(1),
+                limit: count("limit").map_or(DEFAULT_LINE_LIMIT, |n| usize::try_from(n).unwrap_or(usize::MAX)),
+                line_numbers,
+            };
+            return read_line_window(path, path_str, &window).await;
+        }
+
         // Check file size
         if metadata.len() > MAX_FILE_SIZE {
             return Err(ToolError::FileTooLarge {
@@ -68,13 +212,242 @@ impl Tool for ReadFileTool {
             });
         }
 
-        // Read file content
-        let content = tokio::fs::read_to_string(path).await?;
+        let bytes = tokio::fs::read(path).await?;
+        if base64 {
+            return Ok(base64_result(&bytes, json!({ "size": bytes.len() })));
+        }
+
+        let (content, detected) = encoding::decode(&bytes).map_err(|mime| ToolError::BinaryFile {
+            path: path_str.to_string(),
+            mime: mime.to_string(),
+        })?;
+        let total_lines = content.lines().count();
         
-        Ok(ToolResult::success(content))
+        Ok(ToolResult::success_with_data(
+            content,
+            json!({
+                "total_lines": total_lines,
+                "encoding": encoding::encoding_label(detected),
+            }),
+        ))
+    }
+}
+
+/// Lines returned by a paginated read when no `limit` is given
+const DEFAULT_LINE_LIMIT: usize = 2000;
+/// Chunk size used when streaming a file for a line window
+const READ_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Requested line window for `read_file`
+struct LineWindow {
+    /// First line to return (1-based)
+    offset: usize,
+    limit: usize,
+    line_numbers: bool,
+}
+
+/// Accumulates the requested window while every line of a file is counted
+struct LineCollector<'a> {
+    window: &'a LineWindow,
+    output: String,
+    total_lines: usize,
+    returned: usize,
+    truncated: bool,
+}
+
+impl LineCollector<'_> {
+    fn push(&mut self, line: &str) {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        self.total_lines += 1;
+        if self.total_lines < self.window.offset || self.returned >= self.window.limit || self.truncated {
+            return;
+        }
+        let mut line = line;
+        let max_len = usize::try_from(MAX_FILE_SIZE).unwrap_or(usize::MAX);
+        if self.output.len() + line.len() > max_len {
+            self.truncated = true;
+            if self.returned > 0 {
+                return;
+            }
+            // A first line too long to return whole is cut, so the caller can
+            // still move past it
+            let mut end = max_len;
+            while !line.is_char_boundary(end) {
+                end -= 1;
+            }
+            line = &line[..end];
+        }
+        if self.window.line_numbers {
+            let _ = write!(self.output, "{:>6}\t", self.total_lines);
+        }
+        self.output.push_str(line);
+        self.output.push('\n');
+        self.returned += 1;
+    }
+}
+
+/// Stream a file and return one window of lines.
+///
+/// The whole file is scanned so `total_lines` is exact, but only the window
+/// is kept in memory. Output stops early once it reaches `MAX_FILE_SIZE`.
+/// The encoding is sniffed from the first bytes and decoded incrementally; a
+/// file that sniffs as UTF-8 but has invalid bytes further on is scanned
+/// again as Latin-1, so the window never mixes encodings.
+async fn read_line_window(
+    path: &Path,
+    path_str: &str,
+    window: &LineWindow,
+) -> Result<ToolResult, ToolError> {
+    let (mut detected, mut fallback) = sniff_file(path, path_str).await?;
+    let collector = loop {
+        if let Some(collector) = collect_lines(path, detected, fallback.is_some(), window).await? {
+            break collector;
+        }
+        detected = fallback.take().unwrap_or(detected);
+    };
+
+    let LineCollector { output, total_lines, returned, truncated, .. } = collector;
+    let end_line = if returned == 0 { None } else { Some(window.offset + returned - 1) };
+    let has_more = end_line.map_or(window.offset <= total_lines, |end| end < total_lines);
+
+    Ok(ToolResult::success_with_data(
+        output,
+        json!({
+            "total_lines": total_lines,
+            "start_line": window.offset,
+            "end_line": end_line,
+            "lines_returned": returned,
+            "has_more": has_more,
+            "truncated": truncated,
+            "encoding": encoding::encoding_label(detected),
+        }),
+    ))
+}
+
+/// Sniff a file's first bytes: its encoding, and the one to switch to if
+/// later bytes don't fit (see [`encoding::fallback`]).
+async fn sniff_file(
+    path: &Path,
+    path_str: &str,
+) -> Result<(&'static encoding_rs::Encoding, Option<&'static encoding_rs::Encoding>), ToolError> {
+    use tokio::io::AsyncReadExt;
+
+    let file = tokio::fs::File::open(path).await.map_err(ToolError::from)?;
+    let mut head = Vec::with_capacity(encoding::SNIFF_LEN);
+    file.take(encoding::SNIFF_LEN as u64)
+        .read_to_end(&mut head)
+        .await
+        .map_err(ToolError::from)?;
+    match encoding::sniff(&head) {
+        encoding::Sniffed::Text(detected) => Ok((detected, encoding::fallback(&head, detected))),
+        encoding::Sniffed::Binary(mime) => Err(ToolError::BinaryFile {
+            path: path_str.to_string(),
+            mime: mime.to_string(),
+        }),
+    }
+}
+
+/// Decode a whole file in `encoding`, counting its lines and keeping the window.
+///
+/// With `strict`, gives up (returning `None`) at the first invalid byte.
+async fn collect_lines<'a>(
+    path: &Path,
+    encoding: &'static encoding_rs::Encoding,
+    strict: bool,
+    window: &'a LineWindow,
+) -> Result<Option<LineCollector<'a>>, ToolError> {
+    use tokio::io::AsyncReadExt;
+
+    let mut file = tokio::fs::File::open(path).await.map_err(ToolError::from)?;
+    let mut chunk = vec![0u8; READ_CHUNK_SIZE];
+    let mut decoder = encoding.new_decoder_with_bom_removal();
+    let mut collector = LineCollector {
+        window,
+        output: String::new(),
+        total_lines: 0,
+        returned: 0,
+        truncated: false,
+    };
+    let mut pending = String::new();
+    loop {
+        let filled = file.read(&mut chunk).await.map_err(ToolError::from)?;
+        let last = filled == 0;
+        let needed = decoder
+            .max_utf8_buffer_length(filled)
+            .unwrap_or(filled * 3 + 16);
+        pending.reserve(needed);
+        let (_, _, replaced) = decoder.decode_to_string(&chunk[..filled], &mut pending, last);
+        if replaced && strict {
+            return Ok(None);
+        }
+
+        let mut start = 0;
+        while let Some(pos) = pending[start..].find('\n') {
+            collector.push(&pending[start..start + pos]);
+            start += pos + 1;
+        }
+        pending.drain(..start);
+
+        if last {
+            if !pending.is_empty() {
+                collector.push(&pending);
+            }
+            return Ok(Some(collector));
+        }
     }
 }
 
+/// Read `length` bytes starting at `offset`.
+///
+/// In text mode the bytes are decoded in the encoding sniffed from the start
+/// of the file (binaries are refused), with characters cut at the edges
+/// replaced; in base64 mode the raw bytes are encoded.
+async fn read_byte_range(
+    path: &Path,
+    path_str: &str,
+    total_bytes: u64,
+    offset: u64,
+    length: u64,
+    base64: bool,
+) -> Result<ToolResult, ToolError> {
+    use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+    let sniffed = if base64 {
+        None
+    } else {
+        Some(sniff_file(path, path_str).await?)
+    };
+
+    let mut file = tokio::fs::File::open(path).await.map_err(ToolError::from)?;
+    file.seek(std::io::SeekFrom::Start(offset)).await.map_err(ToolError::from)?;
+
+    let mut buffer = Vec::new();
+    file.take(length).read_to_end(&mut buffer).await.map_err(ToolError::from)?;
+    let bytes_read = buffer.len() as u64;
+    let mut data = json!({
+        "byte_offset": offset,
+        "bytes_read": bytes_read,
+        "total_bytes": total_bytes,
+        "has_more": offset + bytes_read < total_bytes,
+    });
+
+    let Some((detected, fallback)) = sniffed else {
+        return Ok(base64_result(&buffer, data));
+    };
+    let (text, detected) = encoding::decode_range(&buffer, detected, fallback, offset == 0);
+    data["encoding"] = json!(encoding::encoding_label(detected));
+    Ok(ToolResult::success_with_data(text, data))
+}
+
+/// Build a base64 `read_file` result, adding the encoding and MIME type to `data`
+fn base64_result(bytes: &[u8], mut data: serde_json::Value) -> ToolResult {
+    use base64::Engine;
+
+    data["encoding"] = json!("base64");
+    data["mime_type"] = json!(encoding::mime_type(bytes));
+    ToolResult::success_with_data(base64::engine::general_purpose::STANDARD.encode(bytes), data)
+}
+
 /// Tool for writing file contents
 pub struct WriteFileTool;
 
@@ -95,7 +468,7 @@ impl Tool for WriteFileTool {
             "properties": {
                 "path": {
                     "type": "string",
-                    "description": "The absolute path to the file to write"
+                    "description": "The path to the file to write (absolute, or relative to the project root)"
                 },
                 "content": {
                     "type": "string",
@@ -106,7 +479,29 @@ impl Tool for WriteFileTool {
         })
     }
 
+    fn annotations(&self) -> ToolAnnotations {
+        ToolAnnotations::write("Write file", true, true)
+    }
+
     async fn execute(&self, args: serde_json::Value) -> Result<ToolResult, ToolError> {
+        self.run(args, None).await
+    }
+
+    async fn execute_sandboxed(
+        &self,
+        args: serde_json::Value,
+        sandbox: &PathSandbox,
+    ) -> Result<ToolResult, ToolError> {
+        self.run(args, Some(sandbox)).await
+    }
+}
+
+impl WriteFileTool {
+    async fn run(
+        &self,
+        args: serde_json::Value,
+        sandbox: Option<&PathSandbox>,
+    ) -> Result<ToolResult, ToolError> {
         let path_str = args
             .get("path")
             .and_then(|v| v.as_str())
@@ -117,24 +512,16 @@ impl Tool for WriteFileTool {
             .and_then(|v| v.as_str())
             .ok_or_else(|| ToolError::InvalidArguments("Missing 'content' parameter".to_string()))?;
 
-        let path = Path::new(path_str);
-        
-        // Validate path is absolute
-        if !path.is_absolute() {
-            return Err(ToolError::InvalidPath(
-                format!("Path must be absolute: {}", path_str)
-            ));
-        }
+        let path = resolve_path(path_str, sandbox)?;
+        guard_git_dir(&path)?;
+        let path = path.as_path();
 
         // Create parent directories if needed
         if let Some(parent) = path.parent() {
             tokio::fs::create_dir_all(parent).await.map_err(ToolError::from)?;
         }
 
-        // Write file atomically (write to temp, then rename)
-        let temp_path = path.with_extension("tmp");
-        tokio::fs::write(&temp_path, content).await.map_err(ToolError::from)?;
-        tokio::fs::rename(&temp_path, path).await.map_err(ToolError::from)?;
+        write_atomic(path, content).await?;
 
         Ok(ToolResult::success(format!(
             "Successfully wrote {} bytes to {}",
@@ -144,6 +531,256 @@ impl Tool for WriteFileTool {
     }
 }
 
+/// Write file atomically (write to temp, then rename)
+async fn write_atomic(path: &Path, content: &str) -> Result<(), ToolError> {
+    let temp_path = path.with_extension("tmp");
+    tokio::fs::write(&temp_path, content).await.map_err(ToolError::from)?;
+    tokio::fs::rename(&temp_path, path).await.map_err(ToolError::from)?;
+    Ok(())
+}
+
+/// Tool for editing part of a file in place
+pub struct EditFileTool;
+
+#[async_trait]
+impl Tool for EditFileTool {
+    fn name(&self) -> &'static str {
+        "edit_file"
+    }
+
+    fn description(&self) -> &'static str {
+        "Edit an existing file with exact search/replace blocks or a unified diff. \
+         All changes are applied atomically; each search block must match exactly once. \
+         Returns the resulting diff."
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "path": {
+                    "type": "string",
+                    "description": "The path to the file to edit (absolute, or relative to the project root)"
+                },
+                "edits": {
+                    "type": "array",
+                    "description": "Search/replace blocks, applied in order",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "search": {
+                                "type": "string",
+                                "description": "Exact text to find; must occur exactly once"
+                            },
+                            "replace": {
+                                "type": "string",
+                                "description": "Text to put in its place"
+                            }
+                        },
+                        "required": ["search", "replace"]
+                    }
+                },
+                "diff": {
+                    "type": "string",
+                    "description": "Unified diff to apply instead of 'edits'"
+                }
+            },
+            "required": ["path"]
+        })
+    }
+
+    fn output_schema(&self) -> Option<serde_json::Value> {
+        Some(json!({
+            "type": "object",
+            "properties": {
+                "path": { "type": "string" },
+                "changes_applied": { "type": "integer" },
+                "diff": { "type": "string" }
+            },
+            "required": ["path", "changes_applied", "diff"]
+        }))
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        ToolAnnotations::write("Edit file", true, false)
+    }
+
+    async fn execute(&self, args: serde_json::Value) -> Result<ToolResult, ToolError> {
+        self.run(args, None).await
+    }
+
+    async fn execute_sandboxed(
+        &self,
+        args: serde_json::Value,
+        sandbox: &PathSandbox,
+    ) -> Result<ToolResult, ToolError> {
+        self.run(args, Some(sandbox)).await
+    }
+}
+
+impl EditFileTool {
+    async fn run(
+        &self,
+        args: serde_json::Value,
+        sandbox: Option<&PathSandbox>,
+    ) -> Result<ToolResult, ToolError> {
+        let path_str = args
+            .get("path")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ToolError::InvalidArguments("Missing 'path' parameter".to_string()))?;
+
+        let path = resolve_path(path_str, sandbox)?;
+        guard_git_dir(&path)?;
+        let path = path.as_path();
+
+        let metadata = tokio::fs::metadata(path).await.map_err(ToolError::from)?;
+        if !metadata.is_file() {
+            return Err(ToolError::InvalidPath(format!(
+                "Path is not a file: {path_str}"
+            )));
+        }
+        if metadata.len() > MAX_FILE_SIZE {
+            return Err(ToolError::FileTooLarge {
+                path: path_str.to_string(),
+                size: metadata.len(),
+                max_size: MAX_FILE_SIZE,
+            });
+        }
+
+        let original = tokio::fs::read_to_string(path).await?;
+
+        let (updated, applied) = match (args.get("edits"), args.get("diff")) {
+            (Some(edits), None) => {
+                let edits = parse_edits(edits)?;
+                (apply_edits(&original, &edits, path_str)?, edits.len())
+            }
+            (None, Some(diff)) => {
+                let diff = diff.as_str().ok_or_else(|| {
+                    ToolError::InvalidArguments("'diff' must be a string".to_string())
+                })?;
+                apply_unified_diff(&original, diff, path_str)?
+            }
+            (Some(_), Some(_)) => {
+                return Err(ToolError::InvalidArguments(
+                    "Provide either 'edits' or 'diff', not both".to_string(),
+                ))
+            }
+            (None, None) => {
+                return Err(ToolError::InvalidArguments(
+                    "Missing 'edits' or 'diff' parameter".to_string(),
+                ))
+            }
+        };
+
+        let diff = diffy::DiffOptions::new()
+            .set_original_filename(format!("a/{path_str}"))
+            .set_modified_filename(format!("b/{path_str}"))
+            .create_patch(&original, &updated)
+            .to_string();
+
+        if updated != original {
+            write_atomic(path, &updated).await?;
+        }
+
+        Ok(ToolResult::success_with_data(
+            format!("Applied {applied} change(s) to {path_str}\n\n{diff}"),
+            json!({
+                "path": path_str,
+                "changes_applied": applied,
+                "diff": diff,
+            }),
+        ))
+    }
+}
+
+/// A single search/replace block for `edit_file`
+struct EditBlock {
+    search: String,
+    replace: String,
+}
+
+fn parse_edits(value: &serde_json::Value) -> Result<Vec<EditBlock>, ToolError> {
+    let items = value
+        .as_array()
+        .ok_or_else(|| ToolError::InvalidArguments("'edits' must be an array".to_string()))?;
+    if items.is_empty() {
+        return Err(ToolError::InvalidArguments("'edits' must not be empty".to_string()));
+    }
+
+    items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| {
+            let field = |key: &str| {
+                item.get(key).and_then(|v| v.as_str()).map(str::to_string).ok_or_else(|| {
+                    ToolError::InvalidArguments(format!("Edit #{} is missing '{}'", i + 1, key))
+                })
+            };
+            let search = field("search")?;
+            if search.is_empty() {
+                return Err(ToolError::InvalidArguments(format!(
+                    "Edit #{} has an empty 'search'",
+                    i + 1
+                )));
+            }
+            Ok(EditBlock {
+                search,
+                replace: field("replace")?,
+            })
+        })
+        .collect()
+}
+
+/// Apply search/replace blocks in order; every search must match exactly once
+fn apply_edits(original: &str, edits: &[EditBlock], path_str: &str) -> Result<String, ToolError> {
+    let mut content = original.to_string();
+
+    for (i, edit) in edits.iter().enumerate() {
+        let mut found = content.match_indices(&edit.search);
+        let Some((start, _)) = found.next() else {
+            return Err(ToolError::EditFailed(format!(
+                "edit #{}: search text not found in {}",
+                i + 1,
+                path_str
+            )));
+        };
+        let extra = found.count();
+        if extra > 0 {
+            return Err(ToolError::EditFailed(format!(
+                "edit #{}: search text matches {} times in {}; include more surrounding context",
+                i + 1,
+                extra + 1,
+                path_str
+            )));
+        }
+        content.replace_range(start..start + edit.search.len(), &edit.replace);
+    }
+
+    Ok(content)
+}
+
+/// Apply a unified diff, returning the new content and the number of hunks
+fn apply_unified_diff(
+    original: &str,
+    diff: &str,
+    path_str: &str,
+) -> Result<(String, usize), ToolError> {
+    let patch = diffy::Patch::from_str(diff)
+        .map_err(|e| ToolError::InvalidArguments(format!("Invalid unified diff: {e}")))?;
+    if patch.hunks().is_empty() {
+        return Err(ToolError::InvalidArguments(
+            "Unified diff contains no hunks".to_string(),
+        ));
+    }
+
+    let updated = diffy::apply(original, &patch).map_err(|e| {
+        ToolError::EditFailed(format!(
+            "{e} in {path_str}: context does not match the file"
+        ))
+    })?;
+    Ok((updated, patch.hunks().len()))
+}
+
 /// Tool for listing directory contents
 pub struct ListDirectoryTool;
 
@@ -163,7 +800,7 @@ impl Tool for ListDirectoryTool {
             "properties": {
                 "path": {
                     "type": "string",
-                    "description": "The absolute path to the directory to list"
+                    "description": "The path to the directory to list (absolute, or relative to the project root)"
                 },
                 "recursive": {
                     "type": "boolean",
@@ -175,26 +812,41 @@ impl Tool for ListDirectoryTool {
         })
     }
 
+    fn annotations(&self) -> ToolAnnotations {
+        ToolAnnotations::read_only("List directory")
+    }
+
     async fn execute(&self, args: serde_json::Value) -> Result<ToolResult, ToolError> {
+        self.run(args, None).await
+    }
+
+    async fn execute_sandboxed(
+        &self,
+        args: serde_json::Value,
+        sandbox: &PathSandbox,
+    ) -> Result<ToolResult, ToolError> {
+        self.run(args, Some(sandbox)).await
+    }
+}
+
+impl ListDirectoryTool {
+    async fn run(
+        &self,
+        args: serde_json::Value,
+        sandbox: Option<&PathSandbox>,
+    ) -> Result<ToolResult, ToolError> {
         let path_str = args
             .get("path")
             .and_then(|v| v.as_str())
             .ok_or_else(|| ToolError::InvalidArguments("Missing 'path' parameter".to_string()))?;
 
         let recursive = args.get("recursive").and_then(|v| v.as_bool()).unwrap_or(false);
-        let path = Path::new(path_str);
-        
-        // Validate path is absolute
-        if !path.is_absolute() {
-            return Err(ToolError::InvalidPath(
-                format!("Path must be absolute: {}", path_str)
-            ));
-        }
+        let path = resolve_path(path_str, sandbox)?;
 
         if recursive {
-            list_recursive(path, path_str).await
+            list_recursive(&path, path_str, sandbox).await
         } else {
-            list_flat(path, path_str).await
+            list_flat(&path, path_str).await
         }
     }
 }
@@ -231,14 +883,24 @@ async fn list_flat(path: &Path, base_path: &str) -> Result<ToolResult, ToolError
     Ok(ToolResult::success(output))
 }
 
-async fn list_recursive(path: &Path, base_path: &str) -> Result<ToolResult, ToolError> {
-    let mut result = vec![format!("Directory tree: {}", base_path)];
-    
-    async fn walk(dir: &Path, prefix: &str, result: &mut Vec<String>) -> Result<(), ToolError> {
+async fn list_recursive(
+    path: &Path,
+    base_path: &str,
+    sandbox: Option<&PathSandbox>,
+) -> Result<ToolResult, ToolError> {
+    async fn walk(
+        dir: &Path,
+        prefix: &str,
+        result: &mut Vec<String>,
+        sandbox: Option<&PathSandbox>,
+    ) -> Result<(), ToolError> {
         let mut entries = tokio::fs::read_dir(dir).await.map_err(ToolError::from)?;
         let mut items = Vec::new();
 
         while let Some(entry) = entries.next_entry().await.map_err(ToolError::from)? {
+            if !entry_in_sandbox(&entry, sandbox).await {
+                continue;
+            }
             let name = entry.file_name().to_string_lossy().to_string();
             let metadata = entry.metadata().await.map_err(ToolError::from)?;
             items.push((name, metadata.is_dir(), entry.path()));
@@ -263,17 +925,31 @@ async fn list_recursive(path: &Path, base_path: &str) -> Result<ToolResult, Tool
             
             if is_dir {
                 let new_prefix = format!("{}{}", prefix, if is_last { "    " } else { "│   " });
-                Box::pin(walk(&path, &new_prefix, result)).await?;
+                Box::pin(walk(&path, &new_prefix, result, sandbox)).await?;
             }
         }
         
         Ok(())
     }
 
-    walk(path, "", &mut result).await?;
+    let mut result = vec![format!("Directory tree: {base_path}")];
+    walk(path, "", &mut result, sandbox).await?;
     Ok(ToolResult::success(result.join("\n")))
 }
 
+/// Default cap on reported matches for `search_files`
+const DEFAULT_MAX_RESULTS: usize = 500;
+/// Hard cap on reported matches for `search_files`
+const MAX_RESULTS_LIMIT: usize = 5000;
+/// Project-specific ignore file, read alongside `.gitignore`
+const AIHARNESS_IGNORE_FILE: &str = ".aiharnessignore";
+/// Directories skipped while ignore handling is enabled, even without ignore files
+const DEFAULT_SKIP_DIRS: &[&str] = &[".git", ".aiharness", "node_modules", "target"];
+/// Longest line excerpt shown in text output
+const MAX_LINE_DISPLAY: usize = 200;
+/// Files searched between progress updates
+const SEARCH_PROGRESS_INTERVAL: usize = 100;
+
 /// Tool for searching files
 pub struct SearchFilesTool;
 
@@ -284,8 +960,9 @@ impl Tool for SearchFilesTool {
     }
 
     fn description(&self) -> &str {
-        "Search for files containing a pattern. Supports simple string search. \
-         Returns matching file paths with line numbers."
+        "Search file contents for a literal string or regex. Honors .gitignore and \
+         .aiharnessignore, supports include/exclude globs and context lines. \
+         Returns matching lines with file, line and column."
     }
 
     fn input_schema(&self) -> serde_json::Value {
@@ -294,12 +971,55 @@ impl Tool for SearchFilesTool {
             "properties": {
                 "path": {
                     "type": "string",
-                    "description": "The directory to search in"
+                    "description": "The directory to search in (absolute, or relative to the project root)"
                 },
                 "pattern": {
                     "type": "string",
                     "description": "The pattern to search for"
                 },
+                "regex": {
+                    "type": "boolean",
+                    "description": "Treat the pattern as a regular expression",
+                    "default": false
+                },
+                "case_insensitive": {
+                    "type": "boolean",
+                    "description": "Match case-insensitively",
+                    "default": false
+                },
+                "include": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Only search files matching these globs (e.g. \"*.rs\", \"src/**\")"
+                },
+                "exclude": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Skip files matching these globs"
+                },
+                "context_lines": {
+                    "type": "integer",
+                    "description": "Lines of context before and after each match",
+                    "default": 0
+                },
+                "before_context": {
+                    "type": "integer",
+                    "description": "Lines of context before each match (overrides context_lines)"
+                },
+                "after_context": {
+                    "type": "integer",
+                    "description": "Lines of context after each match (overrides context_lines)"
+                },
+                "max_results": {
+                    "type": "integer",
+                    "description": "Maximum number of matching lines to return",
+                    "default": DEFAULT_MAX_RESULTS
+                },
+                "respect_ignore": {
+                    "type": "boolean",
+                    "description": "Honor .gitignore/.aiharnessignore and skip .git, node_modules and target",
+                    "default": true
+                },
                 "recursive": {
                     "type": "boolean",
                     "description": "Whether to search recursively",
@@ -310,7 +1030,70 @@ impl Tool for SearchFilesTool {
         })
     }
 
+    fn output_schema(&self) -> Option<serde_json::Value> {
+        Some(json!({
+            "type": "object",
+            "properties": {
+                "matches": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "file": { "type": "string" },
+                            "line": { "type": "integer" },
+                            "column": { "type": "integer" },
+                            "match": { "type": "string" },
+                            "text": { "type": "string" },
+                            "before": { "type": "array", "items": { "type": "string" } },
+                            "after": { "type": "array", "items": { "type": "string" } }
+                        },
+                        "required": ["file", "line", "column", "match", "text"]
+                    }
+                },
+                "files_searched": { "type": "integer" },
+                "files_matched": { "type": "integer" },
+                "binary_skipped": { "type": "integer" },
+                "truncated": { "type": "boolean" }
+            },
+            "required": ["matches", "files_searched", "files_matched", "binary_skipped", "truncated"]
+        }))
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        ToolAnnotations::read_only("Search files")
+    }
+
     async fn execute(&self, args: serde_json::Value) -> Result<ToolResult, ToolError> {
+        self.run(args, None, ProgressReporter::default(), CancellationToken::new()).await
+    }
+
+    async fn execute_sandboxed(
+        &self,
+        args: serde_json::Value,
+        sandbox: &PathSandbox,
+    ) -> Result<ToolResult, ToolError> {
+        self.run(args, Some(sandbox), ProgressReporter::default(), CancellationToken::new())
+            .await
+    }
+
+    async fn execute_with_context(
+        &self,
+        args: serde_json::Value,
+        ctx: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        self.run(args, Some(&ctx.sandbox), ctx.progress.clone(), ctx.cancel.clone())
+            .await
+    }
+}
+
+impl SearchFilesTool {
+    async fn run(
+        &self,
+        args: serde_json::Value,
+        sandbox: Option<&PathSandbox>,
+        progress: ProgressReporter,
+        cancel: CancellationToken,
+    ) -> Result<ToolResult, ToolError> {
         let path_str = args
             .get("path")
             .and_then(|v| v.as_str())
@@ -321,175 +1104,1263 @@ impl Tool for SearchFilesTool {
             .and_then(|v| v.as_str())
             .ok_or_else(|| ToolError::InvalidArguments("Missing 'pattern' parameter".to_string()))?;
 
-        let recursive = args.get("recursive").and_then(|v| v.as_bool()).unwrap_or(true);
-        let path = Path::new(path_str);
-        
-        // Validate path is absolute
-        if !path.is_absolute() {
-            return Err(ToolError::InvalidPath(
-                format!("Path must be absolute: {}", path_str)
-            ));
-        }
+        let options = SearchOptions::from_args(&args, pattern)?;
+        let path = resolve_path(path_str, sandbox)?;
+        tokio::fs::metadata(&path).await.map_err(ToolError::from)?;
 
-        let mut matches = Vec::new();
-        let mut files_searched = 0;
-        
-        if recursive {
-            search_recursive(path, pattern, &mut matches, &mut files_searched).await?;
-        } else {
-            search_flat(path, pattern, &mut matches, &mut files_searched).await?;
-        }
+        let sandbox = sandbox.cloned();
+        let outcome = tokio::task::spawn_blocking(move || {
+            search_tree(&path, &options, sandbox.as_ref(), &progress, &cancel)
+        })
+        .await
+        .map_err(|e| ToolError::IoError(e.to_string()))??;
 
-        let output = if matches.is_empty() {
+        let output = if outcome.hits.is_empty() {
             format!("No matches found for '{}' in {} (searched {} files)", 
-                    pattern, path_str, files_searched)
+                    pattern, path_str, outcome.files_searched)
         } else {
-            format!(
+            let mut output = format!(
                 "Found {} matches in {} files (searched {} files total):\n\n{}",
-                matches.len(),
-                matches.iter().map(|m: &String| m.split(':').next().unwrap()).collect::<std::collections::HashSet<_>>().len(),
-                files_searched,
-                matches.join("\n")
-            )
+                outcome.hits.len(),
+                outcome.files_matched,
+                outcome.files_searched,
+                format_hits(&outcome.hits)
+            );
+            if outcome.truncated {
+                let _ = write!(
+                    output,
+                    "\n\n(results truncated at {} matches)",
+                    outcome.hits.len()
+                );
+            }
+            output
         };
 
-        Ok(ToolResult::success(output))
+        Ok(ToolResult::success_with_data(
+            output,
+            json!({
+                "matches": outcome.hits,
+                "files_searched": outcome.files_searched,
+                "files_matched": outcome.files_matched,
+                "binary_skipped": outcome.binary_skipped,
+                "truncated": outcome.truncated,
+            }),
+        ))
     }
 }
 
-async fn search_file(file_path: &Path, pattern: &str) -> Result<Vec<String>, ToolError> {
-    let content = match tokio::fs::read_to_string(file_path).await {
-        Ok(c) => c,
-        Err(_) => return Ok(Vec::new()), // Skip binary/unreadable files
-    };
+/// Parsed `search_files` arguments
+struct SearchOptions {
+    matcher: Regex,
+    recursive: bool,
+    respect_ignore: bool,
+    include: Vec<String>,
+    exclude: Vec<String>,
+    before_context: usize,
+    after_context: usize,
+    max_results: usize,
+}
 
-    let path_str = file_path.to_string_lossy();
-    let mut matches = Vec::new();
+impl SearchOptions {
+    fn from_args(args: &serde_json::Value, pattern: &str) -> Result<Self, ToolError> {
+        let flag = |key: &str, default: bool| {
+            args.get(key)
+                .and_then(serde_json::Value::as_bool)
+                .unwrap_or(default)
+        };
+        let count = |key: &str| {
+            args.get(key)
+                .and_then(serde_json::Value::as_u64)
+                .map(|n| usize::try_from(n).unwrap_or(usize::MAX))
+        };
 
-    for (line_num, line) in content.lines().enumerate() {
-        if line.contains(pattern) {
-            // Truncate long lines
-            let display_line = if line.len() > 200 {
-                format!("{}...", &line[..200])
-            } else {
-                line.to_string()
-            };
-            matches.push(format!("{}:{}: {}", path_str, line_num + 1, display_line));
-        }
+        let source = if flag("regex", false) {
+            pattern.to_string()
+        } else {
+            regex::escape(pattern)
+        };
+        let matcher = RegexBuilder::new(&source)
+            .case_insensitive(flag("case_insensitive", false))
+            .build()
+            .map_err(|e| ToolError::InvalidArguments(format!("Invalid pattern: {e}")))?;
+
+        let context = count("context_lines").unwrap_or(0);
+        Ok(Self {
+            matcher,
+            recursive: flag("recursive", true),
+            respect_ignore: flag("respect_ignore", true),
+            include: glob_list(args, "include")?,
+            exclude: glob_list(args, "exclude")?,
+            before_context: count("before_context").unwrap_or(context),
+            after_context: count("after_context").unwrap_or(context),
+            max_results: count("max_results")
+                .unwrap_or(DEFAULT_MAX_RESULTS)
+                .clamp(1, MAX_RESULTS_LIMIT),
+        })
     }
-
-    Ok(matches)
 }
 
-async fn search_flat(
-    dir: &Path, 
-    pattern: &str, 
-    matches: &mut Vec<String>,
-    files_searched: &mut usize
-) -> Result<(), ToolError> {
-    let mut entries = tokio::fs::read_dir(dir).await.map_err(ToolError::from)?;
-
-    while let Some(entry) = entries.next_entry().await.map_err(ToolError::from)? {
-        let path = entry.path();
-        let metadata = entry.metadata().await.map_err(ToolError::from)?;
-
-        if metadata.is_file() {
-            *files_searched += 1;
-            let file_matches = search_file(&path, pattern).await?;
-            matches.extend(file_matches);
-        }
+/// Read a glob list argument given as a string or an array of strings
+fn glob_list(args: &serde_json::Value, key: &str) -> Result<Vec<String>, ToolError> {
+    match args.get(key) {
+        None | Some(serde_json::Value::Null) => Ok(Vec::new()),
+        Some(serde_json::Value::String(glob)) => Ok(vec![glob.clone()]),
+        Some(serde_json::Value::Array(globs)) => globs
+            .iter()
+            .map(|g| {
+                g.as_str().map(str::to_string).ok_or_else(|| {
+                    ToolError::InvalidArguments(format!("'{key}' must contain only strings"))
+                })
+            })
+            .collect(),
+        Some(_) => Err(ToolError::InvalidArguments(format!(
+            "'{key}' must be a string or an array of strings"
+        ))),
     }
+}
 
-    Ok(())
+/// A single matching line
+#[derive(Debug, Serialize)]
+struct SearchHit {
+    file: String,
+    line: usize,
+    column: usize,
+    #[serde(rename = "match")]
+    matched: String,
+    text: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    before: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    after: Vec<String>,
 }
 
-async fn search_recursive(
-    dir: &Path, 
-    pattern: &str, 
-    matches: &mut Vec<String>,
-    files_searched: &mut usize
-) -> Result<(), ToolError> {
-    let mut entries = tokio::fs::read_dir(dir).await.map_err(ToolError::from)?;
+struct SearchOutcome {
+    hits: Vec<SearchHit>,
+    files_searched: usize,
+    files_matched: usize,
+    binary_skipped: usize,
+    truncated: bool,
+}
 
-    while let Some(entry) = entries.next_entry().await.map_err(ToolError::from)? {
-        let path = entry.path();
-        let metadata = entry.metadata().await.map_err(ToolError::from)?;
+/// Walk `root` and collect matching lines.
+///
+/// Runs on a blocking thread; the `ignore` walker handles ignore files and globs.
+/// Reports progress every [`SEARCH_PROGRESS_INTERVAL`] files and stops with
+/// [`ToolError::Cancelled`] once `cancel` fires.
+fn search_tree(
+    root: &Path,
+    options: &SearchOptions,
+    sandbox: Option<&PathSandbox>,
+    progress: &ProgressReporter,
+    cancel: &CancellationToken,
+) -> Result<SearchOutcome, ToolError> {
+    let mut overrides = OverrideBuilder::new(root);
+    for glob in &options.include {
+        overrides
+            .add(glob)
+            .map_err(|e| ToolError::InvalidArguments(format!("Invalid include glob: {e}")))?;
+    }
+    for glob in &options.exclude {
+        overrides
+            .add(&format!("!{glob}"))
+            .map_err(|e| ToolError::InvalidArguments(format!("Invalid exclude glob: {e}")))?;
+    }
+    let overrides = overrides
+        .build()
+        .map_err(|e| ToolError::InvalidArguments(e.to_string()))?;
+
+    let respect_ignore = options.respect_ignore;
+    let mut builder = WalkBuilder::new(root);
+    builder
+        .hidden(false)
+        .follow_links(false)
+        .require_git(false)
+        .git_global(false)
+        .git_ignore(respect_ignore)
+        .git_exclude(respect_ignore)
+        .ignore(respect_ignore)
+        .parents(respect_ignore)
+        .overrides(overrides)
+        .sort_by_file_name(Ord::cmp)
+        .filter_entry(move |entry| {
+            let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+            let skipped = entry
+                .file_name()
+                .to_str()
+                .is_some_and(|name| DEFAULT_SKIP_DIRS.contains(&name));
+            !(respect_ignore && is_dir && entry.depth() > 0 && skipped)
+        });
+    if respect_ignore {
+        builder.add_custom_ignore_filename(AIHARNESS_IGNORE_FILE);
+    }
+    if !options.recursive {
+        builder.max_depth(Some(1));
+    }
+
+    let mut outcome = SearchOutcome {
+        hits: Vec::new(),
+        files_searched: 0,
+        files_matched: 0,
+        binary_skipped: 0,
+        truncated: false,
+    };
+
+    for entry in builder.build() {
+        if cancel.is_cancelled() {
+            return Err(ToolError::Cancelled);
+        }
+        let Ok(entry) = entry else {
+            continue;
+        };
+        let Some(file_type) = entry.file_type() else {
+            continue;
+        };
+        if file_type.is_symlink() {
+            let target = std::fs::canonicalize(entry.path());
+            let allowed = match (&target, sandbox) {
+                (Ok(target), Some(sandbox)) => sandbox.contains(target),
+                (Ok(_), None) => true,
+                (Err(_), _) => false,
+            };
+            if !allowed || !entry.path().is_file() {
+                continue;
+            }
+        } else if !file_type.is_file() {
+            continue;
+        }
+
+        let Ok(bytes) = std::fs::read(entry.path()) else {
+            continue;
+        };
+        let Ok((content, _)) = encoding::decode(&bytes) else {
+            outcome.binary_skipped += 1;
+            continue;
+        };
+        outcome.files_searched += 1;
+        if outcome.files_searched % SEARCH_PROGRESS_INTERVAL == 0 {
+            progress.report_count(
+                outcome.files_searched,
+                Some(format!("Searched {} files", outcome.files_searched)),
+            );
+        }
+
+        let file = entry.path().to_string_lossy().to_string();
+        let before = outcome.hits.len();
+        search_content(&file, &content, options, &mut outcome);
+        if outcome.hits.len() > before {
+            outcome.files_matched += 1;
+        }
+        if outcome.truncated {
+            break;
+        }
+    }
+
+    Ok(outcome)
+}
+
+/// Collect matching lines (with context) from one file's content
+fn search_content(file: &str, content: &str, options: &SearchOptions, outcome: &mut SearchOutcome) {
+    let lines: Vec<&str> = content.lines().collect();
+
+    for (index, line) in lines.iter().enumerate() {
+        let Some(found) = options.matcher.find(line) else {
+            continue;
+        };
+        if outcome.hits.len() >= options.max_results {
+            outcome.truncated = true;
+            return;
+        }
+
+        let before_start = index.saturating_sub(options.before_context);
+        let after_end = (index + 1 + options.after_context).min(lines.len());
+        outcome.hits.push(SearchHit {
+            file: file.to_string(),
+            line: index + 1,
+            column: line[..found.start()].chars().count() + 1,
+            matched: found.as_str().to_string(),
+            text: (*line).to_string(),
+            before: lines[before_start..index].iter().map(|l| (*l).to_string()).collect(),
+            after: lines[index + 1..after_end].iter().map(|l| (*l).to_string()).collect(),
+        });
+    }
+}
+
+/// Format hits grep-style: `file:line: text` for matches, `file-line- text` for context
+fn format_hits(hits: &[SearchHit]) -> String {
+    let with_context = hits.iter().any(|h| !h.before.is_empty() || !h.after.is_empty());
+    let mut lines = Vec::new();
+
+    for hit in hits {
+        if with_context && !lines.is_empty() {
+            lines.push("--".to_string());
+        }
+        let first_before = hit.line - hit.before.len();
+        for (offset, text) in hit.before.iter().enumerate() {
+            lines.push(format!("{}-{}- {}", hit.file, first_before + offset, truncate_line(text)));
+        }
+        lines.push(format!("{}:{}: {}", hit.file, hit.line, truncate_line(&hit.text)));
+        for (offset, text) in hit.after.iter().enumerate() {
+            lines.push(format!("{}-{}- {}", hit.file, hit.line + 1 + offset, truncate_line(text)));
+        }
+    }
+
+    lines.join("\n")
+}
+
+/// Truncate long lines for display
+fn truncate_line(line: &str) -> String {
+    match line.char_indices().nth(MAX_LINE_DISPLAY) {
+        Some((cut, _)) => format!("{}...", &line[..cut]),
+        None => line.to_string(),
+    }
+}
+
+/// Directory in a project root holding AIHarness's own data
+const DATA_DIR: &str = ".aiharness";
+
+/// Where `delete_path` moves trashed entries, inside [`DATA_DIR`]
+const TRASH_DIR: &str = "trash";
+
+/// Resolve a path to the entry itself, without following a final symlink.
+///
+/// Tools that move, delete or stat an entry act on a symlink rather than its
+/// target, so only the parent directory is resolved through the sandbox.
+fn resolve_entry(path_str: &str, sandbox: Option<&PathSandbox>) -> Result<PathBuf, ToolError> {
+    let path = Path::new(path_str);
+    let name = path
+        .file_name()
+        .ok_or_else(|| ToolError::InvalidPath(format!("Path has no file name: {path_str}")))?;
+    let Some(sandbox) = sandbox else {
+        return resolve_path(path_str, None);
+    };
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_string_lossy().into_owned(),
+        _ => ".".to_string(),
+    };
+    Ok(sandbox.resolve(&parent)?.join(name))
+}
+
+/// Refuse to write inside a `.git` directory (in any letter case, since
+/// case-insensitive file systems treat `.GIT` as the same directory).
+///
+/// Git runs programs named in its config and hooks, so letting a client
+/// without shell access write there would let it run commands through the
+/// git tools.
+fn guard_git_dir(path: &Path) -> Result<(), ToolError> {
+    if path
+        .components()
+        .any(|c| c.as_os_str().eq_ignore_ascii_case(".git"))
+    {
+        return Err(ToolError::PermissionDenied(format!(
+            "{} is inside a .git directory",
+            path.display()
+        )));
+    }
+    Ok(())
+}
+
+/// Refuse to change a project database or a directory holding one.
+fn guard_project_db(path: &Path, sandbox: Option<&PathSandbox>) -> Result<(), ToolError> {
+    let names: Vec<_> = path.components().map(|c| c.as_os_str().to_string_lossy()).collect();
+    let is_db = names
+        .windows(2)
+        .any(|pair| pair[0] == DATA_DIR && pair[1].starts_with("project.db"));
+    let holds_db = path.file_name().is_some_and(|name| name == DATA_DIR)
+        || sandbox.is_some_and(|s| s.root().join(DATA_DIR).starts_with(path))
+        || path.join(DATA_DIR).join("project.db").exists();
+
+    if is_db || holds_db {
+        return Err(ToolError::PermissionDenied(format!(
+            "{} is or contains the project database ({}/project.db)",
+            path.display(),
+            DATA_DIR
+        )));
+    }
+    Ok(())
+}
+
+/// Remove whatever is at `path`, without following a symlink there
+async fn remove_entry(path: &Path) -> Result<(), ToolError> {
+    let metadata = tokio::fs::symlink_metadata(path).await.map_err(ToolError::from)?;
+    if metadata.is_dir() {
+        tokio::fs::remove_dir_all(path).await.map_err(ToolError::from)
+    } else {
+        tokio::fs::remove_file(path).await.map_err(ToolError::from)
+    }
+}
+
+/// Check a move or copy destination, clearing it when `overwrite` is set,
+/// and create its parent directories.
+async fn prepare_destination(
+    source: &Path,
+    destination: &Path,
+    destination_str: &str,
+    overwrite: bool,
+) -> Result<(), ToolError> {
+    if destination == source {
+        return Err(ToolError::InvalidArguments(
+            "Source and destination are the same".to_string(),
+        ));
+    }
+    if destination.starts_with(source) {
+        return Err(ToolError::InvalidArguments(format!(
+            "Cannot put {} inside itself",
+            source.display()
+        )));
+    }
+    if tokio::fs::symlink_metadata(destination).await.is_ok() {
+        if !overwrite {
+            return Err(ToolError::InvalidArguments(format!(
+                "Destination already exists: {destination_str} (set 'overwrite' to replace it)"
+            )));
+        }
+        remove_entry(destination).await?;
+    }
+    if let Some(parent) = destination.parent() {
+        tokio::fs::create_dir_all(parent).await.map_err(ToolError::from)?;
+    }
+    Ok(())
+}
+
+/// Source, destination and overwrite flag of a move or copy, resolved and checked
+fn transfer_args(
+    args: &serde_json::Value,
+    sandbox: Option<&PathSandbox>,
+) -> Result<(PathBuf, PathBuf, String, bool), ToolError> {
+    let source_str = args
+        .get("source")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| ToolError::InvalidArguments("Missing 'source' parameter".to_string()))?;
+    let destination_str = args
+        .get("destination")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| {
+            ToolError::InvalidArguments("Missing 'destination' parameter".to_string())
+        })?;
+    let overwrite = args
+        .get("overwrite")
+        .and_then(serde_json::Value::as_bool)
+        .unwrap_or(false);
+
+    let source = resolve_entry(source_str, sandbox)?;
+    let destination = resolve_entry(destination_str, sandbox)?;
+    guard_project_db(&source, sandbox)?;
+    guard_project_db(&destination, sandbox)?;
+    guard_git_dir(&destination)?;
+    Ok((source, destination, destination_str.to_string(), overwrite))
+}
+
+/// Schema shared by `move_path` and `copy_path`
+fn transfer_schema(verb: &str) -> serde_json::Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "source": {
+                "type": "string",
+                "description": format!("The file or directory to {verb} (absolute, or relative to the project root)")
+            },
+            "destination": {
+                "type": "string",
+                "description": "Its new path, including the name (not the directory to put it in)"
+            },
+            "overwrite": {
+                "type": "boolean",
+                "description": "Replace an existing destination",
+                "default": false
+            }
+        },
+        "required": ["source", "destination"]
+    })
+}
+
+pub struct MovePathTool;
+
+#[async_trait]
+impl Tool for MovePathTool {
+    fn name(&self) -> &'static str {
+        "move_path"
+    }
+
+    fn description(&self) -> &'static str {
+        "Move or rename a file or directory. Creates the destination's parent \
+         directories; fails if the destination exists unless 'overwrite' is set."
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        transfer_schema("move")
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        ToolAnnotations::write("Move path", true, false)
+    }
+
+    async fn execute(&self, args: serde_json::Value) -> Result<ToolResult, ToolError> {
+        self.run(args, None).await
+    }
+
+    async fn execute_sandboxed(
+        &self,
+        args: serde_json::Value,
+        sandbox: &PathSandbox,
+    ) -> Result<ToolResult, ToolError> {
+        self.run(args, Some(sandbox)).await
+    }
+}
+
+impl MovePathTool {
+    async fn run(
+        &self,
+        args: serde_json::Value,
+        sandbox: Option<&PathSandbox>,
+    ) -> Result<ToolResult, ToolError> {
+        let (source, destination, destination_str, overwrite) = transfer_args(&args, sandbox)?;
+        guard_git_dir(&source)?;
+        tokio::fs::symlink_metadata(&source).await.map_err(ToolError::from)?;
+
+        prepare_destination(&source, &destination, &destination_str, overwrite).await?;
+        tokio::fs::rename(&source, &destination).await.map_err(ToolError::from)?;
+
+        Ok(ToolResult::success_with_data(
+            format!("Moved {} to {}", source.display(), destination.display()),
+            json!({
+                "source": source.to_string_lossy(),
+                "destination": destination.to_string_lossy()
+            }),
+        ))
+    }
+}
+
+pub struct CopyPathTool;
+
+#[async_trait]
+impl Tool for CopyPathTool {
+    fn name(&self) -> &'static str {
+        "copy_path"
+    }
+
+    fn description(&self) -> &'static str {
+        "Copy a file, or a directory with everything in it. Symlinks are copied as \
+         links. Fails if the destination exists unless 'overwrite' is set."
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        transfer_schema("copy")
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        ToolAnnotations::write("Copy path", true, true)
+    }
+
+    async fn execute(&self, args: serde_json::Value) -> Result<ToolResult, ToolError> {
+        self.run(args, None).await
+    }
+
+    async fn execute_sandboxed(
+        &self,
+        args: serde_json::Value,
+        sandbox: &PathSandbox,
+    ) -> Result<ToolResult, ToolError> {
+        self.run(args, Some(sandbox)).await
+    }
+}
+
+impl CopyPathTool {
+    async fn run(
+        &self,
+        args: serde_json::Value,
+        sandbox: Option<&PathSandbox>,
+    ) -> Result<ToolResult, ToolError> {
+        let (source, destination, destination_str, overwrite) = transfer_args(&args, sandbox)?;
+        tokio::fs::symlink_metadata(&source).await.map_err(ToolError::from)?;
+
+        prepare_destination(&source, &destination, &destination_str, overwrite).await?;
+        let (from, to) = (source.clone(), destination.clone());
+        let (files, bytes) = tokio::task::spawn_blocking(move || copy_tree(&from, &to))
+            .await
+            .map_err(|e| ToolError::IoError(e.to_string()))?
+            .map_err(ToolError::from)?;
+
+        Ok(ToolResult::success_with_data(
+            format!(
+                "Copied {} to {} ({} files, {} bytes)",
+                source.display(),
+                destination.display(),
+                files,
+                bytes
+            ),
+            json!({
+                "source": source.to_string_lossy(),
+                "destination": destination.to_string_lossy(),
+                "files": files,
+                "bytes": bytes
+            }),
+        ))
+    }
+}
+
+/// Copy a file, symlink or directory tree, returning the files and bytes copied.
+///
+/// Symlinks are recreated as links (and skipped where that needs privileges).
+fn copy_tree(source: &Path, destination: &Path) -> std::io::Result<(u64, u64)> {
+    let metadata = std::fs::symlink_metadata(source)?;
+    if metadata.is_symlink() {
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(std::fs::read_link(source)?, destination)?;
+        return Ok((0, 0));
+    }
+    if metadata.is_dir() {
+        std::fs::create_dir(destination)?;
+        let (mut files, mut bytes) = (0, 0);
+        for entry in std::fs::read_dir(source)? {
+            let entry = entry?;
+            let copied = copy_tree(&entry.path(), &destination.join(entry.file_name()))?;
+            files += copied.0;
+            bytes += copied.1;
+        }
+        return Ok((files, bytes));
+    }
+    Ok((1, std::fs::copy(source, destination)?))
+}
+
+pub struct DeletePathTool;
+
+#[async_trait]
+impl Tool for DeletePathTool {
+    fn name(&self) -> &'static str {
+        "delete_path"
+    }
+
+    fn description(&self) -> &'static str {
+        "Delete a file, symlink or directory. Non-empty directories need 'recursive'. \
+         With 'trash', the entry is moved to the project's .aiharness/trash/ instead, \
+         from where it can be moved back."
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "path": {
+                    "type": "string",
+                    "description": "The path to delete (absolute, or relative to the project root)"
+                },
+                "recursive": {
+                    "type": "boolean",
+                    "description": "Allow deleting a non-empty directory",
+                    "default": false
+                },
+                "trash": {
+                    "type": "boolean",
+                    "description": "Move to the project trash instead of deleting",
+                    "default": false
+                }
+            },
+            "required": ["path"]
+        })
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        ToolAnnotations::write("Delete path", true, true)
+    }
+
+    async fn execute(&self, args: serde_json::Value) -> Result<ToolResult, ToolError> {
+        self.run(args, None).await
+    }
+
+    async fn execute_sandboxed(
+        &self,
+        args: serde_json::Value,
+        sandbox: &PathSandbox,
+    ) -> Result<ToolResult, ToolError> {
+        self.run(args, Some(sandbox)).await
+    }
+}
+
+impl DeletePathTool {
+    async fn run(
+        &self,
+        args: serde_json::Value,
+        sandbox: Option<&PathSandbox>,
+    ) -> Result<ToolResult, ToolError> {
+        let path_str = args
+            .get("path")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ToolError::InvalidArguments("Missing 'path' parameter".to_string()))?;
+        let recursive = args
+            .get("recursive")
+            .and_then(serde_json::Value::as_bool)
+            .unwrap_or(false);
+        let trash = args
+            .get("trash")
+            .and_then(serde_json::Value::as_bool)
+            .unwrap_or(false);
+
+        let path = resolve_entry(path_str, sandbox)?;
+        guard_project_db(&path, sandbox)?;
+        guard_git_dir(&path)?;
+        let metadata = tokio::fs::symlink_metadata(&path).await.map_err(ToolError::from)?;
+        if metadata.is_dir() && !recursive {
+            let mut entries = tokio::fs::read_dir(&path).await.map_err(ToolError::from)?;
+            if entries.next_entry().await.map_err(ToolError::from)?.is_some() {
+                return Err(ToolError::InvalidArguments(format!(
+                    "Directory is not empty: {path_str} (set 'recursive' to delete it)"
+                )));
+            }
+        }
+
+        if !trash {
+            remove_entry(&path).await?;
+            return Ok(ToolResult::success_with_data(
+                format!("Deleted {}", path.display()),
+                json!({ "path": path.to_string_lossy(), "trash_path": null }),
+            ));
+        }
+
+        let sandbox = sandbox.ok_or_else(|| {
+            ToolError::InvalidArguments("'trash' needs a project to keep the trash in".to_string())
+        })?;
+        let trash_path = move_to_trash(&path, sandbox).await?;
+        Ok(ToolResult::success_with_data(
+            format!("Moved {} to {}", path.display(), trash_path.display()),
+            json!({ "path": path.to_string_lossy(), "trash_path": trash_path.to_string_lossy() }),
+        ))
+    }
+}
+
+/// Move an entry to `.aiharness/trash/<timestamp>/`, keeping its path
+/// relative to the project root so it is easy to put back.
+async fn move_to_trash(path: &Path, sandbox: &PathSandbox) -> Result<PathBuf, ToolError> {
+    let trash = sandbox.root().join(DATA_DIR).join(TRASH_DIR);
+    if path.starts_with(&trash) {
+        return Err(ToolError::InvalidArguments(format!(
+            "{} is already in the trash",
+            path.display()
+        )));
+    }
+    let relative = match path.strip_prefix(sandbox.root()) {
+        Ok(relative) => relative.to_path_buf(),
+        Err(_) => PathBuf::from(path.file_name().unwrap_or_default()),
+    };
+
+    let stamp = chrono::Utc::now().format("%Y%m%dT%H%M%S%.3fZ").to_string();
+    let mut target = trash.join(&stamp).join(&relative);
+    let mut attempt = 1;
+    while tokio::fs::symlink_metadata(&target).await.is_ok() {
+        attempt += 1;
+        target = trash.join(format!("{stamp}-{attempt}")).join(&relative);
+    }
+    if let Some(parent) = target.parent() {
+        tokio::fs::create_dir_all(parent).await.map_err(ToolError::from)?;
+    }
+    tokio::fs::rename(path, &target).await.map_err(ToolError::from)?;
+    Ok(target)
+}
+
+pub struct CreateDirectoryTool;
+
+#[async_trait]
+impl Tool for CreateDirectoryTool {
+    fn name(&self) -> &'static str {
+        "create_directory"
+    }
+
+    fn description(&self) -> &'static str {
+        "Create a directory and any missing parents. Succeeds if it already exists."
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "path": {
+                    "type": "string",
+                    "description": "The directory to create (absolute, or relative to the project root)"
+                }
+            },
+            "required": ["path"]
+        })
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        ToolAnnotations::write("Create directory", false, true)
+    }
+
+    async fn execute(&self, args: serde_json::Value) -> Result<ToolResult, ToolError> {
+        self.run(args, None).await
+    }
+
+    async fn execute_sandboxed(
+        &self,
+        args: serde_json::Value,
+        sandbox: &PathSandbox,
+    ) -> Result<ToolResult, ToolError> {
+        self.run(args, Some(sandbox)).await
+    }
+}
+
+impl CreateDirectoryTool {
+    async fn run(
+        &self,
+        args: serde_json::Value,
+        sandbox: Option<&PathSandbox>,
+    ) -> Result<ToolResult, ToolError> {
+        let path_str = args
+            .get("path")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ToolError::InvalidArguments("Missing 'path' parameter".to_string()))?;
+
+        let path = resolve_path(path_str, sandbox)?;
+        guard_project_db(&path, sandbox)?;
+        guard_git_dir(&path)?;
+        let created = match tokio::fs::metadata(&path).await {
+            Ok(metadata) if metadata.is_dir() => false,
+            Ok(_) => {
+                return Err(ToolError::InvalidPath(format!(
+                    "{path_str} exists and is not a directory"
+                )))
+            }
+            Err(_) => {
+                tokio::fs::create_dir_all(&path).await.map_err(ToolError::from)?;
+                true
+            }
+        };
+
+        let content = if created {
+            format!("Created directory {}", path.display())
+        } else {
+            format!("Directory already exists: {}", path.display())
+        };
+        Ok(ToolResult::success_with_data(
+            content,
+            json!({ "path": path.to_string_lossy(), "created": created }),
+        ))
+    }
+}
+
+pub struct StatPathTool;
+
+/// Metadata returned by `stat_path`
+#[derive(Debug, Serialize)]
+struct PathStat {
+    path: String,
+    /// `file`, `directory` or `symlink`
+    kind: &'static str,
+    size: u64,
+    modified: Option<String>,
+    accessed: Option<String>,
+    created: Option<String>,
+    readonly: bool,
+    /// Unix permission bits in octal, e.g. `644`
+    mode: Option<String>,
+    /// Where a symlink points
+    target: Option<String>,
+    /// Number of entries in a directory
+    entries: Option<usize>,
+}
+
+#[async_trait]
+impl Tool for StatPathTool {
+    fn name(&self) -> &'static str {
+        "stat_path"
+    }
+
+    fn description(&self) -> &'static str {
+        "Get a file's or directory's metadata: kind, size, modification/access/creation \
+         times, permissions, a symlink's target or a directory's entry count."
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "path": {
+                    "type": "string",
+                    "description": "The path to inspect (absolute, or relative to the project root)"
+                }
+            },
+            "required": ["path"]
+        })
+    }
+
+    fn output_schema(&self) -> Option<serde_json::Value> {
+        Some(json!({
+            "type": "object",
+            "properties": {
+                "path": { "type": "string" },
+                "kind": { "type": "string", "enum": ["file", "directory", "symlink"] },
+                "size": { "type": "integer" },
+                "modified": { "type": ["string", "null"] },
+                "accessed": { "type": ["string", "null"] },
+                "created": { "type": ["string", "null"] },
+                "readonly": { "type": "boolean" },
+                "mode": { "type": ["string", "null"] },
+                "target": { "type": ["string", "null"] },
+                "entries": { "type": ["integer", "null"] }
+            },
+            "required": ["path", "kind", "size", "readonly"]
+        }))
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        ToolAnnotations::read_only("Stat path")
+    }
+
+    async fn execute(&self, args: serde_json::Value) -> Result<ToolResult, ToolError> {
+        self.run(args, None).await
+    }
+
+    async fn execute_sandboxed(
+        &self,
+        args: serde_json::Value,
+        sandbox: &PathSandbox,
+    ) -> Result<ToolResult, ToolError> {
+        self.run(args, Some(sandbox)).await
+    }
+}
+
+impl StatPathTool {
+    async fn run(
+        &self,
+        args: serde_json::Value,
+        sandbox: Option<&PathSandbox>,
+    ) -> Result<ToolResult, ToolError> {
+        let path_str = args
+            .get("path")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ToolError::InvalidArguments("Missing 'path' parameter".to_string()))?;
+
+        // The project root itself has no parent inside the sandbox
+        let path = match resolve_entry(path_str, sandbox) {
+            Ok(path) => path,
+            Err(ToolError::InvalidPath(_) | ToolError::PathOutsideProject { .. }) => {
+                resolve_path(path_str, sandbox)?
+            }
+            Err(e) => return Err(e),
+        };
+        let metadata = tokio::fs::symlink_metadata(&path).await.map_err(ToolError::from)?;
+        let time = |time: std::io::Result<std::time::SystemTime>| {
+            time.ok()
+                .map(|time| chrono::DateTime::<chrono::Utc>::from(time).to_rfc3339())
+        };
+
+        let kind = if metadata.is_symlink() {
+            "symlink"
+        } else if metadata.is_dir() {
+            "directory"
+        } else {
+            "file"
+        };
+        let target = if metadata.is_symlink() {
+            tokio::fs::read_link(&path)
+                .await
+                .ok()
+                .map(|target| target.to_string_lossy().into_owned())
+        } else {
+            None
+        };
+        let entries = if metadata.is_dir() {
+            let mut dir = tokio::fs::read_dir(&path).await.map_err(ToolError::from)?;
+            let mut count = 0;
+            while dir.next_entry().await.map_err(ToolError::from)?.is_some() {
+                count += 1;
+            }
+            Some(count)
+        } else {
+            None
+        };
+        #[cfg(unix)]
+        let mode = {
+            use std::os::unix::fs::PermissionsExt;
+            Some(format!("{:o}", metadata.permissions().mode() & 0o7777))
+        };
+        #[cfg(not(unix))]
+        let mode = None;
+
+        let stat = PathStat {
+            path: path.to_string_lossy().into_owned(),
+            kind,
+            size: metadata.len(),
+            modified: time(metadata.modified()),
+            accessed: time(metadata.accessed()),
+            created: time(metadata.created()),
+            readonly: metadata.permissions().readonly(),
+            mode,
+            target,
+            entries,
+        };
+
+        let mut details = vec![stat.kind.to_string(), format!("{} bytes", stat.size)];
+        if let Some(target) = &stat.target {
+            details.push(format!("-> {target}"));
+        }
+        if let Some(entries) = stat.entries {
+            details.push(format!("{entries} entries"));
+        }
+        if let Some(modified) = &stat.modified {
+            details.push(format!("modified {modified}"));
+        }
+        let content = format!("{}: {}", stat.path, details.join(", "));
+        Ok(ToolResult::success_with_data(
+            content,
+            serde_json::to_value(&stat).map_err(|e| ToolError::IoError(e.to_string()))?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    // ReadFileTool tests
+    #[tokio::test]
+    async fn read_file_tool_reads_existing_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("test.txt");
+        tokio::fs::write(&file_path, "Hello, World!").await.unwrap();
+
+        let tool = ReadFileTool;
+        let args = json!({"path": file_path.to_str().unwrap()});
+        let result = tool.execute(args).await.unwrap();
+
+        assert!(result.success);
+        assert_eq!(result.content, "Hello, World!");
+    }
+
+    #[tokio::test]
+    async fn read_file_tool_fails_for_missing_file() {
+        let tool = ReadFileTool;
+        let args = json!({"path": "/tmp/nonexistent/file.txt"});
+        let result = tool.execute(args).await;
+
+        assert!(result.is_err());
+        assert!(matches!(result.unwrap_err(), ToolError::FileNotFound(_)));
+    }
+
+    #[tokio::test]
+    async fn read_file_tool_fails_for_relative_path() {
+        let tool = ReadFileTool;
+        let args = json!({"path": "relative/path.txt"});
+        let result = tool.execute(args).await;
+
+        assert!(result.is_err());
+        assert!(matches!(result.unwrap_err(), ToolError::InvalidPath(_)));
+    }
+
+    #[tokio::test]
+    async fn read_file_tool_fails_for_directory() {
+        let temp_dir = TempDir::new().unwrap();
+        let tool = ReadFileTool;
+        let args = json!({"path": temp_dir.path().to_str().unwrap()});
+        let result = tool.execute(args).await;
+
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn read_file_tool_fails_for_large_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("large.txt");
+        let large_content = "x".repeat((MAX_FILE_SIZE + 1) as usize);
+        tokio::fs::write(&file_path, large_content).await.unwrap();
+
+        let tool = ReadFileTool;
+        let args = json!({"path": file_path.to_str().unwrap()});
+        let result = tool.execute(args).await;
+
+        assert!(result.is_err());
+        assert!(matches!(result.unwrap_err(), ToolError::FileTooLarge { .. }));
+    }
+
+    #[tokio::test]
+    async fn read_file_tool_reads_line_window() {
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("log.txt");
+        let content = (1..=10)
+            .map(|i| format!("line {i}\n"))
+            .collect::<Vec<_>>()
+            .concat();
+        tokio::fs::write(&file_path, content).await.unwrap();
+
+        let tool = ReadFileTool;
+        let args = json!({"path": file_path.to_str().unwrap(), "offset": 3, "limit": 2});
+        let result = tool.execute(args).await.unwrap();
 
-        if metadata.is_dir() {
-            Box::pin(search_recursive(&path, pattern, matches, files_searched)).await?;
-        } else if metadata.is_file() {
-            *files_searched += 1;
-            let file_matches = search_file(&path, pattern).await?;
-            matches.extend(file_matches);
-        }
+        assert_eq!(result.content, "line 3\nline 4\n");
+        let data = result.data.unwrap();
+        assert_eq!(data["total_lines"], 10);
+        assert_eq!(data["end_line"], 4);
+        assert_eq!(data["has_more"], true);
     }
 
-    Ok(())
-}
+    #[tokio::test]
+    async fn read_file_tool_prefixes_line_numbers() {
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("f.txt");
+        tokio::fs::write(&file_path, "a\nb\n").await.unwrap();
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use tempfile::TempDir;
+        let tool = ReadFileTool;
+        let args = json!({"path": file_path.to_str().unwrap(), "line_numbers": true});
+        let result = tool.execute(args).await.unwrap();
+
+        assert_eq!(result.content, "     1\ta\n     2\tb\n");
+        assert_eq!(result.data.unwrap()["has_more"], false);
+    }
 
-    // ReadFileTool tests
     #[tokio::test]
-    async fn read_file_tool_reads_existing_file() {
+    async fn read_file_tool_pages_through_large_file() {
         let temp_dir = TempDir::new().unwrap();
-        let file_path = temp_dir.path().join("test.txt");
-        tokio::fs::write(&file_path, "Hello, World!").await.unwrap();
+        let file_path = temp_dir.path().join("large.txt");
+        let content = format!("{}\n", "x".repeat(99)).repeat(20_000);
+        tokio::fs::write(&file_path, content).await.unwrap();
 
         let tool = ReadFileTool;
-        let args = json!({"path": file_path.to_str().unwrap()});
+        let args = json!({"path": file_path.to_str().unwrap(), "offset": 19_999});
         let result = tool.execute(args).await.unwrap();
 
-        assert!(result.success);
-        assert_eq!(result.content, "Hello, World!");
+        let data = result.data.unwrap();
+        assert_eq!(data["total_lines"], 20_000);
+        assert_eq!(data["lines_returned"], 2);
     }
 
     #[tokio::test]
-    async fn read_file_tool_fails_for_missing_file() {
+    async fn read_file_tool_cuts_an_oversized_first_line() {
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("minified.js");
+        let max_len = usize::try_from(MAX_FILE_SIZE).unwrap();
+        let long = "x".repeat(max_len + 10);
+        tokio::fs::write(&file_path, format!("short\n{long}\nafter\n"))
+            .await
+            .unwrap();
+
         let tool = ReadFileTool;
-        let args = json!({"path": "/tmp/nonexistent/file.txt"});
-        let result = tool.execute(args).await;
+        let args = json!({"path": file_path.to_str().unwrap(), "offset": 2});
+        let result = tool.execute(args).await.unwrap();
 
-        assert!(result.is_err());
-        assert!(matches!(result.unwrap_err(), ToolError::FileNotFound(_)));
+        assert_eq!(result.content.len(), max_len + 1);
+        let data = result.data.unwrap();
+        assert_eq!(data["lines_returned"], 1);
+        assert_eq!(data["end_line"], 2);
+        assert_eq!(data["truncated"], true);
+        assert_eq!(data["has_more"], true);
     }
 
     #[tokio::test]
-    async fn read_file_tool_fails_for_relative_path() {
+    async fn read_file_tool_reads_byte_range() {
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("f.txt");
+        tokio::fs::write(&file_path, "0123456789").await.unwrap();
+
         let tool = ReadFileTool;
-        let args = json!({"path": "relative/path.txt"});
-        let result = tool.execute(args).await;
+        let args = json!({"path": file_path.to_str().unwrap(), "byte_offset": 2, "byte_length": 3});
+        let result = tool.execute(args).await.unwrap();
 
-        assert!(result.is_err());
-        assert!(matches!(result.unwrap_err(), ToolError::InvalidPath(_)));
+        assert_eq!(result.content, "234");
+        let data = result.data.unwrap();
+        assert_eq!(data["total_bytes"], 10);
+        assert_eq!(data["has_more"], true);
     }
 
     #[tokio::test]
-    async fn read_file_tool_fails_for_directory() {
+    async fn read_file_tool_rejects_binary_with_mime() {
         let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("logo.png");
+        tokio::fs::write(&file_path, b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").await.unwrap();
+
         let tool = ReadFileTool;
-        let args = json!({"path": temp_dir.path().to_str().unwrap()});
-        let result = tool.execute(args).await;
+        let args = json!({"path": file_path.to_str().unwrap()});
+        let err = tool.execute(args).await.unwrap_err();
 
-        assert!(result.is_err());
+        assert!(matches!(err, ToolError::BinaryFile { ref mime, .. } if mime == "image/png"));
     }
 
     #[tokio::test]
-    async fn read_file_tool_fails_for_large_file() {
+    async fn read_file_tool_returns_base64_for_binary() {
         let temp_dir = TempDir::new().unwrap();
-        let file_path = temp_dir.path().join("large.txt");
-        let large_content = "x".repeat((MAX_FILE_SIZE + 1) as usize);
-        tokio::fs::write(&file_path, large_content).await.unwrap();
+        let file_path = temp_dir.path().join("logo.png");
+        tokio::fs::write(&file_path, b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").await.unwrap();
 
         let tool = ReadFileTool;
-        let args = json!({"path": file_path.to_str().unwrap()});
-        let result = tool.execute(args).await;
+        let args = json!({"path": file_path.to_str().unwrap(), "encoding": "base64"});
+        let result = tool.execute(args).await.unwrap();
 
-        assert!(result.is_err());
-        assert!(matches!(result.unwrap_err(), ToolError::FileTooLarge { .. }));
+        assert_eq!(result.content, "iVBORw0KGgoAAAANSUhEUg==");
+        let data = result.data.unwrap();
+        assert_eq!(data["encoding"], "base64");
+        assert_eq!(data["mime_type"], "image/png");
+    }
+
+    #[tokio::test]
+    async fn read_file_tool_decodes_utf16_and_latin1() {
+        let temp_dir = TempDir::new().unwrap();
+        let utf16_path = temp_dir.path().join("utf16.txt");
+        let mut bytes = vec![0xFF, 0xFE];
+        for unit in "one\ntwo\n".encode_utf16() {
+            bytes.extend_from_slice(&unit.to_le_bytes());
+        }
+        tokio::fs::write(&utf16_path, bytes).await.unwrap();
+        let latin1_path = temp_dir.path().join("latin1.txt");
+        tokio::fs::write(&latin1_path, b"caf\xe9\n").await.unwrap();
+
+        let tool = ReadFileTool;
+        let result = tool.execute(json!({"path": utf16_path.to_str().unwrap()})).await.unwrap();
+        assert_eq!(result.content, "one\ntwo\n");
+        assert_eq!(result.data.unwrap()["encoding"], "UTF-16LE");
+
+        let result = tool
+            .execute(json!({"path": utf16_path.to_str().unwrap(), "offset": 2}))
+            .await
+            .unwrap();
+        assert_eq!(result.content, "two\n");
+
+        let result = tool.execute(json!({"path": latin1_path.to_str().unwrap()})).await.unwrap();
+        assert_eq!(result.content, "café\n");
+        assert_eq!(result.data.unwrap()["encoding"], "latin1");
+    }
+
+    #[tokio::test]
+    async fn read_file_tool_windows_and_ranges_keep_the_file_encoding() {
+        let temp_dir = TempDir::new().unwrap();
+        // Plain ASCII past the sniffed bytes, then Latin-1
+        let mut latin1 = "line\n".repeat(encoding::SNIFF_LEN).into_bytes();
+        latin1.extend_from_slice(b"caf\xe9\n");
+        let latin1_path = temp_dir.path().join("latin1.txt");
+        tokio::fs::write(&latin1_path, &latin1).await.unwrap();
+        let path = latin1_path.to_str().unwrap();
+
+        let tool = ReadFileTool;
+        let window = tool
+            .execute(json!({"path": path, "offset": encoding::SNIFF_LEN + 1}))
+            .await
+            .unwrap();
+        assert_eq!(window.content, "café\n");
+        assert_eq!(window.data.unwrap()["encoding"], "latin1");
+        let range = tool
+            .execute(json!({"path": path, "byte_offset": latin1.len() - 5}))
+            .await
+            .unwrap();
+        assert_eq!(range.content, "café\n");
+        assert_eq!(range.data.unwrap()["encoding"], "latin1");
+
+        // A UTF-16 range is decoded as UTF-16; a binary range is refused
+        let utf16_path = temp_dir.path().join("utf16.txt");
+        let mut bytes = vec![0xFF, 0xFE];
+        for unit in "one\ntwo\n".encode_utf16() {
+            bytes.extend_from_slice(&unit.to_le_bytes());
+        }
+        tokio::fs::write(&utf16_path, bytes).await.unwrap();
+        let range = tool
+            .execute(json!({"path": utf16_path.to_str().unwrap(), "byte_offset": 10}))
+            .await
+            .unwrap();
+        assert_eq!(range.content, "two\n");
+        let png_path = temp_dir.path().join("logo.png");
+        tokio::fs::write(&png_path, b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").await.unwrap();
+        let err = tool
+            .execute(json!({"path": png_path.to_str().unwrap(), "byte_offset": 1}))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ToolError::BinaryFile { .. }));
     }
 
     // WriteFileTool tests
@@ -567,6 +2438,110 @@ mod tests {
         assert!(matches!(result.unwrap_err(), ToolError::InvalidPath(_)));
     }
 
+    // EditFileTool tests
+    #[tokio::test]
+    async fn edit_file_tool_applies_search_replace_blocks() {
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("main.rs");
+        tokio::fs::write(&file_path, "fn a() {}\nfn b() {}\n").await.unwrap();
+
+        let tool = EditFileTool;
+        let args = json!({
+            "path": file_path.to_str().unwrap(),
+            "edits": [
+                {"search": "fn a()", "replace": "fn alpha()"},
+                {"search": "fn b()", "replace": "fn beta()"}
+            ]
+        });
+        let result = tool.execute(args).await.unwrap();
+
+        let content = tokio::fs::read_to_string(&file_path).await.unwrap();
+        assert_eq!(content, "fn alpha() {}\nfn beta() {}\n");
+        let diff = result.data.unwrap()["diff"].as_str().unwrap().to_string();
+        assert!(diff.contains("-fn a() {}"));
+        assert!(diff.contains("+fn alpha() {}"));
+    }
+
+    #[tokio::test]
+    async fn edit_file_tool_fails_for_missing_anchor_without_writing() {
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("f.txt");
+        tokio::fs::write(&file_path, "one\ntwo\n").await.unwrap();
+
+        let tool = EditFileTool;
+        let args = json!({
+            "path": file_path.to_str().unwrap(),
+            "edits": [
+                {"search": "one", "replace": "1"},
+                {"search": "three", "replace": "3"}
+            ]
+        });
+        let err = tool.execute(args).await.unwrap_err();
+
+        assert!(matches!(err, ToolError::EditFailed(ref msg) if msg.contains("edit #2")));
+        let content = tokio::fs::read_to_string(&file_path).await.unwrap();
+        assert_eq!(content, "one\ntwo\n");
+    }
+
+    #[tokio::test]
+    async fn edit_file_tool_fails_for_ambiguous_anchor() {
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("f.txt");
+        tokio::fs::write(&file_path, "x = 1\nx = 1\n").await.unwrap();
+
+        let tool = EditFileTool;
+        let args = json!({
+            "path": file_path.to_str().unwrap(),
+            "edits": [{"search": "x = 1", "replace": "x = 2"}]
+        });
+        let err = tool.execute(args).await.unwrap_err();
+
+        assert!(matches!(err, ToolError::EditFailed(ref msg) if msg.contains("matches 2 times")));
+    }
+
+    #[tokio::test]
+    async fn edit_file_tool_applies_unified_diff() {
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("f.txt");
+        tokio::fs::write(&file_path, "one\ntwo\nthree\n").await.unwrap();
+
+        let tool = EditFileTool;
+        let diff = "--- a/f.txt\n+++ b/f.txt\n@@ -1,3 +1,3 @@\n one\n-two\n+TWO\n three\n";
+        let args = json!({"path": file_path.to_str().unwrap(), "diff": diff});
+        let result = tool.execute(args).await.unwrap();
+
+        assert!(result.success);
+        let content = tokio::fs::read_to_string(&file_path).await.unwrap();
+        assert_eq!(content, "one\nTWO\nthree\n");
+    }
+
+    #[tokio::test]
+    async fn edit_file_tool_rejects_non_matching_diff() {
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("f.txt");
+        tokio::fs::write(&file_path, "one\ntwo\n").await.unwrap();
+
+        let tool = EditFileTool;
+        let diff = "--- a/f.txt\n+++ b/f.txt\n@@ -1,2 +1,2 @@\n one\n-zwei\n+TWO\n";
+        let args = json!({"path": file_path.to_str().unwrap(), "diff": diff});
+        let err = tool.execute(args).await.unwrap_err();
+
+        assert!(matches!(err, ToolError::EditFailed(_)));
+    }
+
+    #[tokio::test]
+    async fn edit_file_tool_requires_edits_or_diff() {
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("f.txt");
+        tokio::fs::write(&file_path, "one\n").await.unwrap();
+
+        let tool = EditFileTool;
+        let args = json!({"path": file_path.to_str().unwrap()});
+        let err = tool.execute(args).await.unwrap_err();
+
+        assert!(matches!(err, ToolError::InvalidArguments(_)));
+    }
+
     // ListDirectoryTool tests
     #[tokio::test]
     async fn list_directory_tool_lists_flat_directory() {
@@ -716,4 +2691,479 @@ mod tests {
         assert!(result.is_err());
         assert!(matches!(result.unwrap_err(), ToolError::InvalidArguments(_)));
     }
+
+    #[tokio::test]
+    async fn search_files_tool_supports_regex_and_case_insensitive() {
+        let temp_dir = TempDir::new().unwrap();
+        tokio::fs::write(temp_dir.path().join("lib.rs"), "fn main() {}\nFN Helper() {}").await.unwrap();
+
+        let tool = SearchFilesTool;
+        let args = json!({
+            "path": temp_dir.path().to_str().unwrap(),
+            "pattern": r"fn \w+\(",
+            "regex": true,
+            "case_insensitive": true
+        });
+        let result = tool.execute(args).await.unwrap();
+
+        let matches = result.data.unwrap()["matches"].as_array().unwrap().clone();
+        assert_eq!(matches.len(), 2);
+        assert_eq!(matches[1]["line"], 2);
+        assert_eq!(matches[1]["column"], 1);
+        assert_eq!(matches[1]["match"], "FN Helper(");
+    }
+
+    #[tokio::test]
+    async fn search_files_tool_rejects_invalid_regex() {
+        let temp_dir = TempDir::new().unwrap();
+        let tool = SearchFilesTool;
+        let args = json!({
+            "path": temp_dir.path().to_str().unwrap(),
+            "pattern": "(unclosed",
+            "regex": true
+        });
+        let result = tool.execute(args).await;
+
+        assert!(matches!(result.unwrap_err(), ToolError::InvalidArguments(_)));
+    }
+
+    #[tokio::test]
+    async fn search_files_tool_honors_ignore_files_and_default_skips() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        tokio::fs::create_dir_all(root.join("node_modules/pkg")).await.unwrap();
+        tokio::fs::create_dir_all(root.join("build")).await.unwrap();
+        tokio::fs::write(root.join("node_modules/pkg/index.js"), "needle").await.unwrap();
+        tokio::fs::write(root.join("build/out.txt"), "needle").await.unwrap();
+        tokio::fs::write(root.join("secret.log"), "needle").await.unwrap();
+        tokio::fs::write(root.join("main.txt"), "needle").await.unwrap();
+        tokio::fs::write(root.join(".gitignore"), "build/\n").await.unwrap();
+        tokio::fs::write(root.join(".aiharnessignore"), "*.log\n").await.unwrap();
+
+        let tool = SearchFilesTool;
+        let result = tool
+            .execute(json!({"path": root.to_str().unwrap(), "pattern": "needle"}))
+            .await
+            .unwrap();
+        assert!(result.content.contains("main.txt"));
+        assert!(!result.content.contains("index.js"));
+        assert!(!result.content.contains("out.txt"));
+        assert!(!result.content.contains("secret.log"));
+
+        let result = tool
+            .execute(json!({
+                "path": root.to_str().unwrap(),
+                "pattern": "needle",
+                "respect_ignore": false
+            }))
+            .await
+            .unwrap();
+        assert!(result.content.contains("index.js"));
+        assert!(result.content.contains("secret.log"));
+    }
+
+    #[tokio::test]
+    async fn search_files_tool_filters_with_include_and_exclude_globs() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        tokio::fs::create_dir_all(root.join("src")).await.unwrap();
+        tokio::fs::write(root.join("src/a.rs"), "needle").await.unwrap();
+        tokio::fs::write(root.join("src/a_test.rs"), "needle").await.unwrap();
+        tokio::fs::write(root.join("notes.md"), "needle").await.unwrap();
+
+        let tool = SearchFilesTool;
+        let result = tool
+            .execute(json!({
+                "path": root.to_str().unwrap(),
+                "pattern": "needle",
+                "include": ["*.rs"],
+                "exclude": "*_test.rs"
+            }))
+            .await
+            .unwrap();
+
+        assert!(result.content.contains("a.rs"));
+        assert!(!result.content.contains("a_test.rs"));
+        assert!(!result.content.contains("notes.md"));
+    }
+
+    #[tokio::test]
+    async fn search_files_tool_returns_context_lines() {
+        let temp_dir = TempDir::new().unwrap();
+        tokio::fs::write(temp_dir.path().join("f.txt"), "one\ntwo\nneedle\nfour\nfive")
+            .await
+            .unwrap();
+
+        let tool = SearchFilesTool;
+        let result = tool
+            .execute(json!({
+                "path": temp_dir.path().to_str().unwrap(),
+                "pattern": "needle",
+                "before_context": 2,
+                "after_context": 1
+            }))
+            .await
+            .unwrap();
+
+        let hit = &result.data.unwrap()["matches"][0];
+        assert_eq!(hit["before"], json!(["one", "two"]));
+        assert_eq!(hit["after"], json!(["four"]));
+        assert!(result.content.contains("f.txt-4- four"));
+    }
+
+    #[tokio::test]
+    async fn search_files_tool_skips_binary_files() {
+        let temp_dir = TempDir::new().unwrap();
+        tokio::fs::write(temp_dir.path().join("blob.bin"), b"needle\0\x01\x02").await.unwrap();
+        tokio::fs::write(temp_dir.path().join("notes.txt"), b"caf\xe9 needle").await.unwrap();
+
+        let tool = SearchFilesTool;
+        let result = tool
+            .execute(json!({"path": temp_dir.path().to_str().unwrap(), "pattern": "needle"}))
+            .await
+            .unwrap();
+
+        assert!(result.content.contains("notes.txt"));
+        assert!(!result.content.contains("blob.bin"));
+        assert_eq!(result.data.unwrap()["binary_skipped"], 1);
+    }
+
+    #[tokio::test]
+    async fn search_files_tool_truncates_at_max_results() {
+        let temp_dir = TempDir::new().unwrap();
+        tokio::fs::write(temp_dir.path().join("f.txt"), "x\nx\nx\nx").await.unwrap();
+
+        let tool = SearchFilesTool;
+        let result = tool
+            .execute(json!({
+                "path": temp_dir.path().to_str().unwrap(),
+                "pattern": "x",
+                "max_results": 2
+            }))
+            .await
+            .unwrap();
+
+        let data = result.data.unwrap();
+        assert_eq!(data["matches"].as_array().unwrap().len(), 2);
+        assert_eq!(data["truncated"], true);
+        assert!(result.content.contains("results truncated"));
+    }
+
+    #[tokio::test]
+    async fn search_files_reports_progress_and_stops_when_cancelled() {
+        let temp_dir = TempDir::new().unwrap();
+        for i in 0..150 {
+            std::fs::write(temp_dir.path().join(format!("f{i}.txt")), "x").unwrap();
+        }
+        let mut ctx = crate::tools::context::test_context(temp_dir.path()).await;
+        let updates = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = updates.clone();
+        ctx.progress = ProgressReporter::new(move |update| sink.lock().unwrap().push(update));
+
+        let args = json!({ "path": ".", "pattern": "x" });
+        let result = SearchFilesTool.execute_with_context(args.clone(), &ctx).await.unwrap();
+        assert_eq!(result.data.unwrap()["files_searched"], 150);
+        let updates = updates.lock().unwrap().clone();
+        assert_eq!(updates.len(), 1);
+        assert!((updates[0].progress - 100.0).abs() < f64::EPSILON);
+
+        ctx.cancel.cancel();
+        let result = SearchFilesTool.execute_with_context(args, &ctx).await;
+        assert!(matches!(result, Err(ToolError::Cancelled)));
+    }
+
+    // Sandboxed execution tests
+    #[tokio::test]
+    async fn read_file_sandboxed_resolves_relative_path() {
+        let temp_dir = TempDir::new().unwrap();
+        tokio::fs::write(temp_dir.path().join("notes.txt"), "inside").await.unwrap();
+        let sandbox = PathSandbox::new(temp_dir.path(), &[]).unwrap();
+
+        let tool = ReadFileTool;
+        let result = tool
+            .execute_sandboxed(json!({"path": "notes.txt"}), &sandbox)
+            .await
+            .unwrap();
+
+        assert_eq!(result.content, "inside");
+    }
+
+    #[tokio::test]
+    async fn read_file_sandboxed_rejects_outside_path() {
+        let temp_dir = TempDir::new().unwrap();
+        let outside = TempDir::new().unwrap();
+        let secret = outside.path().join("secret.txt");
+        tokio::fs::write(&secret, "secret").await.unwrap();
+        let sandbox = PathSandbox::new(temp_dir.path(), &[]).unwrap();
+
+        let tool = ReadFileTool;
+        let result = tool
+            .execute_sandboxed(json!({"path": secret.to_str().unwrap()}), &sandbox)
+            .await;
+
+        assert!(matches!(result.unwrap_err(), ToolError::PathOutsideProject { .. }));
+    }
+
+    #[tokio::test]
+    async fn write_file_sandboxed_rejects_parent_escape() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = temp_dir.path().join("project");
+        tokio::fs::create_dir(&project).await.unwrap();
+        let sandbox = PathSandbox::new(&project, &[]).unwrap();
+
+        let tool = WriteFileTool;
+        let result = tool
+            .execute_sandboxed(json!({"path": "../escaped.txt", "content": "x"}), &sandbox)
+            .await;
+
+        assert!(matches!(result.unwrap_err(), ToolError::PathOutsideProject { .. }));
+        assert!(!temp_dir.path().join("escaped.txt").exists());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn search_files_sandboxed_skips_symlink_escape() {
+        let temp_dir = TempDir::new().unwrap();
+        let outside = TempDir::new().unwrap();
+        tokio::fs::write(outside.path().join("leak.txt"), "needle").await.unwrap();
+        tokio::fs::write(temp_dir.path().join("own.txt"), "needle").await.unwrap();
+        std::os::unix::fs::symlink(outside.path(), temp_dir.path().join("link")).unwrap();
+        let sandbox = PathSandbox::new(temp_dir.path(), &[]).unwrap();
+
+        let tool = SearchFilesTool;
+        let result = tool
+            .execute_sandboxed(json!({"path": ".", "pattern": "needle"}), &sandbox)
+            .await
+            .unwrap();
+
+        assert!(result.content.contains("own.txt"));
+        assert!(!result.content.contains("leak.txt"));
+    }
+
+    // File management tools
+    #[tokio::test]
+    async fn move_path_renames_and_creates_parents() {
+        let temp_dir = TempDir::new().unwrap();
+        tokio::fs::write(temp_dir.path().join("old.rs"), "mod a;").await.unwrap();
+        let sandbox = PathSandbox::new(temp_dir.path(), &[]).unwrap();
+
+        let tool = MovePathTool;
+        tool.execute_sandboxed(json!({"source": "old.rs", "destination": "src/new.rs"}), &sandbox)
+            .await
+            .unwrap();
+
+        assert!(!temp_dir.path().join("old.rs").exists());
+        let moved = tokio::fs::read_to_string(temp_dir.path().join("src/new.rs")).await.unwrap();
+        assert_eq!(moved, "mod a;");
+    }
+
+    #[tokio::test]
+    async fn move_path_refuses_existing_destination_without_overwrite() {
+        let temp_dir = TempDir::new().unwrap();
+        tokio::fs::write(temp_dir.path().join("a.txt"), "a").await.unwrap();
+        tokio::fs::write(temp_dir.path().join("b.txt"), "b").await.unwrap();
+        let sandbox = PathSandbox::new(temp_dir.path(), &[]).unwrap();
+
+        let tool = MovePathTool;
+        let result = tool
+            .execute_sandboxed(json!({"source": "a.txt", "destination": "b.txt"}), &sandbox)
+            .await;
+        assert!(matches!(result.unwrap_err(), ToolError::InvalidArguments(_)));
+
+        tool.execute_sandboxed(
+            json!({"source": "a.txt", "destination": "b.txt", "overwrite": true}),
+            &sandbox,
+        )
+        .await
+        .unwrap();
+        let content = tokio::fs::read_to_string(temp_dir.path().join("b.txt")).await.unwrap();
+        assert_eq!(content, "a");
+    }
+
+    #[tokio::test]
+    async fn copy_path_copies_directory_tree() {
+        let temp_dir = TempDir::new().unwrap();
+        tokio::fs::create_dir_all(temp_dir.path().join("src/nested")).await.unwrap();
+        tokio::fs::write(temp_dir.path().join("src/lib.rs"), "lib").await.unwrap();
+        tokio::fs::write(temp_dir.path().join("src/nested/mod.rs"), "mod").await.unwrap();
+        let sandbox = PathSandbox::new(temp_dir.path(), &[]).unwrap();
+
+        let tool = CopyPathTool;
+        let result = tool
+            .execute_sandboxed(json!({"source": "src", "destination": "backup"}), &sandbox)
+            .await
+            .unwrap();
+
+        let data = result.data.unwrap();
+        assert_eq!(data["files"], 2);
+        assert_eq!(data["bytes"], 6);
+        assert!(temp_dir.path().join("src/lib.rs").exists());
+        let copied = tokio::fs::read_to_string(temp_dir.path().join("backup/nested/mod.rs"))
+            .await
+            .unwrap();
+        assert_eq!(copied, "mod");
+
+        let into_itself = tool
+            .execute_sandboxed(json!({"source": "src", "destination": "src/copy"}), &sandbox)
+            .await;
+        assert!(matches!(into_itself.unwrap_err(), ToolError::InvalidArguments(_)));
+    }
+
+    #[tokio::test]
+    async fn delete_path_needs_recursive_for_non_empty_directory() {
+        let temp_dir = TempDir::new().unwrap();
+        tokio::fs::create_dir(temp_dir.path().join("stale")).await.unwrap();
+        tokio::fs::write(temp_dir.path().join("stale/old.rs"), "").await.unwrap();
+        let sandbox = PathSandbox::new(temp_dir.path(), &[]).unwrap();
+
+        let tool = DeletePathTool;
+        let result = tool.execute_sandboxed(json!({"path": "stale"}), &sandbox).await;
+        assert!(matches!(result.unwrap_err(), ToolError::InvalidArguments(_)));
+
+        tool.execute_sandboxed(json!({"path": "stale", "recursive": true}), &sandbox)
+            .await
+            .unwrap();
+        assert!(!temp_dir.path().join("stale").exists());
+
+        let missing = tool.execute_sandboxed(json!({"path": "stale"}), &sandbox).await;
+        assert!(matches!(missing.unwrap_err(), ToolError::FileNotFound(_)));
+    }
+
+    #[tokio::test]
+    async fn delete_path_moves_to_trash() {
+        let temp_dir = TempDir::new().unwrap();
+        tokio::fs::create_dir(temp_dir.path().join("src")).await.unwrap();
+        tokio::fs::write(temp_dir.path().join("src/old.rs"), "old").await.unwrap();
+        let sandbox = PathSandbox::new(temp_dir.path(), &[]).unwrap();
+
+        let tool = DeletePathTool;
+        let result = tool
+            .execute_sandboxed(json!({"path": "src/old.rs", "trash": true}), &sandbox)
+            .await
+            .unwrap();
+
+        assert!(!temp_dir.path().join("src/old.rs").exists());
+        let trash_path = PathBuf::from(result.data.unwrap()["trash_path"].as_str().unwrap());
+        assert!(trash_path.starts_with(sandbox.root().join(".aiharness/trash")));
+        assert!(trash_path.ends_with("src/old.rs"));
+        assert_eq!(tokio::fs::read_to_string(&trash_path).await.unwrap(), "old");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn delete_path_removes_symlink_not_target() {
+        let temp_dir = TempDir::new().unwrap();
+        tokio::fs::write(temp_dir.path().join("target.txt"), "keep").await.unwrap();
+        std::os::unix::fs::symlink(temp_dir.path().join("target.txt"), temp_dir.path().join("link"))
+            .unwrap();
+        let sandbox = PathSandbox::new(temp_dir.path(), &[]).unwrap();
+
+        let tool = DeletePathTool;
+        tool.execute_sandboxed(json!({"path": "link"}), &sandbox).await.unwrap();
+
+        assert!(temp_dir.path().join("target.txt").exists());
+        assert!(std::fs::symlink_metadata(temp_dir.path().join("link")).is_err());
+    }
+
+    #[tokio::test]
+    async fn file_management_tools_refuse_project_db() {
+        let temp_dir = TempDir::new().unwrap();
+        tokio::fs::create_dir(temp_dir.path().join(".aiharness")).await.unwrap();
+        tokio::fs::write(temp_dir.path().join(".aiharness/project.db"), "db").await.unwrap();
+        let sandbox = PathSandbox::new(temp_dir.path(), &[]).unwrap();
+
+        for path in [".aiharness/project.db", ".aiharness", "."] {
+            let result = DeletePathTool
+                .execute_sandboxed(json!({"path": path, "recursive": true}), &sandbox)
+                .await;
+            assert!(result.is_err(), "deleted {path}");
+        }
+        let moved = MovePathTool
+            .execute_sandboxed(
+                json!({"source": ".aiharness/project.db", "destination": "db.bak"}),
+                &sandbox,
+            )
+            .await;
+        assert!(matches!(moved.unwrap_err(), ToolError::PermissionDenied(_)));
+        let overwritten = CopyPathTool
+            .execute_sandboxed(
+                json!({"source": "other.db", "destination": ".aiharness/project.db"}),
+                &sandbox,
+            )
+            .await;
+        assert!(matches!(overwritten.unwrap_err(), ToolError::PermissionDenied(_)));
+        assert!(temp_dir.path().join(".aiharness/project.db").exists());
+    }
+
+    #[tokio::test]
+    async fn write_tools_refuse_git_dir() {
+        let temp_dir = TempDir::new().unwrap();
+        tokio::fs::create_dir(temp_dir.path().join(".git")).await.unwrap();
+        tokio::fs::write(temp_dir.path().join(".git/config"), "").await.unwrap();
+        tokio::fs::write(temp_dir.path().join("hook.sh"), "").await.unwrap();
+        let sandbox = PathSandbox::new(temp_dir.path(), &[]).unwrap();
+
+        let written = WriteFileTool
+            .execute_sandboxed(
+                json!({"path": ".git/config", "content": "[core]\nfsmonitor = ./x"}),
+                &sandbox,
+            )
+            .await;
+        assert!(matches!(written.unwrap_err(), ToolError::PermissionDenied(_)));
+        let moved = MovePathTool
+            .execute_sandboxed(
+                json!({"source": "hook.sh", "destination": ".git/hooks/pre-commit"}),
+                &sandbox,
+            )
+            .await;
+        assert!(matches!(moved.unwrap_err(), ToolError::PermissionDenied(_)));
+        // `.GIT` is the same directory on case-insensitive file systems
+        let upper = WriteFileTool
+            .execute_sandboxed(json!({"path": ".Git/config", "content": "x"}), &sandbox)
+            .await;
+        assert!(matches!(upper.unwrap_err(), ToolError::PermissionDenied(_)));
+        assert_eq!(tokio::fs::read_to_string(temp_dir.path().join(".git/config")).await.unwrap(), "");
+    }
+
+    #[tokio::test]
+    async fn create_directory_and_stat_path() {
+        let temp_dir = TempDir::new().unwrap();
+        let sandbox = PathSandbox::new(temp_dir.path(), &[]).unwrap();
+
+        let created = CreateDirectoryTool
+            .execute_sandboxed(json!({"path": "a/b/c"}), &sandbox)
+            .await
+            .unwrap();
+        assert_eq!(created.data.unwrap()["created"], true);
+        let again = CreateDirectoryTool
+            .execute_sandboxed(json!({"path": "a/b/c"}), &sandbox)
+            .await
+            .unwrap();
+        assert_eq!(again.data.unwrap()["created"], false);
+
+        tokio::fs::write(temp_dir.path().join("a/file.txt"), "12345").await.unwrap();
+        let stat = StatPathTool
+            .execute_sandboxed(json!({"path": "a/file.txt"}), &sandbox)
+            .await
+            .unwrap()
+            .data
+            .unwrap();
+        assert_eq!(stat["kind"], "file");
+        assert_eq!(stat["size"], 5);
+        assert!(stat["modified"].is_string());
+
+        let stat = StatPathTool
+            .execute_sandboxed(json!({"path": "a"}), &sandbox)
+            .await
+            .unwrap()
+            .data
+            .unwrap();
+        assert_eq!(stat["kind"], "directory");
+        assert_eq!(stat["entries"], 2);
+
+        let root = StatPathTool.execute_sandboxed(json!({"path": "."}), &sandbox).await;
+        assert_eq!(root.unwrap().data.unwrap()["kind"], "directory");
+        let outside = StatPathTool.execute_sandboxed(json!({"path": "../x"}), &sandbox).await;
+        assert!(outside.is_err());
+    }
 }
@@ -0,0 +1,1304 @@
+//! Git tools for AIHarness
+//!
+//! Read the project repository (`git_status`, `git_diff`, `git_log`,
+//! `git_show`, `git_blame`, `git_branch`) and commit to it (`git_commit`).
+//! Every tool runs the `git` CLI in the project root and returns structured
+//! data alongside a text rendering.
+
+use super::{require_str, Tool, ToolAnnotations, ToolContext, ToolResult};
+use crate::error::ToolError;
+use async_trait::async_trait;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::process::Stdio;
+
+/// Longest diff or file text returned
+const MAX_OUTPUT_BYTES: usize = 512 * 1024;
+
+const DEFAULT_LOG_COUNT: u64 = 20;
+const MAX_LOG_COUNT: u64 = 200;
+
+/// AIHarness's own data in the project root, never committed
+const DATA_DIR: &str = ".aiharness";
+
+/// Field and record separators for `--format` output
+const FIELD: char = '\x1f';
+const RECORD: char = '\x1e';
+
+/// Settings that keep git from running programs named in the repository's
+/// config: the file tools can write there, so honouring them would hand any
+/// client without shell access a way to run commands. Bare repositories
+/// (which bring their own config) are only used when named explicitly, so a
+/// bare repo written into the project can't stand in for the real one.
+const SAFE_CONFIG: &[&str] = &[
+    "-c",
+    "safe.bareRepository=explicit",
+    "-c",
+    "core.fsmonitor=false",
+    "-c",
+    "core.hooksPath=/dev/null",
+    "-c",
+    "core.pager=cat",
+    "-c",
+    "diff.external=",
+];
+
+/// Run git in the project root, returning stdout.
+async fn git(ctx: &ToolContext, args: &[&str]) -> Result<Vec<u8>, ToolError> {
+    let output = tokio::process::Command::new("git")
+        .arg("-C")
+        .arg(ctx.root())
+        .args([
+            "--no-pager",
+            "--literal-pathspecs",
+            "-c",
+            "core.quotepath=off",
+        ])
+        .args(SAFE_CONFIG)
+        .args(args)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .env("GIT_OPTIONAL_LOCKS", "0")
+        .stdin(Stdio::null())
+        .kill_on_drop(true)
+        .output()
+        .await
+        .map_err(|e| ToolError::CommandFailed(format!("Failed to run git: {e}")))?;
+    if output.status.success() {
+        return Ok(output.stdout);
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    Err(ToolError::CommandFailed(if stderr.is_empty() {
+        format!(
+            "git {} failed ({})",
+            args.first().unwrap_or(&""),
+            output.status
+        )
+    } else {
+        stderr
+    }))
+}
+
+async fn git_text(ctx: &ToolContext, args: &[&str]) -> Result<String, ToolError> {
+    Ok(String::from_utf8_lossy(&git(ctx, args).await?).into_owned())
+}
+
+/// Output as text, cut to [`MAX_OUTPUT_BYTES`]; the flag says whether it was cut.
+fn capped(bytes: &[u8]) -> (String, bool) {
+    let mut text = String::from_utf8_lossy(bytes).into_owned();
+    if text.len() <= MAX_OUTPUT_BYTES {
+        return (text, false);
+    }
+    let mut end = MAX_OUTPUT_BYTES;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    text.truncate(end);
+    (text, true)
+}
+
+/// A revision argument, refused if git could mistake it for an option.
+fn revision<'a>(args: &'a Value, key: &str) -> Result<Option<&'a str>, ToolError> {
+    match args.get(key).and_then(|v| v.as_str()) {
+        None => Ok(None),
+        Some(rev)
+            if rev.is_empty() || rev.starts_with('-') || rev.contains(char::is_whitespace) =>
+        {
+            Err(ToolError::InvalidArguments(format!(
+                "Invalid revision: '{rev}'"
+            )))
+        }
+        Some(rev) => Ok(Some(rev)),
+    }
+}
+
+/// A path inside the project, relative to the root (`.` for the root itself).
+fn repo_path(ctx: &ToolContext, path: &str) -> Result<String, ToolError> {
+    let resolved = ctx.sandbox.resolve(path)?;
+    let relative = resolved
+        .strip_prefix(ctx.root())
+        .map_err(|_| ToolError::InvalidPath(format!("{path} is not in the project repository")))?;
+    let relative = relative.to_string_lossy().replace('\\', "/");
+    Ok(if relative.is_empty() {
+        ".".to_string()
+    } else {
+        relative
+    })
+}
+
+/// The `paths` (array) or `path` (string) argument as repository paths
+fn repo_paths(ctx: &ToolContext, args: &Value) -> Result<Vec<String>, ToolError> {
+    let mut paths = Vec::new();
+    if let Some(path) = args.get("path").and_then(|v| v.as_str()) {
+        paths.push(repo_path(ctx, path)?);
+    }
+    match args.get("paths") {
+        None | Some(Value::Null) => {}
+        Some(Value::Array(items)) => {
+            for item in items {
+                let path = item.as_str().ok_or_else(|| {
+                    ToolError::InvalidArguments("'paths' must be an array of strings".to_string())
+                })?;
+                paths.push(repo_path(ctx, path)?);
+            }
+        }
+        Some(_) => {
+            return Err(ToolError::InvalidArguments(
+                "'paths' must be an array of strings".to_string(),
+            ))
+        }
+    }
+    Ok(paths)
+}
+
+/// Lines added and removed in one file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FileChange {
+    pub path: String,
+    /// Previous path, for renames and copies
+    pub old_path: Option<String>,
+    /// `None` for binary files
+    pub additions: Option<u64>,
+    pub deletions: Option<u64>,
+}
+
+/// Parse `--numstat -z` output.
+fn parse_numstat(output: &[u8]) -> Vec<FileChange> {
+    let text = String::from_utf8_lossy(output);
+    let mut fields = text.split('\0');
+    let mut changes = Vec::new();
+    while let Some(record) = fields.next() {
+        let record = record.trim_start_matches('\n');
+        let mut parts = record.splitn(3, '\t');
+        let (Some(added), Some(deleted), Some(path)) = (parts.next(), parts.next(), parts.next())
+        else {
+            continue;
+        };
+        // A rename leaves the path empty and puts old and new paths in the next fields
+        let (path, old_path) = if path.is_empty() {
+            let old_path = fields.next().unwrap_or_default().to_string();
+            (
+                fields.next().unwrap_or_default().to_string(),
+                Some(old_path),
+            )
+        } else {
+            (path.to_string(), None)
+        };
+        changes.push(FileChange {
+            path,
+            old_path,
+            additions: added.parse().ok(),
+            deletions: deleted.parse().ok(),
+        });
+    }
+    changes
+}
+
+fn describe_changes(changes: &[FileChange]) -> String {
+    changes
+        .iter()
+        .map(|change| {
+            let stat = match (change.additions, change.deletions) {
+                (Some(added), Some(deleted)) => format!("+{added} -{deleted}"),
+                _ => "binary".to_string(),
+            };
+            match &change.old_path {
+                Some(old) => format!("{} => {} ({})", old, change.path, stat),
+                None => format!("{} ({})", change.path, stat),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// A commit's metadata
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CommitInfo {
+    pub hash: String,
+    pub short_hash: String,
+    pub author: String,
+    pub email: String,
+    /// Author date, RFC 3339
+    pub date: String,
+    pub parents: Vec<String>,
+    pub subject: String,
+    pub body: String,
+}
+
+/// `--format` for [`parse_commits`]
+const COMMIT_FORMAT: &str = "--format=%H%x1f%h%x1f%an%x1f%ae%x1f%aI%x1f%P%x1f%s%x1f%b%x1e";
+
+fn parse_commits(text: &str) -> Vec<CommitInfo> {
+    text.split(RECORD)
+        .map(|record| record.trim_start_matches('\n'))
+        .filter(|record| !record.trim().is_empty())
+        .filter_map(|record| {
+            let fields: Vec<_> = record.splitn(8, FIELD).collect();
+            let [hash, short_hash, author, email, date, parents, subject, body] = fields[..] else {
+                return None;
+            };
+            Some(CommitInfo {
+                hash: hash.to_string(),
+                short_hash: short_hash.to_string(),
+                author: author.to_string(),
+                email: email.to_string(),
+                date: date.to_string(),
+                parents: parents.split_whitespace().map(str::to_string).collect(),
+                subject: subject.to_string(),
+                body: body.trim_end().to_string(),
+            })
+        })
+        .collect()
+}
+
+pub struct GitStatusTool;
+
+/// A changed path in `git status`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StatusEntry {
+    pub path: String,
+    pub old_path: Option<String>,
+    /// Status in the index (staged), e.g. `M`, `A`, `D`, `R`; `.` if unchanged
+    pub index: String,
+    /// Status in the working tree (unstaged); `.` if unchanged
+    pub worktree: String,
+    /// `changed`, `renamed`, `unmerged` or `untracked`
+    pub kind: String,
+}
+
+/// Parse `git status --porcelain=v2 --branch -z`.
+fn parse_status(text: &str) -> Value {
+    let mut branch = None;
+    let mut upstream = None;
+    let mut ahead = 0i64;
+    let mut behind = 0i64;
+    let mut entries = Vec::new();
+
+    let mut records = text.split('\0').filter(|record| !record.is_empty());
+    while let Some(record) = records.next() {
+        let entry = |kind: &str, xy: &str, path: &str, old_path: Option<String>| StatusEntry {
+            path: path.to_string(),
+            old_path,
+            index: xy.get(0..1).unwrap_or(".").to_string(),
+            worktree: xy.get(1..2).unwrap_or(".").to_string(),
+            kind: kind.to_string(),
+        };
+        if let Some(header) = record.strip_prefix("# ") {
+            match header.split_once(' ') {
+                Some(("branch.head", head)) if head != "(detached)" => {
+                    branch = Some(head.to_string());
+                }
+                Some(("branch.upstream", name)) => upstream = Some(name.to_string()),
+                Some(("branch.ab", counts)) => {
+                    for count in counts.split_whitespace() {
+                        if let Some(n) = count.strip_prefix('+') {
+                            ahead = n.parse().unwrap_or(0);
+                        } else if let Some(n) = count.strip_prefix('-') {
+                            behind = n.parse().unwrap_or(0);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        } else if let Some(rest) = record.strip_prefix("1 ") {
+            let fields: Vec<_> = rest.splitn(8, ' ').collect();
+            if let [xy, .., path] = fields[..] {
+                entries.push(entry("changed", xy, path, None));
+            }
+        } else if let Some(rest) = record.strip_prefix("2 ") {
+            let fields: Vec<_> = rest.splitn(9, ' ').collect();
+            if let [xy, .., path] = fields[..] {
+                let old_path = records.next().map(str::to_string);
+                entries.push(entry("renamed", xy, path, old_path));
+            }
+        } else if let Some(rest) = record.strip_prefix("u ") {
+            let fields: Vec<_> = rest.splitn(10, ' ').collect();
+            if let [xy, .., path] = fields[..] {
+                entries.push(entry("unmerged", xy, path, None));
+            }
+        } else if let Some(path) = record.strip_prefix("? ") {
+            entries.push(entry("untracked", "??", path, None));
+        }
+    }
+
+    json!({
+        "branch": branch,
+        "upstream": upstream,
+        "ahead": ahead,
+        "behind": behind,
+        "clean": entries.is_empty(),
+        "entries": entries
+    })
+}
+
+fn describe_status(status: &Value) -> String {
+    let mut lines = vec![match status["branch"].as_str() {
+        Some(branch) => match status["upstream"].as_str() {
+            Some(upstream) => format!(
+                "On branch {} (ahead {}, behind {} {})",
+                branch, status["ahead"], status["behind"], upstream
+            ),
+            None => format!("On branch {branch}"),
+        },
+        None => "HEAD detached".to_string(),
+    }];
+    let entries = status["entries"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    if entries.is_empty() {
+        lines.push("nothing to commit, working tree clean".to_string());
+    }
+    for entry in entries {
+        let code = format!(
+            "{}{}",
+            entry["index"].as_str().unwrap_or("."),
+            entry["worktree"].as_str().unwrap_or(".")
+        )
+        .replace('.', " ");
+        let path = entry["path"].as_str().unwrap_or_default();
+        lines.push(match entry["old_path"].as_str() {
+            Some(old) => format!("{code} {old} -> {path}"),
+            None => format!("{code} {path}"),
+        });
+    }
+    lines.join("\n")
+}
+
+#[async_trait]
+impl Tool for GitStatusTool {
+    fn name(&self) -> &'static str {
+        "git_status"
+    }
+
+    fn description(&self) -> &'static str {
+        "Show the project repository's branch, how far it is ahead of/behind its upstream, \
+         and its staged, unstaged and untracked changes."
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({ "type": "object", "properties": {} })
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        ToolAnnotations::read_only("Git status")
+    }
+
+    async fn execute(&self, _args: Value) -> Result<ToolResult, ToolError> {
+        Err(ToolError::ProjectRequired(self.name().to_string()))
+    }
+
+    async fn execute_with_context(
+        &self,
+        _args: Value,
+        ctx: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        let output = git_text(ctx, &["status", "--porcelain=v2", "--branch", "-z"]).await?;
+        let status = parse_status(&output);
+        Ok(ToolResult::success_with_data(
+            describe_status(&status),
+            status,
+        ))
+    }
+}
+
+pub struct GitDiffTool;
+
+#[async_trait]
+impl Tool for GitDiffTool {
+    fn name(&self) -> &'static str {
+        "git_diff"
+    }
+
+    fn description(&self) -> &'static str {
+        "Show a diff in the project repository: unstaged changes by default, staged changes \
+         with 'staged', or between revisions with 'from' (and optionally 'to'). \
+         Limit it to 'paths'. Returns per-file line counts and the unified diff."
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "staged": { "type": "boolean", "description": "Diff the index instead of the working tree" },
+                "from": { "type": "string", "description": "Revision to diff from (e.g. HEAD~3, main)" },
+                "to": { "type": "string", "description": "Revision to diff to (default: the working tree or index)" },
+                "paths": { "type": "array", "items": { "type": "string" } },
+                "context_lines": { "type": "integer", "minimum": 0, "maximum": 100 }
+            }
+        })
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        ToolAnnotations::read_only("Git diff")
+    }
+
+    async fn execute(&self, _args: Value) -> Result<ToolResult, ToolError> {
+        Err(ToolError::ProjectRequired(self.name().to_string()))
+    }
+
+    async fn execute_with_context(
+        &self,
+        args: Value,
+        ctx: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        let from = revision(&args, "from")?;
+        let to = revision(&args, "to")?;
+        if to.is_some() && from.is_none() {
+            return Err(ToolError::InvalidArguments("'to' needs 'from'".to_string()));
+        }
+        let paths = repo_paths(ctx, &args)?;
+        let unified = args
+            .get("context_lines")
+            .and_then(Value::as_u64)
+            .map(|n| format!("-U{}", n.min(100)));
+
+        let mut selection: Vec<&str> = Vec::new();
+        if args.get("staged").and_then(Value::as_bool).unwrap_or(false) {
+            selection.push("--cached");
+        }
+        selection.extend(from);
+        selection.extend(to);
+        selection.push("--");
+        selection.extend(paths.iter().map(String::as_str));
+
+        let numstat: Vec<&str> = ["diff", "--no-ext-diff", "--no-textconv", "--numstat", "-z"]
+            .into_iter()
+            .chain(selection.iter().copied())
+            .collect();
+        let files = parse_numstat(&git(ctx, &numstat).await?);
+        let patch: Vec<&str> = ["diff", "--no-ext-diff", "--no-textconv", "--no-color"]
+            .into_iter()
+            .chain(unified.as_deref())
+            .chain(selection.iter().copied())
+            .collect();
+        let (diff, truncated) = capped(&git(ctx, &patch).await?);
+
+        let content = if files.is_empty() {
+            "No differences".to_string()
+        } else if truncated {
+            format!("{diff}\n[diff truncated at {MAX_OUTPUT_BYTES} bytes]")
+        } else {
+            diff.clone()
+        };
+        Ok(ToolResult::success_with_data(
+            content,
+            json!({ "files": files, "diff": diff, "truncated": truncated }),
+        ))
+    }
+}
+
+pub struct GitLogTool;
+
+#[async_trait]
+impl Tool for GitLogTool {
+    fn name(&self) -> &'static str {
+        "git_log"
+    }
+
+    fn description(&self) -> &'static str {
+        "List commits in the project repository, newest first, optionally only those \
+         touching 'path' or reachable from 'rev'."
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "rev": { "type": "string", "description": "Revision or range (default: HEAD)" },
+                "path": { "type": "string", "description": "Only commits touching this path" },
+                "max_count": { "type": "integer", "minimum": 1, "maximum": MAX_LOG_COUNT },
+                "skip": { "type": "integer", "minimum": 0 }
+            }
+        })
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        ToolAnnotations::read_only("Git log")
+    }
+
+    async fn execute(&self, _args: Value) -> Result<ToolResult, ToolError> {
+        Err(ToolError::ProjectRequired(self.name().to_string()))
+    }
+
+    async fn execute_with_context(
+        &self,
+        args: Value,
+        ctx: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        let rev = revision(&args, "rev")?;
+        let paths = repo_paths(ctx, &json!({ "path": args.get("path") }))?;
+        let max_count = format!(
+            "--max-count={}",
+            args.get("max_count")
+                .and_then(Value::as_u64)
+                .unwrap_or(DEFAULT_LOG_COUNT)
+                .clamp(1, MAX_LOG_COUNT)
+        );
+        let skip = format!(
+            "--skip={}",
+            args.get("skip").and_then(Value::as_u64).unwrap_or(0)
+        );
+
+        let mut log_args = vec!["log", COMMIT_FORMAT, max_count.as_str(), skip.as_str()];
+        log_args.extend(rev);
+        log_args.push("--");
+        log_args.extend(paths.iter().map(String::as_str));
+        let commits = parse_commits(&git_text(ctx, &log_args).await?);
+
+        let content = if commits.is_empty() {
+            "No commits".to_string()
+        } else {
+            commits
+                .iter()
+                .map(|c| {
+                    format!(
+                        "{} {} {}: {}",
+                        c.short_hash,
+                        c.date.get(..10).unwrap_or(&c.date),
+                        c.author,
+                        c.subject
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        Ok(ToolResult::success_with_data(
+            content,
+            json!({ "commits": commits }),
+        ))
+    }
+}
+
+pub struct GitShowTool;
+
+#[async_trait]
+impl Tool for GitShowTool {
+    fn name(&self) -> &'static str {
+        "git_show"
+    }
+
+    fn description(&self) -> &'static str {
+        "Show a commit (metadata, changed files and patch), or with 'path', the file's \
+         contents at that revision."
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "rev": { "type": "string", "description": "Revision (default: HEAD)" },
+                "path": { "type": "string", "description": "Show this file at the revision instead" }
+            }
+        })
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        ToolAnnotations::read_only("Git show")
+    }
+
+    async fn execute(&self, _args: Value) -> Result<ToolResult, ToolError> {
+        Err(ToolError::ProjectRequired(self.name().to_string()))
+    }
+
+    async fn execute_with_context(
+        &self,
+        args: Value,
+        ctx: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        let rev = revision(&args, "rev")?.unwrap_or("HEAD");
+
+        if let Some(path) = args.get("path").and_then(|v| v.as_str()) {
+            let path = repo_path(ctx, path)?;
+            let object = format!("{rev}:./{path}");
+            let (text, truncated) = capped(&git(ctx, &["show", "--no-textconv", &object]).await?);
+            let content = if truncated {
+                format!("{text}\n[truncated at {MAX_OUTPUT_BYTES} bytes]")
+            } else {
+                text.clone()
+            };
+            return Ok(ToolResult::success_with_data(
+                content,
+                json!({ "rev": rev, "path": path, "content": text, "truncated": truncated }),
+            ));
+        }
+
+        let commit =
+            parse_commits(&git_text(ctx, &["show", "-s", COMMIT_FORMAT, rev, "--"]).await?)
+                .into_iter()
+                .next()
+                .ok_or_else(|| ToolError::CommandFailed(format!("Not a commit: {rev}")))?;
+        let files = parse_numstat(
+            &git(
+                ctx,
+                &[
+                    "show",
+                    "--format=",
+                    "--no-textconv",
+                    "--numstat",
+                    "-z",
+                    rev,
+                    "--",
+                ],
+            )
+            .await?,
+        );
+        let (patch, truncated) = capped(
+            &git(
+                ctx,
+                &[
+                    "show",
+                    "--format=",
+                    "--no-ext-diff",
+                    "--no-textconv",
+                    "--no-color",
+                    "--patch",
+                    rev,
+                    "--",
+                ],
+            )
+            .await?,
+        );
+
+        let mut content = format!(
+            "commit {}\nAuthor: {} <{}>\nDate:   {}\n\n    {}\n",
+            commit.hash, commit.author, commit.email, commit.date, commit.subject
+        );
+        if !commit.body.is_empty() {
+            content.push('\n');
+            for line in commit.body.lines() {
+                content.push_str("    ");
+                content.push_str(line);
+                content.push('\n');
+            }
+        }
+        content.push('\n');
+        content.push_str(&patch);
+        if truncated {
+            let _ = write!(content, "\n[patch truncated at {MAX_OUTPUT_BYTES} bytes]");
+        }
+        Ok(ToolResult::success_with_data(
+            content,
+            json!({ "commit": commit, "files": files, "patch": patch, "truncated": truncated }),
+        ))
+    }
+}
+
+pub struct GitBlameTool;
+
+/// Who last changed a line
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BlameLine {
+    pub line: u64,
+    pub commit: String,
+    pub author: String,
+    /// Author date, RFC 3339
+    pub date: String,
+    pub summary: String,
+    pub text: String,
+}
+
+/// Parse `git blame --porcelain`.
+fn parse_blame(text: &str) -> Vec<BlameLine> {
+    #[derive(Default, Clone)]
+    struct Commit {
+        author: String,
+        time: i64,
+        summary: String,
+    }
+
+    let mut commits: HashMap<String, Commit> = HashMap::new();
+    let mut lines = Vec::new();
+    let mut current: Option<(String, u64)> = None;
+    for line in text.lines() {
+        if let Some(content) = line.strip_prefix('\t') {
+            if let Some((hash, number)) = current.take() {
+                let commit = commits.get(&hash).cloned().unwrap_or_default();
+                lines.push(BlameLine {
+                    line: number,
+                    commit: hash.chars().take(8).collect(),
+                    author: commit.author,
+                    date: chrono::DateTime::from_timestamp(commit.time, 0)
+                        .map(|date| date.to_rfc3339())
+                        .unwrap_or_default(),
+                    summary: commit.summary,
+                    text: content.to_string(),
+                });
+            }
+            continue;
+        }
+        let mut parts = line.split(' ');
+        let first = parts.next().unwrap_or_default();
+        if first.len() == 40 && first.bytes().all(|b| b.is_ascii_hexdigit()) {
+            let number = parts.nth(1).and_then(|n| n.parse().ok()).unwrap_or(0);
+            commits.entry(first.to_string()).or_default();
+            current = Some((first.to_string(), number));
+        } else if let Some((hash, _)) = &current {
+            let value = line
+                .split_once(' ')
+                .map(|(_, value)| value)
+                .unwrap_or_default();
+            let commit = commits.entry(hash.clone()).or_default();
+            match first {
+                "author" => commit.author = value.to_string(),
+                "author-time" => commit.time = value.parse().unwrap_or(0),
+                "summary" => commit.summary = value.to_string(),
+                _ => {}
+            }
+        }
+    }
+    lines
+}
+
+#[async_trait]
+impl Tool for GitBlameTool {
+    fn name(&self) -> &'static str {
+        "git_blame"
+    }
+
+    fn description(&self) -> &'static str {
+        "Show which commit and author last changed each line of a file, optionally for a \
+         line range or at a revision."
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "path": { "type": "string", "description": "File to blame" },
+                "start_line": { "type": "integer", "minimum": 1 },
+                "end_line": { "type": "integer", "minimum": 1 },
+                "rev": { "type": "string", "description": "Blame the file as of this revision" }
+            },
+            "required": ["path"]
+        })
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        ToolAnnotations::read_only("Git blame")
+    }
+
+    async fn execute(&self, _args: Value) -> Result<ToolResult, ToolError> {
+        Err(ToolError::ProjectRequired(self.name().to_string()))
+    }
+
+    async fn execute_with_context(
+        &self,
+        args: Value,
+        ctx: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        let path = repo_path(ctx, require_str(&args, "path")?)?;
+        let rev = revision(&args, "rev")?;
+        let start = args.get("start_line").and_then(Value::as_u64);
+        let end = args.get("end_line").and_then(Value::as_u64);
+        let range = match (start, end) {
+            (None, None) => None,
+            (start, end) => Some(format!(
+                "-L{},{}",
+                start.unwrap_or(1),
+                end.map(|end| end.to_string()).unwrap_or_default()
+            )),
+        };
+
+        let mut blame_args = vec!["blame", "--porcelain", "--no-textconv"];
+        blame_args.extend(range.as_deref());
+        blame_args.extend(rev);
+        blame_args.extend(["--", path.as_str()]);
+        let lines = parse_blame(&git_text(ctx, &blame_args).await?);
+
+        let content = lines
+            .iter()
+            .map(|l| {
+                format!(
+                    "{} ({} {}) {:>4}: {}",
+                    l.commit,
+                    l.author,
+                    l.date.get(..10).unwrap_or(&l.date),
+                    l.line,
+                    l.text
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        Ok(ToolResult::success_with_data(
+            content,
+            json!({ "path": path, "lines": lines }),
+        ))
+    }
+}
+
+pub struct GitBranchTool;
+
+/// A local or remote-tracking branch
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BranchInfo {
+    pub name: String,
+    pub commit: String,
+    pub current: bool,
+    pub remote: bool,
+    pub upstream: Option<String>,
+    /// e.g. `[ahead 1, behind 2]`
+    pub track: Option<String>,
+    pub subject: String,
+}
+
+#[async_trait]
+impl Tool for GitBranchTool {
+    fn name(&self) -> &'static str {
+        "git_branch"
+    }
+
+    fn description(&self) -> &'static str {
+        "List the project repository's branches (with 'all', remote-tracking branches too), \
+         marking the current one."
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "all": { "type": "boolean", "description": "Include remote-tracking branches" }
+            }
+        })
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        ToolAnnotations::read_only("Git branches")
+    }
+
+    async fn execute(&self, _args: Value) -> Result<ToolResult, ToolError> {
+        Err(ToolError::ProjectRequired(self.name().to_string()))
+    }
+
+    async fn execute_with_context(
+        &self,
+        args: Value,
+        ctx: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        let mut ref_args = vec![
+            "for-each-ref",
+            "--format=%(refname)%1f%(refname:short)%1f%(objectname:short)%1f%(HEAD)%1f%(upstream:short)%1f%(upstream:track)%1f%(subject)",
+            "refs/heads",
+        ];
+        if args.get("all").and_then(Value::as_bool).unwrap_or(false) {
+            ref_args.push("refs/remotes");
+        }
+        let output = git_text(ctx, &ref_args).await?;
+
+        let branches: Vec<BranchInfo> = output
+            .lines()
+            .filter_map(|line| {
+                let fields: Vec<_> = line.splitn(7, FIELD).collect();
+                let [refname, name, commit, head, upstream, track, subject] = fields[..] else {
+                    return None;
+                };
+                let optional = |s: &str| (!s.is_empty()).then(|| s.to_string());
+                Some(BranchInfo {
+                    name: name.to_string(),
+                    commit: commit.to_string(),
+                    current: head == "*",
+                    remote: refname.starts_with("refs/remotes/"),
+                    upstream: optional(upstream),
+                    track: optional(track),
+                    subject: subject.to_string(),
+                })
+            })
+            .filter(|branch| !branch.name.ends_with("/HEAD"))
+            .collect();
+        let current = branches.iter().find(|b| b.current).map(|b| b.name.clone());
+
+        let content = branches
+            .iter()
+            .map(|b| {
+                format!(
+                    "{} {} {}{} {}",
+                    if b.current { "*" } else { " " },
+                    b.name,
+                    b.commit,
+                    b.track
+                        .as_deref()
+                        .map(|t| format!(" {t}"))
+                        .unwrap_or_default(),
+                    b.subject
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        Ok(ToolResult::success_with_data(
+            content,
+            json!({ "current": current, "branches": branches }),
+        ))
+    }
+}
+
+pub struct GitCommitTool;
+
+#[async_trait]
+impl Tool for GitCommitTool {
+    fn name(&self) -> &'static str {
+        "git_commit"
+    }
+
+    fn description(&self) -> &'static str {
+        "Commit to the project repository. Stages 'paths' first (or every change with 'all'), \
+         then commits what is staged with 'message'. Refuses empty commits and AIHarness's \
+         own .aiharness/ data. Hooks are not run; nothing is amended or pushed."
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "message": { "type": "string", "description": "Commit message" },
+                "paths": { "type": "array", "items": { "type": "string" }, "description": "Paths to stage before committing" },
+                "all": { "type": "boolean", "description": "Stage every change, including untracked files" }
+            },
+            "required": ["message"]
+        })
+    }
+
+    fn output_schema(&self) -> Option<Value> {
+        Some(json!({
+            "type": "object",
+            "properties": {
+                "commit": { "type": "object" },
+                "files": { "type": "array" }
+            },
+            "required": ["commit", "files"]
+        }))
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        ToolAnnotations::write("Git commit", false, false)
+    }
+
+    async fn execute(&self, _args: Value) -> Result<ToolResult, ToolError> {
+        Err(ToolError::ProjectRequired(self.name().to_string()))
+    }
+
+    async fn execute_with_context(
+        &self,
+        args: Value,
+        ctx: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        let message = require_str(&args, "message")?;
+        if message.trim().is_empty() {
+            return Err(ToolError::InvalidArguments(
+                "Commit message is empty".to_string(),
+            ));
+        }
+        let paths = repo_paths(ctx, &args)?;
+        if paths
+            .iter()
+            .any(|path| path == DATA_DIR || path.starts_with(&format!("{DATA_DIR}/")))
+        {
+            return Err(ToolError::PermissionDenied(format!(
+                "{DATA_DIR}/ holds AIHarness's project data and is never committed"
+            )));
+        }
+
+        let all = args.get("all").and_then(Value::as_bool).unwrap_or(false);
+        if all {
+            git(ctx, &["add", "--all"]).await?;
+        }
+        if !paths.is_empty() {
+            let mut add_args = vec!["add", "--"];
+            add_args.extend(paths.iter().map(String::as_str));
+            git(ctx, &add_args).await?;
+        }
+        if all || !paths.is_empty() {
+            // `--all` or a parent directory such as "." picks up DATA_DIR too;
+            // put it back the way HEAD has it
+            git(ctx, &["reset", "-q", "--", DATA_DIR]).await?;
+        }
+
+        let staged = git_text(ctx, &["diff", "--cached", "--name-only", "-z"]).await?;
+        let staged: Vec<_> = staged.split('\0').filter(|p| !p.is_empty()).collect();
+        if staged.is_empty() {
+            return Err(ToolError::CommandFailed(
+                "Nothing staged to commit".to_string(),
+            ));
+        }
+        if let Some(path) = staged
+            .iter()
+            .find(|p| p.starts_with(&format!("{DATA_DIR}/")))
+        {
+            return Err(ToolError::PermissionDenied(format!(
+                "Refusing to commit {path} (AIHarness's project data); unstage it first"
+            )));
+        }
+
+        git(ctx, &["commit", "-q", "--no-verify", "-m", message]).await?;
+        let commit =
+            parse_commits(&git_text(ctx, &["show", "-s", COMMIT_FORMAT, "HEAD", "--"]).await?)
+                .into_iter()
+                .next()
+                .ok_or_else(|| {
+                    ToolError::CommandFailed("Commit not found after committing".to_string())
+                })?;
+        let files = parse_numstat(
+            &git(
+                ctx,
+                &[
+                    "show",
+                    "--format=",
+                    "--no-textconv",
+                    "--numstat",
+                    "-z",
+                    "--root",
+                    "HEAD",
+                    "--",
+                ],
+            )
+            .await?,
+        );
+
+        Ok(ToolResult::success_with_data(
+            format!(
+                "Committed {} {}\n{}",
+                commit.short_hash,
+                commit.subject,
+                describe_changes(&files)
+            ),
+            json!({ "commit": commit, "files": files }),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::context::test_context;
+    use tempfile::TempDir;
+
+    fn run(dir: &std::path::Path, args: &[&str]) {
+        let status = std::process::Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(args)
+            .stdout(Stdio::null())
+            .status()
+            .unwrap();
+        assert!(status.success(), "git {args:?}");
+    }
+
+    fn init_repo(dir: &std::path::Path) {
+        run(dir, &["init", "-q", "-b", "main"]);
+        run(dir, &["config", "user.name", "Test"]);
+        run(dir, &["config", "user.email", "test@example.com"]);
+        run(dir, &["config", "commit.gpgsign", "false"]);
+    }
+
+    #[test]
+    fn parsers_read_porcelain_output() {
+        let status = parse_status(
+            "# branch.oid abc\0# branch.head main\0# branch.upstream origin/main\0# branch.ab +2 -1\0\
+             1 .M N... 100644 100644 100644 aaa bbb src/my file.rs\0\
+             2 R. N... 100644 100644 100644 aaa bbb R100 new.rs\0old.rs\0? notes.txt\0",
+        );
+        assert_eq!(status["branch"], "main");
+        assert_eq!(status["ahead"], 2);
+        assert_eq!(status["behind"], 1);
+        let entries = status["entries"].as_array().unwrap();
+        assert_eq!(entries[0]["path"], "src/my file.rs");
+        assert_eq!(entries[0]["worktree"], "M");
+        assert_eq!(entries[1]["old_path"], "old.rs");
+        assert_eq!(entries[2]["kind"], "untracked");
+        assert_eq!(
+            describe_status(&status),
+            "On branch main (ahead 2, behind 1 origin/main)\n M src/my file.rs\nR  old.rs -> new.rs\n?? notes.txt"
+        );
+
+        let changes = parse_numstat(b"3\t1\ta.rs\0-\t-\tlogo.png\0\n0\t0\t\0old.rs\0new.rs\0");
+        assert_eq!(changes[0].additions, Some(3));
+        assert_eq!(changes[1].additions, None);
+        assert_eq!(changes[2].old_path.as_deref(), Some("old.rs"));
+        assert_eq!(changes[2].path, "new.rs");
+    }
+
+    #[tokio::test]
+    async fn git_tools_read_and_commit_the_project_repo() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        init_repo(root);
+        std::fs::write(root.join("lib.rs"), "fn one() {}\n").unwrap();
+        run(root, &["add", "lib.rs"]);
+        run(root, &["commit", "-q", "-m", "Add lib"]);
+        // The test store's database sits in the root rather than .aiharness/
+        std::fs::write(root.join(".git/info/exclude"), "project.db*\n").unwrap();
+        std::fs::create_dir(root.join(DATA_DIR)).unwrap();
+        std::fs::write(root.join(DATA_DIR).join("state.json"), "{}").unwrap();
+        let ctx = test_context(root).await;
+
+        std::fs::write(root.join("lib.rs"), "fn one() {}\nfn two() {}\n").unwrap();
+        std::fs::write(root.join("new.txt"), "new\n").unwrap();
+        let status = GitStatusTool
+            .execute_with_context(json!({}), &ctx)
+            .await
+            .unwrap();
+        let data = status.data.unwrap();
+        assert_eq!(data["branch"], "main");
+        let kinds: Vec<_> = data["entries"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| e["kind"].as_str().unwrap())
+            .collect();
+        assert!(
+            kinds.contains(&"changed") && kinds.contains(&"untracked"),
+            "{kinds:?}"
+        );
+
+        let diff = GitDiffTool
+            .execute_with_context(json!({ "paths": ["lib.rs"] }), &ctx)
+            .await
+            .unwrap();
+        let data = diff.data.unwrap();
+        assert_eq!(data["files"][0]["additions"], 1);
+        assert!(data["diff"].as_str().unwrap().contains("+fn two() {}"));
+        let staged = GitDiffTool
+            .execute_with_context(json!({ "staged": true }), &ctx)
+            .await
+            .unwrap();
+        assert_eq!(staged.content, "No differences");
+        let bad = GitDiffTool
+            .execute_with_context(json!({ "from": "--output=/tmp/x" }), &ctx)
+            .await;
+        assert!(matches!(bad, Err(ToolError::InvalidArguments(_))));
+
+        // Committing refuses empty commits
+        let empty = GitCommitTool
+            .execute_with_context(json!({ "message": "nothing" }), &ctx)
+            .await;
+        assert!(matches!(empty, Err(ToolError::CommandFailed(_))));
+
+        let committed = GitCommitTool
+            .execute_with_context(
+                json!({ "message": "Add two\n\nAnd a note", "all": true }),
+                &ctx,
+            )
+            .await
+            .unwrap();
+        let data = committed.data.unwrap();
+        assert_eq!(data["commit"]["subject"], "Add two");
+        let files: Vec<_> = data["files"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|f| f["path"].as_str().unwrap())
+            .collect();
+        assert_eq!(files, vec!["lib.rs", "new.txt"]);
+
+        let log = GitLogTool
+            .execute_with_context(json!({ "path": "lib.rs" }), &ctx)
+            .await
+            .unwrap();
+        let commits = log.data.unwrap()["commits"].clone();
+        assert_eq!(commits.as_array().unwrap().len(), 2);
+        assert_eq!(commits[0]["body"], "And a note");
+
+        let show = GitShowTool
+            .execute_with_context(json!({ "rev": "HEAD~1", "path": "lib.rs" }), &ctx)
+            .await
+            .unwrap();
+        assert_eq!(show.content, "fn one() {}\n");
+        let show = GitShowTool
+            .execute_with_context(json!({}), &ctx)
+            .await
+            .unwrap();
+        assert!(show.content.contains("+fn two() {}"));
+
+        let blame = GitBlameTool
+            .execute_with_context(json!({ "path": "lib.rs", "start_line": 2 }), &ctx)
+            .await
+            .unwrap();
+        let lines = blame.data.unwrap()["lines"].clone();
+        assert_eq!(lines.as_array().unwrap().len(), 1);
+        assert_eq!(lines[0]["summary"], "Add two");
+        assert_eq!(lines[0]["author"], "Test");
+
+        let branches = GitBranchTool
+            .execute_with_context(json!({}), &ctx)
+            .await
+            .unwrap();
+        assert_eq!(branches.data.unwrap()["current"], "main");
+    }
+
+    #[tokio::test]
+    async fn git_commit_keeps_aiharness_data_out() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        init_repo(root);
+        // The test store's database sits in the root rather than .aiharness/
+        std::fs::write(root.join(".git/info/exclude"), "project.db*\n").unwrap();
+        std::fs::create_dir(root.join(DATA_DIR)).unwrap();
+        std::fs::write(root.join(DATA_DIR).join("state.json"), "{}").unwrap();
+        let ctx = test_context(root).await;
+
+        let refused = GitCommitTool
+            .execute_with_context(
+                json!({ "message": "db", "paths": [".aiharness/project.db"] }),
+                &ctx,
+            )
+            .await;
+        assert!(matches!(refused, Err(ToolError::PermissionDenied(_))));
+
+        // Staging the whole root leaves AIHarness's data out of the index
+        std::fs::write(root.join("other.txt"), "other\n").unwrap();
+        let committed = GitCommitTool
+            .execute_with_context(json!({ "message": "Add other", "paths": ["."] }), &ctx)
+            .await
+            .unwrap();
+        assert_eq!(committed.data.unwrap()["files"][0]["path"], "other.txt");
+        let staged = std::process::Command::new("git")
+            .arg("-C")
+            .arg(root)
+            .args(["diff", "--cached", "--name-only"])
+            .output()
+            .unwrap();
+        assert!(staged.stdout.is_empty(), "{staged:?}");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn git_tools_ignore_commands_in_repo_config() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        init_repo(root);
+        let marker = root.join("ran");
+        let script = format!("#!/bin/sh\ntouch '{}'\nexit 1\n", marker.display());
+        std::fs::write(root.join("run.sh"), &script).unwrap();
+        std::fs::write(root.join(".git/hooks/pre-commit"), &script).unwrap();
+        for script in [root.join("run.sh"), root.join(".git/hooks/pre-commit")] {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(script, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+        let command = root.join("run.sh").display().to_string();
+        run(root, &["config", "core.fsmonitor", &command]);
+        run(root, &["config", "diff.evil.textconv", &command]);
+        std::fs::write(root.join(".gitattributes"), "*.txt diff=evil\n").unwrap();
+        std::fs::write(root.join("a.txt"), "one\n").unwrap();
+        let ctx = test_context(root).await;
+
+        GitCommitTool
+            .execute_with_context(
+                json!({ "message": "Add a", "paths": ["a.txt", ".gitattributes"] }),
+                &ctx,
+            )
+            .await
+            .unwrap();
+        std::fs::write(root.join("a.txt"), "two\n").unwrap();
+        GitStatusTool
+            .execute_with_context(json!({}), &ctx)
+            .await
+            .unwrap();
+        let diff = GitDiffTool
+            .execute_with_context(json!({}), &ctx)
+            .await
+            .unwrap();
+        assert!(diff.content.contains("+two"));
+
+        assert!(!marker.exists());
+    }
+}
@@ -1,16 +1,31 @@
 //! Tool system for AIHarness
 //!
 //! Provides a pluggable tool architecture where each tool is a pure function
-//! that takes arguments and returns a result.
+//! that takes arguments and returns a result. Every tool exposed over HTTP,
+//! MCP or the UI is registered here and run with a [`ToolContext`].
 
 use crate::error::ToolError;
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
+use std::sync::Arc;
 
+pub mod build;
+pub mod context;
+pub mod encoding;
 pub mod file;
+pub mod git;
+pub mod next_session;
+pub mod process;
+pub mod sandbox;
+pub mod shell;
+pub mod terminal;
 pub mod test;
+pub mod todo;
+
+pub use context::{Caller, Progress, ProgressReporter, ToolContext};
+pub use sandbox::PathSandbox;
 
 /// The result of executing a tool
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -56,6 +71,69 @@ impl ToolResult {
     }
 }
 
+/// Read a required string argument
+pub(crate) fn require_str<'a>(args: &'a Value, key: &str) -> Result<&'a str, ToolError> {
+    args.get(key)
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| ToolError::InvalidArguments(format!("Missing '{key}' parameter")))
+}
+
+/// Read a required integer argument
+pub(crate) fn require_i64(args: &Value, key: &str) -> Result<i64, ToolError> {
+    args.get(key)
+        .and_then(Value::as_i64)
+        .ok_or_else(|| ToolError::InvalidArguments(format!("Missing '{key}' parameter")))
+}
+
+/// Hints about a tool's behavior, sent to MCP clients as `annotations`
+///
+/// The destructive and idempotent hints only mean something for tools that
+/// are not read-only.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolAnnotations {
+    /// Human-readable title
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// The tool does not modify anything
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub read_only_hint: Option<bool>,
+    /// The tool may overwrite or delete data
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub destructive_hint: Option<bool>,
+    /// Repeating a call with the same arguments has no further effect
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idempotent_hint: Option<bool>,
+}
+
+impl ToolAnnotations {
+    /// Annotations for a tool that only reads
+    #[must_use]
+    pub fn read_only(title: &str) -> Self {
+        Self {
+            title: Some(title.to_string()),
+            read_only_hint: Some(true),
+            ..Self::default()
+        }
+    }
+
+    /// Annotations for a tool that modifies something
+    #[must_use]
+    pub fn write(title: &str, destructive: bool, idempotent: bool) -> Self {
+        Self {
+            title: Some(title.to_string()),
+            read_only_hint: Some(false),
+            destructive_hint: Some(destructive),
+            idempotent_hint: Some(idempotent),
+        }
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
 /// Definition of a tool for the HTTP tool protocol
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToolDefinition {
@@ -65,6 +143,12 @@ pub struct ToolDefinition {
     pub description: String,
     /// JSON schema for input validation
     pub input_schema: Value,
+    /// JSON schema of the result's structured `data`, if the tool returns any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_schema: Option<Value>,
+    /// Behavior hints
+    #[serde(default, skip_serializing_if = "ToolAnnotations::is_empty")]
+    pub annotations: ToolAnnotations,
 }
 
 /// Trait that all tools must implement
@@ -78,6 +162,18 @@ pub trait Tool: Send + Sync {
     
     /// Get the input schema
     fn input_schema(&self) -> Value;
+
+    /// Get the schema of the structured `data` in successful results
+    ///
+    /// Tools that declare one must return matching `data` on every success.
+    fn output_schema(&self) -> Option<Value> {
+        None
+    }
+
+    /// Get the behavior hints
+    fn annotations(&self) -> ToolAnnotations {
+        ToolAnnotations::default()
+    }
     
     /// Execute the tool with the given arguments
     /// 
@@ -85,6 +181,43 @@ pub trait Tool: Send + Sync {
     /// 
     /// Returns a `ToolError` if execution fails
     async fn execute(&self, args: Value) -> Result<ToolResult, ToolError>;
+
+    /// Execute the tool confined to a project's path sandbox
+    ///
+    /// Tools run through the default [`Tool::execute_with_context`] override
+    /// this to resolve paths through the sandbox. The default refuses, so a
+    /// tool can't end up running unconfined by leaving it out.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `ToolError` if execution fails, or `PermissionDenied` if the
+    /// tool doesn't support the sandbox
+    async fn execute_sandboxed(
+        &self,
+        _args: Value,
+        _sandbox: &PathSandbox,
+    ) -> Result<ToolResult, ToolError> {
+        Err(ToolError::PermissionDenied(format!(
+            "{} can't run inside a project sandbox",
+            self.name()
+        )))
+    }
+
+    /// Execute the tool against a project
+    ///
+    /// This is what the dispatcher calls. Project-scoped tools override it to
+    /// use the project's stores; the default runs inside the path sandbox.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `ToolError` if execution fails
+    async fn execute_with_context(
+        &self,
+        args: Value,
+        ctx: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        self.execute_sandboxed(args, &ctx.sandbox).await
+    }
     
     /// Get the full tool definition
     fn definition(&self) -> ToolDefinition {
@@ -92,6 +225,8 @@ pub trait Tool: Send + Sync {
             name: self.name().to_string(),
             description: self.description().to_string(),
             input_schema: self.input_schema(),
+            output_schema: self.output_schema(),
+            annotations: self.annotations(),
         }
     }
 }
@@ -99,7 +234,7 @@ pub trait Tool: Send + Sync {
 /// Registry of available tools
 #[derive(Default)]
 pub struct ToolRegistry {
-    tools: HashMap<String, Box<dyn Tool>>,
+    tools: HashMap<String, Arc<dyn Tool>>,
 }
 
 impl ToolRegistry {
@@ -114,7 +249,7 @@ impl ToolRegistry {
     /// Register a tool
     pub fn register(&mut self, tool: Box<dyn Tool>) {
         let name = tool.name().to_string();
-        self.tools.insert(name, tool);
+        self.tools.insert(name, Arc::from(tool));
     }
 
     /// Get a tool by name
@@ -123,18 +258,27 @@ impl ToolRegistry {
         self.tools.get(name).map(|t| t.as_ref())
     }
 
+    /// Get a shared handle to a tool, usable after the registry is unlocked
+    #[must_use]
+    pub fn get_shared(&self, name: &str) -> Option<Arc<dyn Tool>> {
+        self.tools.get(name).cloned()
+    }
+
     /// Check if a tool exists
     #[must_use]
     pub fn has(&self, name: &str) -> bool {
         self.tools.contains_key(name)
     }
 
-    /// List all available tools
+    /// List all available tools, sorted by name
     pub fn list(&self) -> Vec<ToolDefinition> {
-        self.tools
+        let mut tools = self
+            .tools
             .values()
             .map(|t| t.definition())
-            .collect()
+            .collect::<Vec<_>>();
+        tools.sort_by(|a, b| a.name.cmp(&b.name));
+        tools
     }
 
     /// Get the number of registered tools
@@ -157,9 +301,54 @@ pub fn create_standard_registry(port: u16) -> ToolRegistry {
     
     registry.register(Box::new(file::ReadFileTool));
     registry.register(Box::new(file::WriteFileTool));
+    registry.register(Box::new(file::EditFileTool));
     registry.register(Box::new(file::ListDirectoryTool));
     registry.register(Box::new(file::SearchFilesTool));
+    registry.register(Box::new(file::MovePathTool));
+    registry.register(Box::new(file::CopyPathTool));
+    registry.register(Box::new(file::DeletePathTool));
+    registry.register(Box::new(file::CreateDirectoryTool));
+    registry.register(Box::new(file::StatPathTool));
     registry.register(Box::new(test::SelfTestTool { port }));
+
+    registry.register(Box::new(todo::TodoAddTool));
+    registry.register(Box::new(todo::TodoInsertTool));
+    registry.register(Box::new(todo::TodoRemoveTool));
+    registry.register(Box::new(todo::TodoCheckTool));
+    registry.register(Box::new(todo::TodoListTool));
+    registry.register(Box::new(todo::TodoGetNextTool));
+    registry.register(Box::new(todo::TodoMoveTool));
+
+    registry.register(Box::new(build::BuildAddCommandTool));
+    registry.register(Box::new(build::BuildRemoveCommandTool));
+    registry.register(Box::new(build::BuildListCommandsTool));
+    registry.register(Box::new(build::BuildRunCommandTool));
+    registry.register(Box::new(build::BuildSetDefaultTool));
+    registry.register(Box::new(build::BuildGetDefaultTool));
+
+    registry.register(Box::new(shell::RunCommandTool));
+
+    registry.register(Box::new(terminal::TerminalOpenTool));
+    registry.register(Box::new(terminal::TerminalSendTool));
+    registry.register(Box::new(terminal::TerminalReadTool));
+    registry.register(Box::new(terminal::TerminalCloseTool));
+
+    registry.register(Box::new(process::ProcessStartTool));
+    registry.register(Box::new(process::ProcessListTool));
+    registry.register(Box::new(process::ProcessLogsTool));
+    registry.register(Box::new(process::ProcessStopTool));
+    registry.register(Box::new(process::ProcessRestartTool));
+
+    registry.register(Box::new(git::GitStatusTool));
+    registry.register(Box::new(git::GitDiffTool));
+    registry.register(Box::new(git::GitLogTool));
+    registry.register(Box::new(git::GitShowTool));
+    registry.register(Box::new(git::GitBlameTool));
+    registry.register(Box::new(git::GitBranchTool));
+    registry.register(Box::new(git::GitCommitTool));
+
+    registry.register(Box::new(next_session::NextSessionReadTool));
+    registry.register(Box::new(next_session::NextSessionWriteTool));
     
     registry
 }
@@ -209,6 +398,8 @@ mod tests {
             name: "test".to_string(),
             description: "A test tool".to_string(),
             input_schema: schema.clone(),
+            output_schema: None,
+            annotations: ToolAnnotations::default(),
         };
         assert_eq!(def.name, "test");
         assert_eq!(def.description, "A test tool");
@@ -255,6 +446,8 @@ mod tests {
         
         let tools = registry.list();
         assert_eq!(tools.len(), 2);
+        assert_eq!(tools[0].name, "read_file");
+        assert_eq!(tools[1].name, "write_file");
     }
 
     #[test]
@@ -268,8 +461,19 @@ mod tests {
         let registry = create_standard_registry(8080);
         assert!(registry.has("read_file"));
         assert!(registry.has("write_file"));
+        assert!(registry.has("edit_file"));
         assert!(registry.has("list_directory"));
         assert!(registry.has("search_files"));
+        assert!(registry.has("delete_path"));
+        assert!(registry.has("stat_path"));
+        assert!(registry.has("todo_add"));
+        assert!(registry.has("build_run_command"));
+        assert!(registry.has("run_command"));
+        assert!(registry.has("terminal_read"));
+        assert!(registry.has("process_logs"));
+        assert!(registry.has("git_status"));
+        assert!(registry.has("git_commit"));
+        assert!(registry.has("next_session_write"));
     }
 
     #[test]
@@ -279,5 +483,61 @@ mod tests {
         assert_eq!(def.name, "read_file");
         assert!(!def.description.is_empty());
         assert!(!def.input_schema.is_null());
+        assert!(def.output_schema.is_some());
+        assert_eq!(def.annotations.read_only_hint, Some(true));
+    }
+
+    #[test]
+    fn tool_annotations_serialize_as_mcp_hints() {
+        let annotations = serde_json::to_value(ToolAnnotations::write("Write file", true, true)).unwrap();
+        assert_eq!(
+            annotations,
+            serde_json::json!({
+                "title": "Write file",
+                "readOnlyHint": false,
+                "destructiveHint": true,
+                "idempotentHint": true
+            })
+        );
+        assert!(ToolAnnotations::default().is_empty());
+    }
+
+    struct Unconfined;
+
+    #[async_trait]
+    impl Tool for Unconfined {
+        fn name(&self) -> &'static str {
+            "unconfined"
+        }
+
+        fn description(&self) -> &'static str {
+            "Has no sandboxed form"
+        }
+
+        fn input_schema(&self) -> Value {
+            serde_json::json!({ "type": "object" })
+        }
+
+        async fn execute(&self, _args: Value) -> Result<ToolResult, ToolError> {
+            Ok(ToolResult::success("ran unconfined"))
+        }
+    }
+
+    #[tokio::test]
+    async fn execute_sandboxed_refuses_tools_without_a_sandboxed_form() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let sandbox = PathSandbox::new(temp_dir.path(), &[]).unwrap();
+        let result = Unconfined.execute_sandboxed(serde_json::json!({}), &sandbox).await;
+        assert!(matches!(result, Err(ToolError::PermissionDenied(_))));
+
+        // The self-test's write check is confined too
+        let outside = tempfile::TempDir::new().unwrap();
+        let result = test::SelfTestTool { port: 0 }
+            .execute_sandboxed(
+                serde_json::json!({ "project_path": outside.path().to_str().unwrap() }),
+                &sandbox,
+            )
+            .await;
+        assert!(matches!(result, Err(ToolError::PathOutsideProject { .. })));
     }
 }
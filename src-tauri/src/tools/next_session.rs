@@ -0,0 +1,109 @@
+//! Next session briefing tools for AIHarness
+//!
+//! Read and write the briefing left for the next session on a project.
+
+use super::{require_str, Tool, ToolAnnotations, ToolContext, ToolResult};
+use crate::error::ToolError;
+use async_trait::async_trait;
+use serde_json::json;
+
+pub struct NextSessionReadTool;
+
+#[async_trait]
+impl Tool for NextSessionReadTool {
+    fn name(&self) -> &'static str {
+        "next_session_read"
+    }
+
+    fn description(&self) -> &'static str {
+        "Read the next session briefing for the project."
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        json!({
+            "type": "object",
+            "properties": {}
+        })
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        ToolAnnotations::read_only("Read next session briefing")
+    }
+
+    async fn execute(&self, _args: serde_json::Value) -> Result<ToolResult, ToolError> {
+        Err(ToolError::ProjectRequired(self.name().to_string()))
+    }
+
+    async fn execute_with_context(
+        &self,
+        _args: serde_json::Value,
+        ctx: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        let briefing = ctx.store.next_session_store.read().await.get().await?;
+        Ok(ToolResult::success(
+            briefing.map(|b| b.content).unwrap_or_default(),
+        ))
+    }
+}
+
+pub struct NextSessionWriteTool;
+
+#[async_trait]
+impl Tool for NextSessionWriteTool {
+    fn name(&self) -> &'static str {
+        "next_session_write"
+    }
+
+    fn description(&self) -> &'static str {
+        "Write the next session briefing for the project."
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        json!({
+            "type": "object",
+            "properties": { "content": { "type": "string" } },
+            "required": ["content"]
+        })
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        ToolAnnotations::write("Write next session briefing", true, true)
+    }
+
+    async fn execute(&self, _args: serde_json::Value) -> Result<ToolResult, ToolError> {
+        Err(ToolError::ProjectRequired(self.name().to_string()))
+    }
+
+    async fn execute_with_context(
+        &self,
+        args: serde_json::Value,
+        ctx: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        let content = require_str(&args, "content")?;
+        let briefing = ctx.store.next_session_store.read().await.set(content).await?;
+        Ok(ToolResult::success(briefing.content))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::context::test_context;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn next_session_write_then_read() {
+        let temp = TempDir::new().unwrap();
+        let ctx = test_context(temp.path()).await;
+
+        let empty = NextSessionReadTool.execute_with_context(json!({}), &ctx).await.unwrap();
+        assert_eq!(empty.content, "");
+
+        NextSessionWriteTool
+            .execute_with_context(json!({ "content": "pick up the parser" }), &ctx)
+            .await
+            .unwrap();
+        let read = NextSessionReadTool.execute_with_context(json!({}), &ctx).await.unwrap();
+        assert_eq!(read.content, "pick up the parser");
+    }
+}
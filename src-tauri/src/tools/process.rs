@@ -0,0 +1,431 @@
+//! Background process tools for AIHarness
+//!
+//! `process_start` launches a command without waiting for it (dev servers,
+//! watchers); `process_list`, `process_logs`, `process_stop` and
+//! `process_restart` manage it afterwards. See [`crate::processes`].
+
+use super::shell::parse_env;
+use super::{require_str, Tool, ToolAnnotations, ToolContext, ToolResult};
+use crate::error::ToolError;
+use crate::processes::{LogQuery, LogStream, ProcessInfo, ProcessSpec, DEFAULT_STOP_TIMEOUT};
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+const DEFAULT_TAIL: usize = 100;
+const MAX_TAIL: usize = 1000;
+
+const MAX_STOP_TIMEOUT_MS: u64 = 60_000;
+
+/// One line per process, e.g. `dev (3f2a...) running pid 4242: npm run dev`
+fn describe(process: &ProcessInfo) -> String {
+    let status = match (process.pid, process.exit_code) {
+        (_, Some(code)) if !process.status.is_active() => {
+            format!("{:?} with exit code {}", process.status, code)
+        }
+        (Some(pid), _) if process.status.is_active() => format!("{:?} pid {}", process.status, pid),
+        _ => format!("{:?}", process.status),
+    };
+    format!(
+        "{} ({}) {}: {}",
+        process.name,
+        process.id,
+        status.to_lowercase(),
+        process.command
+    )
+}
+
+fn stop_timeout(args: &Value) -> Duration {
+    args.get("timeout_ms")
+        .and_then(Value::as_u64)
+        .map_or(DEFAULT_STOP_TIMEOUT, |ms| {
+            Duration::from_millis(ms.min(MAX_STOP_TIMEOUT_MS))
+        })
+}
+
+fn process_data(process: &ProcessInfo) -> Value {
+    serde_json::to_value(process).unwrap_or(Value::Null)
+}
+
+pub struct ProcessStartTool;
+
+#[async_trait]
+impl Tool for ProcessStartTool {
+    fn name(&self) -> &'static str {
+        "process_start"
+    }
+
+    fn description(&self) -> &'static str {
+        "Start a long-running shell command in the background (a dev server, a file watcher) \
+         and return immediately. Use process_logs to see its output and process_stop to stop it. \
+         Refer to it later by the returned id or by 'name'."
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "command": { "type": "string", "description": "Shell command line" },
+                "name": { "type": "string", "description": "Name to refer to the process by (default: the command)" },
+                "cwd": { "type": "string", "description": "Working directory, relative to the project root" },
+                "env": {
+                    "type": "object",
+                    "additionalProperties": { "type": "string" },
+                    "description": "Environment variables to set"
+                }
+            },
+            "required": ["command"]
+        })
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        ToolAnnotations::write("Start background process", false, false)
+    }
+
+    async fn execute(&self, _args: Value) -> Result<ToolResult, ToolError> {
+        Err(ToolError::ProjectRequired(self.name().to_string()))
+    }
+
+    async fn execute_with_context(
+        &self,
+        args: Value,
+        ctx: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        let command = require_str(&args, "command")?;
+        let cwd = match args.get("cwd").and_then(|v| v.as_str()) {
+            Some(cwd) => ctx.sandbox.resolve(cwd)?,
+            None => ctx.root().to_path_buf(),
+        };
+        if !cwd.is_dir() {
+            return Err(ToolError::InvalidArguments(format!(
+                "Not a directory: {}",
+                cwd.display()
+            )));
+        }
+        let spec = ProcessSpec {
+            name: args
+                .get("name")
+                .and_then(|v| v.as_str())
+                .map(ToString::to_string),
+            command: command.to_string(),
+            cwd,
+            env: parse_env(&args)?,
+        };
+
+        let process = ctx
+            .store
+            .processes
+            .start(spec)
+            .map_err(ToolError::CommandFailed)?;
+        Ok(ToolResult::success_with_data(
+            format!("Started {}", describe(&process)),
+            process_data(&process),
+        ))
+    }
+}
+
+pub struct ProcessListTool;
+
+#[async_trait]
+impl Tool for ProcessListTool {
+    fn name(&self) -> &'static str {
+        "process_list"
+    }
+
+    fn description(&self) -> &'static str {
+        "List the project's background processes, running and finished, with their status."
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({ "type": "object", "properties": {} })
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        ToolAnnotations::read_only("List background processes")
+    }
+
+    async fn execute(&self, _args: Value) -> Result<ToolResult, ToolError> {
+        Err(ToolError::ProjectRequired(self.name().to_string()))
+    }
+
+    async fn execute_with_context(
+        &self,
+        _args: Value,
+        ctx: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        let processes = ctx.store.processes.list();
+        let content = if processes.is_empty() {
+            "No background processes".to_string()
+        } else {
+            processes.iter().map(describe).collect::<Vec<_>>().join("\n")
+        };
+        Ok(ToolResult::success_with_data(
+            content,
+            json!({ "processes": processes }),
+        ))
+    }
+}
+
+pub struct ProcessLogsTool;
+
+#[async_trait]
+impl Tool for ProcessLogsTool {
+    fn name(&self) -> &'static str {
+        "process_logs"
+    }
+
+    fn description(&self) -> &'static str {
+        "Show a background process's recent output (stdout and stderr, the last 5000 lines \
+         are kept). Filter with a regex 'grep' or by 'stream', and pass the returned cursor \
+         as 'since' to get only newer lines."
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "id": { "type": "string", "description": "Process id or name" },
+                "tail": { "type": "integer", "minimum": 1, "maximum": MAX_TAIL, "description": "Newest lines to return (default 100)" },
+                "grep": { "type": "string", "description": "Only lines matching this regex" },
+                "stream": { "type": "string", "enum": ["stdout", "stderr"] },
+                "since": { "type": "integer", "minimum": 0, "description": "Only lines after this cursor" }
+            },
+            "required": ["id"]
+        })
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        ToolAnnotations::read_only("Read process logs")
+    }
+
+    async fn execute(&self, _args: Value) -> Result<ToolResult, ToolError> {
+        Err(ToolError::ProjectRequired(self.name().to_string()))
+    }
+
+    async fn execute_with_context(
+        &self,
+        args: Value,
+        ctx: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        let id = require_str(&args, "id")?;
+        let grep = args
+            .get("grep")
+            .and_then(|v| v.as_str())
+            .map(regex::Regex::new)
+            .transpose()
+            .map_err(|e| ToolError::InvalidArguments(format!("Invalid grep pattern: {e}")))?;
+        let stream = args
+            .get("stream")
+            .filter(|v| !v.is_null())
+            .map(|v| serde_json::from_value::<LogStream>(v.clone()))
+            .transpose()
+            .map_err(|_| {
+                ToolError::InvalidArguments("'stream' must be stdout or stderr".to_string())
+            })?;
+        let query = LogQuery {
+            since: args.get("since").and_then(Value::as_u64),
+            tail: args
+                .get("tail")
+                .and_then(Value::as_u64)
+                .map_or(DEFAULT_TAIL, |n| usize::try_from(n).unwrap_or(MAX_TAIL))
+                .clamp(1, MAX_TAIL),
+            grep,
+            stream,
+        };
+
+        let processes = &ctx.store.processes;
+        let process = processes.get(id).map_err(ToolError::InvalidArguments)?;
+        let page = processes
+            .logs(id, &query)
+            .map_err(ToolError::InvalidArguments)?;
+
+        let mut content = vec![describe(&process)];
+        if page.omitted > 0 {
+            content.push(format!("[{} earlier matching lines omitted]", page.omitted));
+        }
+        content.extend(page.lines.iter().map(|line| match line.stream {
+            LogStream::Stdout => line.text.clone(),
+            LogStream::Stderr => format!("[stderr] {}", line.text),
+        }));
+        if page.lines.is_empty() {
+            content.push("(no matching output)".to_string());
+        }
+
+        Ok(ToolResult::success_with_data(
+            content.join("\n"),
+            json!({
+                "process": process,
+                "lines": page.lines,
+                "cursor": page.cursor,
+                "omitted": page.omitted
+            }),
+        ))
+    }
+}
+
+pub struct ProcessStopTool;
+
+#[async_trait]
+impl Tool for ProcessStopTool {
+    fn name(&self) -> &'static str {
+        "process_stop"
+    }
+
+    fn description(&self) -> &'static str {
+        "Stop a background process (and anything it started): SIGTERM first, then a kill \
+         if it is still running after timeout_ms (default 5000)."
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "id": { "type": "string", "description": "Process id or name" },
+                "timeout_ms": { "type": "integer", "minimum": 0, "maximum": MAX_STOP_TIMEOUT_MS }
+            },
+            "required": ["id"]
+        })
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        ToolAnnotations::write("Stop background process", true, true)
+    }
+
+    async fn execute(&self, _args: Value) -> Result<ToolResult, ToolError> {
+        Err(ToolError::ProjectRequired(self.name().to_string()))
+    }
+
+    async fn execute_with_context(
+        &self,
+        args: Value,
+        ctx: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        let id = require_str(&args, "id")?;
+        let process = ctx
+            .store
+            .processes
+            .stop(id, stop_timeout(&args))
+            .await
+            .map_err(ToolError::InvalidArguments)?;
+        Ok(ToolResult::success_with_data(
+            describe(&process),
+            process_data(&process),
+        ))
+    }
+}
+
+pub struct ProcessRestartTool;
+
+#[async_trait]
+impl Tool for ProcessRestartTool {
+    fn name(&self) -> &'static str {
+        "process_restart"
+    }
+
+    fn description(&self) -> &'static str {
+        "Restart a background process with the same command, stopping it first if it is \
+         running. Its log is kept."
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "id": { "type": "string", "description": "Process id or name" },
+                "timeout_ms": { "type": "integer", "minimum": 0, "maximum": MAX_STOP_TIMEOUT_MS }
+            },
+            "required": ["id"]
+        })
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        ToolAnnotations::write("Restart background process", true, false)
+    }
+
+    async fn execute(&self, _args: Value) -> Result<ToolResult, ToolError> {
+        Err(ToolError::ProjectRequired(self.name().to_string()))
+    }
+
+    async fn execute_with_context(
+        &self,
+        args: Value,
+        ctx: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        let id = require_str(&args, "id")?;
+        let process = ctx
+            .store
+            .processes
+            .restart(id, stop_timeout(&args))
+            .await
+            .map_err(ToolError::CommandFailed)?;
+        Ok(ToolResult::success_with_data(
+            format!("Restarted {}", describe(&process)),
+            process_data(&process),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::context::test_context;
+    use tempfile::TempDir;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn process_tools_start_log_and_stop() {
+        let temp_dir = TempDir::new().unwrap();
+        let ctx = test_context(temp_dir.path()).await;
+
+        let started = ProcessStartTool
+            .execute_with_context(
+                json!({ "command": "echo ready; echo oops >&2; sleep 30", "name": "dev" }),
+                &ctx,
+            )
+            .await
+            .unwrap();
+        assert_eq!(started.data.unwrap()["status"], "running");
+
+        let mut logs = Value::Null;
+        for _ in 0..100 {
+            logs = ProcessLogsTool
+                .execute_with_context(json!({ "id": "dev" }), &ctx)
+                .await
+                .unwrap()
+                .data
+                .unwrap();
+            if logs["lines"].as_array().unwrap().len() == 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(logs["cursor"], 2);
+
+        let stderr = ProcessLogsTool
+            .execute_with_context(json!({ "id": "dev", "stream": "stderr" }), &ctx)
+            .await
+            .unwrap();
+        assert!(stderr.content.ends_with("[stderr] oops"), "{}", stderr.content);
+        let bad = ProcessLogsTool
+            .execute_with_context(json!({ "id": "dev", "grep": "(" }), &ctx)
+            .await;
+        assert!(matches!(bad, Err(ToolError::InvalidArguments(_))));
+
+        let listed = ProcessListTool
+            .execute_with_context(json!({}), &ctx)
+            .await
+            .unwrap();
+        assert!(listed.content.starts_with("dev ("), "{}", listed.content);
+
+        let stopped = ProcessStopTool
+            .execute_with_context(json!({ "id": "dev" }), &ctx)
+            .await
+            .unwrap();
+        assert_eq!(stopped.data.unwrap()["status"], "stopped");
+
+        let missing = ProcessStopTool
+            .execute_with_context(json!({ "id": "nope" }), &ctx)
+            .await;
+        assert!(matches!(missing, Err(ToolError::InvalidArguments(_))));
+    }
+}
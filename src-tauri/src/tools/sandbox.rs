@@ -0,0 +1,220 @@
+//! Project path sandbox for file tools
+//!
+//! Resolves tool paths against a project root, canonicalizes them and
+//! rejects anything that escapes the root or its allowed extra roots.
+
+use crate::error::ToolError;
+use std::path::{Path, PathBuf};
+
+/// Directories a project-scoped tool call is allowed to touch
+#[derive(Debug, Clone)]
+pub struct PathSandbox {
+    root: PathBuf,
+    extra_roots: Vec<PathBuf>,
+}
+
+impl PathSandbox {
+    /// Create a sandbox for a project root plus extra allowed roots
+    ///
+    /// Extra roots that no longer exist are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToolError::InvalidPath` if the project root cannot be canonicalized
+    pub fn new(root: impl AsRef<Path>, extra_roots: &[String]) -> Result<Self, ToolError> {
+        let root = root.as_ref();
+        let root = std::fs::canonicalize(root)
+            .map_err(|e| ToolError::InvalidPath(format!("{}: {}", root.display(), e)))?;
+
+        let extra_roots = extra_roots
+            .iter()
+            .filter_map(|extra| match std::fs::canonicalize(extra) {
+                Ok(path) => Some(path),
+                Err(e) => {
+                    tracing::warn!("Skipping allowed root {extra}: {e}");
+                    None
+                }
+            })
+            .collect();
+
+        Ok(Self { root, extra_roots })
+    }
+
+    /// The canonical project root
+    #[must_use]
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Check whether a canonical path lies inside the root or an allowed root
+    #[must_use]
+    pub fn contains(&self, path: &Path) -> bool {
+        path.starts_with(&self.root) || self.extra_roots.iter().any(|r| path.starts_with(r))
+    }
+
+    /// Resolve a tool-supplied path to a canonical path inside the sandbox
+    ///
+    /// Relative paths are resolved against the project root. Paths that do
+    /// not exist yet are resolved through their nearest existing ancestor.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToolError::PathOutsideProject` if the path escapes the sandbox
+    /// through `..` or a symlink, or `ToolError::InvalidPath` if it cannot be resolved
+    pub fn resolve(&self, path_str: &str) -> Result<PathBuf, ToolError> {
+        let path = Path::new(path_str);
+        let joined = if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            self.root.join(path)
+        };
+
+        let resolved = canonicalize_lenient(&joined)?;
+        if self.contains(&resolved) {
+            Ok(resolved)
+        } else {
+            Err(ToolError::PathOutsideProject {
+                path: path_str.to_string(),
+                root: self.root.to_string_lossy().to_string(),
+            })
+        }
+    }
+}
+
+/// Canonicalize a path whose trailing components may not exist yet.
+///
+/// The deepest existing ancestor is canonicalized (resolving symlinks and
+/// `..`) and the missing components are appended. A missing tail cannot
+/// contain `..` or a dangling symlink, since neither can be resolved safely.
+fn canonicalize_lenient(path: &Path) -> Result<PathBuf, ToolError> {
+    let mut existing = path;
+    let mut missing = Vec::new();
+
+    loop {
+        match std::fs::canonicalize(existing) {
+            Ok(canonical) => {
+                let mut resolved = canonical;
+                for name in missing.iter().rev() {
+                    resolved.push(name);
+                }
+                return Ok(resolved);
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                if std::fs::symlink_metadata(existing).is_ok() {
+                    return Err(ToolError::InvalidPath(format!(
+                        "Dangling symlink: {}",
+                        existing.display()
+                    )));
+                }
+                let name = existing.file_name().ok_or_else(|| {
+                    ToolError::InvalidPath(format!("Cannot resolve path: {}", path.display()))
+                })?;
+                missing.push(name.to_os_string());
+                existing = existing.parent().ok_or_else(|| {
+                    ToolError::InvalidPath(format!("Cannot resolve path: {}", path.display()))
+                })?;
+            }
+            Err(e) => return Err(ToolError::from(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn sandbox_for(dir: &TempDir) -> PathSandbox {
+        PathSandbox::new(dir.path(), &[]).unwrap()
+    }
+
+    #[test]
+    fn resolve_relative_path_against_root() {
+        let temp = TempDir::new().unwrap();
+        std::fs::write(temp.path().join("a.txt"), "a").unwrap();
+        let sandbox = sandbox_for(&temp);
+
+        let resolved = sandbox.resolve("a.txt").unwrap();
+        assert_eq!(resolved, sandbox.root().join("a.txt"));
+    }
+
+    #[test]
+    fn resolve_allows_missing_file_inside_root() {
+        let temp = TempDir::new().unwrap();
+        let sandbox = sandbox_for(&temp);
+
+        let resolved = sandbox.resolve("new/dir/file.txt").unwrap();
+        assert_eq!(resolved, sandbox.root().join("new/dir/file.txt"));
+    }
+
+    #[test]
+    fn resolve_rejects_parent_dir_escape() {
+        let temp = TempDir::new().unwrap();
+        let project = temp.path().join("project");
+        std::fs::create_dir(&project).unwrap();
+        std::fs::write(temp.path().join("secret.txt"), "s").unwrap();
+        let sandbox = PathSandbox::new(&project, &[]).unwrap();
+
+        let err = sandbox.resolve("../secret.txt").unwrap_err();
+        assert!(matches!(err, ToolError::PathOutsideProject { .. }));
+    }
+
+    #[test]
+    fn resolve_rejects_absolute_path_outside_root() {
+        let temp = TempDir::new().unwrap();
+        let other = TempDir::new().unwrap();
+        let sandbox = sandbox_for(&temp);
+
+        let err = sandbox.resolve(other.path().to_str().unwrap()).unwrap_err();
+        assert!(matches!(err, ToolError::PathOutsideProject { .. }));
+    }
+
+    #[test]
+    fn resolve_rejects_parent_dir_in_missing_tail() {
+        let temp = TempDir::new().unwrap();
+        let sandbox = sandbox_for(&temp);
+
+        let err = sandbox.resolve("missing/../../outside.txt").unwrap_err();
+        assert!(matches!(
+            err,
+            ToolError::PathOutsideProject { .. } | ToolError::InvalidPath(_)
+        ));
+    }
+
+    #[test]
+    fn resolve_accepts_allowed_extra_root() {
+        let temp = TempDir::new().unwrap();
+        let shared = TempDir::new().unwrap();
+        std::fs::write(shared.path().join("lib.rs"), "").unwrap();
+        let sandbox =
+            PathSandbox::new(temp.path(), &[shared.path().to_string_lossy().to_string()]).unwrap();
+
+        let path = shared.path().join("lib.rs");
+        assert!(sandbox.resolve(path.to_str().unwrap()).is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn resolve_rejects_symlink_escape() {
+        let temp = TempDir::new().unwrap();
+        let outside = TempDir::new().unwrap();
+        std::fs::write(outside.path().join("id_rsa"), "key").unwrap();
+        std::os::unix::fs::symlink(outside.path(), temp.path().join("link")).unwrap();
+        let sandbox = sandbox_for(&temp);
+
+        let err = sandbox.resolve("link/id_rsa").unwrap_err();
+        assert!(matches!(err, ToolError::PathOutsideProject { .. }));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn resolve_rejects_dangling_symlink() {
+        let temp = TempDir::new().unwrap();
+        let outside = TempDir::new().unwrap();
+        std::os::unix::fs::symlink(outside.path().join("nope"), temp.path().join("dangling"))
+            .unwrap();
+        let sandbox = sandbox_for(&temp);
+
+        assert!(sandbox.resolve("dangling").is_err());
+    }
+}
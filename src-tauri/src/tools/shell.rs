@@ -0,0 +1,576 @@
+//! Shell command tool for AIHarness
+//!
+//! `run_command` runs an arbitrary shell command in the project, with a hard
+//! timeout, environment overrides, optional stdin and capped output.
+
+use super::{require_str, ProgressReporter, Tool, ToolAnnotations, ToolContext, ToolResult};
+use crate::error::ToolError;
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, VecDeque};
+use std::path::Path;
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+
+const DEFAULT_TIMEOUT_MS: u64 = 60_000;
+const MAX_TIMEOUT_MS: u64 = 600_000;
+
+/// Output kept per stream (and for the combined output) unless asked otherwise
+const DEFAULT_MAX_OUTPUT_BYTES: usize = 64 * 1024;
+const MAX_OUTPUT_BYTES: usize = 1024 * 1024;
+
+/// Longest progress message (one chunk of output)
+const PROGRESS_MESSAGE_CHARS: usize = 200;
+
+/// A command run through the platform shell: `sh -lc` (`cmd /C` on Windows)
+pub(crate) fn shell_command(command: &str) -> tokio::process::Command {
+    #[cfg(target_os = "windows")]
+    let cmd = {
+        let mut cmd = tokio::process::Command::new("cmd");
+        cmd.arg("/C").arg(command);
+        cmd
+    };
+
+    #[cfg(not(target_os = "windows"))]
+    let cmd = {
+        let mut cmd = tokio::process::Command::new("sh");
+        cmd.arg("-lc").arg(command);
+        cmd
+    };
+
+    cmd
+}
+
+/// Make a command lead its own process group, so [`signal_group`] reaches
+/// everything it starts.
+pub(crate) fn own_process_group(cmd: &mut tokio::process::Command) {
+    #[cfg(unix)]
+    cmd.process_group(0);
+    #[cfg(not(unix))]
+    let _ = cmd;
+}
+
+#[derive(Clone, Copy)]
+pub(crate) enum Signal {
+    Terminate,
+    Kill,
+}
+
+/// Signal a process's whole group (it must lead its own; see [`own_process_group`]).
+#[cfg(unix)]
+pub(crate) fn signal_group(pid: Option<u32>, signal: Signal) {
+    use nix::sys::signal::{killpg, Signal as UnixSignal};
+    use nix::unistd::Pid;
+
+    let Some(pid) = pid.and_then(|pid| i32::try_from(pid).ok()) else {
+        return;
+    };
+    let signal = match signal {
+        Signal::Terminate => UnixSignal::SIGTERM,
+        Signal::Kill => UnixSignal::SIGKILL,
+    };
+    let _ = killpg(Pid::from_raw(pid), signal);
+}
+
+/// Without process groups only the process itself is killed.
+#[cfg(not(unix))]
+pub(crate) fn signal_group(_pid: Option<u32>, _signal: Signal) {}
+
+/// Kills a command's process group when dropped, unless it finished first.
+///
+/// `kill_on_drop` only reaches the shell; this also stops what it started
+/// (`npm`, `cargo`, servers) when a command times out or is cancelled.
+pub(crate) struct GroupKillGuard(Option<u32>);
+
+impl GroupKillGuard {
+    pub(crate) fn new(pid: Option<u32>) -> Self {
+        Self(pid)
+    }
+
+    /// The command exited on its own; leave its group alone
+    pub(crate) fn disarm(mut self) {
+        self.0 = None;
+    }
+}
+
+impl Drop for GroupKillGuard {
+    fn drop(&mut self) {
+        if self.0.is_some() {
+            signal_group(self.0, Signal::Kill);
+        }
+    }
+}
+
+/// Read the optional `env` argument: an object of variable names to values
+pub(crate) fn parse_env(args: &Value) -> Result<BTreeMap<String, String>, ToolError> {
+    match args.get("env") {
+        None | Some(Value::Null) => Ok(BTreeMap::new()),
+        Some(Value::Object(vars)) => vars
+            .iter()
+            .map(|(name, value)| match value.as_str() {
+                Some(value) => Ok((name.clone(), value.to_string())),
+                None => Err(ToolError::InvalidArguments(format!(
+                    "env value for '{name}' must be a string"
+                ))),
+            })
+            .collect(),
+        Some(_) => Err(ToolError::InvalidArguments(
+            "'env' must be an object of strings".to_string(),
+        )),
+    }
+}
+
+/// How to run a command with [`run_captured`]
+#[derive(Debug, Clone)]
+pub struct CommandOptions {
+    pub env: BTreeMap<String, String>,
+    /// Written to the command's stdin, which is then closed
+    pub stdin: Option<String>,
+    /// Bytes kept of stdout, stderr and the combined output each
+    pub max_output_bytes: usize,
+}
+
+/// What a finished command printed and how it exited
+#[derive(Debug, Clone)]
+pub struct CommandOutput {
+    /// `None` if the process was killed by a signal
+    pub exit_code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+    /// stdout and stderr interleaved in the order they were read
+    pub combined: String,
+    /// Some output was dropped to stay within `max_output_bytes`
+    pub truncated: bool,
+    pub duration: Duration,
+}
+
+/// Run `command` in `cwd`, reporting output as progress as it arrives.
+///
+/// The command and everything it started are killed if the returned future
+/// is dropped (on timeout or cancellation).
+///
+/// # Errors
+///
+/// Returns a message if the command cannot be started or its output read
+pub async fn run_captured(
+    command: &str,
+    cwd: &Path,
+    options: &CommandOptions,
+    progress: &ProgressReporter,
+) -> Result<CommandOutput, String> {
+    let started = Instant::now();
+    let mut cmd = shell_command(command);
+    cmd.current_dir(cwd)
+        .envs(&options.env)
+        .stdin(if options.stdin.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    own_process_group(&mut cmd);
+
+    let mut child = cmd
+        .spawn()
+        .map_err(|e| format!("Failed to run command: {e}"))?;
+    let group = GroupKillGuard::new(child.id());
+
+    // Write stdin from a task so a command that doesn't read it can't stall us
+    if let (Some(mut pipe), Some(input)) = (child.stdin.take(), options.stdin.clone()) {
+        tokio::spawn(async move {
+            let _ = pipe.write_all(input.as_bytes()).await;
+        });
+    }
+
+    let mut stdout_pipe = child.stdout.take();
+    let mut stderr_pipe = child.stderr.take();
+    let mut stdout = CappedOutput::new(options.max_output_bytes);
+    let mut stderr = CappedOutput::new(options.max_output_bytes);
+    let mut combined = CappedOutput::new(options.max_output_bytes);
+    let mut stdout_chunk = vec![0u8; 8192];
+    let mut stderr_chunk = vec![0u8; 8192];
+    let mut read_total = 0usize;
+
+    while stdout_pipe.is_some() || stderr_pipe.is_some() {
+        let (read, from_stdout) = tokio::select! {
+            read = read_chunk(&mut stdout_pipe, &mut stdout_chunk), if stdout_pipe.is_some() => (read, true),
+            read = read_chunk(&mut stderr_pipe, &mut stderr_chunk), if stderr_pipe.is_some() => (read, false),
+        };
+        let n = match read {
+            Ok(n) if n > 0 => n,
+            _ => {
+                if from_stdout {
+                    stdout_pipe = None;
+                } else {
+                    stderr_pipe = None;
+                }
+                continue;
+            }
+        };
+        let chunk = if from_stdout {
+            &stdout_chunk[..n]
+        } else {
+            &stderr_chunk[..n]
+        };
+        if from_stdout {
+            stdout.push(chunk);
+        } else {
+            stderr.push(chunk);
+        }
+        combined.push(chunk);
+
+        read_total += n;
+        if progress.is_enabled() {
+            let text = String::from_utf8_lossy(chunk);
+            let message = text.trim_end().chars().take(PROGRESS_MESSAGE_CHARS).collect();
+            progress.report_count(read_total, Some(message));
+        }
+    }
+
+    let status = child
+        .wait()
+        .await
+        .map_err(|e| format!("Failed to run command: {e}"))?;
+    group.disarm();
+
+    Ok(CommandOutput {
+        exit_code: status.code(),
+        truncated: stdout.truncated() || stderr.truncated() || combined.truncated(),
+        stdout: stdout.into_string(),
+        stderr: stderr.into_string(),
+        combined: combined.into_string(),
+        duration: started.elapsed(),
+    })
+}
+
+/// Read the next chunk from an output pipe that is still open
+async fn read_chunk<R: AsyncRead + Unpin>(
+    pipe: &mut Option<R>,
+    chunk: &mut [u8],
+) -> std::io::Result<usize> {
+    match pipe {
+        Some(pipe) => pipe.read(chunk).await,
+        None => Ok(0),
+    }
+}
+
+/// Keeps the first and last halves of a stream's output up to a byte limit,
+/// where error messages and summaries usually are
+struct CappedOutput {
+    limit: usize,
+    head: Vec<u8>,
+    tail: VecDeque<u8>,
+    total: usize,
+}
+
+impl CappedOutput {
+    fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            head: Vec::new(),
+            tail: VecDeque::new(),
+            total: 0,
+        }
+    }
+
+    fn push(&mut self, bytes: &[u8]) {
+        self.total += bytes.len();
+        let head_room = (self.limit / 2).saturating_sub(self.head.len());
+        let (head, rest) = bytes.split_at(head_room.min(bytes.len()));
+        self.head.extend_from_slice(head);
+
+        let tail_limit = self.limit - self.limit / 2;
+        self.tail.extend(rest);
+        let excess = self.tail.len().saturating_sub(tail_limit);
+        self.tail.drain(..excess);
+    }
+
+    fn truncated(&self) -> bool {
+        self.total > self.head.len() + self.tail.len()
+    }
+
+    fn into_string(self) -> String {
+        let omitted = self.total - self.head.len() - self.tail.len();
+        let mut head = self.head;
+        let tail = self.tail.into_iter().collect::<Vec<_>>();
+        if omitted == 0 {
+            head.extend(tail);
+            return String::from_utf8_lossy(&head).to_string();
+        }
+        format!(
+            "{}\n[... {} bytes omitted ...]\n{}",
+            String::from_utf8_lossy(&head),
+            omitted,
+            String::from_utf8_lossy(&tail)
+        )
+    }
+}
+
+pub struct RunCommandTool;
+
+impl RunCommandTool {
+    fn options(args: &Value) -> Result<(CommandOptions, Duration), ToolError> {
+        let env = parse_env(args)?;
+        let stdin = args
+            .get("stdin")
+            .and_then(|v| v.as_str())
+            .map(ToString::to_string);
+        let timeout_ms = args
+            .get("timeout_ms")
+            .and_then(Value::as_u64)
+            .unwrap_or(DEFAULT_TIMEOUT_MS)
+            .clamp(1, MAX_TIMEOUT_MS);
+        let max_output_bytes = args
+            .get("max_output_bytes")
+            .and_then(Value::as_u64)
+            .map_or(DEFAULT_MAX_OUTPUT_BYTES, |n| {
+                usize::try_from(n).unwrap_or(MAX_OUTPUT_BYTES)
+            })
+            .clamp(1, MAX_OUTPUT_BYTES);
+
+        Ok((
+            CommandOptions {
+                env,
+                stdin,
+                max_output_bytes,
+            },
+            Duration::from_millis(timeout_ms),
+        ))
+    }
+}
+
+#[async_trait]
+impl Tool for RunCommandTool {
+    fn name(&self) -> &'static str {
+        "run_command"
+    }
+
+    fn description(&self) -> &'static str {
+        "Run a shell command in the project and return its exit code and output. \
+         The working directory must be inside the project (default: the project root). \
+         Output is reported as progress while the command runs. The command is killed \
+         when the timeout (default 60s, max 600s) expires."
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "command": { "type": "string", "description": "Shell command line" },
+                "cwd": { "type": "string", "description": "Working directory, relative to the project root" },
+                "env": {
+                    "type": "object",
+                    "additionalProperties": { "type": "string" },
+                    "description": "Environment variables to set"
+                },
+                "stdin": { "type": "string", "description": "Input written to the command's stdin" },
+                "timeout_ms": { "type": "integer", "minimum": 1, "maximum": MAX_TIMEOUT_MS },
+                "max_output_bytes": {
+                    "type": "integer",
+                    "minimum": 1,
+                    "maximum": MAX_OUTPUT_BYTES,
+                    "description": "Output kept per stream; the middle of longer output is dropped"
+                }
+            },
+            "required": ["command"]
+        })
+    }
+
+    fn output_schema(&self) -> Option<Value> {
+        Some(json!({
+            "type": "object",
+            "properties": {
+                "exit_code": { "type": ["integer", "null"] },
+                "stdout": { "type": "string" },
+                "stderr": { "type": "string" },
+                "duration_ms": { "type": "integer" },
+                "truncated": { "type": "boolean" }
+            },
+            "required": ["exit_code", "stdout", "stderr", "duration_ms", "truncated"]
+        }))
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        ToolAnnotations::write("Run command", true, false)
+    }
+
+    async fn execute(&self, _args: Value) -> Result<ToolResult, ToolError> {
+        Err(ToolError::ProjectRequired(self.name().to_string()))
+    }
+
+    async fn execute_with_context(
+        &self,
+        args: Value,
+        ctx: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        let command = require_str(&args, "command")?;
+        let cwd = match args.get("cwd").and_then(|v| v.as_str()) {
+            Some(cwd) => ctx.sandbox.resolve(cwd)?,
+            None => ctx.root().to_path_buf(),
+        };
+        if !cwd.is_dir() {
+            return Err(ToolError::InvalidArguments(format!(
+                "Not a directory: {}",
+                cwd.display()
+            )));
+        }
+        let (options, timeout) = Self::options(&args)?;
+
+        let output = tokio::time::timeout(
+            timeout,
+            run_captured(command, &cwd, &options, &ctx.progress),
+        )
+        .await
+        .map_err(|_| ToolError::Timeout {
+            tool: self.name().to_string(),
+            duration_ms: u64::try_from(timeout.as_millis()).unwrap_or(u64::MAX),
+        })?
+        .map_err(ToolError::CommandFailed)?;
+
+        let duration_ms = u64::try_from(output.duration.as_millis()).unwrap_or(u64::MAX);
+        let status = match output.exit_code {
+            Some(code) => format!("exit code {code}"),
+            None => "killed by a signal".to_string(),
+        };
+        let separator = if output.combined.is_empty() || output.combined.ends_with('\n') {
+            ""
+        } else {
+            "\n"
+        };
+        let content = format!(
+            "{}{}[{} after {}ms]",
+            output.combined, separator, status, duration_ms
+        );
+
+        Ok(ToolResult::success_with_data(
+            content,
+            json!({
+                "exit_code": output.exit_code,
+                "stdout": output.stdout,
+                "stderr": output.stderr,
+                "duration_ms": duration_ms,
+                "truncated": output.truncated
+            }),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::context::test_context;
+    use tempfile::TempDir;
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn timed_out_command_kills_its_children() {
+        let temp_dir = TempDir::new().unwrap();
+        let options = CommandOptions {
+            env: BTreeMap::new(),
+            stdin: None,
+            max_output_bytes: DEFAULT_MAX_OUTPUT_BYTES,
+        };
+        let progress = ProgressReporter::default();
+        let run = run_captured(
+            "sleep 30 & echo $! > child.pid; wait",
+            temp_dir.path(),
+            &options,
+            &progress,
+        );
+        assert!(tokio::time::timeout(Duration::from_millis(500), run).await.is_err());
+
+        let pid = std::fs::read_to_string(temp_dir.path().join("child.pid")).unwrap();
+        // Gone, or a zombie waiting for init to reap it
+        let alive = || {
+            std::fs::read_to_string(format!("/proc/{}/stat", pid.trim()))
+                .is_ok_and(|stat| !stat.contains(") Z"))
+        };
+        for _ in 0..50 {
+            if !alive() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert!(!alive());
+    }
+
+    #[test]
+    fn capped_output_keeps_head_and_tail() {
+        let mut output = CappedOutput::new(8);
+        output.push(b"abc");
+        output.push(b"defghijkl");
+        assert!(output.truncated());
+        assert_eq!(output.into_string(), "abcd\n[... 4 bytes omitted ...]\nijkl");
+
+        let mut output = CappedOutput::new(8);
+        output.push(b"abcdefgh");
+        assert!(!output.truncated());
+        assert_eq!(output.into_string(), "abcdefgh");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn run_command_reports_exit_code_streams_env_and_stdin() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::create_dir(temp_dir.path().join("sub")).unwrap();
+        let ctx = test_context(temp_dir.path()).await;
+
+        let result = RunCommandTool
+            .execute_with_context(
+                json!({
+                    "command": "pwd; echo \"$GREETING\"; cat; echo oops >&2; exit 3",
+                    "cwd": "sub",
+                    "env": { "GREETING": "hello" },
+                    "stdin": "from stdin\n"
+                }),
+                &ctx,
+            )
+            .await
+            .unwrap();
+        let data = result.data.unwrap();
+        assert_eq!(data["exit_code"], 3);
+        let stdout = data["stdout"].as_str().unwrap();
+        assert!(stdout.trim_start().ends_with("sub\nhello\nfrom stdin\n"), "{stdout}");
+        assert_eq!(data["stderr"], "oops\n");
+        assert_eq!(data["truncated"], false);
+        assert!(result.content.contains("oops"));
+        assert!(result.content.ends_with(&format!("[exit code 3 after {}ms]", data["duration_ms"])));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn run_command_times_out_truncates_and_stays_in_the_project() {
+        let temp_dir = TempDir::new().unwrap();
+        let ctx = test_context(temp_dir.path()).await;
+
+        let timed_out = RunCommandTool
+            .execute_with_context(json!({ "command": "sleep 5", "timeout_ms": 100 }), &ctx)
+            .await;
+        assert!(matches!(
+            timed_out,
+            Err(ToolError::Timeout { duration_ms: 100, .. })
+        ));
+
+        let result = RunCommandTool
+            .execute_with_context(
+                json!({ "command": "seq 1 10000", "max_output_bytes": 100 }),
+                &ctx,
+            )
+            .await
+            .unwrap();
+        let data = result.data.unwrap();
+        assert_eq!(data["truncated"], true);
+        let stdout = data["stdout"].as_str().unwrap();
+        assert!(stdout.starts_with("1\n2\n"));
+        assert!(stdout.ends_with("9999\n10000\n"));
+        assert!(stdout.contains("bytes omitted"));
+
+        let outside = RunCommandTool
+            .execute_with_context(json!({ "command": "ls", "cwd": "../" }), &ctx)
+            .await;
+        assert!(matches!(outside, Err(ToolError::PathOutsideProject { .. })));
+    }
+}
@@ -0,0 +1,396 @@
+//! Terminal tools for AIHarness
+//!
+//! `terminal_open`, `terminal_send`, `terminal_read` and `terminal_close`
+//! drive persistent shells (see [`crate::terminals`]), so state such as the
+//! working directory and exported variables survives between commands.
+
+use super::shell::parse_env;
+use super::{require_str, Tool, ToolAnnotations, ToolContext, ToolResult};
+use crate::error::ToolError;
+use crate::terminals::{strip_ansi, TerminalOptions};
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::fmt::Write;
+use std::time::Duration;
+
+const DEFAULT_COLS: u16 = 120;
+const DEFAULT_ROWS: u16 = 40;
+
+const DEFAULT_WAIT_MS: u64 = 1_000;
+const MAX_WAIT_MS: u64 = 60_000;
+
+const DEFAULT_MAX_BYTES: usize = 64 * 1024;
+const MAX_BYTES: usize = 1024 * 1024;
+
+fn optional_u64(args: &Value, key: &str) -> Option<u64> {
+    args.get(key).and_then(Value::as_u64)
+}
+
+pub struct TerminalOpenTool;
+
+#[async_trait]
+impl Tool for TerminalOpenTool {
+    fn name(&self) -> &'static str {
+        "terminal_open"
+    }
+
+    fn description(&self) -> &'static str {
+        "Open a persistent terminal: a shell on a pseudo-terminal that keeps its state \
+         (working directory, environment, activated virtualenvs) between terminal_send \
+         calls. Returns the terminal id. Terminals unused for 30 minutes are closed."
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "shell": { "type": "string", "description": "Program to run (default: the user's login shell)" },
+                "cwd": { "type": "string", "description": "Working directory, relative to the project root" },
+                "env": {
+                    "type": "object",
+                    "additionalProperties": { "type": "string" },
+                    "description": "Environment variables to set"
+                },
+                "cols": { "type": "integer", "minimum": 20, "maximum": 500 },
+                "rows": { "type": "integer", "minimum": 5, "maximum": 200 }
+            }
+        })
+    }
+
+    fn output_schema(&self) -> Option<Value> {
+        Some(json!({
+            "type": "object",
+            "properties": {
+                "id": { "type": "string" },
+                "shell": { "type": "string" },
+                "cwd": { "type": "string" },
+                "cursor": { "type": "integer" }
+            },
+            "required": ["id", "shell", "cwd", "cursor"]
+        }))
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        ToolAnnotations::write("Open terminal", false, false)
+    }
+
+    async fn execute(&self, _args: Value) -> Result<ToolResult, ToolError> {
+        Err(ToolError::ProjectRequired(self.name().to_string()))
+    }
+
+    async fn execute_with_context(
+        &self,
+        args: Value,
+        ctx: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        let cwd = match args.get("cwd").and_then(|v| v.as_str()) {
+            Some(cwd) => ctx.sandbox.resolve(cwd)?,
+            None => ctx.root().to_path_buf(),
+        };
+        if !cwd.is_dir() {
+            return Err(ToolError::InvalidArguments(format!(
+                "Not a directory: {}",
+                cwd.display()
+            )));
+        }
+        let size = |key, default, min, max| {
+            optional_u64(&args, key)
+                .map_or(default, |n| u16::try_from(n).unwrap_or(max))
+                .clamp(min, max)
+        };
+        let options = TerminalOptions {
+            shell: args
+                .get("shell")
+                .and_then(|v| v.as_str())
+                .map(ToString::to_string),
+            cwd,
+            env: parse_env(&args)?,
+            cols: size("cols", DEFAULT_COLS, 20, 500),
+            rows: size("rows", DEFAULT_ROWS, 5, 200),
+        };
+
+        let info = ctx
+            .terminals
+            .open(&ctx.project_id, &options)
+            .map_err(ToolError::CommandFailed)?;
+        Ok(ToolResult::success_with_data(
+            format!("Opened terminal {} ({}) in {}", info.id, info.shell, info.cwd),
+            json!({
+                "id": info.id,
+                "shell": info.shell,
+                "cwd": info.cwd,
+                "cursor": info.cursor
+            }),
+        ))
+    }
+}
+
+pub struct TerminalSendTool;
+
+#[async_trait]
+impl Tool for TerminalSendTool {
+    fn name(&self) -> &'static str {
+        "terminal_send"
+    }
+
+    fn description(&self) -> &'static str {
+        "Type input into a terminal. Enter is pressed afterwards unless 'enter' is false; \
+         send control keys as characters (e.g. \"\\u0003\" for Ctrl-C). Returns the cursor \
+         the resulting output starts at; read it with terminal_read."
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "id": { "type": "string", "description": "Terminal id" },
+                "input": { "type": "string", "description": "Text to type" },
+                "enter": { "type": "boolean", "description": "Press Enter after the input (default: true)" }
+            },
+            "required": ["id", "input"]
+        })
+    }
+
+    fn output_schema(&self) -> Option<Value> {
+        Some(json!({
+            "type": "object",
+            "properties": {
+                "cursor": { "type": "integer" }
+            },
+            "required": ["cursor"]
+        }))
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        ToolAnnotations::write("Send to terminal", true, false)
+    }
+
+    async fn execute(&self, _args: Value) -> Result<ToolResult, ToolError> {
+        Err(ToolError::ProjectRequired(self.name().to_string()))
+    }
+
+    async fn execute_with_context(
+        &self,
+        args: Value,
+        ctx: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        let id = require_str(&args, "id")?.to_string();
+        let mut input = require_str(&args, "input")?.to_string();
+        if args.get("enter").and_then(Value::as_bool).unwrap_or(true) {
+            input.push('\r');
+        }
+
+        let terminals = ctx.terminals.clone();
+        let project_id = ctx.project_id.clone();
+        let bytes = input.len();
+        let cursor = tokio::task::spawn_blocking(move || terminals.send(&project_id, &id, &input))
+            .await
+            .map_err(|e| ToolError::CommandFailed(e.to_string()))?
+            .map_err(ToolError::InvalidArguments)?;
+
+        Ok(ToolResult::success_with_data(
+            format!("Sent {bytes} bytes; output starts at cursor {cursor}"),
+            json!({ "cursor": cursor }),
+        ))
+    }
+}
+
+pub struct TerminalReadTool;
+
+#[async_trait]
+impl Tool for TerminalReadTool {
+    fn name(&self) -> &'static str {
+        "terminal_read"
+    }
+
+    fn description(&self) -> &'static str {
+        "Read a terminal's output since a cursor (default: where the last read stopped). \
+         Waits up to wait_ms (default 1000) for new output and returns once it goes quiet. \
+         Escape sequences are stripped unless 'raw' is true. Returns the cursor to continue from."
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "id": { "type": "string", "description": "Terminal id" },
+                "cursor": { "type": "integer", "minimum": 0 },
+                "wait_ms": { "type": "integer", "minimum": 0, "maximum": MAX_WAIT_MS },
+                "max_bytes": { "type": "integer", "minimum": 1024, "maximum": MAX_BYTES },
+                "raw": { "type": "boolean", "description": "Keep escape sequences (colors, cursor movement)" }
+            },
+            "required": ["id"]
+        })
+    }
+
+    fn output_schema(&self) -> Option<Value> {
+        Some(json!({
+            "type": "object",
+            "properties": {
+                "output": { "type": "string" },
+                "cursor": { "type": "integer" },
+                "dropped": { "type": "integer" },
+                "more": { "type": "boolean" },
+                "exited": { "type": "boolean" },
+                "exit_code": { "type": ["integer", "null"] }
+            },
+            "required": ["output", "cursor", "dropped", "more", "exited", "exit_code"]
+        }))
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        ToolAnnotations::read_only("Read terminal")
+    }
+
+    async fn execute(&self, _args: Value) -> Result<ToolResult, ToolError> {
+        Err(ToolError::ProjectRequired(self.name().to_string()))
+    }
+
+    async fn execute_with_context(
+        &self,
+        args: Value,
+        ctx: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        let id = require_str(&args, "id")?;
+        let wait_ms = optional_u64(&args, "wait_ms")
+            .unwrap_or(DEFAULT_WAIT_MS)
+            .min(MAX_WAIT_MS);
+        let max_bytes = optional_u64(&args, "max_bytes")
+            .map_or(DEFAULT_MAX_BYTES, |n| usize::try_from(n).unwrap_or(MAX_BYTES))
+            .clamp(1024, MAX_BYTES);
+        let raw = args.get("raw").and_then(Value::as_bool).unwrap_or(false);
+
+        let read = ctx.terminals.read(
+            &ctx.project_id,
+            id,
+            optional_u64(&args, "cursor"),
+            Duration::from_millis(wait_ms),
+            max_bytes,
+        );
+        let mut output = tokio::select! {
+            result = read => result.map_err(ToolError::InvalidArguments)?,
+            () = ctx.cancel.cancelled() => return Err(ToolError::Cancelled),
+        };
+        if !raw {
+            output.output = strip_ansi(&output.output);
+        }
+
+        let mut content = if output.output.is_empty() {
+            "(no new output)".to_string()
+        } else {
+            output.output.clone()
+        };
+        if output.dropped > 0 {
+            content.insert_str(0, &format!("[{} older bytes no longer kept]\n", output.dropped));
+        }
+        if output.exited {
+            let status = output.exit_code.map_or_else(
+                || "unknown status".to_string(),
+                |code| format!("exit code {code}"),
+            );
+            let _ = write!(content, "\n[terminal exited with {status}]");
+        } else if output.more {
+            let _ = write!(content, "\n[more output from cursor {}]", output.cursor);
+        }
+
+        Ok(ToolResult::success_with_data(
+            content,
+            serde_json::to_value(&output).unwrap_or(Value::Null),
+        ))
+    }
+}
+
+pub struct TerminalCloseTool;
+
+#[async_trait]
+impl Tool for TerminalCloseTool {
+    fn name(&self) -> &'static str {
+        "terminal_close"
+    }
+
+    fn description(&self) -> &'static str {
+        "Close a terminal, killing its shell and anything still running in it."
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "id": { "type": "string", "description": "Terminal id" }
+            },
+            "required": ["id"]
+        })
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        ToolAnnotations::write("Close terminal", true, false)
+    }
+
+    async fn execute(&self, _args: Value) -> Result<ToolResult, ToolError> {
+        Err(ToolError::ProjectRequired(self.name().to_string()))
+    }
+
+    async fn execute_with_context(
+        &self,
+        args: Value,
+        ctx: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        let id = require_str(&args, "id")?;
+        ctx.terminals
+            .close(&ctx.project_id, id)
+            .map_err(ToolError::InvalidArguments)?;
+        Ok(ToolResult::success(format!("Closed terminal {id}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::context::test_context;
+    use tempfile::TempDir;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn terminal_tools_run_commands_in_one_shell() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::create_dir(temp_dir.path().join("sub")).unwrap();
+        let ctx = test_context(temp_dir.path()).await;
+
+        let opened = TerminalOpenTool
+            .execute_with_context(json!({ "shell": "sh", "cwd": "sub" }), &ctx)
+            .await
+            .unwrap();
+        let id = opened.data.unwrap()["id"].as_str().unwrap().to_string();
+
+        TerminalSendTool
+            .execute_with_context(json!({ "id": id, "input": "printf '\\033[1mbold\\033[0m %s\\n' $(basename $PWD)" }), &ctx)
+            .await
+            .unwrap();
+        let mut output = String::new();
+        for _ in 0..20 {
+            let read = TerminalReadTool
+                .execute_with_context(json!({ "id": id, "wait_ms": 500 }), &ctx)
+                .await
+                .unwrap();
+            output.push_str(read.data.unwrap()["output"].as_str().unwrap());
+            if output.contains("bold sub") {
+                break;
+            }
+        }
+        assert!(output.contains("bold sub"), "{output:?}");
+
+        let outside = TerminalOpenTool
+            .execute_with_context(json!({ "cwd": "../" }), &ctx)
+            .await;
+        assert!(matches!(outside, Err(ToolError::PathOutsideProject { .. })));
+
+        TerminalCloseTool
+            .execute_with_context(json!({ "id": id }), &ctx)
+            .await
+            .unwrap();
+        let closed = TerminalReadTool
+            .execute_with_context(json!({ "id": id }), &ctx)
+            .await;
+        assert!(matches!(closed, Err(ToolError::InvalidArguments(_))));
+    }
+}
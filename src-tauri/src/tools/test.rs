@@ -1,6 +1,6 @@
 //! System self-test tool for AIHarness
 
-use super::{Tool, ToolResult};
+use super::{PathSandbox, Tool, ToolAnnotations, ToolResult};
 use crate::error::ToolError;
 use async_trait::async_trait;
 use serde_json::json;
@@ -33,6 +33,23 @@ impl Tool for SelfTestTool {
         })
     }
 
+    fn annotations(&self) -> ToolAnnotations {
+        ToolAnnotations::write("Run self-test", false, true)
+    }
+
+    async fn execute_sandboxed(
+        &self,
+        mut args: serde_json::Value,
+        sandbox: &PathSandbox,
+    ) -> Result<ToolResult, ToolError> {
+        // The write check stays inside the project like the file tools
+        if let Some(path) = args.get("project_path").and_then(|v| v.as_str()) {
+            let resolved = sandbox.resolve(path)?;
+            args["project_path"] = json!(resolved.to_string_lossy());
+        }
+        self.execute(args).await
+    }
+
     async fn execute(&self, args: serde_json::Value) -> Result<ToolResult, ToolError> {
         let mut results = Vec::new();
         let mut all_pass = true;
@@ -0,0 +1,405 @@
+//! Todo list tools for AIHarness
+//!
+//! Operate on the ordered todo list of the calling project.
+
+use super::{require_i64, require_str, Tool, ToolAnnotations, ToolContext, ToolResult};
+use crate::error::ToolError;
+use async_trait::async_trait;
+use serde_json::json;
+
+fn description_arg(args: &serde_json::Value) -> Option<String> {
+    args.get("description")
+        .and_then(|v| v.as_str())
+        .map(ToString::to_string)
+}
+
+pub struct TodoAddTool;
+
+#[async_trait]
+impl Tool for TodoAddTool {
+    fn name(&self) -> &'static str {
+        "todo_add"
+    }
+
+    fn description(&self) -> &'static str {
+        "Add a todo item to the ordered list"
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "title": {"type": "string"},
+                "description": {"type": "string"},
+                "position": {"type": "integer"},
+                "project_id": {"type": "string"}
+            },
+            "required": ["title"]
+        })
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        ToolAnnotations::write("Add todo", false, false)
+    }
+
+    async fn execute(&self, _args: serde_json::Value) -> Result<ToolResult, ToolError> {
+        Err(ToolError::ProjectRequired(self.name().to_string()))
+    }
+
+    async fn execute_with_context(
+        &self,
+        args: serde_json::Value,
+        ctx: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        let title = require_str(&args, "title")?;
+        let position = args.get("position").and_then(serde_json::Value::as_i64);
+        let todo = ctx
+            .store
+            .todo_store
+            .read()
+            .await
+            .add(title, description_arg(&args), position)
+            .await?;
+        Ok(ToolResult::success(
+            serde_json::to_string_pretty(&todo).unwrap_or_else(|_| "{}".to_string()),
+        ))
+    }
+}
+
+pub struct TodoInsertTool;
+
+#[async_trait]
+impl Tool for TodoInsertTool {
+    fn name(&self) -> &'static str {
+        "todo_insert"
+    }
+
+    fn description(&self) -> &'static str {
+        "Insert a todo at a specific position"
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "title": {"type": "string"},
+                "description": {"type": "string"},
+                "position": {"type": "integer"}
+            },
+            "required": ["title", "position"]
+        })
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        ToolAnnotations::write("Insert todo", false, false)
+    }
+
+    async fn execute(&self, _args: serde_json::Value) -> Result<ToolResult, ToolError> {
+        Err(ToolError::ProjectRequired(self.name().to_string()))
+    }
+
+    async fn execute_with_context(
+        &self,
+        args: serde_json::Value,
+        ctx: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        let title = require_str(&args, "title")?;
+        let position = require_i64(&args, "position")?;
+        let todo = ctx
+            .store
+            .todo_store
+            .read()
+            .await
+            .add(title, description_arg(&args), Some(position))
+            .await?;
+        Ok(ToolResult::success(
+            serde_json::to_string_pretty(&todo).unwrap_or_else(|_| "{}".to_string()),
+        ))
+    }
+}
+
+pub struct TodoRemoveTool;
+
+#[async_trait]
+impl Tool for TodoRemoveTool {
+    fn name(&self) -> &'static str {
+        "todo_remove"
+    }
+
+    fn description(&self) -> &'static str {
+        "Remove a todo item"
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        json!({
+            "type": "object",
+            "properties": {"id": {"type": "string"}},
+            "required": ["id"]
+        })
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        ToolAnnotations::write("Remove todo", true, true)
+    }
+
+    async fn execute(&self, _args: serde_json::Value) -> Result<ToolResult, ToolError> {
+        Err(ToolError::ProjectRequired(self.name().to_string()))
+    }
+
+    async fn execute_with_context(
+        &self,
+        args: serde_json::Value,
+        ctx: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        let id = require_str(&args, "id")?;
+        ctx.store.todo_store.read().await.remove(id).await?;
+        Ok(ToolResult::success("removed"))
+    }
+}
+
+pub struct TodoCheckTool;
+
+#[async_trait]
+impl Tool for TodoCheckTool {
+    fn name(&self) -> &'static str {
+        "todo_check"
+    }
+
+    fn description(&self) -> &'static str {
+        "Mark a todo item completed or not"
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "id": {"type": "string"},
+                "completed": {"type": "boolean"}
+            },
+            "required": ["id"]
+        })
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        ToolAnnotations::write("Check off todo", false, true)
+    }
+
+    async fn execute(&self, _args: serde_json::Value) -> Result<ToolResult, ToolError> {
+        Err(ToolError::ProjectRequired(self.name().to_string()))
+    }
+
+    async fn execute_with_context(
+        &self,
+        args: serde_json::Value,
+        ctx: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        let id = require_str(&args, "id")?;
+        let completed = args
+            .get("completed")
+            .and_then(serde_json::Value::as_bool)
+            .unwrap_or(true);
+        ctx.store
+            .todo_store
+            .read()
+            .await
+            .set_completed(id, completed)
+            .await?;
+        Ok(ToolResult::success("updated"))
+    }
+}
+
+pub struct TodoListTool;
+
+#[async_trait]
+impl Tool for TodoListTool {
+    fn name(&self) -> &'static str {
+        "todo_list"
+    }
+
+    fn description(&self) -> &'static str {
+        "List all todos in order"
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        json!({
+            "type": "object",
+            "properties": {"project_id": {"type": "string"}}
+        })
+    }
+
+    fn output_schema(&self) -> Option<serde_json::Value> {
+        Some(json!({
+            "type": "object",
+            "properties": {
+                "todos": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "id": { "type": "string" },
+                            "title": { "type": "string" },
+                            "description": { "type": ["string", "null"] },
+                            "completed": { "type": "boolean" },
+                            "position": { "type": "integer" },
+                            "created_at": { "type": "string" },
+                            "updated_at": { "type": "string" }
+                        },
+                        "required": ["id", "title", "completed", "position"]
+                    }
+                }
+            },
+            "required": ["todos"]
+        }))
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        ToolAnnotations::read_only("List todos")
+    }
+
+    async fn execute(&self, _args: serde_json::Value) -> Result<ToolResult, ToolError> {
+        Err(ToolError::ProjectRequired(self.name().to_string()))
+    }
+
+    async fn execute_with_context(
+        &self,
+        _args: serde_json::Value,
+        ctx: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        let todos = ctx.store.todo_store.read().await.list().await?;
+        Ok(ToolResult::success_with_data(
+            serde_json::to_string_pretty(&todos).unwrap_or_else(|_| "[]".to_string()),
+            json!({ "todos": todos }),
+        ))
+    }
+}
+
+pub struct TodoGetNextTool;
+
+#[async_trait]
+impl Tool for TodoGetNextTool {
+    fn name(&self) -> &'static str {
+        "todo_get_next"
+    }
+
+    fn description(&self) -> &'static str {
+        "Get the next incomplete todo"
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        json!({
+            "type": "object",
+            "properties": {"project_id": {"type": "string"}}
+        })
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        ToolAnnotations::read_only("Get next todo")
+    }
+
+    async fn execute(&self, _args: serde_json::Value) -> Result<ToolResult, ToolError> {
+        Err(ToolError::ProjectRequired(self.name().to_string()))
+    }
+
+    async fn execute_with_context(
+        &self,
+        _args: serde_json::Value,
+        ctx: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        let todo = ctx.store.todo_store.read().await.get_next().await?;
+        Ok(ToolResult::success(
+            serde_json::to_string_pretty(&todo).unwrap_or_else(|_| "null".to_string()),
+        ))
+    }
+}
+
+pub struct TodoMoveTool;
+
+#[async_trait]
+impl Tool for TodoMoveTool {
+    fn name(&self) -> &'static str {
+        "todo_move"
+    }
+
+    fn description(&self) -> &'static str {
+        "Move a todo to a new position"
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        json!({
+            "type": "object",
+            "properties": {"id": {"type": "string"}, "position": {"type": "integer"}},
+            "required": ["id", "position"]
+        })
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        ToolAnnotations::write("Move todo", false, true)
+    }
+
+    async fn execute(&self, _args: serde_json::Value) -> Result<ToolResult, ToolError> {
+        Err(ToolError::ProjectRequired(self.name().to_string()))
+    }
+
+    async fn execute_with_context(
+        &self,
+        args: serde_json::Value,
+        ctx: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        let id = require_str(&args, "id")?;
+        let position = require_i64(&args, "position")?;
+        ctx.store.todo_store.read().await.move_to(id, position).await?;
+        Ok(ToolResult::success("moved"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::context::test_context;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn todo_tools_add_list_and_complete() {
+        let temp = TempDir::new().unwrap();
+        let ctx = test_context(temp.path()).await;
+
+        let added = TodoAddTool
+            .execute_with_context(json!({ "title": "write docs" }), &ctx)
+            .await
+            .unwrap();
+        let todo: serde_json::Value = serde_json::from_str(&added.content).unwrap();
+        let id = todo["id"].as_str().unwrap();
+
+        TodoCheckTool
+            .execute_with_context(json!({ "id": id }), &ctx)
+            .await
+            .unwrap();
+        let next = TodoGetNextTool
+            .execute_with_context(json!({}), &ctx)
+            .await
+            .unwrap();
+        assert_eq!(next.content, "null");
+
+        let list = TodoListTool.execute_with_context(json!({}), &ctx).await.unwrap();
+        assert!(list.content.contains("write docs"));
+        let data = list.data.unwrap();
+        assert_eq!(data["todos"][0]["completed"], true);
+    }
+
+    #[tokio::test]
+    async fn todo_tools_require_a_project() {
+        let err = TodoListTool.execute(json!({})).await.unwrap_err();
+        assert_eq!(err, ToolError::ProjectRequired("todo_list".to_string()));
+    }
+
+    #[tokio::test]
+    async fn todo_insert_requires_position() {
+        let temp = TempDir::new().unwrap();
+        let ctx = test_context(temp.path()).await;
+        let err = TodoInsertTool
+            .execute_with_context(json!({ "title": "x" }), &ctx)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ToolError::InvalidArguments(_)));
+    }
+}
@@ -0,0 +1,349 @@
+//! Upstream MCP servers registered per project.
+//!
+//! AIHarness connects to these as an MCP client (launching stdio servers as
+//! child processes) and serves their tools and resources through its own MCP
+//! endpoint under the server's name as a namespace. See [`crate::mcp_gateway`].
+
+use crate::error::ContextError;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// How AIHarness talks to an upstream server
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UpstreamTransport {
+    /// Launch `command` and speak JSON-RPC over its stdin/stdout
+    Stdio,
+    /// POST to a Streamable HTTP endpoint at `url`
+    Http,
+}
+
+impl UpstreamTransport {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Stdio => "stdio",
+            Self::Http => "http",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "http" => Self::Http,
+            _ => Self::Stdio,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UpstreamServer {
+    pub id: String,
+    /// Namespace for the server's tools and resources
+    pub name: String,
+    pub transport: UpstreamTransport,
+    /// Program to launch (stdio)
+    pub command: Option<String>,
+    /// Arguments for `command` (stdio)
+    pub args: Vec<String>,
+    /// Extra environment variables for `command` (stdio)
+    pub env: BTreeMap<String, String>,
+    /// Endpoint URL (http)
+    pub url: Option<String>,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Input for registering an upstream server
+#[derive(Debug, Clone, Deserialize)]
+pub struct NewUpstreamServer {
+    pub name: String,
+    pub transport: UpstreamTransport,
+    #[serde(default)]
+    pub command: Option<String>,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    #[serde(default)]
+    pub url: Option<String>,
+}
+
+pub struct UpstreamServerStore {
+    db_path: String,
+}
+
+impl UpstreamServerStore {
+    /// Open the store, creating its table if needed
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database can't be opened
+    pub fn new(db_path: &str) -> Result<Self, ContextError> {
+        let store = Self {
+            db_path: db_path.to_string(),
+        };
+        store.init_schema()?;
+        Ok(store)
+    }
+
+    fn get_db(&self) -> Result<rusqlite::Connection, ContextError> {
+        Ok(rusqlite::Connection::open(&self.db_path)?)
+    }
+
+    fn init_schema(&self) -> Result<(), ContextError> {
+        let db = self.get_db()?;
+        db.execute(
+            "CREATE TABLE IF NOT EXISTS upstream_servers (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL UNIQUE,
+                transport TEXT NOT NULL,
+                command TEXT,
+                args TEXT NOT NULL,
+                env TEXT NOT NULL,
+                url TEXT,
+                enabled INTEGER NOT NULL DEFAULT 1,
+                created_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+        Ok(())
+    }
+
+    /// All servers, sorted by name
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database query fails
+    pub fn list(&self) -> Result<Vec<UpstreamServer>, ContextError> {
+        self.query("ORDER BY name ASC", None)
+    }
+
+    /// The server with this id, if any
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database query fails
+    pub fn get(&self, id: &str) -> Result<Option<UpstreamServer>, ContextError> {
+        Ok(self.query("WHERE id = ?1", Some(id))?.into_iter().next())
+    }
+
+    /// The server called `name`, if any
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database query fails
+    pub fn get_by_name(&self, name: &str) -> Result<Option<UpstreamServer>, ContextError> {
+        Ok(self.query("WHERE name = ?1", Some(name))?.into_iter().next())
+    }
+
+    fn query(&self, filter: &str, param: Option<&str>) -> Result<Vec<UpstreamServer>, ContextError> {
+        let db = self.get_db()?;
+        let mut stmt = db.prepare(&format!(
+            "SELECT id, name, transport, command, args, env, url, enabled, created_at
+             FROM upstream_servers {filter}"
+        ))?;
+
+        let map_row = |row: &rusqlite::Row<'_>| {
+            Ok(UpstreamServer {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                transport: UpstreamTransport::parse(&row.get::<_, String>(2)?),
+                command: row.get(3)?,
+                args: serde_json::from_str(&row.get::<_, String>(4)?).unwrap_or_default(),
+                env: serde_json::from_str(&row.get::<_, String>(5)?).unwrap_or_default(),
+                url: row.get(6)?,
+                enabled: row.get::<_, i64>(7)? != 0,
+                created_at: row
+                    .get::<_, String>(8)?
+                    .parse()
+                    .unwrap_or_else(|_| Utc::now()),
+            })
+        };
+        let rows = match param {
+            Some(param) => stmt.query_map([param], map_row)?,
+            None => stmt.query_map([], map_row)?,
+        };
+
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| ContextError::Database(e.to_string()))
+    }
+
+    /// Register a server after validating its name and transport settings.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the server is invalid, its name is taken, or it can't
+    /// be saved
+    pub fn add(&self, server: NewUpstreamServer) -> Result<UpstreamServer, ContextError> {
+        validate(&server)?;
+        if self.get_by_name(&server.name)?.is_some() {
+            return Err(ContextError::Config(format!(
+                "Upstream server already exists: {}",
+                server.name
+            )));
+        }
+
+        let id = uuid::Uuid::new_v4().to_string();
+        let now = Utc::now();
+        let db = self.get_db()?;
+        db.execute(
+            "INSERT INTO upstream_servers (id, name, transport, command, args, env, url, enabled, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 1, ?8)",
+            rusqlite::params![
+                id,
+                server.name,
+                server.transport.as_str(),
+                server.command,
+                serde_json::to_string(&server.args).unwrap_or_else(|_| "[]".to_string()),
+                serde_json::to_string(&server.env).unwrap_or_else(|_| "{}".to_string()),
+                server.url,
+                now.to_rfc3339()
+            ],
+        )?;
+
+        Ok(UpstreamServer {
+            id,
+            name: server.name,
+            transport: server.transport,
+            command: server.command,
+            args: server.args,
+            env: server.env,
+            url: server.url,
+            enabled: true,
+            created_at: now,
+        })
+    }
+
+    /// Turn a server on or off
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no server has this id
+    pub fn set_enabled(&self, id: &str, enabled: bool) -> Result<(), ContextError> {
+        let db = self.get_db()?;
+        let rows = db.execute(
+            "UPDATE upstream_servers SET enabled = ?1 WHERE id = ?2",
+            rusqlite::params![i64::from(enabled), id],
+        )?;
+        if rows == 0 {
+            return Err(ContextError::NotInContext(id.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Delete a server
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no server has this id
+    pub fn remove(&self, id: &str) -> Result<(), ContextError> {
+        let db = self.get_db()?;
+        let rows = db.execute("DELETE FROM upstream_servers WHERE id = ?1", [id])?;
+        if rows == 0 {
+            return Err(ContextError::NotInContext(id.to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// Names become tool name prefixes (`name__tool`), so they are limited to
+/// letters, digits, `-` and single `_` that don't end the name.
+fn validate(server: &NewUpstreamServer) -> Result<(), ContextError> {
+    let name = &server.name;
+    let valid_chars = name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if name.is_empty() || !valid_chars || name.contains("__") || name.ends_with('_') {
+        return Err(ContextError::Config(format!(
+            "Invalid upstream server name '{name}': use letters, digits, '-' and single '_'"
+        )));
+    }
+
+    match server.transport {
+        UpstreamTransport::Stdio if server.command.as_deref().map_or(true, |c| c.trim().is_empty()) => {
+            Err(ContextError::Config("A stdio upstream server needs a command".to_string()))
+        }
+        UpstreamTransport::Http
+            if !server
+                .url
+                .as_deref()
+                .is_some_and(|url| url.starts_with("http://") || url.starts_with("https://")) =>
+        {
+            Err(ContextError::Config(
+                "An http upstream server needs an http:// or https:// url".to_string(),
+            ))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn stdio(name: &str, command: &str) -> NewUpstreamServer {
+        NewUpstreamServer {
+            name: name.to_string(),
+            transport: UpstreamTransport::Stdio,
+            command: Some(command.to_string()),
+            args: vec!["--flag".to_string()],
+            env: BTreeMap::from([("KEY".to_string(), "value".to_string())]),
+            url: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn servers_round_trip_and_can_be_disabled() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("project.db");
+        let store = UpstreamServerStore::new(db_path.to_str().unwrap()).unwrap();
+
+        let added = store.add(stdio("files", "mcp-files")).unwrap();
+        assert!(added.enabled);
+        assert_eq!(store.list().unwrap(), vec![added.clone()]);
+
+        store.set_enabled(&added.id, false).unwrap();
+        let loaded = store.get_by_name("files").unwrap().unwrap();
+        assert!(!loaded.enabled);
+        assert_eq!(loaded.args, vec!["--flag".to_string()]);
+        assert_eq!(loaded.env["KEY"], "value");
+
+        store.remove(&added.id).unwrap();
+        assert!(store.get(&added.id).unwrap().is_none());
+        assert!(matches!(
+            store.remove(&added.id),
+            Err(ContextError::NotInContext(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn add_validates_names_and_transports() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("project.db");
+        let store = UpstreamServerStore::new(db_path.to_str().unwrap()).unwrap();
+
+        for name in ["", "a__b", "trailing_", "has space", "dot.name"] {
+            assert!(store.add(stdio(name, "x")).is_err(), "{name}");
+        }
+        assert!(store.add(stdio("blank", " ")).is_err());
+
+        let http = NewUpstreamServer {
+            name: "remote".to_string(),
+            transport: UpstreamTransport::Http,
+            command: None,
+            args: Vec::new(),
+            env: BTreeMap::new(),
+            url: Some("ftp://example.com".to_string()),
+        };
+        assert!(store.add(http.clone()).is_err());
+        let http = NewUpstreamServer {
+            url: Some("http://127.0.0.1:9000/mcp".to_string()),
+            ..http
+        };
+        assert_eq!(store.add(http).unwrap().transport, UpstreamTransport::Http);
+
+        store.add(stdio("my-server_1", "x")).unwrap();
+        assert!(store.add(stdio("my-server_1", "y")).is_err());
+    }
+}
@@ -19,6 +19,14 @@ export interface ToolCall {
   success: boolean;
   content: string;
   duration_ms: number;
+  /** Human approval decision, for calls that required one */
+  approval?: {
+    decision: 'approved' | 'denied' | 'timed_out' | 'no_approver';
+    decided_at: string;
+    reason?: string;
+  };
+  /** The caller cancelled the call before it finished */
+  cancelled?: boolean;
 }
 
 /** Project metadata */
@@ -112,3 +120,128 @@ export interface McpConfigResult {
   message: string;
   config_path: string | null;
 }
+
+/** Persistent terminal opened by an agent */
+export interface TerminalInfo {
+  id: string;
+  project_id: string;
+  shell: string;
+  cwd: string;
+  cols: number;
+  rows: number;
+  created_at: string;
+  cursor: number;
+  exited: boolean;
+  exit_code: number | null;
+}
+
+/** Terminal activity, for mirroring a terminal in the UI */
+export type TerminalEvent = {
+  terminal_id: string;
+  project_id: string;
+  timestamp: string;
+} & (
+  | { kind: 'opened'; shell: string; cwd: string }
+  | { kind: 'input'; data: string }
+  | { kind: 'output'; data: string }
+  | { kind: 'exited'; exit_code: number | null }
+  | { kind: 'closed'; reason: 'closed' | 'idle' | 'shutdown' }
+);
+
+/** Background process started by an agent */
+export interface ProcessInfo {
+  id: string;
+  name: string;
+  command: string;
+  cwd: string;
+  status: 'running' | 'stopping' | 'exited' | 'stopped';
+  pid: number | null;
+  exit_code: number | null;
+  started_at: string;
+  exited_at: string | null;
+  restarts: number;
+}
+
+/** A background process changed state */
+export interface ProcessEvent {
+  project_id: string;
+  kind: 'started' | 'exited' | 'stopped' | 'removed';
+  process: ProcessInfo;
+  timestamp: string;
+}
+
+/** A changed path in `git_status` data */
+export interface GitStatusEntry {
+  path: string;
+  old_path: string | null;
+  /** Staged status, e.g. 'M', 'A', 'D', 'R'; '.' if unchanged */
+  index: string;
+  /** Unstaged status; '.' if unchanged */
+  worktree: string;
+  kind: 'changed' | 'renamed' | 'unmerged' | 'untracked';
+}
+
+/** `git_status` data */
+export interface GitStatus {
+  branch: string | null;
+  upstream: string | null;
+  ahead: number;
+  behind: number;
+  clean: boolean;
+  entries: GitStatusEntry[];
+}
+
+/** Lines changed in one file (`git_diff`, `git_show`, `git_commit`) */
+export interface GitFileChange {
+  path: string;
+  old_path: string | null;
+  /** null for binary files */
+  additions: number | null;
+  deletions: number | null;
+}
+
+/** A commit in `git_log`, `git_show` and `git_commit` data */
+export interface GitCommit {
+  hash: string;
+  short_hash: string;
+  author: string;
+  email: string;
+  date: string;
+  parents: string[];
+  subject: string;
+  body: string;
+}
+
+/** A line of `git_blame` data */
+export interface GitBlameLine {
+  line: number;
+  commit: string;
+  author: string;
+  date: string;
+  summary: string;
+  text: string;
+}
+
+/** A branch in `git_branch` data */
+export interface GitBranch {
+  name: string;
+  commit: string;
+  current: boolean;
+  remote: boolean;
+  upstream: string | null;
+  track: string | null;
+  subject: string;
+}
+
+/** Upstream MCP server served through the gateway */
+export interface UpstreamServer {
+  id: string;
+  name: string;
+  transport: 'stdio' | 'http';
+  command: string | null;
+  args: string[];
+  env: Record<string, string>;
+  url: string | null;
+  enabled: boolean;
+  created_at: string;
+}